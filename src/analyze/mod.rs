@@ -0,0 +1,260 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::cache::TarballStore;
+
+/// A package found on disk, in node_modules or the tarball store.
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    pub name: String,
+    pub version: String,
+    pub path: PathBuf,
+    /// Bytes on disk, not counting the package's own nested node_modules
+    pub size: u64,
+    // Names the package depends on at runtime
+    #[serde(skip)]
+    pub dependencies: Vec<String>,
+}
+
+/// A package present at more than one version.
+#[derive(Debug, Clone, Serialize)]
+pub struct Duplicate {
+    pub name: String,
+    pub versions: Vec<String>,
+    pub copies: usize,
+    /// Bytes taken by every copy but the largest, what deduplicating would save at most
+    pub cost: u64,
+}
+
+/// The size a direct dependency brings in with everything below it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Attributed {
+    pub name: String,
+    /// Packages shared by several direct dependencies are split evenly between them
+    pub size: u64,
+    pub packages: usize,
+}
+
+/// Disk usage of an install or the tarball store.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub packages: usize,
+    pub total_size: u64,
+    pub largest: Vec<Entry>,
+    pub duplicates: Vec<Duplicate>,
+    pub direct: Vec<Attributed>,
+}
+
+// The fields of package.json the report needs
+#[derive(Deserialize)]
+struct Manifest {
+    name: String,
+    version: String,
+    #[serde(default)]
+    dependencies: HashMap<String, String>,
+    #[serde(default, rename = "optionalDependencies")]
+    optional_dependencies: HashMap<String, String>,
+}
+
+impl Manifest {
+    fn into_entry(self, path: PathBuf, size: u64) -> Entry {
+        let mut dependencies: Vec<String> = self
+            .dependencies
+            .into_keys()
+            .chain(self.optional_dependencies.into_keys())
+            .collect();
+        dependencies.sort();
+        dependencies.dedup();
+        Entry {
+            name: self.name,
+            version: self.version,
+            path,
+            size,
+            dependencies,
+        }
+    }
+}
+
+/// Every package below `node_modules`, nested installs included.
+pub fn scan_node_modules(node_modules: &Path) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    scan_dir(node_modules, &mut entries)?;
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+fn scan_dir(node_modules: &Path, entries: &mut Vec<Entry>) -> Result<()> {
+    let Ok(children) = std::fs::read_dir(node_modules) else {
+        return Ok(());
+    };
+    for child in children.flatten() {
+        let path = child.path();
+        let name = child.file_name().to_string_lossy().to_string();
+        // .bin, .package-lock.json and the like aren't packages
+        if name.starts_with('.') || !path.is_dir() {
+            continue;
+        }
+        if name.starts_with('@') {
+            scan_dir(&path, entries)?;
+            continue;
+        }
+
+        let manifest_path = path.join("package.json");
+        if let Ok(content) = std::fs::read_to_string(&manifest_path) {
+            let manifest: Manifest = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+            entries.push(manifest.into_entry(path.clone(), package_size(&path)));
+        }
+        scan_dir(&path.join("node_modules"), entries)?;
+    }
+    Ok(())
+}
+
+// Size of a package directory without the packages nested inside it
+fn package_size(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || entry.file_name() != "node_modules")
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Every tarball in the store, sized as stored (compressed). Tarballs that
+/// can't be read are skipped.
+pub fn scan_store(store: &TarballStore) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let Ok(children) = std::fs::read_dir(store.root()) else {
+        return Ok(entries);
+    };
+    for child in children.flatten() {
+        let path = child.path();
+        if path.extension().is_none_or(|extension| extension != "tgz") {
+            continue;
+        }
+        let size = child.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        if let Some(manifest) = tarball_manifest(&path) {
+            entries.push(manifest.into_entry(path, size));
+        }
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+// The package.json at the top of a tarball, usually package/package.json
+fn tarball_manifest(path: &Path) -> Option<Manifest> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    for entry in archive.entries().ok()? {
+        let entry = entry.ok()?;
+        let entry_path = entry.path().ok()?.to_path_buf();
+        if entry_path.components().count() == 2 && entry_path.ends_with("package.json") {
+            return serde_json::from_reader(entry).ok();
+        }
+    }
+    None
+}
+
+/// Summarize `entries`: the `top` largest packages, every duplicated package,
+/// and the size each of `direct` (names at the top of node_modules) pulls in.
+pub fn report(entries: &[Entry], root: &Path, direct: &[String], top: usize) -> Report {
+    let mut largest = entries.to_vec();
+    largest.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    largest.truncate(top);
+
+    let mut by_name: BTreeMap<&str, Vec<&Entry>> = BTreeMap::new();
+    for entry in entries {
+        by_name.entry(&entry.name).or_default().push(entry);
+    }
+    let mut duplicates: Vec<Duplicate> = by_name
+        .into_iter()
+        .filter_map(|(name, copies)| {
+            let mut versions: Vec<String> = copies.iter().map(|copy| copy.version.clone()).collect();
+            versions.sort_by(|a, b| match (semver::Version::parse(a), semver::Version::parse(b)) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            });
+            versions.dedup();
+            if versions.len() < 2 {
+                return None;
+            }
+            let total: u64 = copies.iter().map(|copy| copy.size).sum();
+            let kept = copies.iter().map(|copy| copy.size).max().unwrap_or(0);
+            Some(Duplicate {
+                name: name.to_string(),
+                versions,
+                copies: copies.len(),
+                cost: total - kept,
+            })
+        })
+        .collect();
+    duplicates.sort_by(|a, b| b.cost.cmp(&a.cost).then_with(|| a.name.cmp(&b.name)));
+
+    Report {
+        packages: entries.len(),
+        total_size: entries.iter().map(|entry| entry.size).sum(),
+        largest,
+        duplicates,
+        direct: attribute(entries, root, direct),
+    }
+}
+
+// Walk each direct dependency's graph the way Node resolves requires: from a
+// package, a dependency is the nearest node_modules/<name> above it
+fn attribute(entries: &[Entry], root: &Path, direct: &[String]) -> Vec<Attributed> {
+    let by_path: HashMap<&Path, usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| (entry.path.as_path(), index))
+        .collect();
+    let resolve = |from: &Path, name: &str| {
+        from.ancestors()
+            .take_while(|dir| dir.starts_with(root))
+            .find_map(|dir| by_path.get(dir.join("node_modules").join(name).as_path()).copied())
+    };
+
+    let reached: Vec<(String, Vec<usize>)> = direct
+        .iter()
+        .filter_map(|name| {
+            let start = resolve(root, name)?;
+            let mut seen = vec![false; entries.len()];
+            let mut queue = vec![start];
+            seen[start] = true;
+            while let Some(index) = queue.pop() {
+                for dependency in &entries[index].dependencies {
+                    if let Some(next) = resolve(&entries[index].path, dependency)
+                        && !seen[next]
+                    {
+                        seen[next] = true;
+                        queue.push(next);
+                    }
+                }
+            }
+            let packages = (0..entries.len()).filter(|index| seen[*index]).collect();
+            Some((name.clone(), packages))
+        })
+        .collect();
+
+    let mut sharers = vec![0u64; entries.len()];
+    for (_, packages) in &reached {
+        for index in packages {
+            sharers[*index] += 1;
+        }
+    }
+
+    let mut attributed: Vec<Attributed> = reached
+        .into_iter()
+        .map(|(name, packages)| Attributed {
+            name,
+            size: packages.iter().map(|index| entries[*index].size / sharers[*index]).sum(),
+            packages: packages.len(),
+        })
+        .collect();
+    attributed.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    attributed
+}
@@ -0,0 +1,129 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::dependency::versions;
+use crate::dependency::Lockfile;
+use crate::registry::{Advisory, NpmRegistry};
+
+/// Advisory severity, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    Moderate,
+    High,
+    Critical,
+}
+
+impl Severity {
+    // Registries occasionally report severities we don't know; count those as high
+    fn parse(severity: &str) -> Self {
+        match severity.to_ascii_lowercase().as_str() {
+            "info" => Severity::Info,
+            "low" => Severity::Low,
+            "moderate" => Severity::Moderate,
+            "critical" => Severity::Critical,
+            _ => Severity::High,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Moderate => "moderate",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// An advisory affecting an installed package version.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub name: String,
+    pub version: String,
+    pub severity: Severity,
+    pub advisory: Advisory,
+}
+
+#[derive(Debug, Default)]
+pub struct AuditReport {
+    pub findings: Vec<Finding>,
+}
+
+impl AuditReport {
+    /// Findings at or above `level`.
+    pub fn at_or_above(&self, level: Severity) -> usize {
+        self.findings.iter().filter(|finding| finding.severity >= level).count()
+    }
+
+    /// One-line summary, e.g. "found 3 vulnerabilities (1 moderate, 2 high)".
+    pub fn summary(&self) -> String {
+        let total = self.findings.len();
+        let noun = if total == 1 { "vulnerability" } else { "vulnerabilities" };
+        if total == 0 {
+            return format!("found 0 {}", noun);
+        }
+
+        let mut counts: BTreeMap<Severity, usize> = BTreeMap::new();
+        for finding in &self.findings {
+            *counts.entry(finding.severity).or_default() += 1;
+        }
+        let breakdown: Vec<String> = counts
+            .iter()
+            .map(|(severity, count)| format!("{} {}", count, severity.label()))
+            .collect();
+
+        format!("found {} {} ({})", total, noun, breakdown.join(", "))
+    }
+}
+
+/// Check every package version in the lockfile against the registry's advisories.
+pub async fn check_lockfile(registry: &NpmRegistry, lockfile: &Lockfile) -> Result<AuditReport> {
+    let mut packages: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (key, entry) in &lockfile.packages {
+        let name = key
+            .strip_suffix(&format!("@{}", entry.version))
+            .unwrap_or(key);
+        packages.entry(name.to_string()).or_default().push(entry.version.clone());
+    }
+
+    if packages.is_empty() {
+        return Ok(AuditReport::default());
+    }
+
+    let advisories = registry.bulk_advisories(&packages).await?;
+
+    let mut findings = Vec::new();
+    for (name, advisories) in advisories {
+        let Some(installed) = packages.get(&name) else {
+            continue;
+        };
+        for advisory in advisories {
+            // Unparseable ranges are trusted to apply, as the registry only
+            // returns advisories relevant to the versions we sent
+            let range = versions::parse_req(&advisory.vulnerable_versions);
+            for version in installed {
+                let affected = match (&range, versions::parse_version(version)) {
+                    (Some(range), Some(version)) => range.matches(&version),
+                    _ => true,
+                };
+                if affected {
+                    findings.push(Finding {
+                        name: name.clone(),
+                        version: version.clone(),
+                        severity: Severity::parse(&advisory.severity),
+                        advisory: advisory.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    findings.sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| a.name.cmp(&b.name)));
+    Ok(AuditReport { findings })
+}
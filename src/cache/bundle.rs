@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::{CachedPackument, MetadataCache, TarballStore};
+use crate::registry::PackageInfo;
+use crate::utils;
+
+// Always the first entry of a bundle, so import knows what it is reading
+const MANIFEST: &str = "rjs-bundle.json";
+const BUNDLE_VERSION: u32 = 1;
+
+/// Table of contents of a bundle archive.
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleManifest {
+    version: u32,
+    // Registry the packuments were fetched from; they're cached under it on import
+    registry: String,
+    tarballs: Vec<BundledTarball>,
+    packuments: Vec<BundledPackument>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BundledTarball {
+    resolved: String,
+    integrity: Option<String>,
+    file: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BundledPackument {
+    name: String,
+    file: String,
+}
+
+/// A tarball already in the store, to be packed into a bundle.
+pub struct ExportTarball {
+    pub resolved: String,
+    pub integrity: Option<String>,
+}
+
+/// What `import` added to the local caches.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub registry: String,
+    pub tarballs: usize,
+    pub packuments: usize,
+}
+
+/// Pack stored tarballs and packuments into a single gzipped tar at `path`.
+/// Blocking.
+pub fn export(
+    path: &Path,
+    registry: &str,
+    store: &TarballStore,
+    tarballs: &[ExportTarball],
+    packuments: &[PackageInfo],
+) -> Result<()> {
+    let mut manifest = BundleManifest {
+        version: BUNDLE_VERSION,
+        registry: registry.to_string(),
+        tarballs: Vec::with_capacity(tarballs.len()),
+        packuments: Vec::with_capacity(packuments.len()),
+    };
+
+    let mut tarball_files = Vec::with_capacity(tarballs.len());
+    for tarball in tarballs {
+        let source = store.path_for(&tarball.resolved, tarball.integrity.as_deref());
+        let file_name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let file = format!("tarballs/{}", file_name);
+        manifest.tarballs.push(BundledTarball {
+            resolved: tarball.resolved.clone(),
+            integrity: tarball.integrity.clone(),
+            file: file.clone(),
+        });
+        tarball_files.push((file, source));
+    }
+
+    let mut packument_files = Vec::with_capacity(packuments.len());
+    for info in packuments {
+        // Names can contain a scope slash, so files are named by hash
+        let file = format!("metadata/{}.json", utils::calculate_sha256(info.name.as_bytes()));
+        manifest.packuments.push(BundledPackument {
+            name: info.name.clone(),
+            file: file.clone(),
+        });
+        packument_files.push((file, serde_json::to_vec(info)?));
+    }
+
+    // Write next to the destination so a failed export never leaves a truncated bundle
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".partial");
+    let temp = PathBuf::from(temp);
+    let result = (|| -> Result<()> {
+        let file = File::create(&temp)
+            .with_context(|| format!("Failed to create {}", temp.display()))?;
+        let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+        append_bytes(&mut archive, MANIFEST, &serde_json::to_vec_pretty(&manifest)?)?;
+        for (name, data) in &packument_files {
+            append_bytes(&mut archive, name, data)?;
+        }
+        for (name, source) in &tarball_files {
+            archive
+                .append_path_with_name(source, name)
+                .with_context(|| format!("Failed to add {} to bundle", source.display()))?;
+        }
+
+        archive.into_inner()?.finish()?;
+        std::fs::rename(&temp, path)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+fn append_bytes<W: std::io::Write>(archive: &mut tar::Builder<W>, name: &str, data: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive
+        .append_data(&mut header, name, data)
+        .with_context(|| format!("Failed to add {} to bundle", name))
+}
+
+/// Load a bundle's tarballs into the store and its packuments into the
+/// metadata cache, verifying every tarball against its integrity. Blocking.
+pub fn import(path: &Path, store: &TarballStore, cache: &MetadataCache) -> Result<ImportSummary> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut entries = archive.entries()?;
+
+    let manifest: BundleManifest = match entries.next() {
+        Some(entry) => {
+            let mut entry = entry?;
+            if entry.path()?.to_string_lossy() != MANIFEST {
+                return Err(anyhow::anyhow!("{} is not an rjs bundle", path.display()));
+            }
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            serde_json::from_slice(&data).context("Failed to parse bundle manifest")?
+        }
+        None => return Err(anyhow::anyhow!("{} is empty", path.display())),
+    };
+
+    if manifest.version != BUNDLE_VERSION {
+        return Err(anyhow::anyhow!(
+            "Unsupported bundle version {} (expected {})",
+            manifest.version,
+            BUNDLE_VERSION
+        ));
+    }
+
+    let tarballs: HashMap<&str, &BundledTarball> = manifest
+        .tarballs
+        .iter()
+        .map(|tarball| (tarball.file.as_str(), tarball))
+        .collect();
+    let packuments: HashMap<&str, &str> = manifest
+        .packuments
+        .iter()
+        .map(|packument| (packument.file.as_str(), packument.name.as_str()))
+        .collect();
+
+    let mut summary = ImportSummary {
+        registry: manifest.registry.clone(),
+        ..Default::default()
+    };
+
+    for entry in entries {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+
+        if let Some(tarball) = tarballs.get(name.as_str()) {
+            store.insert(&tarball.resolved, tarball.integrity.as_deref(), &data)?;
+            summary.tarballs += 1;
+        } else if let Some(package_name) = packuments.get(name.as_str()) {
+            let info: PackageInfo = serde_json::from_slice(&data)
+                .with_context(|| format!("Failed to parse bundled metadata for {}", package_name))?;
+            cache.insert(&manifest.registry, package_name, &CachedPackument { etag: None, info })?;
+            summary.packuments += 1;
+        } else {
+            debug!("Ignoring unknown bundle entry {}", name);
+        }
+    }
+
+    Ok(summary)
+}
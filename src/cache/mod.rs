@@ -0,0 +1,186 @@
+use anyhow::{Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use crate::registry::PackageInfo;
+use crate::utils;
+
+pub mod bundle;
+pub mod store;
+
+pub use store::{StoreOutcome, TarballStore};
+
+// Renamed whenever PackageInfo gains fields or the encoding changes, so
+// packuments cached earlier don't lack them until the registry's ETag happens
+// to change
+const PACKUMENTS_TREE: &str = "packuments-v4";
+
+// Packuments are mostly repeated keys and URLs and shrink about tenfold even
+// at a fast level; higher levels buy little more for much slower writes
+const COMPRESSION_LEVEL: i32 = 3;
+
+// sled only lets one handle per process open a database, so share it
+static SHARED: OnceLock<Option<Arc<MetadataCache>>> = OnceLock::new();
+
+/// Write the shared cache's pending changes to disk. sled flushes on its own
+/// only every half second or when dropped, and the shared handle never is, so
+/// what a short command cached would be lost at exit without this.
+pub fn flush() {
+    if let Some(Some(cache)) = SHARED.get()
+        && let Err(e) = cache.db.flush()
+    {
+        debug!("Failed to flush the metadata cache: {}", e);
+    }
+}
+
+/// A parsed packument together with the ETag it was served with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedPackument {
+    pub etag: Option<String>,
+    pub info: PackageInfo,
+}
+
+/// How a metadata lookup was served, recorded for `rjs cache stats`.
+#[derive(Debug, Clone, Copy)]
+pub enum CacheOutcome {
+    /// The registry confirmed the cached copy is current (HTTP 304)
+    Hit,
+    /// Nothing usable was cached, the packument was fetched and parsed
+    Miss,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CacheStats {
+    pub path: PathBuf,
+    pub entries: usize,
+    pub size_on_disk: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / total as f64
+    }
+}
+
+/// Packuments stored in a compact binary encoding, zstd-compressed, in an
+/// embedded database, so repeated runs skip downloading and re-parsing large
+/// JSON documents.
+pub struct MetadataCache {
+    path: PathBuf,
+    db: sled::Db,
+    packuments: sled::Tree,
+    stats: sled::Tree,
+}
+
+impl MetadataCache {
+    pub fn open(path: &Path) -> Result<Self> {
+        let db = sled::open(path)
+            .with_context(|| format!("Failed to open metadata cache at {}", path.display()))?;
+        let packuments = db.open_tree(PACKUMENTS_TREE)?;
+        // Reclaim the space of earlier formats' entries
+        for previous in ["packuments", "packuments-v2", "packuments-v3"] {
+            let _ = db.drop_tree(previous);
+        }
+        let stats = db.open_tree("stats")?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            db,
+            packuments,
+            stats,
+        })
+    }
+
+    /// The process-wide cache in the rjs cache directory.
+    ///
+    /// Returns `None` when the cache can't be opened, e.g. because another rjs
+    /// process holds it; callers then simply go to the network.
+    pub fn shared() -> Option<Arc<MetadataCache>> {
+        SHARED
+            .get_or_init(|| {
+                let path = utils::get_cache_dir().ok()?.join("metadata");
+                match Self::open(&path) {
+                    Ok(cache) => Some(Arc::new(cache)),
+                    Err(e) => {
+                        debug!("Metadata cache unavailable: {:#}", e);
+                        None
+                    }
+                }
+            })
+            .clone()
+    }
+
+    fn key(registry: &str, name: &str) -> Vec<u8> {
+        format!("{}\n{}", registry, name).into_bytes()
+    }
+
+    pub fn get(&self, registry: &str, name: &str) -> Option<CachedPackument> {
+        let bytes = self.packuments.get(Self::key(registry, name)).ok()??;
+
+        // Decoded as it's decompressed, without the whole encoding in memory.
+        // Entries written by an older rjs may not decode; treat them as missing
+        let decoded = zstd::stream::Decoder::new(bytes.as_ref())
+            .map_err(anyhow::Error::from)
+            .and_then(|decoder| Ok(rmp_serde::from_read(decoder)?));
+        match decoded {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                debug!("Discarding unreadable cache entry for {}: {}", name, e);
+                None
+            }
+        }
+    }
+
+    pub fn insert(&self, registry: &str, name: &str, entry: &CachedPackument) -> Result<()> {
+        let mut encoder = zstd::stream::Encoder::new(Vec::new(), COMPRESSION_LEVEL)?;
+        rmp_serde::encode::write(&mut encoder, entry).context("Failed to encode packument")?;
+        let bytes = encoder.finish().context("Failed to compress packument")?;
+        self.packuments.insert(Self::key(registry, name), bytes)?;
+        Ok(())
+    }
+
+    pub fn record(&self, outcome: CacheOutcome) {
+        let counter = match outcome {
+            CacheOutcome::Hit => "hits",
+            CacheOutcome::Miss => "misses",
+        };
+
+        let _ = self.stats.update_and_fetch(counter, |old| {
+            let value = old.map(read_counter).unwrap_or(0) + 1;
+            Some(value.to_be_bytes().to_vec())
+        });
+    }
+
+    pub fn stats(&self) -> Result<CacheStats> {
+        let counter = |name: &str| -> Result<u64> {
+            Ok(self.stats.get(name)?.map(|v| read_counter(&v)).unwrap_or(0))
+        };
+
+        Ok(CacheStats {
+            path: self.path.clone(),
+            entries: self.packuments.len(),
+            size_on_disk: self.db.size_on_disk().unwrap_or(0),
+            hits: counter("hits")?,
+            misses: counter("misses")?,
+        })
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        self.packuments.clear()?;
+        self.stats.clear()?;
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+fn read_counter(bytes: &[u8]) -> u64 {
+    bytes.try_into().map(u64::from_be_bytes).unwrap_or(0)
+}
@@ -0,0 +1,261 @@
+use anyhow::{Context, Result};
+use console::style;
+use log::debug;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::fs;
+
+use crate::config;
+use crate::dependency::events::{InstallEvent, InstallEvents};
+use crate::registry::NpmRegistry;
+use crate::timing::{self, Phase};
+use crate::utils::{self, icon};
+
+// Distinguishes concurrent downloads into the store
+static DOWNLOAD: AtomicUsize = AtomicUsize::new(0);
+
+/// Where a tarball handed out by the store came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreOutcome {
+    /// Already in the store and still matching its integrity
+    Cached,
+    /// Downloaded from the registry and added to the store
+    Downloaded,
+}
+
+// A downloaded tarball that doesn't match the integrity it should have
+#[derive(Debug)]
+struct Mismatch(String);
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+// The same tarball on each configured mirror: its path under the registry,
+// or on the registry's host for tarballs from elsewhere, under the mirror
+fn mirror_urls(tarball_url: &str) -> Vec<String> {
+    let settings = config::settings();
+    let Some(mirrors) = &settings.mirrors else {
+        return Vec::new();
+    };
+    let path = match tarball_url.strip_prefix(&settings.registry) {
+        Some(path) => path.to_string(),
+        None => match url::Url::parse(tarball_url) {
+            Ok(url) => url.path().to_string(),
+            Err(_) => return Vec::new(),
+        },
+    };
+    mirrors
+        .split(',')
+        .map(|mirror| mirror.trim().trim_end_matches('/'))
+        .filter(|mirror| !mirror.is_empty())
+        .map(|mirror| format!("{}{}", mirror, path))
+        .collect()
+}
+
+// The hash of `integrity` a tarball is stored under: sha512 if it lists one,
+// then sha256, else the string as given
+fn store_key(integrity: &str) -> &str {
+    ["sha512-", "sha256-"]
+        .iter()
+        .find_map(|algorithm| integrity.split_whitespace().find(|hash| hash.starts_with(algorithm)))
+        .unwrap_or(integrity)
+}
+
+/// Package tarballs kept in the rjs cache directory, addressed by their
+/// integrity (or their URL when the lockfile has no integrity).
+#[derive(Debug, Clone)]
+pub struct TarballStore {
+    root: PathBuf,
+}
+
+impl TarballStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// The store in the rjs cache directory.
+    pub fn open() -> Result<Self> {
+        Ok(Self::new(utils::get_cache_dir()?.join("tarballs")))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path a tarball is stored at, whether or not it has been fetched yet.
+    ///
+    /// The key is the integrity's strongest hash, so the same artifact is
+    /// found whatever URL it's requested from and whichever other hashes the
+    /// lockfile or packument lists alongside it.
+    pub fn path_for(&self, tarball_url: &str, integrity: Option<&str>) -> PathBuf {
+        let key = integrity.map_or(tarball_url, store_key);
+        self.root.join(format!("{}.tgz", utils::calculate_sha256(key.as_bytes())))
+    }
+
+    /// Return the stored tarball, downloading and verifying it first if needed.
+    /// Download progress is reported to `events` under `name`.
+    ///
+    /// A stored tarball that no longer matches its integrity is discarded and
+    /// downloaded again. A download that doesn't match is retried once and
+    /// then fetched from the `mirrors` setting's registries, in order.
+    pub async fn fetch(
+        &self,
+        registry: &NpmRegistry,
+        tarball_url: &str,
+        integrity: Option<&str>,
+        name: &str,
+        events: &dyn InstallEvents,
+    ) -> Result<(PathBuf, StoreOutcome)> {
+        let path = self.path_for(tarball_url, integrity);
+
+        if path.exists() {
+            match integrity {
+                Some(integrity) => {
+                    let data = utils::read_file(&path).await?;
+                    if utils::verify_integrity(&data, integrity).is_ok() {
+                        return Ok((path, StoreOutcome::Cached));
+                    }
+                    debug!("Discarding corrupt cached tarball {}", path.display());
+                    let _ = fs::remove_file(&path).await;
+                }
+                None => return Ok((path, StoreOutcome::Cached)),
+            }
+        }
+
+        let _span = timing::span(Phase::Download, name);
+        utils::ensure_dir(&self.root).await?;
+
+        // A tarball that doesn't match its integrity may have been corrupted on
+        // the way: it's downloaded once more, then tried from each mirror
+        let mut sources = vec![tarball_url.to_string(), tarball_url.to_string()];
+        sources.extend(mirror_urls(tarball_url));
+        let mut attempts = Vec::new();
+
+        for source in sources {
+            let progress = |downloaded, total| {
+                events.on_event(&InstallEvent::DownloadProgress {
+                    name: name.to_string(),
+                    downloaded,
+                    total,
+                })
+            };
+            match self.download(registry, &source, integrity, &path, &progress).await {
+                Ok(()) => {
+                    if !attempts.is_empty() {
+                        eprintln!(
+                            "{} {} failed verification and was fetched again from {}:\n  {}",
+                            style(format!("{} Warning:", icon("⚠"))).bold().yellow(),
+                            name,
+                            source,
+                            attempts.join("\n  ")
+                        );
+                    }
+                    return Ok((path, StoreOutcome::Downloaded));
+                }
+                // Other failures are already retried by the registry client
+                Err(e) if attempts.is_empty() && e.downcast_ref::<Mismatch>().is_none() => return Err(e),
+                Err(e) => {
+                    debug!("Fetching {} from {} failed: {:#}", name, source, e);
+                    attempts.push(format!("{}: {:#}", source, e));
+                }
+            }
+        }
+
+        anyhow::bail!(
+            "{} failed integrity verification from every source; if the lockfile's integrity is \
+             outdated, remove the entry and install again:\n  {}",
+            name,
+            attempts.join("\n  ")
+        )
+    }
+
+    // Download `tarball_url` and move it to `path` once it matches `integrity`
+    async fn download(
+        &self,
+        registry: &NpmRegistry,
+        tarball_url: &str,
+        integrity: Option<&str>,
+        path: &Path,
+        progress: &(dyn Fn(u64, Option<u64>) + Sync),
+    ) -> Result<()> {
+        // Download next to the final path so only verified tarballs get renamed in
+        let temp = self.root.join(format!(
+            ".{}-{}.tgz.tmp",
+            std::process::id(),
+            DOWNLOAD.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let result = async {
+            registry.download_package(tarball_url, &temp, progress).await?;
+
+            if let Some(integrity) = integrity {
+                let data = utils::read_file(&temp).await?;
+                if let Err(e) = utils::verify_integrity(&data, integrity) {
+                    return Err(anyhow::Error::new(Mismatch(e.to_string())));
+                }
+            }
+
+            fs::rename(&temp, path)
+                .await
+                .with_context(|| format!("Failed to store tarball {}", path.display()))
+        }
+        .await;
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp).await;
+        }
+        result
+    }
+
+    /// Add a tarball obtained elsewhere (e.g. from a bundle) to the store,
+    /// verifying it against its integrity first. Blocking.
+    pub fn insert(&self, tarball_url: &str, integrity: Option<&str>, data: &[u8]) -> Result<PathBuf> {
+        if let Some(integrity) = integrity {
+            utils::verify_integrity(data, integrity)
+                .with_context(|| format!("Tarball {} failed verification", tarball_url))?;
+        }
+
+        let path = self.path_for(tarball_url, integrity);
+        std::fs::create_dir_all(&self.root)
+            .with_context(|| format!("Failed to create {}", self.root.display()))?;
+
+        let temp = self.root.join(format!(
+            ".{}-{}.tgz.tmp",
+            std::process::id(),
+            DOWNLOAD.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = std::fs::write(&temp, data).and_then(|_| std::fs::rename(&temp, &path));
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e).with_context(|| format!("Failed to store tarball {}", path.display()));
+        }
+
+        Ok(path)
+    }
+
+    /// Number of stored tarballs and their total size.
+    pub fn stats(&self) -> (usize, u64) {
+        let entries = std::fs::read_dir(&self.root)
+            .map(|dir| {
+                dir.filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "tgz"))
+                    .count()
+            })
+            .unwrap_or(0);
+        (entries, utils::dir_size(&self.root))
+    }
+
+    pub async fn clear(&self) -> Result<()> {
+        if self.root.exists() {
+            fs::remove_dir_all(&self.root)
+                .await
+                .with_context(|| format!("Failed to remove {}", self.root.display()))?;
+        }
+        Ok(())
+    }
+}
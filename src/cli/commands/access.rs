@@ -0,0 +1,84 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use console::style;
+use std::path::Path;
+
+use crate::npmrc::Npmrc;
+use crate::pack;
+use crate::registry::NpmRegistry;
+use crate::utils::icon;
+
+#[derive(Args)]
+pub struct AccessOptions {
+    #[command(subcommand)]
+    action: AccessAction,
+
+    /// One-time password from your authenticator, for accounts with two-factor auth
+    #[arg(long, global = true)]
+    otp: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum AccessAction {
+    /// Let anyone install a scoped package (default: the current package)
+    Public {
+        package: Option<String>,
+    },
+    /// Only let the package's owners and teams install it (default: the current package)
+    Restricted {
+        package: Option<String>,
+    },
+    /// Give an org team access, e.g. `rjs access grant read-write @acme:devs @acme/pkg`
+    Grant {
+        #[arg(value_parser = ["read-only", "read-write"])]
+        permissions: String,
+        /// Team as @scope:team
+        team: String,
+        /// Package to share (default: the current package)
+        package: Option<String>,
+    },
+    /// Take an org team's access away
+    Revoke {
+        /// Team as @scope:team
+        team: String,
+        /// Package to change (default: the current package)
+        package: Option<String>,
+    },
+}
+
+pub async fn execute(opts: AccessOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let npmrc = Npmrc::load(&cwd);
+    let client = |name: &str| NpmRegistry::logged_in(&npmrc.registry_for(name), &npmrc).with_otp(opts.otp.clone());
+    let package_name = |package: &Option<String>| match package {
+        Some(package) => Ok(package.clone()),
+        None => pack::package_id(&cwd).map(|(name, _)| name),
+    };
+
+    match &opts.action {
+        AccessAction::Public { package } | AccessAction::Restricted { package } => {
+            let access = match opts.action {
+                AccessAction::Public { .. } => "public",
+                _ => "restricted",
+            };
+            let name = package_name(package)?;
+            // Unscoped packages are always public
+            if !name.starts_with('@') {
+                anyhow::bail!("Only scoped packages can change access; {} is always public", name);
+            }
+            client(&name).set_access(&name, access).await?;
+            println!("{} {} is now {}", style(icon("✓")).green(), name, access);
+        }
+        AccessAction::Grant { permissions, team, package } => {
+            let name = package_name(package)?;
+            client(&name).grant_access(team, &name, permissions).await?;
+            println!("{} {} has {} access to {}", style("+").green(), team, permissions, name);
+        }
+        AccessAction::Revoke { team, package } => {
+            let name = package_name(package)?;
+            client(&name).revoke_access(team, &name).await?;
+            println!("{} {} no longer has access to {}", style("-").red(), team, name);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use anyhow::Result;
+use clap::Args;
+use console::style;
+use std::path::Path;
+
+use crate::analyze::{self, Report};
+use crate::cache::TarballStore;
+use crate::dependency;
+use crate::utils::format_size;
+
+#[derive(Args)]
+pub struct AnalyzeOptions {
+    /// Analyze the tarball store instead of the project's node_modules
+    #[arg(long)]
+    store: bool,
+
+    /// How many of the largest packages to list
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+
+    /// Print the report as JSON, e.g. for dashboards
+    #[arg(long)]
+    json: bool,
+}
+
+pub async fn execute(opts: AnalyzeOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+
+    let (label, report) = if opts.store {
+        let store = TarballStore::open()?;
+        let entries = analyze::scan_store(&store)?;
+        (store.root().display().to_string(), analyze::report(&entries, store.root(), &[], opts.top))
+    } else {
+        let node_modules = cwd.join("node_modules");
+        if !node_modules.is_dir() {
+            anyhow::bail!("No node_modules in {}; run `rjs install` first", cwd.display());
+        }
+        let entries = analyze::scan_node_modules(&node_modules)?;
+        let package_json = cwd.join("package.json");
+        let direct: Vec<String> = if package_json.exists() {
+            let package = dependency::read_package_json(&package_json).await?;
+            package.dependencies.into_keys().chain(package.dev_dependencies.into_keys()).collect()
+        } else {
+            Vec::new()
+        };
+        ("node_modules".to_string(), analyze::report(&entries, &cwd, &direct, opts.top))
+    };
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&label, &report, opts.store);
+    }
+    Ok(())
+}
+
+fn print_report(label: &str, report: &Report, store: bool) {
+    println!(
+        "{}: {} packages, {}{}",
+        style(label).bold(),
+        report.packages,
+        format_size(report.total_size),
+        if store { " compressed" } else { "" }
+    );
+
+    if !report.largest.is_empty() {
+        println!("\n{}", style("Largest packages").bold());
+        for entry in &report.largest {
+            println!("  {:>8}  {}@{}", format_size(entry.size), entry.name, entry.version);
+        }
+    }
+
+    if !report.duplicates.is_empty() {
+        println!("\n{}", style("Duplicate versions").bold());
+        for duplicate in &report.duplicates {
+            println!(
+                "  {}  {}  {}",
+                style(&duplicate.name).yellow(),
+                duplicate.versions.join(", "),
+                style(format!("({} copies, {} extra)", duplicate.copies, format_size(duplicate.cost))).dim()
+            );
+        }
+    }
+
+    if !report.direct.is_empty() {
+        println!("\n{}", style("Size by direct dependency").bold());
+        for direct in &report.direct {
+            println!(
+                "  {:>8}  {}  {}",
+                format_size(direct.size),
+                direct.name,
+                style(format!("({} packages)", direct.packages)).dim()
+            );
+        }
+    }
+}
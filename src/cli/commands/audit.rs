@@ -0,0 +1,126 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::audit::{self, Finding, Severity};
+use crate::config;
+use crate::dependency::{self, installer};
+use crate::registry::{Advisory, NpmRegistry};
+use crate::utils::icon;
+use crate::workspace::{self, Workspace};
+
+#[derive(Args)]
+pub struct AuditOptions {
+    /// Fail when advisories at or above this severity are found (default: the audit-level setting)
+    #[arg(long, value_enum)]
+    audit_level: Option<Severity>,
+
+    /// Print the findings as JSON, grouped by workspace
+    #[arg(long)]
+    json: bool,
+
+    /// Registry to check advisories against, instead of the registry setting
+    #[arg(long)]
+    registry: Option<String>,
+}
+
+// A workspace's findings, for --json
+#[derive(Serialize)]
+struct Group<'a> {
+    workspace: &'a str,
+    name: &'a str,
+    findings: Vec<JsonFinding<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonFinding<'a> {
+    name: &'a str,
+    version: &'a str,
+    severity: Severity,
+    advisory: &'a Advisory,
+}
+
+pub async fn execute(opts: AuditOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let (root, members) = workspace::members(&cwd)?;
+    let lockfile = super::fetch::read_lockfile(&root).await?;
+
+    let registry = NpmRegistry::for_registry(opts.registry.as_deref());
+    let report = audit::check_lockfile(&registry, &lockfile)
+        .await
+        .context("Advisory check failed")?;
+
+    // Every workspace shares the root lockfile; each gets the findings in
+    // the part of it its own dependencies lead to
+    let mut groups: Vec<(&Workspace, Vec<&Finding>)> = Vec::new();
+    for member in &members {
+        let package = dependency::read_package_json(&member.dir.join("package.json")).await?;
+        let roots: HashMap<String, String> = package.dependencies.into_iter().chain(package.dev_dependencies).collect();
+        let reachable = installer::reachable(&lockfile, &roots);
+        let findings: Vec<&Finding> = report
+            .findings
+            .iter()
+            .filter(|finding| reachable.contains(format!("{}@{}", finding.name, finding.version).as_str()))
+            .collect();
+        groups.push((member, findings));
+    }
+
+    if opts.json {
+        let groups: Vec<Group> = groups
+            .iter()
+            .map(|(member, findings)| Group {
+                workspace: &member.path,
+                name: &member.name,
+                findings: findings
+                    .iter()
+                    .map(|finding| JsonFinding {
+                        name: &finding.name,
+                        version: &finding.version,
+                        severity: finding.severity,
+                        advisory: &finding.advisory,
+                    })
+                    .collect(),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&groups)?);
+    } else {
+        let grouped = members.len() > 1;
+        for (member, findings) in &groups {
+            if grouped {
+                let summary = match findings.len() {
+                    0 => style("no vulnerabilities".to_string()).green(),
+                    1 => style("1 vulnerability".to_string()).yellow(),
+                    count => style(format!("{} vulnerabilities", count)).yellow(),
+                };
+                println!("{} ({}): {}", style(&member.path).bold(), member.name, summary);
+            }
+            for finding in findings {
+                println!(
+                    "  {} {}@{}: {}{}",
+                    style(finding.severity.label()).bold(),
+                    finding.name,
+                    finding.version,
+                    finding.advisory.title,
+                    finding.advisory.url.as_deref().map(|url| format!(" ({})", url)).unwrap_or_default()
+                );
+            }
+        }
+        let icon = if report.findings.is_empty() {
+            style(icon("✓")).green()
+        } else {
+            style("!").yellow()
+        };
+        println!("{} Audit: {}", icon, report.summary());
+    }
+
+    if let Some(level) = opts.audit_level.or(config::settings().audit_level) {
+        let failing = report.at_or_above(level);
+        if failing > 0 {
+            anyhow::bail!("{} advisories at or above {} severity", failing, level.label());
+        }
+    }
+    Ok(())
+}
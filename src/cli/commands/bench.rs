@@ -0,0 +1,289 @@
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use console::style;
+use log::info;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+use crate::registry::NetworkStats;
+use crate::utils::icon;
+use crate::utils;
+
+#[derive(Args)]
+pub struct BenchOptions {
+    /// Scenarios to run (default: all)
+    #[arg(short, long, value_enum)]
+    scenario: Vec<Scenario>,
+
+    /// Number of measured runs per scenario
+    #[arg(short = 'n', long, default_value_t = 5)]
+    runs: usize,
+
+    /// Package added by the add-one-package scenario
+    #[arg(long, default_value = "lodash")]
+    add: String,
+
+    /// Print results as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Scenario {
+    /// Empty metadata cache, no lockfile, no node_modules
+    ColdInstall,
+    /// Warm metadata cache, no lockfile, no node_modules
+    WarmInstall,
+    /// `install --frozen` from an existing lockfile into an empty node_modules
+    Frozen,
+    /// Add a single package to an already locked project
+    AddOnePackage,
+}
+
+impl Scenario {
+    fn all() -> Vec<Scenario> {
+        vec![
+            Scenario::ColdInstall,
+            Scenario::WarmInstall,
+            Scenario::Frozen,
+            Scenario::AddOnePackage,
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Scenario::ColdInstall => "cold install",
+            Scenario::WarmInstall => "warm install",
+            Scenario::Frozen => "frozen install",
+            Scenario::AddOnePackage => "add one package",
+        }
+    }
+}
+
+// Outcome of a single measured run
+struct RunResult {
+    duration: Duration,
+    network: NetworkStats,
+    disk_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct ScenarioReport {
+    scenario: Scenario,
+    runs: usize,
+    failures: usize,
+    p50_ms: f64,
+    p95_ms: f64,
+    avg_requests: f64,
+    avg_bytes_downloaded: f64,
+    avg_node_modules_bytes: f64,
+}
+
+pub async fn execute(opts: BenchOptions, root: &Path) -> Result<()> {
+    let project_dir = root.to_path_buf();
+    if !project_dir.join("package.json").exists() {
+        return Err(anyhow::anyhow!("No package.json found. Run 'rjs init' first."));
+    }
+
+    let scenarios = if opts.scenario.is_empty() {
+        Scenario::all()
+    } else {
+        opts.scenario.clone()
+    };
+    let runs = opts.runs.max(1);
+
+    let bench_root = utils::get_temp_dir()?.join(format!("bench-{}", std::process::id()));
+    utils::ensure_dir(&bench_root).await?;
+
+    let result = run_benchmarks(&project_dir, &bench_root, &scenarios, runs, &opts).await;
+    let _ = tokio::fs::remove_dir_all(&bench_root).await;
+    let reports = result?;
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+        return Ok(());
+    }
+
+    println!(
+        "\n{:<18} {:>6} {:>11} {:>11} {:>10} {:>12} {:>14}",
+        "scenario",
+        "runs",
+        "p50",
+        "p95",
+        "requests",
+        "downloaded",
+        "node_modules"
+    );
+    for report in &reports {
+        println!(
+            "{:<18} {:>6} {:>9.0}ms {:>9.0}ms {:>10.0} {:>12} {:>14}",
+            report.scenario.name(),
+            format!("{}/{}", report.runs - report.failures, report.runs),
+            report.p50_ms,
+            report.p95_ms,
+            report.avg_requests,
+            format_bytes(report.avg_bytes_downloaded),
+            format_bytes(report.avg_node_modules_bytes),
+        );
+    }
+
+    Ok(())
+}
+
+async fn run_benchmarks(
+    project_dir: &Path,
+    bench_root: &Path,
+    scenarios: &[Scenario],
+    runs: usize,
+    opts: &BenchOptions,
+) -> Result<Vec<ScenarioReport>> {
+    let quiet = opts.json;
+
+    // One untimed install produces the lockfile and warm cache later scenarios reuse
+    if !quiet {
+        println!("{} Preparing warm cache and lockfile...", style(icon("⏱")).cyan());
+    }
+    let warm_cache = bench_root.join("warm-cache");
+    let seed_dir = bench_root.join("seed");
+    copy_project(project_dir, &seed_dir, false).await?;
+    run_rjs(&seed_dir, &warm_cache, &["install", "--no-progress"], bench_root).await?;
+
+    let mut reports = Vec::with_capacity(scenarios.len());
+
+    for scenario in scenarios {
+        info!("Running scenario {}", scenario.name());
+        let mut results = Vec::with_capacity(runs);
+        let mut failures = 0;
+
+        for run in 0..runs {
+            let run_dir = bench_root.join(format!("run-{}", run));
+            let _ = tokio::fs::remove_dir_all(&run_dir).await;
+
+            let (with_lockfile, cache_dir, args): (bool, PathBuf, Vec<&str>) = match scenario {
+                Scenario::ColdInstall => (false, bench_root.join(format!("cold-cache-{}", run)), vec!["install", "--no-progress"]),
+                Scenario::WarmInstall => (false, warm_cache.clone(), vec!["install", "--no-progress"]),
+                Scenario::Frozen => (true, warm_cache.clone(), vec!["install", "--frozen", "--no-progress"]),
+                Scenario::AddOnePackage => (true, warm_cache.clone(), vec!["install", opts.add.as_str(), "--no-progress"]),
+            };
+
+            copy_project(&seed_dir, &run_dir, with_lockfile).await?;
+
+            match run_rjs(&run_dir, &cache_dir, &args, bench_root).await {
+                Ok((duration, network)) => results.push(RunResult {
+                    duration,
+                    network,
+                    disk_bytes: utils::dir_size(&run_dir.join("node_modules")),
+                }),
+                Err(e) => {
+                    failures += 1;
+                    eprintln!("  {} {} run {} failed: {}", style(icon("✗")).red(), scenario.name(), run + 1, e);
+                }
+            }
+
+            let _ = tokio::fs::remove_dir_all(&run_dir).await;
+        }
+
+        if !quiet {
+            println!("  {} {} ({}/{} runs)", style(icon("✓")).green(), scenario.name(), runs - failures, runs);
+        }
+        reports.push(summarize(*scenario, runs, failures, &results));
+    }
+
+    Ok(reports)
+}
+
+fn summarize(scenario: Scenario, runs: usize, failures: usize, results: &[RunResult]) -> ScenarioReport {
+    let mut durations: Vec<f64> = results
+        .iter()
+        .map(|r| r.duration.as_secs_f64() * 1000.0)
+        .collect();
+    durations.sort_by(|a, b| a.total_cmp(b));
+
+    let average = |f: &dyn Fn(&RunResult) -> f64| {
+        if results.is_empty() {
+            0.0
+        } else {
+            results.iter().map(f).sum::<f64>() / results.len() as f64
+        }
+    };
+
+    ScenarioReport {
+        scenario,
+        runs,
+        failures,
+        p50_ms: percentile(&durations, 50.0),
+        p95_ms: percentile(&durations, 95.0),
+        avg_requests: average(&|r| r.network.requests as f64),
+        avg_bytes_downloaded: average(&|r| r.network.bytes_downloaded as f64),
+        avg_node_modules_bytes: average(&|r| r.disk_bytes as f64),
+    }
+}
+
+// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn format_bytes(bytes: f64) -> String {
+    if bytes >= 1024.0 * 1024.0 {
+        format!("{:.1} MB", bytes / 1024.0 / 1024.0)
+    } else {
+        format!("{:.1} KB", bytes / 1024.0)
+    }
+}
+
+async fn copy_project(from: &Path, to: &Path, with_lockfile: bool) -> Result<()> {
+    utils::ensure_dir(to).await?;
+    tokio::fs::copy(from.join("package.json"), to.join("package.json"))
+        .await
+        .context("Failed to copy package.json")?;
+
+    let lockfile = from.join("rjs-lock.json");
+    if with_lockfile && lockfile.exists() {
+        tokio::fs::copy(&lockfile, to.join("rjs-lock.json"))
+            .await
+            .context("Failed to copy rjs-lock.json")?;
+    }
+    Ok(())
+}
+
+// Run this rjs binary in `dir` with an isolated cache and collect its counters
+async fn run_rjs(dir: &Path, cache_dir: &Path, args: &[&str], bench_root: &Path) -> Result<(Duration, NetworkStats)> {
+    let stats_file = bench_root.join("stats.json");
+    let _ = tokio::fs::remove_file(&stats_file).await;
+
+    let start = Instant::now();
+    let status = Command::new(std::env::current_exe()?)
+        .args(args)
+        .current_dir(dir)
+        .env("RJS_CACHE_DIR", cache_dir)
+        .env("RJS_STATS_FILE", &stats_file)
+        .env("RJS_NO_DAEMON", "1")
+        .env("RUST_LOG", "error")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .context("Failed to run rjs")?;
+    let duration = start.elapsed();
+
+    if !status.success() {
+        return Err(anyhow::anyhow!("rjs {} exited with {}", args.join(" "), status));
+    }
+
+    let network = match tokio::fs::read_to_string(&stats_file).await {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(_) => NetworkStats::default(),
+    };
+
+    Ok((duration, network))
+}
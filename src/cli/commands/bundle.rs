@@ -0,0 +1,141 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use console::style;
+use futures::{stream, StreamExt, TryStreamExt};
+use log::info;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::cache::bundle::{self, ExportTarball};
+use crate::cache::{MetadataCache, TarballStore};
+use crate::dependency::events::NoopEvents;
+use crate::registry::NpmRegistry;
+use crate::utils::icon;
+
+#[derive(Args)]
+pub struct BundleOptions {
+    #[command(subcommand)]
+    action: BundleAction,
+}
+
+#[derive(Subcommand)]
+enum BundleAction {
+    /// Pack every tarball and packument in rjs-lock.json into a single archive
+    Export {
+        /// Archive to write
+        file: PathBuf,
+
+        /// Number of concurrent downloads (default: number of CPU cores * 4)
+        #[arg(short = 'j', long)]
+        concurrency: Option<usize>,
+    },
+    /// Load an archive made by `rjs bundle export` into the local cache
+    Import {
+        /// Archive to read
+        file: PathBuf,
+    },
+}
+
+pub async fn execute(opts: BundleOptions, root: &Path) -> Result<()> {
+    match opts.action {
+        BundleAction::Export { file, concurrency } => export(root, file, concurrency).await,
+        BundleAction::Import { file } => import(file).await,
+    }
+}
+
+async fn export(root: &Path, file: PathBuf, concurrency: Option<usize>) -> Result<()> {
+    let start_time = Instant::now();
+    let lockfile = super::fetch::read_lockfile(root).await?;
+    let store = TarballStore::open()?;
+    let registry = NpmRegistry::new();
+
+    let mut seen = HashSet::new();
+    let tarballs: Vec<ExportTarball> = lockfile
+        .packages
+        .values()
+        .filter_map(|entry| {
+            let resolved = entry.resolved.clone()?;
+            seen.insert(store.path_for(&resolved, entry.integrity.as_deref()))
+                .then(|| ExportTarball { resolved, integrity: entry.integrity.clone() })
+        })
+        .collect();
+    let names = lockfile.package_names();
+
+    println!(
+        "{} Bundling {} tarballs and {} packuments from rjs-lock.json",
+        style(icon("📦")).bold().cyan(),
+        style(tarballs.len()).bold(),
+        style(names.len()).bold()
+    );
+
+    // Make sure everything is in the store first, downloading what's missing
+    let concurrency = concurrency.unwrap_or_else(|| num_cpus::get() * 4).max(1);
+    stream::iter(&tarballs)
+        .map(|tarball| {
+            let (store, registry) = (&store, &registry);
+            async move {
+                store
+                    .fetch(registry, &tarball.resolved, tarball.integrity.as_deref(), &tarball.resolved, &NoopEvents)
+                    .await
+            }
+        })
+        .buffer_unordered(concurrency)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let packuments = stream::iter(&names)
+        .map(|name| registry.get_package_info(name))
+        .buffer_unordered(concurrency)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let registry_url = registry.get_registry_url().to_string();
+    let tarball_count = tarballs.len();
+    let packument_count = packuments.len();
+    let output = file.clone();
+    tokio::task::spawn_blocking(move || {
+        bundle::export(&output, &registry_url, &store, &tarballs, &packuments)
+    })
+    .await??;
+
+    let size = std::fs::metadata(&file).map(|meta| meta.len()).unwrap_or(0);
+    info!("Wrote bundle {} ({} bytes)", file.display(), size);
+    println!(
+        "{} Wrote {} ({} tarballs, {} packuments, {:.1} MB) in {:.2}s",
+        style(icon("✅")).green(),
+        style(file.display()).bold(),
+        tarball_count,
+        packument_count,
+        size as f64 / 1024.0 / 1024.0,
+        start_time.elapsed().as_secs_f64()
+    );
+
+    Ok(())
+}
+
+async fn import(file: PathBuf) -> Result<()> {
+    let cache = MetadataCache::shared().ok_or_else(|| {
+        anyhow::anyhow!("Metadata cache is unavailable (is another rjs process using it?)")
+    })?;
+    let store = TarballStore::open()?;
+
+    let path = file.clone();
+    let summary = tokio::task::spawn_blocking(move || bundle::import(&path, &store, &cache)).await??;
+
+    info!("Imported bundle {} from {}", file.display(), summary.registry);
+    println!(
+        "{} Imported {} tarballs and {} packuments from {}",
+        style(icon("✅")).green(),
+        style(summary.tarballs).bold(),
+        style(summary.packuments).bold(),
+        file.display()
+    );
+    println!(
+        "  {} Run {} to install without registry access",
+        style(icon("•")).yellow(),
+        style("rjs install --frozen --offline").bold()
+    );
+
+    Ok(())
+}
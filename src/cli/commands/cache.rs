@@ -0,0 +1,79 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use console::style;
+
+use crate::cache::{MetadataCache, TarballStore};
+use crate::utils::icon;
+
+#[derive(Args)]
+pub struct CacheOptions {
+    #[command(subcommand)]
+    action: CacheAction,
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Show metadata cache and tarball store size and hit rates
+    Stats,
+    /// Remove all cached metadata and tarballs
+    Clean,
+    /// Print the cache directory, or with a flag one of the stores in it
+    Dir {
+        /// The tarball store
+        #[arg(long)]
+        tarballs: bool,
+        /// The metadata cache
+        #[arg(long, conflicts_with = "tarballs")]
+        metadata: bool,
+    },
+}
+
+pub async fn execute(opts: CacheOptions) -> Result<()> {
+    // Paths only, without opening the cache another rjs may be holding
+    let (tarballs, metadata) = match opts.action {
+        CacheAction::Dir { tarballs, metadata } => (tarballs, metadata),
+        action => return manage(action).await,
+    };
+    let dir = &crate::config::settings().cache_dir;
+    let dir = match (tarballs, metadata) {
+        (true, _) => dir.join("tarballs"),
+        (_, true) => dir.join("metadata"),
+        _ => dir.clone(),
+    };
+    println!("{}", dir.display());
+    Ok(())
+}
+
+async fn manage(action: CacheAction) -> Result<()> {
+    let cache = MetadataCache::shared().ok_or_else(|| {
+        anyhow::anyhow!("Metadata cache is unavailable (is another rjs process using it?)")
+    })?;
+    let store = TarballStore::open()?;
+
+    match action {
+        CacheAction::Stats => {
+            let stats = cache.stats()?;
+            println!("{}", style("Metadata cache").bold());
+            println!("  location:     {}", stats.path.display());
+            println!("  packuments:   {}", stats.entries);
+            println!("  size on disk: {:.1} MB", stats.size_on_disk as f64 / 1024.0 / 1024.0);
+            println!("  hits:         {}", stats.hits);
+            println!("  misses:       {}", stats.misses);
+            println!("  hit rate:     {:.1}%", stats.hit_rate() * 100.0);
+
+            let (tarballs, size) = store.stats();
+            println!("{}", style("Tarball store").bold());
+            println!("  location:     {}", store.root().display());
+            println!("  tarballs:     {}", tarballs);
+            println!("  size on disk: {:.1} MB", size as f64 / 1024.0 / 1024.0);
+        }
+        CacheAction::Clean => {
+            cache.clear()?;
+            store.clear().await?;
+            println!("{} Cleared metadata cache and tarball store", style(icon("✓")).green());
+        }
+        CacheAction::Dir { .. } => unreachable!("cache dir doesn't open the cache"),
+    }
+
+    Ok(())
+}
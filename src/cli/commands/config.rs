@@ -0,0 +1,70 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use console::style;
+
+use crate::config::{self, Source};
+
+#[derive(Args)]
+pub struct ConfigOptions {
+    #[command(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Show every setting, its value and where the value came from
+    #[command(alias = "ls")]
+    List {
+        /// Print as JSON: {"key": {"value": ..., "source": ...}}
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print one setting's value
+    Get {
+        key: String,
+    },
+}
+
+pub async fn execute(opts: ConfigOptions) -> Result<()> {
+    let config = config::get();
+    let values = config.values()?;
+    let source = |key: &str| config.sources.get(key).copied().unwrap_or(Source::Default);
+
+    match opts.action {
+        ConfigAction::List { json: true } => {
+            let entries: serde_json::Map<_, _> = values
+                .iter()
+                .map(|(key, value)| {
+                    let entry = serde_json::json!({ "value": value, "source": source(key) });
+                    (key.clone(), entry)
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&entries)?);
+        }
+        ConfigAction::List { json: false } => {
+            for (source, path) in &config.files {
+                let state = if path.exists() { "" } else { " (not found)" };
+                println!("{}", style(format!("; {} config: {}{}", source.label(), path.display(), state)).dim());
+            }
+            for (key, value) in &values {
+                println!("{} = {}  {}", key, value, style(format!("({})", source(key).label())).dim());
+            }
+        }
+        ConfigAction::Get { key } => match values.get(&key) {
+            // Strings print bare so the value can be used in scripts
+            Some(serde_json::Value::String(value)) => println!("{}", value),
+            Some(value) => println!("{}", value),
+            None if is_known(&key) => {}
+            None => anyhow::bail!("Unknown setting \"{}\"; see `rjs config list`", key),
+        },
+    }
+    Ok(())
+}
+
+// Settings without a value (like an unset audit-level) are still valid keys
+fn is_known(key: &str) -> bool {
+    serde_json::to_value(config::Settings::default())
+        .ok()
+        .and_then(|values| values.as_object().map(|values| values.contains_key(key)))
+        .unwrap_or(false)
+}
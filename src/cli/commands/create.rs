@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use log::info;
+use std::path::{Path, PathBuf};
+
+use crate::dependency::DependencyResolver;
+use crate::registry::NpmRegistry;
+use crate::utils::icon;
+use crate::utils;
+
+#[derive(Args)]
+pub struct CreateOptions {
+    /// Initializer to run, e.g. "vite" for create-vite or "@scope/app" for @scope/create-app
+    initializer: String,
+
+    /// Registry to fetch the initializer from, instead of the registry setting
+    #[arg(long)]
+    registry: Option<String>,
+
+    /// Arguments for the initializer
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+}
+
+pub async fn execute(opts: CreateOptions) -> Result<()> {
+    let (name, spec) = initializer_package(&opts.initializer);
+    println!(
+        "{} Fetching {}",
+        style(icon("📦")).bold().cyan(),
+        style(format!("{}@{}", name, spec)).bold()
+    );
+
+    // A throwaway prefix, so nothing lands in the project being created
+    let prefix = utils::get_temp_dir()?.join(format!("create-{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&prefix).await;
+    utils::ensure_dir(&prefix).await?;
+
+    let registry = NpmRegistry::for_registry(opts.registry.as_deref());
+    let result = run_initializer(registry, &prefix, &name, &spec, &opts.args).await;
+    if let Err(e) = tokio::fs::remove_dir_all(&prefix).await {
+        info!("Failed to remove {}: {}", prefix.display(), e);
+    }
+    result
+}
+
+async fn run_initializer(registry: NpmRegistry, prefix: &Path, name: &str, spec: &str, args: &[String]) -> Result<()> {
+    let resolver = DependencyResolver::new(registry);
+    resolver
+        .resolve_and_install(&[(name.to_string(), spec.to_string())], prefix, false, false)
+        .await
+        .with_context(|| format!("Failed to install {}", name))?;
+
+    let package_dir = prefix.join("node_modules").join(name);
+    if !package_dir.exists() {
+        anyhow::bail!("Failed to install {}@{}", name, spec);
+    }
+
+    let bin = initializer_bin(&package_dir, name)?;
+    info!("Running {} {:?}", bin.display(), args);
+
+    let status = tokio::process::Command::new("node")
+        .arg(&bin)
+        .args(args)
+        .status()
+        .await
+        .context("Failed to run node; is Node.js installed and on PATH?")?;
+    if !status.success() {
+        anyhow::bail!("{} failed ({})", name, status);
+    }
+    Ok(())
+}
+
+// The package `npm create` runs for an initializer:
+//   foo -> create-foo, @scope -> @scope/create, @scope/foo -> @scope/create-foo
+// A trailing "@version" carries over to the package
+fn initializer_package(initializer: &str) -> (String, String) {
+    // The scope's leading "@" isn't a version separator
+    let (target, spec) = match initializer.rsplit_once('@') {
+        Some((target, spec)) if !target.is_empty() && !spec.contains('/') => (target, spec),
+        _ => (initializer, "latest"),
+    };
+
+    let name = match target.strip_prefix('@') {
+        Some(scoped) => match scoped.split_once('/') {
+            Some((scope, name)) => format!("@{}/create-{}", scope, name),
+            None => format!("@{}/create", scoped),
+        },
+        None => format!("create-{}", target),
+    };
+    (name, spec.to_string())
+}
+
+// The executable an installed initializer package declares in its "bin":
+// the only one, or the one named after the package
+fn initializer_bin(package_dir: &Path, name: &str) -> Result<PathBuf> {
+    let manifest_path = package_dir.join("package.json");
+    let manifest: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Failed to read {}", manifest_path.display()))?,
+    )
+    .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+
+    let unscoped = name.rsplit('/').next().unwrap_or(name);
+    let bin = match manifest.get("bin") {
+        Some(serde_json::Value::String(bin)) => Some(bin.as_str()),
+        Some(serde_json::Value::Object(bins)) if bins.len() == 1 => bins.values().next().and_then(|bin| bin.as_str()),
+        Some(serde_json::Value::Object(bins)) => bins.get(unscoped).and_then(|bin| bin.as_str()),
+        _ => None,
+    };
+
+    let bin = bin.with_context(|| format!("{} doesn't declare an executable to run", name))?;
+    Ok(package_dir.join(bin))
+}
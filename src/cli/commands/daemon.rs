@@ -0,0 +1,62 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use console::style;
+
+use crate::daemon::{self, DaemonRequest, DaemonResponse};
+use crate::utils::icon;
+
+#[derive(Args)]
+pub struct DaemonOptions {
+    #[command(subcommand)]
+    action: Option<DaemonAction>,
+}
+
+#[derive(Subcommand)]
+enum DaemonAction {
+    /// Run the daemon in the foreground (default)
+    Start,
+    /// Stop a running daemon
+    Stop,
+    /// Show whether a daemon is running and how warm its caches are
+    Status,
+}
+
+pub async fn execute(opts: DaemonOptions) -> Result<()> {
+    let socket = daemon::socket_path()?;
+
+    match opts.action.unwrap_or(DaemonAction::Start) {
+        DaemonAction::Start => {
+            if daemon::send(&socket, &DaemonRequest::Status).await.is_ok() {
+                println!("{} A daemon is already running on {}", style(icon("ℹ")).blue(), socket.display());
+                return Ok(());
+            }
+
+            println!(
+                "{} Daemon listening on {} (Ctrl-C to stop)",
+                style(icon("●")).green(),
+                socket.display()
+            );
+            daemon::serve(&socket).await
+        }
+        DaemonAction::Stop => {
+            match daemon::send(&socket, &DaemonRequest::Shutdown).await {
+                Ok(_) => println!("{} Daemon stopped", style(icon("✓")).green()),
+                Err(_) => println!("{} No daemon is running", style(icon("ℹ")).blue()),
+            }
+            Ok(())
+        }
+        DaemonAction::Status => {
+            match daemon::send(&socket, &DaemonRequest::Status).await {
+                Ok(DaemonResponse::Status(status)) => {
+                    println!("{} Daemon running (pid {})", style(icon("●")).green(), status.pid);
+                    println!("  socket:          {}", socket.display());
+                    println!("  uptime:          {}s", status.uptime_secs);
+                    println!("  cached packages: {}", status.cached_packages);
+                    println!("  requests:        {} ({} cache hits)", status.requests, status.cache_hits);
+                }
+                _ => println!("{} No daemon is running", style(icon("○")).dim()),
+            }
+            Ok(())
+        }
+    }
+}
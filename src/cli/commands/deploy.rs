@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::config;
+use crate::dependency::installer::{self, Installer, Plan};
+use crate::dependency::patches::Patches;
+use crate::dependency::platform::Host;
+use crate::dependency::vendor::Vendor;
+use crate::dependency::{self, lockfile_key_name, DependencyResolver, Lockfile};
+use crate::npmrc::Npmrc;
+use crate::pack::PackList;
+use crate::registry::NpmRegistry;
+use crate::utils::{self, icon};
+use crate::workspace::{self, catalog::Catalogs};
+
+#[derive(Args)]
+pub struct DeployOptions {
+    /// Directory to write the package and its production node_modules to; must be empty
+    outdir: PathBuf,
+
+    #[command(flatten)]
+    target: super::install::TargetOptions,
+}
+
+pub async fn execute(opts: DeployOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    // A workspace deploys from the root's lockfile
+    let root = workspace::find_root(&cwd).unwrap_or_else(|| cwd.clone());
+    let lockfile = super::fetch::read_lockfile(&root).await?;
+    let package = dependency::read_package_json(&cwd.join("package.json")).await?;
+    let files = PackList::load(&cwd)?;
+
+    let outdir = if opts.outdir.is_absolute() { opts.outdir.clone() } else { cwd.join(&opts.outdir) };
+    if outdir.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
+        anyhow::bail!("{} isn't empty", outdir.display());
+    }
+    println!(
+        "{} Deploying {}@{} to {}",
+        style(icon("📦")).bold().cyan(),
+        style(&files.name).bold(),
+        files.version,
+        outdir.display()
+    );
+    let host = opts.target.host();
+    if host != Host::current() {
+        println!("  {} Installing for {}", style(icon("•")).yellow(), style(&host).bold());
+    }
+
+    // The files a publish would include, package.json among them
+    for file in &files.files {
+        let target = outdir.join(&file.path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(cwd.join(&file.path), &target).with_context(|| format!("Failed to copy {}", file.path))?;
+    }
+
+    // Only what the production dependencies lead to
+    let catalogs = Catalogs::load(&root)?;
+    let roots: HashMap<String, String> = package
+        .dependencies
+        .iter()
+        .map(|(name, spec)| Ok((name.clone(), catalogs.resolve(name, spec)?.to_string())))
+        .collect::<Result<_>>()?;
+    let reachable = installer::reachable(&lockfile, &roots);
+    let mut pruned = Lockfile::new(&files.name, &files.version);
+    pruned.packages = lockfile
+        .packages
+        .iter()
+        .filter(|(key, _)| reachable.contains(key.as_str()))
+        .map(|(key, entry)| (key.clone(), entry.clone()))
+        .collect();
+
+    // Installed as any project is: patched, and only the variants built for
+    // the platform it's deployed to
+    let mut resolver = DependencyResolver::new(NpmRegistry::new().with_npmrc(Npmrc::load(&root)))
+        .with_patches(Patches::load(&root)?)
+        .with_host(host.clone())
+        .with_root(&root);
+    if config::settings().vendor
+        && let Some(vendor) = Vendor::load(&root)?
+    {
+        resolver = resolver.with_vendor(vendor);
+    }
+    let plan = Plan {
+        install: pruned
+            .packages
+            .iter()
+            .map(|(key, entry)| Arc::new(entry.to_package(lockfile_key_name(key))))
+            .collect(),
+        remove: Vec::new(),
+        lockfile: None,
+        graph: None,
+    };
+    Installer::new(&resolver, &outdir).apply(plan).await?;
+    resolver.save_lockfile(&pruned, &outdir).await?;
+    super::install::build_native_packages(&outdir, &host).await?;
+
+    let node_modules = outdir.join("node_modules");
+    let copied = resolve_symlinks(&node_modules)?;
+    if copied > 0 {
+        println!("  {} Replaced {} symlinks with what they point to", style(icon("•")).cyan(), copied);
+    }
+
+    println!(
+        "{} Deployed {} files and {} packages ({})",
+        style(icon("✅")).green(),
+        style(files.files.len()).bold(),
+        style(pruned.packages.len()).bold(),
+        utils::format_size(utils::dir_size(&outdir))
+    );
+    Ok(())
+}
+
+// Replace the symlinks under `dir` with copies of their targets, so the bundle
+// doesn't depend on anything outside it. node_modules/.bin keeps its links,
+// which are relative and point inside the bundle
+fn resolve_symlinks(dir: &Path) -> Result<usize> {
+    let links: Vec<PathBuf> = walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".bin")
+        .flatten()
+        .filter(|entry| entry.path_is_symlink())
+        .map(|entry| entry.into_path())
+        .collect();
+
+    for link in &links {
+        let target = std::fs::canonicalize(link).with_context(|| format!("{} is a broken symlink", link.display()))?;
+        std::fs::remove_file(link).with_context(|| format!("Failed to remove {}", link.display()))?;
+        if target.is_dir() {
+            for entry in walkdir::WalkDir::new(&target).follow_links(true) {
+                let entry = entry?;
+                let copy = link.join(entry.path().strip_prefix(&target)?);
+                if entry.file_type().is_dir() {
+                    std::fs::create_dir_all(&copy)?;
+                } else {
+                    std::fs::copy(entry.path(), &copy)?;
+                }
+            }
+        } else {
+            std::fs::copy(&target, link).with_context(|| format!("Failed to copy {}", target.display()))?;
+        }
+    }
+    Ok(links.len())
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use console::style;
+use std::path::Path;
+
+use crate::dependency::versions;
+use crate::npmrc::Npmrc;
+use crate::pack;
+use crate::registry::NpmRegistry;
+
+#[derive(Args)]
+pub struct DistTagOptions {
+    #[command(subcommand)]
+    action: DistTagAction,
+
+    /// One-time password from your authenticator, for accounts with two-factor auth
+    #[arg(long, global = true)]
+    otp: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum DistTagAction {
+    /// List a package's dist-tags (default: the current package)
+    Ls {
+        package: Option<String>,
+    },
+    /// Point a tag at a version, e.g. `rjs dist-tag add pkg@2.0.0 next`
+    Add {
+        /// Package and version to tag
+        package: String,
+        tag: String,
+    },
+    /// Remove a tag from a package
+    Rm {
+        package: String,
+        tag: String,
+    },
+}
+
+pub async fn execute(opts: DistTagOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let npmrc = Npmrc::load(&cwd);
+    let client = |name: &str| NpmRegistry::logged_in(&npmrc.registry_for(name), &npmrc).with_otp(opts.otp.clone());
+
+    match &opts.action {
+        DistTagAction::Ls { package } => {
+            let name = match package {
+                Some(package) => package.clone(),
+                None => pack::package_id(&cwd)?.0,
+            };
+            for (tag, version) in client(&name).dist_tags(&name).await? {
+                println!("{}: {}", tag, version);
+            }
+        }
+        DistTagAction::Add { package, tag } => {
+            // The scope's leading "@" isn't a version separator
+            let Some((name, version)) = package.rsplit_once('@').filter(|(name, _)| !name.is_empty()) else {
+                anyhow::bail!("Give the version to tag, e.g. {}@1.0.0", package);
+            };
+            if versions::parse_version(version).is_none() {
+                anyhow::bail!("\"{}\" is not a version", version);
+            }
+            if versions::parse_req(tag).is_some() {
+                anyhow::bail!("Tag \"{}\" looks like a version range; pick a name like \"next\"", tag);
+            }
+            client(name).add_dist_tag(name, version, tag).await?;
+            println!("{} {}: {}@{}", style("+").green(), tag, name, version);
+        }
+        DistTagAction::Rm { package, tag } => {
+            if tag == "latest" {
+                anyhow::bail!("The latest tag can't be removed; point it at another version instead");
+            }
+            client(package).remove_dist_tag(package, tag).await?;
+            println!("{} {}: {}", style("-").red(), tag, package);
+        }
+    }
+    Ok(())
+}
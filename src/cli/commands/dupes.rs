@@ -0,0 +1,167 @@
+use anyhow::Result;
+use clap::Args;
+use console::style;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::path::Path;
+
+use crate::dependency::graph::Graph;
+use crate::dependency::{self, versions};
+use crate::utils::icon;
+
+#[derive(Args)]
+pub struct DupesOptions {
+    /// Only report these packages
+    packages: Vec<String>,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+// A package locked at more than one version
+#[derive(Serialize)]
+struct Duplicate {
+    name: String,
+    versions: Vec<String>,
+    requests: Vec<Request>,
+    fix: Fix,
+}
+
+// One dependent's range for the duplicated package
+#[derive(Serialize)]
+struct Request {
+    from: String,
+    range: String,
+    // The locked version the range picks, if any
+    resolved: Option<String>,
+}
+
+// How the versions could be collapsed into one
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Fix {
+    // Every range accepts one of the locked versions
+    Dedupe { version: String },
+    // The ranges conflict; only forcing a version past some of them helps
+    Override { version: String },
+}
+
+pub async fn execute(opts: DupesOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let lockfile = super::fetch::read_lockfile(&cwd).await?;
+
+    let mut locked: BTreeMap<&str, Vec<Arc<semver::Version>>> = BTreeMap::new();
+    for (key, entry) in &lockfile.packages {
+        if let Some(version) = versions::parse_version(&entry.version) {
+            locked.entry(dependency::lockfile_key_name(key)).or_default().push(version);
+        }
+    }
+
+    // Who asks for what: the project itself, then every locked package. What
+    // each got comes from the saved graph when it's there, or else is worked
+    // out from the ranges
+    let graph = Graph::load(&cwd);
+    let mut dependents: Vec<(String, &HashMap<String, String>)> = Vec::new();
+    let package_json = cwd.join("package.json");
+    let root = if package_json.exists() {
+        Some(dependency::read_package_json(&package_json).await?)
+    } else {
+        None
+    };
+    if let Some(root) = &root {
+        dependents.push(("package.json".to_string(), &root.dependencies));
+        dependents.push(("package.json (dev)".to_string(), &root.dev_dependencies));
+    }
+    let mut keys: Vec<&String> = lockfile.packages.keys().collect();
+    keys.sort();
+    for key in keys {
+        dependents.push((key.clone(), &lockfile.packages[key].dependencies));
+    }
+
+    let mut duplicates = Vec::new();
+    for (name, mut found) in locked {
+        found.sort();
+        found.dedup();
+        if found.len() < 2 || (!opts.packages.is_empty() && !opts.packages.iter().any(|wanted| wanted == name)) {
+            continue;
+        }
+
+        let mut ranges = Vec::new();
+        let requests: Vec<Request> = dependents
+            .iter()
+            .filter_map(|(from, dependencies)| {
+                let range = dependencies.get(name)?;
+                let parsed = versions::parse_req_or_any(range);
+                let resolved = match &graph {
+                    Some(graph) => {
+                        let from = from.strip_prefix("package.json").map_or(Some(from.as_str()), |_| None);
+                        graph.edges(from).and_then(|edges| edges.get(name)).map(|edge| edge.version().to_string())
+                    }
+                    None => found.iter().rev().find(|version| parsed.matches(version)).map(|version| version.to_string()),
+                };
+                ranges.push(parsed);
+                Some(Request {
+                    from: from.clone(),
+                    range: range.clone(),
+                    resolved,
+                })
+            })
+            .collect();
+
+        // Prefer the newest version that every range accepts
+        let fix = match found
+            .iter()
+            .rev()
+            .find(|version| ranges.iter().all(|range| range.matches(version)))
+        {
+            Some(version) => Fix::Dedupe { version: version.to_string() },
+            None => Fix::Override { version: found[found.len() - 1].to_string() },
+        };
+
+        duplicates.push(Duplicate {
+            name: name.to_string(),
+            versions: found.iter().map(|version| version.to_string()).collect(),
+            requests,
+            fix,
+        });
+    }
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&duplicates)?);
+        return Ok(());
+    }
+
+    if duplicates.is_empty() {
+        println!("{} No package is locked at more than one version", style(icon("✓")).green());
+        return Ok(());
+    }
+
+    for duplicate in &duplicates {
+        println!("{}  {}", style(&duplicate.name).bold().yellow(), duplicate.versions.join(", "));
+        for request in &duplicate.requests {
+            println!(
+                "  {} wants {} {}",
+                request.from,
+                style(&request.range).cyan(),
+                style(format!("-> {}", request.resolved.as_deref().unwrap_or("none of them"))).dim()
+            );
+        }
+        match &duplicate.fix {
+            Fix::Dedupe { version } => println!(
+                "  {} every range accepts {}; a dedupe would collapse them",
+                style(icon("✓")).green(),
+                version
+            ),
+            Fix::Override { version } => println!(
+                "  {} the ranges conflict; only an override (e.g. to {}) would collapse them",
+                style(icon("✗")).red(),
+                version
+            ),
+        }
+        println!();
+    }
+    println!("{} packages locked at more than one version", duplicates.len());
+    Ok(())
+}
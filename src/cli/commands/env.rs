@@ -0,0 +1,127 @@
+use anyhow::Result;
+use clap::Args;
+use console::style;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::config::{self, Source};
+use crate::npmrc::{self, Npmrc};
+
+#[derive(Args)]
+pub struct EnvOptions {
+    /// Show the setup as a command given --registry would see it
+    #[arg(long)]
+    registry: Option<String>,
+
+    /// Print as JSON: {"section": {"key": {"value": ..., "source": ...}}}
+    #[arg(long)]
+    json: bool,
+}
+
+// A value in effect and where it came from: a flag, the environment, a
+// config or .npmrc file, or rjs's default
+#[derive(Serialize)]
+struct Entry {
+    value: serde_json::Value,
+    source: String,
+    // Whether requests to this registry carry a token; never the token itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auth: Option<bool>,
+}
+
+pub async fn execute(opts: EnvOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let config = config::get();
+    let settings = &config.settings;
+    let npmrc = Npmrc::load(&cwd);
+    let values = config.values()?;
+
+    let setting = |key: &str| {
+        let source = config.sources.get(key).copied().unwrap_or(Source::Default);
+        Entry {
+            value: values.get(key).cloned().unwrap_or(serde_json::Value::Null),
+            source: source.label().to_string(),
+            auth: None,
+        }
+    };
+    let derived = |value: String, from: &str| Entry {
+        value: serde_json::Value::String(value),
+        source: from.to_string(),
+        auth: None,
+    };
+
+    // Where each package goes: the default registry, or its scope's, with a
+    // token when .npmrc has one for it, as install fetches them
+    let mut registry = match &opts.registry {
+        Some(url) => derived(url.trim_end_matches('/').to_string(), "flag"),
+        None => setting("registry"),
+    };
+    let default_url = registry.value.as_str().unwrap_or_default().to_string();
+    registry.auth = Some(npmrc.auth_token(&default_url).is_some());
+    let mut routing = vec![("registry".to_string(), registry)];
+    for (scope, url) in npmrc.scope_registries() {
+        let file = if npmrc.project(&format!("{}:registry", scope)).is_some() { "project .npmrc" } else { "user .npmrc" };
+        let url = url.trim_end_matches('/');
+        let mut entry = derived(url.to_string(), file);
+        entry.auth = Some(npmrc.auth_token(url).is_some());
+        routing.push((scope.to_string(), entry));
+    }
+
+    let section = |keys: &[&str]| -> Vec<(String, Entry)> {
+        keys.iter().map(|key| (key.to_string(), setting(key))).collect()
+    };
+    // The stores live in the cache directory, wherever it was set
+    let mut paths = section(&["cache-dir"]);
+    let (cache_dir, from) = (&settings.cache_dir, paths[0].1.source.clone());
+    paths.push(("tarballs".to_string(), derived(cache_dir.join("tarballs").display().to_string(), &from)));
+    paths.push(("metadata".to_string(), derived(cache_dir.join("metadata").display().to_string(), &from)));
+
+    let sections = [
+        ("registry", routing),
+        ("network", section(&["proxy", "https-proxy", "no-proxy", "http3", "network-family", "prewarm-connections", "mirrors"])),
+        ("paths", paths),
+        (
+            "install",
+            section(&["concurrency", "network-concurrency", "extract-concurrency", "batch-size", "max-packages-in-memory", "vendor", "audit", "audit-level", "fund"]),
+        ),
+    ];
+
+    if opts.json {
+        let json: BTreeMap<&str, BTreeMap<&str, &Entry>> = sections
+            .iter()
+            .map(|(name, entries)| (*name, entries.iter().map(|(key, entry)| (key.as_str(), entry)).collect()))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
+    let mut files: Vec<(String, std::path::PathBuf)> =
+        config.files.iter().map(|(source, path)| (format!("{} config", source.label()), path.clone())).collect();
+    files.push(("project .npmrc".to_string(), cwd.join(".npmrc")));
+    if let Some(path) = npmrc::user_path() {
+        files.push(("user .npmrc".to_string(), path));
+    }
+    for (label, path) in &files {
+        let state = if path.exists() { "" } else { " (not found)" };
+        println!("{}", style(format!("; {}: {}{}", label, path.display(), state)).dim());
+    }
+
+    for (name, entries) in &sections {
+        println!("\n{}", style(name).bold());
+        for (key, entry) in entries {
+            let value = match &entry.value {
+                serde_json::Value::Null => style("(unset)".to_string()).dim().to_string(),
+                serde_json::Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            let auth = match entry.auth {
+                Some(true) => "  auth: token",
+                Some(false) => "  auth: none",
+                None => "",
+            };
+            println!("  {} = {}{}  {}", key, value, auth, style(format!("({})", entry.source)).dim());
+        }
+    }
+    Ok(())
+}
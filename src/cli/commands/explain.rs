@@ -0,0 +1,256 @@
+use anyhow::Result;
+use clap::Args;
+use console::style;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::analyze;
+use crate::dependency::graph::Graph;
+use crate::dependency::{self, versions, Lockfile};
+
+#[derive(Args)]
+pub struct ExplainOptions {
+    /// The locked package: name@version, name@range, or a name for every locked version of it
+    package: String,
+
+    /// Print as JSON: [{"name", "version", "resolved", "integrity", "dependents", "installed"}]
+    #[arg(long)]
+    json: bool,
+}
+
+// One lockfile entry and how it got there
+#[derive(Serialize)]
+struct Explanation<'a> {
+    name: &'a str,
+    version: &'a str,
+    resolved: Option<&'a str>,
+    integrity: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    registry: Option<&'a str>,
+    dependents: Vec<Dependent>,
+    installed: Vec<Placement>,
+}
+
+// A package.json or lockfile entry whose range picks the explained version
+#[derive(Serialize)]
+struct Dependent {
+    from: String,
+    range: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    optional: bool,
+}
+
+// Where the version sits in node_modules
+#[derive(Serialize)]
+struct Placement {
+    path: String,
+    hoisted: bool,
+}
+
+pub async fn execute(opts: ExplainOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let lockfile = super::fetch::read_lockfile(&cwd).await?;
+    let (name, wanted) = match opts.package.rsplit_once('@') {
+        Some((name, version)) if !name.is_empty() => (name, Some(version)),
+        _ => (opts.package.as_str(), None),
+    };
+
+    let mut locked: Vec<&str> = lockfile
+        .packages
+        .iter()
+        .filter(|(key, _)| dependency::lockfile_key_name(key) == name)
+        .map(|(_, entry)| entry.version.as_str())
+        .collect();
+    locked.sort_by_key(|version| versions::parse_version(version));
+    if locked.is_empty() {
+        anyhow::bail!("{} isn't in rjs-lock.json", name);
+    }
+    let explained: Vec<&str> = match wanted {
+        Some(wanted) if locked.contains(&wanted) => vec![wanted],
+        Some(wanted) => {
+            let range = versions::parse_req_or_any(wanted);
+            locked
+                .iter()
+                .copied()
+                .filter(|version| versions::parse_version(version).is_some_and(|version| range.matches(&version)))
+                .collect()
+        }
+        None => locked.clone(),
+    };
+    if explained.is_empty() {
+        anyhow::bail!("rjs-lock.json has {} at {}, not {}", name, locked.join(", "), wanted.unwrap_or_default());
+    }
+
+    let requests = requests(&cwd, &lockfile, name)?;
+    let graph = Graph::load(&cwd);
+    let installed: Vec<analyze::Entry> = analyze::scan_node_modules(&cwd.join("node_modules"))?
+        .into_iter()
+        .filter(|entry| entry.name == name)
+        .collect();
+    // The version Node finds from the project, whichever it is
+    let top = cwd.join("node_modules").join(name);
+    let top_version = installed.iter().find(|copy| copy.path == top).map(|copy| copy.version.as_str());
+
+    let explanations: Vec<Explanation> = explained
+        .iter()
+        .map(|version| {
+            let id = format!("{}@{}", name, version);
+            let entry = &lockfile.packages[&id];
+            let dependents = match &graph {
+                // The edges resolution took, when they're saved for this lockfile
+                Some(graph) => dependents_in(graph, &id),
+                None => requests
+                    .iter()
+                    .filter(|(_, range, _)| picks(&locked, range) == Some(*version))
+                    .map(|(from, range, optional)| Dependent {
+                        from: from.clone(),
+                        range: range.clone(),
+                        optional: *optional,
+                    })
+                    .collect(),
+            };
+            let installed = installed
+                .iter()
+                .filter(|copy| copy.version == *version)
+                .map(|copy| {
+                    let path = copy.path.strip_prefix(&cwd).unwrap_or(&copy.path).to_string_lossy().replace('\\', "/");
+                    Placement {
+                        hoisted: path == format!("node_modules/{}", name),
+                        path,
+                    }
+                })
+                .collect();
+            Explanation {
+                name,
+                version,
+                resolved: entry.resolved.as_deref(),
+                integrity: entry.integrity.as_deref(),
+                license: entry.license.as_deref(),
+                registry: entry.registry.as_deref(),
+                dependents,
+                installed,
+            }
+        })
+        .collect();
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&explanations)?);
+        return Ok(());
+    }
+    for (i, explanation) in explanations.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print(explanation, top_version);
+    }
+    Ok(())
+}
+
+// Every range asking for `name`: the project's own, then each lockfile
+// entry's, as (who, range, through optionalDependencies)
+fn requests(cwd: &std::path::Path, lockfile: &Lockfile, name: &str) -> Result<Vec<(String, String, bool)>> {
+    let mut requests = Vec::new();
+    let package_json = cwd.join("package.json");
+    if package_json.exists() {
+        let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&package_json)?)?;
+        for (field, label) in [
+            ("dependencies", "package.json"),
+            ("devDependencies", "package.json (dev)"),
+            ("optionalDependencies", "package.json"),
+        ] {
+            if let Some(range) = manifest.get(field).and_then(|deps| deps.get(name)).and_then(|range| range.as_str()) {
+                requests.push((label.to_string(), range.to_string(), field == "optionalDependencies"));
+            }
+        }
+    }
+
+    let entries: BTreeMap<&String, _> = lockfile.packages.iter().collect();
+    for (key, entry) in entries {
+        let ranges: [(&HashMap<String, String>, bool); 2] =
+            [(&entry.dependencies, false), (&entry.optional_dependencies, true)];
+        for (dependencies, optional) in ranges {
+            if let Some(range) = dependencies.get(name) {
+                requests.push((key.clone(), range.clone(), optional));
+            }
+        }
+    }
+    Ok(requests)
+}
+
+// Everything whose range resolved to `id`, the project first
+fn dependents_in(graph: &Graph, id: &str) -> Vec<Dependent> {
+    let roots = graph.roots.values().map(|edge| {
+        let from = if edge.dev { "package.json (dev)" } else { "package.json" };
+        (from.to_string(), edge)
+    });
+    let packages = graph
+        .packages
+        .iter()
+        .flat_map(|(from, edges)| edges.values().map(move |edge| (from.clone(), edge)));
+    roots
+        .chain(packages)
+        .filter(|(_, edge)| edge.to == id)
+        .map(|(from, edge)| Dependent {
+            from,
+            range: edge.range.clone(),
+            optional: edge.optional,
+        })
+        .collect()
+}
+
+// The locked version `range` gets: the highest it accepts, or with none the
+// highest of all, as installs from the lockfile pick them
+fn picks<'a>(locked: &[&'a str], range: &str) -> Option<&'a str> {
+    let Some(range) = versions::parse_req(range) else {
+        return locked.last().copied();
+    };
+    let matching = locked
+        .iter()
+        .rev()
+        .find(|version| versions::parse_version(version).is_some_and(|version| range.matches(&version)));
+    matching.or(locked.last()).copied()
+}
+
+fn print(explanation: &Explanation, top_version: Option<&str>) {
+    println!("{}", style(format!("{}@{}", explanation.name, explanation.version)).bold());
+    let mut fields = vec![
+        ("resolved", explanation.resolved.unwrap_or("(not recorded)").to_string()),
+        ("integrity", explanation.integrity.unwrap_or("(not recorded)").to_string()),
+    ];
+    if let Some(license) = explanation.license {
+        fields.push(("license", license.to_string()));
+    }
+    if let Some(registry) = explanation.registry {
+        fields.push(("registry", registry.to_string()));
+    }
+    for (label, value) in fields {
+        println!("  {:<10} {}", label, value);
+    }
+
+    if explanation.installed.is_empty() {
+        // Say what's there instead, if anything
+        let note = match top_version {
+            Some(other) => format!("not installed (node_modules/{} has {})", explanation.name, other),
+            None => "not installed".to_string(),
+        };
+        println!("  {:<10} {}", "installed", style(note).yellow());
+    }
+    for placement in &explanation.installed {
+        let how = if placement.hoisted { "hoisted" } else { "nested" };
+        println!("  {:<10} {} {}", "installed", placement.path, style(format!("({})", how)).dim());
+    }
+
+    if explanation.dependents.is_empty() {
+        println!("  {}", style("Nothing in package.json or rjs-lock.json depends on this version").dim());
+        return;
+    }
+    println!("  depended on by:");
+    let width = explanation.dependents.iter().map(|dependent| dependent.from.len()).max().unwrap_or(0);
+    for dependent in &explanation.dependents {
+        let optional = if dependent.optional { style(" (optional)").dim().to_string() } else { String::new() };
+        println!("    {:<width$}  {}{}", dependent.from, dependent.range, optional, width = width);
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use futures::{stream, StreamExt};
+use indicatif::ProgressBar;
+use log::{info, warn};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::cache::{StoreOutcome, TarballStore};
+use crate::dependency::events::NoopEvents;
+use crate::dependency::Lockfile;
+use crate::npmrc::Npmrc;
+use crate::registry::NpmRegistry;
+use crate::utils::icon;
+use crate::utils;
+
+#[derive(Args)]
+pub struct FetchOptions {
+    /// Number of concurrent downloads (default: number of CPU cores * 4)
+    #[arg(short = 'j', long)]
+    concurrency: Option<usize>,
+
+    /// Skip progress display for non-interactive runs
+    #[arg(long)]
+    no_progress: bool,
+}
+
+pub async fn execute(opts: FetchOptions, root: &Path) -> Result<()> {
+    let start_time = Instant::now();
+    let lockfile = read_lockfile(root).await?;
+    let store = TarballStore::open()?;
+    let registry = NpmRegistry::new().with_npmrc(Npmrc::load(root));
+
+    // The same tarball can be locked under several keys; fetch it once
+    let mut seen = HashSet::new();
+    let mut tarballs = Vec::new();
+    let mut skipped = 0;
+    for (key, entry) in &lockfile.packages {
+        match &entry.resolved {
+            Some(url) => {
+                if seen.insert(store.path_for(url, entry.integrity.as_deref())) {
+                    tarballs.push((key.clone(), url.clone(), entry.integrity.clone()));
+                }
+            }
+            None => skipped += 1,
+        }
+    }
+
+    info!("Fetching {} tarballs into {}", tarballs.len(), store.root().display());
+    println!(
+        "{} Fetching {} packages from rjs-lock.json",
+        style(icon("📦")).bold().cyan(),
+        style(tarballs.len()).bold()
+    );
+
+    let progress_bar = if !opts.no_progress && atty::is(atty::Stream::Stdout) {
+        let bar = super::install::create_progress_bar();
+        bar.set_length(tarballs.len() as u64);
+        bar.set_message("Fetching tarballs...");
+        bar
+    } else {
+        ProgressBar::hidden()
+    };
+
+    let concurrency = opts.concurrency.unwrap_or_else(|| num_cpus::get() * 4).max(1);
+    let mut results = stream::iter(tarballs)
+        .map(|(key, url, integrity)| {
+            let store = &store;
+            let registry = &registry;
+            async move {
+                let result = store.fetch(registry, &url, integrity.as_deref(), &key, &NoopEvents).await;
+                (key, result)
+            }
+        })
+        .buffer_unordered(concurrency);
+
+    let (mut downloaded, mut cached, mut failed) = (0, 0, 0);
+    while let Some((key, result)) = results.next().await {
+        match result {
+            Ok((_, StoreOutcome::Downloaded)) => downloaded += 1,
+            Ok((_, StoreOutcome::Cached)) => cached += 1,
+            Err(e) => {
+                failed += 1;
+                warn!("Failed to fetch {}: {:#}", key, e);
+                progress_bar.println(format!("{} {}: {:#}", style(icon("✗")).red(), key, e));
+            }
+        }
+        progress_bar.inc(1);
+    }
+    progress_bar.finish_and_clear();
+
+    if skipped > 0 {
+        println!(
+            "  {} {} entries have no resolved tarball and were skipped",
+            style(icon("•")).yellow(),
+            skipped
+        );
+    }
+
+    if failed > 0 {
+        return Err(anyhow::anyhow!("Failed to fetch {} packages", failed));
+    }
+
+    println!(
+        "{} Fetched {} packages ({} already cached) in {:.2}s",
+        style(icon("✅")).green(),
+        style(downloaded).bold(),
+        cached,
+        start_time.elapsed().as_secs_f64()
+    );
+
+    Ok(())
+}
+
+// The project's rjs-lock.json, which commands working from the lockfile require
+pub(super) async fn read_lockfile(cwd: &Path) -> Result<Lockfile> {
+    let lockfile_path = cwd.join("rjs-lock.json");
+
+    if !lockfile_path.exists() {
+        return Err(anyhow::anyhow!(
+            "No rjs-lock.json found. Run 'rjs install --lockfile-only' first."
+        ));
+    }
+
+    let content = utils::read_file_string(&lockfile_path).await?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", lockfile_path.display()))
+}
@@ -0,0 +1,60 @@
+use anyhow::Result;
+use clap::Args;
+use console::style;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::dependency::{self, funding};
+
+#[derive(Args)]
+pub struct FundOptions {
+    /// Only show where to fund this installed package
+    package: Option<String>,
+
+    /// Print as JSON: [{"name", "version", "sources": [{"type", "url"}]}]
+    #[arg(long)]
+    json: bool,
+}
+
+pub async fn execute(opts: FundOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let lockfile = super::fetch::read_lockfile(&cwd).await?;
+    let names: BTreeSet<&str> = lockfile.packages.keys().map(|key| dependency::lockfile_key_name(key)).collect();
+
+    let node_modules = cwd.join("node_modules");
+    let funding = match &opts.package {
+        Some(name) => {
+            if !names.contains(name.as_str()) {
+                anyhow::bail!("{} is not installed", name);
+            }
+            funding::read(&node_modules, name).into_iter().collect()
+        }
+        None => funding::collect(&node_modules, names),
+    };
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&funding)?);
+        return Ok(());
+    }
+    if funding.is_empty() {
+        match &opts.package {
+            Some(name) => println!("{} doesn't declare any funding", name),
+            None => println!("No installed packages are looking for funding"),
+        }
+        return Ok(());
+    }
+
+    // Maintainers often share one sponsor page across their packages, so
+    // list each URL once with everything it funds
+    let mut by_url: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for package in &funding {
+        for source in &package.sources {
+            by_url.entry(&source.url).or_default().push(format!("{}@{}", package.name, package.version));
+        }
+    }
+    for (url, packages) in by_url {
+        println!("{}", style(url).cyan().underlined());
+        println!("  {}", style(packages.join(", ")).dim());
+    }
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::path::Path;
+
+use crate::dependency::graph::{Edge, Graph};
+
+#[derive(Args)]
+pub struct GraphOptions {
+    /// Print the saved graph as JSON instead of Graphviz DOT
+    #[arg(long)]
+    json: bool,
+}
+
+pub async fn execute(opts: GraphOptions, root: &Path) -> Result<()> {
+    let graph = Graph::load(root)
+        .context("No dependency graph saved for rjs-lock.json; run `rjs install` to save one")?;
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&graph)?);
+        return Ok(());
+    }
+
+    // The project is named after its package.json, like npm's tree root
+    let project = std::fs::read_to_string(root.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|manifest| manifest.get("name")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| "project".to_string());
+
+    println!("digraph dependencies {{");
+    for edge in graph.roots.values() {
+        print_edge(&project, edge);
+    }
+    for (from, edges) in &graph.packages {
+        for edge in edges.values() {
+            print_edge(from, edge);
+        }
+    }
+    println!("}}");
+    Ok(())
+}
+
+// One DOT edge labelled with its range; dev edges are dashed and optional
+// ones dotted
+fn print_edge(from: &str, edge: &Edge) {
+    let style = match (edge.dev, edge.optional) {
+        (true, _) => ", style=dashed",
+        (_, true) => ", style=dotted",
+        _ => "",
+    };
+    println!("  {:?} -> {:?} [label={:?}{}];", from, edge.to, edge.range, style);
+}
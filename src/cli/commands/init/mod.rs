@@ -0,0 +1,606 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use dialoguer::{Confirm, Input};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::i18n::tr;
+use crate::npmrc::Npmrc;
+
+#[derive(Args)]
+pub struct InitOptions {
+    /// Skip prompts and use defaults
+    #[arg(short, long)]
+    yes: bool,
+
+    /// SPDX identifier for the license field (default: ISC)
+    #[arg(long)]
+    license: Option<String>,
+
+    /// Also write a LICENSE file with the text of the chosen license
+    #[arg(long)]
+    license_file: bool,
+
+    /// Make an ES module package: "type": "module", plus "module" and "exports" fields
+    #[arg(long)]
+    esm: bool,
+
+    /// Set up TypeScript: tsconfig.json, src/index.ts and a build script compiling to dist/
+    #[arg(long, visible_alias = "ts")]
+    typescript: bool,
+
+    /// Scope the package name, e.g. "@org" for @org/name
+    #[arg(long)]
+    scope: Option<String>,
+
+    /// Mark the package "private" so it can never be published
+    #[arg(long, conflicts_with = "access")]
+    private: bool,
+
+    /// Access level for publishing a scoped package (default: public)
+    #[arg(long, value_parser = ["public", "restricted"])]
+    access: Option<String>,
+}
+
+// License texts rjs can write; <year> and <copyright holders> get filled in
+const LICENSE_TEMPLATES: &[(&str, &str)] = &[
+    ("MIT", include_str!("licenses/MIT.txt")),
+    ("ISC", include_str!("licenses/ISC.txt")),
+    ("0BSD", include_str!("licenses/0BSD.txt")),
+    ("BSD-2-Clause", include_str!("licenses/BSD-2-Clause.txt")),
+    ("BSD-3-Clause", include_str!("licenses/BSD-3-Clause.txt")),
+    ("Unlicense", include_str!("licenses/Unlicense.txt")),
+];
+
+#[derive(Serialize, Deserialize)]
+struct PackageJson {
+    name: String,
+    version: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    private: bool,
+    description: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    module_type: Option<String>,
+    main: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    module: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    types: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exports: Option<Exports>,
+    scripts: Scripts,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repository: Option<Repository>,
+    author: String,
+    license: String,
+    #[serde(rename = "publishConfig", skip_serializing_if = "Option::is_none")]
+    publish_config: Option<PublishConfig>,
+    dependencies: serde_json::Value,
+    #[serde(rename = "devDependencies")]
+    dev_dependencies: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Scripts {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    build: Option<String>,
+    test: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Exports {
+    #[serde(rename = ".")]
+    root: ExportTarget,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ExportTarget {
+    File(String),
+    // Fields serialize in order, and TypeScript only honors "types" first
+    Conditions {
+        types: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        import: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        require: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct PublishConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    access: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    registry: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Repository {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+}
+
+// What the environment suggests for the fields users rarely want to type
+struct Defaults {
+    name: String,
+    author: String,
+    repository: Option<String>,
+    license: String,
+    main: String,
+}
+
+pub async fn execute(opts: InitOptions, root: &Path) -> Result<()> {
+    info!("Initializing new package.json");
+
+    let cwd = root.to_path_buf();
+    let package_path = cwd.join("package.json");
+
+    if package_path.exists() && !opts.yes {
+        let overwrite = Confirm::new()
+            .with_prompt(tr!("init-overwrite"))
+            .default(false)
+            .interact()?;
+
+        if !overwrite {
+            info!("Aborted");
+            return Ok(());
+        }
+    }
+
+    let folder_name = cwd
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("my-package");
+
+    let scope = opts.scope.as_deref().map(normalize_scope).transpose()?;
+    let mut name = sanitize_name(folder_name);
+    if let Some(scope) = &scope {
+        name = format!("{}/{}", scope, unscoped_name(&name));
+    }
+
+    let defaults = Defaults {
+        name,
+        author: git_author(&cwd),
+        repository: git_config(&cwd, "remote.origin.url").map(|url| repository_url(&url)),
+        license: opts.license.clone().unwrap_or_else(|| "ISC".to_string()),
+        main: if opts.typescript { "dist/index.js" } else { "index.js" }.to_string(),
+    };
+
+    let mut package_json = if opts.yes {
+        create_default_package_json(defaults)
+    } else {
+        create_interactive_package_json(defaults)?
+    };
+    set_module_fields(&mut package_json, opts.esm, opts.typescript);
+    set_publishing(&cwd, &mut package_json, &opts)?;
+
+    let json_content = serde_json::to_string_pretty(&package_json)?;
+    fs::write(&package_path, json_content)
+        .with_context(|| format!("Failed to write to {}", package_path.display()))?;
+
+    info!("Created package.json");
+    println!("{}", tr!("init-created", file = "package.json"));
+
+    if opts.typescript {
+        write_typescript_files(&cwd, &package_json.main)?;
+    }
+    if opts.license_file {
+        write_license_file(&cwd, &package_json)?;
+    }
+
+    Ok(())
+}
+
+fn create_default_package_json(defaults: Defaults) -> PackageJson {
+    PackageJson {
+        name: defaults.name,
+        version: "1.0.0".to_string(),
+        private: false,
+        description: "".to_string(),
+        module_type: None,
+        main: defaults.main,
+        module: None,
+        types: None,
+        exports: None,
+        scripts: Scripts {
+            build: None,
+            test: "echo \"Error: no test specified\" && exit 1".to_string(),
+        },
+        repository: defaults.repository.map(git_repository),
+        author: defaults.author,
+        license: defaults.license,
+        publish_config: None,
+        dependencies: serde_json::json!({}),
+        dev_dependencies: serde_json::json!({}),
+    }
+}
+
+fn create_interactive_package_json(defaults: Defaults) -> Result<PackageJson> {
+    let name: String = Input::new()
+        .with_prompt(tr!("init-prompt-name"))
+        .default(defaults.name)
+        .validate_with(|name: &String| validate_name(name))
+        .interact_text()?;
+
+    let version: String = Input::new()
+        .with_prompt(tr!("init-prompt-version"))
+        .default("1.0.0".to_string())
+        .interact_text()?;
+
+    let description: String = Input::new()
+        .with_prompt(tr!("init-prompt-description"))
+        .allow_empty(true)
+        .interact_text()?;
+
+    let main: String = Input::new()
+        .with_prompt(tr!("init-prompt-entry"))
+        .default(defaults.main)
+        .interact_text()?;
+
+    let test_cmd: String = Input::new()
+        .with_prompt(tr!("init-prompt-test"))
+        .default("echo \"Error: no test specified\" && exit 1".to_string())
+        .interact_text()?;
+
+    let repository: String = Input::new()
+        .with_prompt(tr!("init-prompt-repository"))
+        .default(defaults.repository.unwrap_or_default())
+        .allow_empty(true)
+        .interact_text()?;
+
+    let author: String = Input::new()
+        .with_prompt(tr!("init-prompt-author"))
+        .default(defaults.author)
+        .allow_empty(true)
+        .interact_text()?;
+
+    let license: String = Input::new()
+        .with_prompt(tr!("init-prompt-license"))
+        .default(defaults.license)
+        .interact_text()?;
+
+    Ok(PackageJson {
+        name,
+        version,
+        private: false,
+        description,
+        module_type: None,
+        main,
+        module: None,
+        types: None,
+        exports: None,
+        scripts: Scripts {
+            build: None,
+            test: test_cmd,
+        },
+        repository: (!repository.is_empty()).then(|| git_repository(repository)),
+        author,
+        license,
+        publish_config: None,
+        dependencies: serde_json::json!({}),
+        dev_dependencies: serde_json::json!({}),
+    })
+}
+
+// "private", or how a scoped package gets published: npm publishes scoped
+// packages as restricted unless told otherwise, and to the scope's registry
+fn set_publishing(dir: &Path, package_json: &mut PackageJson, opts: &InitOptions) -> Result<()> {
+    if opts.private {
+        package_json.private = true;
+        return Ok(());
+    }
+
+    let Some((scope, _)) = package_json
+        .name
+        .strip_prefix('@')
+        .and_then(|scoped| scoped.split_once('/'))
+    else {
+        if opts.access.is_some() {
+            println!("{} {}", style("!").yellow(), tr!("init-access-unscoped"));
+        }
+        return Ok(());
+    };
+    let scope = format!("@{}", scope);
+
+    let registry = scoped_registry(dir, &scope)?;
+    package_json.publish_config = Some(PublishConfig {
+        access: Some(opts.access.clone().unwrap_or_else(|| "public".to_string())),
+        registry: registry.clone(),
+    });
+    if let Some(registry) = registry {
+        println!("{}", tr!("init-scope-registry", registry = registry, scope = scope));
+    }
+    Ok(())
+}
+
+// "org" or "@org" as "@org"
+fn normalize_scope(scope: &str) -> Result<String> {
+    let bare = scope.trim().trim_start_matches('@');
+    if bare.is_empty() || bare.starts_with('.') || bare.starts_with('_') || !bare.chars().all(url_safe) {
+        anyhow::bail!(tr!("init-invalid-scope", scope = scope));
+    }
+    Ok(format!("@{}", bare.to_lowercase()))
+}
+
+fn unscoped_name(name: &str) -> &str {
+    match name.strip_prefix('@') {
+        Some(scoped) => scoped.split_once('/').map_or(scoped, |(_, name)| name),
+        None => name,
+    }
+}
+
+// The registry npm config maps `scope` to ("@org:registry=..."). A mapping
+// found only in the user's ~/.npmrc is copied into the project's .npmrc, so
+// everyone working on the project resolves the scope the same way
+fn scoped_registry(dir: &Path, scope: &str) -> Result<Option<String>> {
+    let key = format!("{}:registry", scope);
+    let npmrc = Npmrc::load(dir);
+    if let Some(registry) = npmrc.project(&key) {
+        return Ok(Some(registry.to_string()));
+    }
+    let Some(registry) = npmrc.user(&key) else {
+        return Ok(None);
+    };
+
+    let project_npmrc = dir.join(".npmrc");
+    let mut content = fs::read_to_string(&project_npmrc).unwrap_or_default();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&format!("{}={}\n", key, registry));
+    fs::write(&project_npmrc, content)
+        .with_context(|| format!("Failed to write to {}", project_npmrc.display()))?;
+    println!("{}", tr!("init-npmrc-added", key = key));
+    Ok(Some(registry.to_string()))
+}
+
+// Point "module", "types" and "exports" at the entry point, so Node and
+// bundlers resolve the same file whichever field they read
+fn set_module_fields(package_json: &mut PackageJson, esm: bool, typescript: bool) {
+    if !esm && !typescript {
+        return;
+    }
+
+    let main = package_json.main.trim_start_matches("./").to_string();
+    let target = format!("./{}", main);
+    let types = typescript.then(|| match main.strip_suffix(".js") {
+        Some(stem) => format!("{}.d.ts", stem),
+        None => format!("{}.d.ts", main),
+    });
+
+    let root = match &types {
+        Some(types) => ExportTarget::Conditions {
+            types: format!("./{}", types),
+            import: esm.then(|| target.clone()),
+            require: (!esm).then_some(target),
+        },
+        None => ExportTarget::File(target),
+    };
+    package_json.exports = Some(Exports { root });
+    if esm {
+        package_json.module_type = Some("module".to_string());
+        package_json.module = Some(main);
+    }
+    if typescript {
+        package_json.types = types;
+        package_json.scripts.build = Some("tsc".to_string());
+        package_json.dev_dependencies = serde_json::json!({ "typescript": "^5" });
+    }
+}
+
+// tsconfig.json compiling src/ next to the entry point, and a first source
+// file; existing files are left alone
+fn write_typescript_files(dir: &Path, main: &str) -> Result<()> {
+    let out_dir = Path::new(main.trim_start_matches("./"))
+        .parent()
+        .and_then(|parent| parent.to_str())
+        .filter(|parent| !parent.is_empty())
+        .unwrap_or(".");
+    let tsconfig = serde_json::json!({
+        "compilerOptions": {
+            "target": "ES2022",
+            "module": "NodeNext",
+            "moduleResolution": "NodeNext",
+            "rootDir": "src",
+            "outDir": out_dir,
+            "declaration": true,
+            "strict": true,
+            "esModuleInterop": true,
+            "skipLibCheck": true
+        },
+        "include": ["src"]
+    });
+
+    let files = [
+        (dir.join("tsconfig.json"), serde_json::to_string_pretty(&tsconfig)?),
+        (
+            dir.join("src").join("index.ts"),
+            "export function hello(name: string): string {\n  return `Hello, ${name}!`;\n}\n".to_string(),
+        ),
+    ];
+    for (path, content) in files {
+        let relative = path.strip_prefix(dir).unwrap_or(&path).display().to_string();
+        if path.exists() {
+            println!("{} {}", style("!").yellow(), tr!("init-exists", file = relative));
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(&path, content).with_context(|| format!("Failed to write to {}", path.display()))?;
+        println!("{}", tr!("init-created", file = relative));
+    }
+    println!("{}", tr!("init-typescript-next"));
+    Ok(())
+}
+
+// Check a name against npm's rules for new packages
+fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("name can't be empty".to_string());
+    }
+    if name.len() > 214 {
+        return Err("name can't be longer than 214 characters".to_string());
+    }
+    if name.starts_with('.') || name.starts_with('_') {
+        return Err("name can't start with a period or an underscore".to_string());
+    }
+    if name.trim() != name {
+        return Err("name can't have leading or trailing spaces".to_string());
+    }
+    if name != name.to_lowercase() {
+        return Err("name can't contain capital letters".to_string());
+    }
+    if name == "node_modules" || name == "favicon.ico" {
+        return Err(format!("{} is not a valid package name", name));
+    }
+
+    let unscoped = match name.strip_prefix('@') {
+        Some(scoped) => match scoped.split_once('/') {
+            Some((scope, unscoped)) if !scope.is_empty() && !unscoped.is_empty() => {
+                if !scope.chars().all(url_safe) {
+                    return Err("scope can only contain URL-friendly characters".to_string());
+                }
+                unscoped
+            }
+            _ => return Err("scoped names look like @scope/name".to_string()),
+        },
+        None => name,
+    };
+    if !unscoped.chars().all(url_safe) {
+        return Err("name can only contain URL-friendly characters".to_string());
+    }
+    Ok(())
+}
+
+// Characters that survive in a URL unescaped, minus the ones npm rejects
+fn url_safe(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-._".contains(c)
+}
+
+// Turn a folder name into a valid package name, e.g. "My Project" -> "my-project"
+fn sanitize_name(folder: &str) -> String {
+    let name: String = folder
+        .to_lowercase()
+        .chars()
+        .map(|c| if url_safe(c) { c } else { '-' })
+        .collect();
+    let name = name.trim_start_matches(['.', '_', '-']).trim_end_matches('-');
+    let name: String = name.chars().take(214).collect();
+
+    if validate_name(&name).is_ok() {
+        name
+    } else {
+        "my-package".to_string()
+    }
+}
+
+// A git setting as seen from `dir`, if git is installed and it's set
+fn git_config(dir: &Path, key: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["config", "--get", key])
+        .current_dir(dir)
+        .output()
+        .map_err(|e| debug!("Failed to run git: {}", e))
+        .ok()?;
+    let value = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !value.is_empty()).then_some(value)
+}
+
+// "Name <email>" from git's user settings
+fn git_author(dir: &Path) -> String {
+    match (git_config(dir, "user.name"), git_config(dir, "user.email")) {
+        (Some(name), Some(email)) => format!("{} <{}>", name, email),
+        (Some(name), None) => name,
+        (None, Some(email)) => format!("<{}>", email),
+        (None, None) => String::new(),
+    }
+}
+
+// The form npm writes a remote in: "git@github.com:o/r.git" and
+// "https://github.com/o/r" both become "git+https://github.com/o/r.git"
+fn repository_url(remote: &str) -> String {
+    let remote = remote.trim_end_matches('/');
+    let url = if let Some((_, rest)) = remote.split_once("://") {
+        // Drop credentials and the ssh user, keep the host and path
+        let rest = rest.rsplit_once('@').map_or(rest, |(_, rest)| rest);
+        match remote.split_once("://").map(|(scheme, _)| scheme) {
+            Some("http" | "https" | "git+https") => format!("https://{}", rest),
+            Some("ssh" | "git+ssh") => {
+                // An ssh port says nothing about where the web host lives
+                let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+                let host = host.split(':').next().unwrap_or(host);
+                format!("https://{}/{}", host, path)
+            }
+            _ => return remote.to_string(),
+        }
+    } else if let Some((host, path)) = remote.split_once(':')
+        && !host.contains('/')
+    {
+        // scp-like ssh syntax: [user@]host:path
+        let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+        format!("https://{}/{}", host, path)
+    } else {
+        // A local path; nothing sensible to publish
+        return remote.to_string();
+    };
+
+    let url = if url.ends_with(".git") { url } else { format!("{}.git", url) };
+    format!("git+{}", url)
+}
+
+fn git_repository(url: String) -> Repository {
+    Repository {
+        kind: "git".to_string(),
+        url,
+    }
+}
+
+// Write the text of the package's license next to package.json
+fn write_license_file(dir: &Path, package_json: &PackageJson) -> Result<()> {
+    let path = dir.join("LICENSE");
+    if path.exists() {
+        println!("{} {}", style("!").yellow(), tr!("init-exists", file = "LICENSE"));
+        return Ok(());
+    }
+
+    let Some((_, template)) = LICENSE_TEMPLATES
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(&package_json.license))
+    else {
+        let known: Vec<&str> = LICENSE_TEMPLATES.iter().map(|(id, _)| *id).collect();
+        println!(
+            "{} {}",
+            style("!").yellow(),
+            tr!("init-no-license-template", license = package_json.license, known = known.join(", "))
+        );
+        return Ok(());
+    };
+
+    // The copyright goes to the author's name, without the email
+    let holder = package_json
+        .author
+        .split('<')
+        .next()
+        .unwrap_or_default()
+        .trim();
+    let year = &humantime::format_rfc3339(std::time::SystemTime::now()).to_string()[..4];
+    let mut text = template.replace("<year>", year);
+    if !holder.is_empty() {
+        text = text.replace("<copyright holders>", holder);
+    }
+
+    fs::write(&path, text).with_context(|| format!("Failed to write to {}", path.display()))?;
+    println!("{}", tr!("init-created", file = "LICENSE"));
+    if holder.is_empty() {
+        println!("{} {}", style("!").yellow(), tr!("init-no-author"));
+    }
+    Ok(())
+}
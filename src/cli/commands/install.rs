@@ -1,16 +1,36 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Args;
 use console::style;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle, ProgressState};
+use dialoguer::{Confirm, Input, MultiSelect};
+use indicatif::{ProgressBar, ProgressStyle};
 use log::{info, warn};
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, Instant};
-use tokio::time;
-use futures::future;
-use std::fmt::Write;
+use tokio_util::sync::CancellationToken;
 
+use crate::analyze;
+use crate::audit::{self, Severity};
+use crate::config;
+use crate::dependency::events::{InstallEvent, InstallEvents};
+use crate::dependency::journal::Journal;
+use crate::dependency::local;
+use crate::dependency::patches::Patches;
+use crate::dependency::vendor::{Vendor, INDEX_FILE, VENDOR_DIR};
+use crate::dependency::platform::{self, Host};
 use crate::dependency::{self, DependencyResolver};
-use crate::registry::NpmRegistry;
+use crate::i18n::tr;
+use crate::npmrc::Npmrc;
+use crate::package_manager;
+use crate::policy::{Policy, POLICY_FILE};
+use crate::registry::{self, NpmRegistry};
+use crate::scripts::native;
+use crate::timing;
+use crate::typosquat;
+use crate::utils::{self, icon};
+use crate::workspace::{self, catalog::{self, Catalogs}};
 
 #[derive(Args)]
 pub struct InstallOptions {
@@ -25,14 +45,63 @@ pub struct InstallOptions {
     #[arg(short, long)]
     frozen: bool,
 
+    /// Resolve and update rjs-lock.json without touching node_modules
+    #[arg(long, conflicts_with = "frozen")]
+    lockfile_only: bool,
+
+    /// Start from an empty node_modules instead of resuming an interrupted install
+    #[arg(long, conflicts_with = "lockfile_only")]
+    clean: bool,
+
+    /// Registry to resolve packages from, instead of the registry setting.
+    /// Versions already in rjs-lock.json keep coming from where they were locked
+    #[arg(long)]
+    registry: Option<String>,
+
+    /// Use only cached metadata and stored tarballs, never the network
+    #[arg(long)]
+    offline: bool,
+
+    /// Check installed packages against security advisories (also enabled by RJS_AUDIT=1)
+    #[arg(long, conflicts_with = "no_audit")]
+    audit: bool,
+
+    /// Skip the advisory check even when RJS_AUDIT is set
+    #[arg(long)]
+    no_audit: bool,
+
+    /// Fail when advisories at or above this severity are found (implies --audit)
+    #[arg(long, value_enum)]
+    audit_level: Option<Severity>,
+
     /// Don't save to dependencies
     #[arg(long)]
     no_save: bool,
+
+    /// Search the registry and pick packages to install from the results; needs a terminal
+    #[arg(short, long, conflicts_with = "frozen")]
+    interactive: bool,
+
+    #[command(flatten)]
+    target: TargetOptions,
+
+    /// Add the packages to this workspace's package.json, by path ("packages/api") or name ("api").
+    /// They're resolved into the root's rjs-lock.json and node_modules, shared by every workspace
+    #[arg(short = 'w', long, requires = "packages")]
+    workspace: Option<String>,
     
-    /// Number of concurrent operations (default: number of CPU cores * 4)
+    /// Concurrent metadata requests while resolving (default: the concurrency setting, or number of CPU cores * 4)
     #[arg(short = 'j', long)]
     concurrency: Option<usize>,
     
+    /// Tarballs downloaded at once (default: the network-concurrency setting, or 64)
+    #[arg(long)]
+    network_concurrency: Option<usize>,
+
+    /// Tarballs extracted at once (default: the extract-concurrency setting, or number of CPU cores)
+    #[arg(long)]
+    extract_concurrency: Option<usize>,
+
     /// Batch size for processing packages (10-100, default: 50)
     #[arg(short = 'b', long)]
     batch_size: Option<usize>,
@@ -40,176 +109,552 @@ pub struct InstallOptions {
     /// Skip progress display for faster non-interactive installs
     #[arg(long)]
     no_progress: bool,
+
+    /// Install packages whose names look like typos of popular ones without asking
+    #[arg(short = 'y', long)]
+    yes: bool,
+
+    /// Also print HTTP statistics: requests, retries, bytes, connection reuse
+    /// and the slowest registry calls, e.g. to find a slow proxy. Twice (-vv)
+    /// adds the connection pool: DNS lookups, pre-opened connections and
+    /// per-host request limits
+    #[arg(short = 'v', long, action = clap::ArgAction::Count)]
+    verbose: u8,
+}
+
+/// The platform to install for, when it isn't this one: packages built for it
+/// are installed instead of the host's, and native builds are skipped as they
+/// can't be compiled here.
+#[derive(Args)]
+pub struct TargetOptions {
+    /// Install for this os instead of the host's, in Node's names (linux, darwin, win32...)
+    #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(platform::OSES))]
+    target_os: Option<String>,
+
+    /// Install for this cpu instead of the host's (x64, arm64...)
+    #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(platform::CPUS))]
+    target_cpu: Option<String>,
+
+    /// Install for this libc on Linux instead of the host's (glibc or musl)
+    #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(platform::LIBCS))]
+    target_libc: Option<String>,
+}
+
+impl TargetOptions {
+    pub fn host(&self) -> Host {
+        Host::target(self.target_os.as_deref(), self.target_cpu.as_deref(), self.target_libc.as_deref())
+    }
 }
 
-pub async fn execute(opts: InstallOptions) -> Result<()> {
+pub async fn execute(mut opts: InstallOptions, root: &Path) -> Result<()> {
     let start_time = Instant::now();
     
-    // Check if package.json exists
-    let cwd = std::env::current_dir()?;
-    let package_json_path = cwd.join("package.json");
+    // Check if package.json exists. Packages added to a workspace are saved
+    // in its package.json but installed and locked at the root
+    let mut cwd = root.to_path_buf();
+    let mut package_json_path = cwd.join("package.json");
+    if let Some(selector) = &opts.workspace {
+        let root = workspace::find_root(&cwd).context("No package.json with \"workspaces\" found here or above")?;
+        let target = workspace::find(&root, selector)?;
+        println!("{} {}", style(icon("•")).cyan(), tr!("install-workspace", workspace = style(&target.path).bold()));
+        package_json_path = target.dir.join("package.json");
+        cwd = root;
+    }
 
     if !package_json_path.exists() {
         warn!("No package.json found. Run 'rjs init' first or specify packages to install.");
-        println!("No package.json found. Run 'rjs init' first or specify packages to install.");
-        if opts.packages.is_empty() {
+        println!("{}", tr!("install-no-package-json"));
+        if opts.packages.is_empty() && !opts.interactive {
             return Ok(());
         }
+    } else {
+        package_manager::check(&cwd)?;
     }
 
+    // Set up progress reporting if enabled
+    let settings = config::settings();
+    let progress_enabled = !opts.no_progress && settings.progress && atty::is(atty::Stream::Stdout);
+    let progress_bar = if progress_enabled {
+        create_progress_bar()
+    } else {
+        ProgressBar::hidden()
+    };
+
     // Create registry and dependency resolver with concurrency
-    let registry = NpmRegistry::new();
-    let mut resolver = DependencyResolver::new(registry);
-    
-    // Set custom concurrency if provided
-    if let Some(concurrency) = opts.concurrency {
-        info!("Using custom concurrency level: {}", concurrency);
-        resolver = resolver.with_concurrency(concurrency);
+    let registry = NpmRegistry::for_registry(opts.registry.as_deref())
+        .with_npmrc(Npmrc::load(root))
+        .with_network_concurrency(opts.network_concurrency.unwrap_or(settings.network_concurrency))
+        .with_extract_concurrency(opts.extract_concurrency.unwrap_or(settings.extract_concurrency));
+    let registry = if opts.offline { registry.offline() } else { registry };
+    registry.prewarm(settings.prewarm_connections);
+
+    // Packages picked from registry searches join the ones named
+    if opts.interactive {
+        opts.packages.extend(search_packages(&registry).await?);
+        if opts.packages.is_empty() {
+            println!("Nothing picked to install");
+            return Ok(());
+        }
+    }
+    let reporter = Arc::new(ProgressReporter::new(progress_bar.clone()));
+    let host = opts.target.host();
+    if host != Host::current() {
+        println!("  {} {}", style(icon("•")).yellow(), tr!("install-target", host = style(&host).bold()));
     }
+    let mut resolver = DependencyResolver::new(registry.clone())
+        .with_events(reporter.clone())
+        .with_cancellation(cancel_on_ctrl_c())
+        .with_lockfile_only(opts.lockfile_only)
+        .with_catalogs(Catalogs::load(&cwd)?)
+        .with_patches(Patches::load(&cwd)?)
+        .with_host(host.clone())
+        .with_root(&cwd);
     
-    // Set custom batch size if provided
-    if let Some(batch_size) = opts.batch_size {
-        info!("Using custom batch size: {}", batch_size);
-        resolver = resolver.with_batch_size(batch_size);
+    // Checked-in packages come before the registry's
+    if settings.vendor {
+        match Vendor::load(&cwd)? {
+            Some(vendor) => resolver = resolver.with_vendor(vendor),
+            None => warn!("vendor is set but there's no {}/{}; run `rjs vendor` first", VENDOR_DIR, INDEX_FILE),
+        }
+    }
+
+    // Check the tree against the project's policy file, if it has one
+    if let Some(policy) = Policy::load(&cwd)? {
+        info!("Using install policy from {}", POLICY_FILE);
+        resolver = resolver.with_policy(policy);
+    }
+
+    let journal = if opts.lockfile_only {
+        None
+    } else {
+        Some(open_journal(&cwd, opts.clean)?)
+    };
+    if let Some(journal) = &journal {
+        resolver = resolver.with_journal(Arc::clone(journal));
     }
 
+    // Flags win over the config files
+    let concurrency = opts.concurrency.unwrap_or(settings.concurrency);
+    info!("Using concurrency level: {}", concurrency);
+    resolver = resolver.with_concurrency(concurrency);
+    
+    let batch_size = opts.batch_size.unwrap_or(settings.batch_size);
+    info!("Using batch size: {}", batch_size);
+    resolver = resolver.with_batch_size(batch_size);
+    resolver = resolver.with_memory_limit(settings.max_packages_in_memory);
+
+    // The summary compares against the lockfile as it was, and reports per-phase
+    // times whether or not --timing was passed
+    let before = locked_versions(&cwd, &resolver).await?;
+    timing::enable();
+
     if opts.packages.is_empty() {
         info!("Installing dependencies from package.json");
-        println!("{} Installing dependencies from package.json", style("📦").bold().cyan());
-        return install_from_package_json(&cwd, &resolver, opts.frozen, opts.no_progress).await;
+        println!("{} {}", style(icon("📦")).bold().cyan(), tr!("install-from-package-json"));
+        install_from_package_json(&cwd, &resolver, opts.frozen, opts.lockfile_only, opts.offline, &progress_bar).await?;
+        if let Some(journal) = &journal {
+            journal.finish()?;
+        }
+        if !opts.lockfile_only {
+            build_native_packages(&cwd, &host).await?;
+        }
+        print_summary(&cwd, &resolver, &before, &reporter, opts.lockfile_only).await?;
+        if opts.verbose > 0 {
+            print_network_stats(opts.verbose);
+            print_spill_stats(&resolver);
+        }
+        return audit_installed(&cwd, &resolver, &registry, &opts).await;
     }
 
     // Install specified packages
     info!("Installing specified packages: {:?}", opts.packages);
-    println!("{} Installing packages: {}", 
-        style("📦").bold().cyan(),
-        opts.packages.iter().map(|p| style(p).bold().to_string()).collect::<Vec<_>>().join(", ")
-    );
+    let packages = opts.packages.iter().map(|p| style(p).bold().to_string()).collect::<Vec<_>>().join(", ");
+    println!("{} {}", style(icon("📦")).bold().cyan(), tr!("install-packages", packages = packages));
 
     // Display frozen mode message if enabled
     if opts.frozen {
-        println!("  {} Using {} mode - exact versions from lockfile", 
-            style("•").yellow(),
-            style("frozen").bold()
+        println!("  {} {}", style(icon("•")).yellow(), tr!("install-mode-frozen", mode = style("frozen").bold()));
+    }
+
+    // Display offline mode message if enabled
+    if opts.offline {
+        println!("  {} {}", style(icon("•")).yellow(), tr!("install-mode-offline", mode = style("offline").bold()));
+    }
+
+    // Display lockfile-only mode message if enabled
+    if opts.lockfile_only {
+        println!(
+            "  {} {}",
+            style(icon("•")).yellow(),
+            tr!("install-mode-lockfile-only", mode = style("lockfile-only").bold())
         );
     }
 
-    // Set up progress bars if enabled
-    let progress_enabled = !opts.no_progress && atty::is(atty::Stream::Stdout);
-    let multi_progress = MultiProgress::new();
-    
-    // High-performance progress bar style
-    let spinner_style = ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {prefix:.bold.dim}: {msg}")
-        .unwrap()
-        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
-        .progress_chars("█▓▒░  ");
-    
-    let progress_bars: Vec<_> = if progress_enabled {
-        opts.packages
-            .iter()
-            .map(|pkg| {
-                let pb = multi_progress.add(ProgressBar::new(100));
-                pb.set_style(spinner_style.clone());
-                pb.set_prefix(pkg.clone());
-                pb.set_message("Resolving...");
-                pb.enable_steady_tick(Duration::from_millis(80));
-                pb
-            })
-            .collect()
-    } else {
-        vec![]
-    };
-    
-    // Convert packages to name/version pairs with "latest" as default version
-    let packages_to_install: Vec<(String, String)> = opts
+    // Convert packages to name/version pairs with "latest" as default version.
+    // Tarball paths are named by their package.json
+    let packages_to_install = opts
         .packages
         .iter()
-        .map(|pkg| {
-            let parts: Vec<&str> = pkg.split('@').collect();
-            if parts.len() > 1 && !parts[0].is_empty() {
-                // Format is name@version
-                (parts[0].to_string(), parts[1..].join("@"))
-            } else {
-                // Just a name or scoped package without version
-                (pkg.clone(), "latest".to_string())
-            }
+        .map(|pkg| match local::is_tarball_arg(pkg) {
+            true => local::spec_for(pkg, &cwd),
+            false => parse_spec(pkg),
         })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Catch "lodahs"-style typos before anything is fetched
+    let from_registry: Vec<_> = packages_to_install
+        .iter()
+        .filter(|(_, spec)| !local::is_file_spec(spec))
+        .cloned()
         .collect();
-    
-    // Create a background task to update progress bars
-    let progress_task = if progress_enabled {
-        let total_packages = packages_to_install.len();
-        let progress_bars_clone = progress_bars.clone();
-        
-        tokio::spawn(async move {
-            for i in 0..total_packages {
-                let pb = &progress_bars_clone[i];
-                
-                // Simulate phases of installation
-                for (phase, pct) in &[
-                    ("Resolving metadata...", 10),
-                    ("Resolving dependencies...", 30),
-                    ("Downloading packages...", 60),
-                    ("Installing...", 80),
-                    ("Finalizing...", 95),
-                ] {
-                    pb.set_message(*phase);
-                    pb.set_position(*pct);
-                    time::sleep(Duration::from_millis(300)).await;
-                }
-            }
-        })
-    } else {
-        tokio::spawn(async {})
-    };
+    confirm_lookalikes(&from_registry, opts.yes)?;
     
     // Actually install packages
     let install_result = resolver
         .resolve_and_install(&packages_to_install, &cwd, opts.save_dev, opts.frozen)
         .await;
     
-    // Complete progress bars if enabled
-    if progress_enabled {
-        for pb in &progress_bars {
-            pb.finish_with_message(format!("{} Done", style("✓").green()));
-            pb.set_position(100);
-        }
-        
-        // Wait for the progress task to complete
-        let _ = progress_task.await;
-    }
-    
     match install_result {
         Ok(installed_packages) => {
+            progress_bar.finish_with_message(format!("{} {}", style(icon("✓")).green(), tr!("install-done")));
+            if let Some(journal) = &journal {
+                journal.finish()?;
+            }
+
             // Update package.json if needed
             if !opts.no_save && package_json_path.exists() {
                 // Create a map of installed packages with their versions
                 let mut dependencies = std::collections::HashMap::new();
-                for package in installed_packages {
-                    dependencies.insert(package.name, package.version);
+                for package in &installed_packages {
+                    dependencies.insert(package.name.clone(), package.version.clone());
+                }
+                // Packages asked for from a catalog or a tarball keep pointing at it
+                for (name, spec) in &packages_to_install {
+                    if catalog::is_reference(spec) || local::is_file_spec(spec) {
+                        dependencies.insert(name.clone(), spec.clone());
+                    }
                 }
                 
                 // Update package.json
                 dependency::update_package_json(&package_json_path, &dependencies, opts.save_dev).await?;
                 info!("Updated package.json");
-                println!("{} Updated package.json", style("✓").green());
+                println!("{} {}", style(icon("✓")).green(), tr!("install-updated-package-json"));
             }
             
+            // What actually went into node_modules, dependencies included, or
+            // with --lockfile-only the requested packages that were locked
             let elapsed = start_time.elapsed();
-            info!("Installed {} packages in {:?}", packages_to_install.len(), elapsed);
-            println!(
-                "{} Installed {} packages in {:.2}s", 
-                style("✅").green(), 
-                style(packages_to_install.len()).bold(),
-                elapsed.as_secs_f64()
-            );
+            let (key, count) = match opts.lockfile_only {
+                true => ("install-locked", installed_packages.len()),
+                false => ("install-installed", reporter.installed.load(Ordering::Relaxed)),
+            };
+            info!("Installed {} packages in {:?}", count, elapsed);
+            let count = style(count).bold();
+            let seconds = format!("{:.2}", elapsed.as_secs_f64());
+            println!("{} {}", style(icon("✅")).green(), tr!(key, count = count, seconds = seconds));
+            if !opts.lockfile_only {
+                build_native_packages(&cwd, &host).await?;
+            }
+            print_summary(&cwd, &resolver, &before, &reporter, opts.lockfile_only).await?;
+            if opts.verbose > 0 {
+                print_network_stats(opts.verbose);
+                print_spill_stats(&resolver);
+            }
         },
         Err(e) => {
-            println!("{} Failed to install packages: {}", style("✗").red(), e);
+            progress_bar.abandon_with_message(format!("{} {}", style(icon("✗")).red(), tr!("install-failed-short")));
+            println!("{} {}", style(icon("✗")).red(), tr!("install-failed", error = e));
             return Err(e);
         }
     }
     
+    audit_installed(&cwd, &resolver, &registry, &opts).await
+}
+
+// The journal of this install. An interrupted install's journal is picked up
+// so the packages it finished are kept, unless --clean asks for a fresh start
+fn open_journal(cwd: &Path, clean: bool) -> Result<Arc<Journal>> {
+    let node_modules = cwd.join("node_modules");
+    if clean && node_modules.exists() {
+        std::fs::remove_dir_all(&node_modules)
+            .map_err(|e| anyhow::anyhow!("Failed to remove {}: {}", node_modules.display(), e))?;
+        println!("  {} {}", style(icon("•")).yellow(), tr!("install-clean"));
+    }
+
+    let journal = Journal::open(&node_modules)?;
+    if journal.resumed() > 0 {
+        println!(
+            "{} {}",
+            style(icon("↻")).bold().cyan(),
+            tr!("install-resuming", count = style(journal.resumed()).bold())
+        );
+    }
+    Ok(Arc::new(journal))
+}
+
+// Split "name@spec" into the name and its version, range or dist-tag. A scoped
+// name's leading "@" isn't a separator, and the spec may be a full npm range
+// with spaces, as in `rjs install "lodash@>=4.17 <5"`
+fn parse_spec(package: &str) -> Result<(String, String)> {
+    let (name, spec) = match package.get(1..).and_then(|rest| rest.find('@')) {
+        Some(at) => (&package[..at + 1], package[at + 2..].trim()),
+        None => (package, ""),
+    };
+    if spec.is_empty() {
+        return Ok((name.to_string(), "latest".to_string()));
+    }
+    // Anything else that isn't a range is a dist-tag, and tags are single words
+    let tag_like = !spec.contains(|c: char| c.is_whitespace() || "<>=|^~".contains(c));
+    if dependency::versions::parse_req(spec).is_none() && !tag_like {
+        anyhow::bail!("Invalid version range \"{}\" for {}", spec, name);
+    }
+    Ok((name.to_string(), spec.to_string()))
+}
+
+// Name -> version of every package in the project's lockfile
+async fn locked_versions(cwd: &Path, resolver: &DependencyResolver) -> Result<HashMap<String, String>> {
+    let lockfile = resolver.load_lockfile(cwd).await?;
+    Ok(lockfile
+        .into_iter()
+        .flat_map(|lockfile| lockfile.packages)
+        .map(|(key, entry)| (dependency::lockfile_key_name(&key).to_string(), entry.version))
+        .collect())
+}
+
+// What the install changed against the lockfile from `before`, how much it
+// unpacked and downloaded, how many packages ask for funding, where the time
+// went, and any deprecated versions
+async fn print_summary(
+    cwd: &Path,
+    resolver: &DependencyResolver,
+    before: &HashMap<String, String>,
+    reporter: &ProgressReporter,
+    lockfile_only: bool,
+) -> Result<()> {
+    let after = locked_versions(cwd, resolver).await?;
+    let added = after.keys().filter(|name| !before.contains_key(*name)).count();
+    let removed = before.keys().filter(|name| !after.contains_key(*name)).count();
+    let changed = after
+        .iter()
+        .filter(|(name, version)| before.get(*name).is_some_and(|old| old != *version))
+        .count();
+    println!("  {}", tr!("install-summary", added = added, removed = removed, changed = changed));
+
+    // node_modules is left alone with --lockfile-only, so there's nothing unpacked
+    let mut sizes = Vec::new();
+    if !lockfile_only {
+        let node_modules = cwd.join("node_modules");
+        let unpacked: u64 = after.keys().map(|name| utils::dir_size(&node_modules.join(name))).sum();
+        sizes.push(format!("{} unpacked", utils::format_size(unpacked)));
+        sizes.push(format!("{} downloaded", utils::format_size(reporter.downloaded.load(Ordering::Relaxed))));
+        sizes.push(format!("{} from cache", reporter.cached.load(Ordering::Relaxed)));
+    }
+    if !sizes.is_empty() {
+        println!("  {}", sizes.join(", "));
+    }
+
+    if !lockfile_only && config::settings().fund {
+        let funding = dependency::funding::collect(&cwd.join("node_modules"), after.keys().map(String::as_str));
+        if !funding.is_empty() {
+            println!("  {}", tr!("install-funding", count = funding.len()));
+            println!("    {}", style(tr!("install-funding-hint")).dim());
+        }
+    }
+
+    if let Some(report) = timing::report() {
+        let phases: Vec<String> = report
+            .phases
+            .iter()
+            .filter(|phase| phase.count > 0)
+            .map(|phase| format!("{} {:.2}s", phase.phase.label(), phase.wall_ms / 1000.0))
+            .collect();
+        if !phases.is_empty() {
+            println!("  {}", style(phases.join(", ")).dim());
+        }
+    }
+
+    let deprecated = reporter.deprecated.lock().unwrap().clone();
+    for ((name, version), (message, path)) in deprecated {
+        println!(
+            "{} {}@{}: {}",
+            style(format!("{} Deprecated", icon("⚠"))).bold().yellow(),
+            style(name).bold(),
+            version,
+            message
+        );
+        // The last link is the package itself; anything before it pulled it in
+        if let Some((_, parents)) = path.split_last()
+            && !parents.is_empty()
+        {
+            println!("    {}", style(format!("via {}", parents.join(" > "))).dim());
+        }
+    }
+    Ok(())
+}
+
+// How much of the tree didn't fit under max-packages-in-memory
+fn print_spill_stats(resolver: &DependencyResolver) {
+    if let Some((spilled, limit)) = resolver.spilled().filter(|(spilled, _)| *spilled > 0) {
+        println!("  memory: {} resolved packages spilled to disk past the first {}", spilled, limit);
+    }
+}
+
+// The process's HTTP counters and slowest calls, for --verbose, and with
+// -vv how the connection pool was used
+fn print_network_stats(verbosity: u8) {
+    let stats = registry::network_stats();
+    let mut line = format!(
+        "  network: {} requests, {} retried, {} received",
+        stats.requests,
+        stats.retries,
+        utils::format_size(stats.bytes_downloaded)
+    );
+    // Hosts given as IP addresses open connections without being counted
+    if stats.connections > 0 {
+        line.push_str(&format!(
+            ", {} connections ({} requests reused one)",
+            stats.connections,
+            stats.requests.saturating_sub(stats.connections)
+        ));
+    }
+    println!("{}", line);
+
+    // Hosts that answered 429/503 or slowed down, and what rjs settled on
+    if stats.throttled > 0 {
+        let limits: Vec<String> = registry::host_limits()
+            .into_iter()
+            .map(|(host, limit)| format!("{} {}", host, limit))
+            .collect();
+        println!(
+            "  throttled: {} responses asked to slow down; requests in flight now {}",
+            stats.throttled,
+            limits.join(", ")
+        );
+    }
+
+    if verbosity > 1 {
+        let hosts = registry::host_limits();
+        println!(
+            "  pool: {} connections opened, {} of them ahead of time; {} DNS lookups for {} hosts; {:.1} requests per connection",
+            stats.connections,
+            stats.prewarmed,
+            stats.dns_lookups,
+            hosts.len(),
+            stats.requests as f64 / stats.connections.max(1) as f64
+        );
+        for (host, limit) in hosts {
+            println!("    {} up to {} requests in flight", host, limit);
+        }
+    }
+
+    let slowest = registry::slowest_calls();
+    if !slowest.is_empty() {
+        println!("  {}", style("slowest:").dim());
+        for call in slowest {
+            println!("    {:>8.0}ms  {}", call.duration.as_secs_f64() * 1000.0, call.url);
+        }
+    }
+}
+
+// Warn about requested packages named like popular ones and make the user
+// confirm them, or pass --yes when there's no terminal to ask on
+fn confirm_lookalikes(packages: &[(String, String)], yes: bool) -> Result<()> {
+    let lookalikes: Vec<(&str, &str)> = packages
+        .iter()
+        .filter_map(|(name, _)| typosquat::lookalike(name).map(|popular| (name.as_str(), popular)))
+        .collect();
+    if lookalikes.is_empty() {
+        return Ok(());
+    }
+
+    for (name, popular) in &lookalikes {
+        eprintln!(
+            "{} {} looks a lot like the popular package {}. Typosquatted names are a common way to ship malware.",
+            style(format!("{} Warning:", icon("⚠"))).bold().yellow(),
+            style(name).bold(),
+            style(popular).bold().green()
+        );
+    }
+
+    if yes {
+        return Ok(());
+    }
+
+    if !atty::is(atty::Stream::Stdin) {
+        anyhow::bail!("Refusing to install possible typosquats; check the names or pass --yes to install anyway");
+    }
+
+    let proceed = Confirm::new()
+        .with_prompt("Install anyway?")
+        .default(false)
+        .interact()?;
+    if !proceed {
+        anyhow::bail!("Installation aborted");
+    }
+    Ok(())
+}
+
+// Run the advisory check after an install if it was asked for, failing when
+// findings reach --audit-level
+async fn audit_installed(
+    cwd: &Path,
+    resolver: &DependencyResolver,
+    registry: &NpmRegistry,
+    opts: &InstallOptions,
+) -> Result<()> {
+    let settings = config::settings();
+    let enabled = opts.audit
+        || opts.audit_level.is_some()
+        || settings.audit;
+    if !enabled || opts.no_audit {
+        return Ok(());
+    }
+    let audit_level = opts.audit_level.or(settings.audit_level);
+
+    let report = match resolver.load_lockfile(cwd).await? {
+        Some(lockfile) => audit::check_lockfile(registry, &lockfile).await,
+        None => Ok(Default::default()),
+    };
+
+    let report = match report {
+        Ok(report) => report,
+        // Without a gate a failed check shouldn't fail an otherwise good install
+        Err(e) if audit_level.is_none() => {
+            warn!("Advisory check failed: {:#}", e);
+            println!("{} Advisory check failed: {:#}", style("!").yellow(), e);
+            return Ok(());
+        }
+        Err(e) => return Err(e.context("Advisory check failed")),
+    };
+
+    let icon = if report.findings.is_empty() {
+        style(icon("✓")).green()
+    } else {
+        style("!").yellow()
+    };
+    println!("{} Audit: {}", icon, report.summary());
+    for finding in &report.findings {
+        println!(
+            "  {} {}@{}: {}{}",
+            style(finding.severity.label()).bold(),
+            finding.name,
+            finding.version,
+            finding.advisory.title,
+            finding.advisory.url.as_deref().map(|url| format!(" ({})", url)).unwrap_or_default()
+        );
+    }
+
+    if let Some(level) = audit_level {
+        let failing = report.at_or_above(level);
+        if failing > 0 {
+            return Err(anyhow::anyhow!(
+                "{} advisories at or above {} severity",
+                failing,
+                level.label()
+            ));
+        }
+    }
+
     Ok(())
 }
 
@@ -217,7 +662,9 @@ async fn install_from_package_json(
     cwd: &Path, 
     resolver: &DependencyResolver, 
     frozen: bool,
-    no_progress: bool
+    lockfile_only: bool,
+    offline: bool,
+    progress_bar: &ProgressBar
 ) -> Result<()> {
     let start_time = Instant::now();
     let package_json_path = cwd.join("package.json");
@@ -230,35 +677,13 @@ async fn install_from_package_json(
 
     if total_deps == 0 {
         info!("No dependencies found in package.json");
-        println!("{} No dependencies found in package.json", style("ℹ").blue());
+        println!("{} {}", style(icon("ℹ")).blue(), tr!("install-no-dependencies"));
         return Ok(());
     }
 
     info!("Found {} dependencies in package.json", total_deps);
-    println!("{} Found {} dependencies in package.json", 
-        style("ℹ").blue(),
-        style(total_deps).bold()
-    );
+    println!("{} {}", style(icon("ℹ")).blue(), tr!("install-found-dependencies", count = style(total_deps).bold()));
 
-    // Set up progress if enabled
-    let progress_enabled = !no_progress && atty::is(atty::Stream::Stdout);
-    let progress_bar = if progress_enabled {
-        let pb = ProgressBar::new(total_deps as u64);
-        pb.set_style(
-            ProgressStyle::with_template(
-                "{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg} {eta}"
-            )
-            .unwrap()
-            .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "({:.1}s)", state.eta().as_secs_f64()).unwrap())
-            .progress_chars("█▓▒░  ")
-        );
-        pb.set_message("Resolving dependencies...");
-        pb.enable_steady_tick(Duration::from_millis(100));
-        pb
-    } else {
-        ProgressBar::hidden()
-    };
-    
     // Convert dependencies to the format expected by resolver
     let regular_deps: Vec<(String, String)> = dependencies
         .iter()
@@ -272,59 +697,301 @@ async fn install_from_package_json(
     
     // Show dependency counts
     if !regular_deps.is_empty() {
-        println!("  {} {} regular dependencies", 
-            style("•").cyan(),
-            style(regular_deps.len()).bold()
-        );
+        println!("  {} {}", style(icon("•")).cyan(), tr!("install-regular-dependencies", count = style(regular_deps.len()).bold()));
     }
     
     if !dev_deps.is_empty() {
-        println!("  {} {} development dependencies", 
-            style("•").magenta(),
-            style(dev_deps.len()).bold()
-        );
+        println!("  {} {}", style(icon("•")).magenta(), tr!("install-dev-dependencies", count = style(dev_deps.len()).bold()));
     }
     
     // Display frozen mode message if enabled
     if frozen {
-        println!("  {} Using {} mode - exact versions from lockfile", 
-            style("•").yellow(),
-            style("frozen").bold()
-        );
+        println!("  {} {}", style(icon("•")).yellow(), tr!("install-mode-frozen", mode = style("frozen").bold()));
     }
-    
-    // Update progress message
-    if progress_enabled {
-        progress_bar.set_message("Installing dependencies...");
+
+    // Display offline mode message if enabled
+    if offline {
+        println!("  {} {}", style(icon("•")).yellow(), tr!("install-mode-offline", mode = style("offline").bold()));
+    }
+
+    // Display lockfile-only mode message if enabled
+    if lockfile_only {
+        println!(
+            "  {} {}",
+            style(icon("•")).yellow(),
+            tr!("install-mode-lockfile-only", mode = style("lockfile-only").bold())
+        );
     }
     
-    // Install both types of dependencies concurrently
-    let (regular_result, dev_result) = future::join(
-        resolver.resolve_and_install(&regular_deps, cwd, false, frozen),
-        resolver.resolve_and_install(&dev_deps, cwd, true, frozen)
-    ).await;
+    // Both types of dependencies go in one tree, so a name they share gets a
+    // single layout and lockfile entries that don't overwrite each other
+    let result = resolver.resolve_and_install_project(&regular_deps, &dev_deps, cwd, frozen).await;
     
     // Check results
-    match (regular_result, dev_result) {
-        (Ok(_), Ok(_)) => {
+    match result {
+        Ok(_) => {
             // Complete the progress bar
-            if progress_enabled {
+            let elapsed = start_time.elapsed();
+            if lockfile_only {
+                progress_bar.finish_with_message("Lockfile updated successfully!");
+                let seconds = format!("{:.2}", elapsed.as_secs_f64());
+                println!("{} {}", style(icon("✅")).green(), tr!("install-lockfile-updated", seconds = seconds));
+            } else {
                 progress_bar.finish_with_message("All dependencies installed successfully!");
+                let seconds = format!("{:.2}", elapsed.as_secs_f64());
+                println!("{} {}", style(icon("✅")).green(), tr!("install-all-installed", seconds = seconds));
             }
-            
-            let elapsed = start_time.elapsed();
-            println!("{} All dependencies installed successfully in {:.2}s!", 
-                style("✅").green(),
-                elapsed.as_secs_f64()
-            );
             Ok(())
         },
-        (Err(e), _) | (_, Err(e)) => {
-            if progress_enabled {
-                progress_bar.abandon_with_message(format!("Failed to install: {}", e));
-            }
-            println!("{} Failed to install dependencies: {}", style("✗").red(), e);
+        Err(e) => {
+            progress_bar.abandon_with_message(format!("Failed to install: {}", e));
+            println!("{} {}", style(icon("✗")).red(), tr!("install-dependencies-failed", error = e));
             Err(e)
         }
     }
 }
+
+// Ask for search terms until an empty one, offering each search's results to
+// pick from. Each search goes to the registry once entered, as dialoguer's
+// prompts can't wait on the network between keystrokes
+async fn search_packages(registry: &NpmRegistry) -> Result<Vec<String>> {
+    if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stdout) {
+        anyhow::bail!("--interactive needs a terminal; name the packages to install instead");
+    }
+
+    let mut picked = Vec::new();
+    loop {
+        let text: String = Input::new()
+            .with_prompt("Search packages (empty to finish)")
+            .allow_empty(true)
+            .interact_text()?;
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(picked);
+        }
+
+        let results = registry.search(text, 20).await?;
+        if results.is_empty() {
+            println!("  No packages match \"{}\"", text);
+            continue;
+        }
+        let labels: Vec<String> = results.iter().map(|result| format!("{}@{}", result.name, result.version)).collect();
+        let width = labels.iter().map(|label| label.len()).max().unwrap_or(0);
+        let items: Vec<String> = labels
+            .iter()
+            .zip(&results)
+            .map(|(label, result)| format!("{:<width$}  {}", label, result.description.as_deref().unwrap_or(""), width = width))
+            .collect();
+
+        // Space picks, enter confirms, escape picks nothing from this search
+        let chosen = MultiSelect::new().with_prompt("Packages to install").items(&items).interact_opt()?;
+        picked.extend(chosen.into_iter().flatten().map(|index| results[index].name.clone()));
+    }
+}
+
+// Compile the native packages that came without a binary for `host`. Built
+// here they'd be for this platform, so installs for another one skip them
+pub(super) async fn build_native_packages(cwd: &Path, host: &Host) -> Result<()> {
+    let entries = analyze::scan_node_modules(&cwd.join("node_modules"))?;
+    for entry in entries.iter().filter(|entry| native::needs_build(&entry.path, host)) {
+        if *host != Host::current() {
+            warn!(
+                "Not building {}@{}: it has no prebuilt binary for {} and can't be compiled for it here",
+                entry.name, entry.version, host
+            );
+            continue;
+        }
+        println!(
+            "{} Building {}@{} from source, it has no prebuilt binary for {}",
+            style(icon("•")).cyan(),
+            style(&entry.name).bold(),
+            entry.version,
+            host
+        );
+        native::build(&entry.path).await?;
+    }
+    Ok(())
+}
+
+// Shared by commands that report per-package progress. Plain mode gets none,
+// as redrawn lines are noise in logs and to screen readers
+pub fn create_progress_bar() -> ProgressBar {
+    if utils::is_plain() {
+        return ProgressBar::hidden();
+    }
+    let pb = ProgressBar::new(0);
+    pb.set_style(
+        ProgressStyle::with_template(
+            "{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}"
+        )
+        .unwrap()
+        .progress_chars("█▓▒░  ")
+    );
+    pb.set_message("Resolving dependencies...");
+    pb.enable_steady_tick(Duration::from_millis(100));
+    pb
+}
+
+// A deprecated version's message and the shortest chain that pulled it in
+type Deprecation = (String, Vec<String>);
+
+// Progress bar subscriber driven by resolver and installer events. It keeps
+// running totals so the bar shows what was actually downloaded and what failed
+struct ProgressReporter {
+    bar: ProgressBar,
+    downloaded: AtomicU64,
+    // Tarballs still downloading: bytes received and announced size
+    transfers: Mutex<HashMap<String, (u64, Option<u64>)>>,
+    // When the first download started, for the transfer rate
+    transfer_started: OnceLock<Instant>,
+    cached: AtomicUsize,
+    installed: AtomicUsize,
+    failed: AtomicUsize,
+    // Deprecated versions seen, for the summary
+    deprecated: Mutex<BTreeMap<(String, String), Deprecation>>,
+}
+
+impl ProgressReporter {
+    fn new(bar: ProgressBar) -> Self {
+        Self {
+            bar,
+            downloaded: AtomicU64::new(0),
+            transfers: Mutex::new(HashMap::new()),
+            transfer_started: OnceLock::new(),
+            cached: AtomicUsize::new(0),
+            installed: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            deprecated: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    // Bytes received and expected across all downloads, finished ones
+    // included. Downloads without an announced size count what they've got
+    fn transfer(&self) -> (u64, u64) {
+        let done = self.downloaded.load(Ordering::Relaxed);
+        let transfers = self.transfers.lock().unwrap();
+        transfers.values().fold((done, done), |(received, expected), (downloaded, total)| {
+            (received + downloaded, expected + total.unwrap_or(*downloaded).max(*downloaded))
+        })
+    }
+
+    // Show `message` followed by the totals so far, e.g.
+    // "(1.2MB of 4.5MB, 2.1MB/s, ~2s left, 1 failed)"
+    fn status(&self, message: String) {
+        let (received, expected) = self.transfer();
+        let failed = self.failed.load(Ordering::Relaxed);
+        let mut totals = Vec::new();
+        if received > 0 {
+            let mut transfer = utils::format_size(received);
+            if expected > received {
+                transfer.push_str(&format!(" of {}", utils::format_size(expected)));
+            }
+            if let Some(started) = self.transfer_started.get() {
+                let rate = received as f64 / started.elapsed().as_secs_f64().max(0.001);
+                transfer.push_str(&format!(", {}/s", utils::format_size(rate as u64)));
+                if expected > received {
+                    transfer.push_str(&format!(", ~{:.0}s left", (expected - received) as f64 / rate));
+                }
+            }
+            totals.push(transfer);
+        }
+        if failed > 0 {
+            totals.push(style(format!("{} failed", failed)).red().to_string());
+        }
+        if totals.is_empty() {
+            self.bar.set_message(message);
+        } else {
+            self.bar.set_message(format!("{} ({})", message, totals.join(", ")));
+        }
+    }
+}
+
+impl InstallEvents for ProgressReporter {
+    fn on_event(&self, event: &InstallEvent) {
+        match event {
+            InstallEvent::ResolveStarted { .. } => {
+                self.bar.set_message("Resolving dependencies...");
+            }
+            InstallEvent::PackageResolved { name, version } => {
+                self.status(format!("Resolved {}@{}", name, version));
+            }
+            InstallEvent::PackageDeprecated { name, version, message, path } => {
+                let mut deprecated = self.deprecated.lock().unwrap();
+                let entry = deprecated
+                    .entry((name.clone(), version.clone()))
+                    .or_insert_with(|| (message.clone(), path.clone()));
+                if path.len() < entry.1.len() {
+                    entry.1 = path.clone();
+                }
+            }
+            InstallEvent::ResolveFailed { name, spec, error } => {
+                self.failed.fetch_add(1, Ordering::Relaxed);
+                self.bar.println(format!("{} Failed to resolve {}@{}: {}", style(icon("✗")).red(), name, spec, error));
+            }
+            InstallEvent::ResolveFinished { total } => {
+                self.status(format!("Resolved {} packages", total));
+            }
+            // Installs of regular and dev dependencies may run concurrently,
+            // so totals accumulate instead of replacing each other
+            InstallEvent::InstallStarted { total } => {
+                self.bar.inc_length(*total as u64);
+                self.bar.set_message("Installing dependencies...");
+            }
+            InstallEvent::DownloadProgress { name, downloaded, total } => {
+                self.transfer_started.get_or_init(Instant::now);
+                self.transfers.lock().unwrap().insert(name.clone(), (*downloaded, *total));
+                self.status(format!("Downloading {}", name));
+            }
+            InstallEvent::PackageDownloaded { name, bytes } => {
+                self.transfers.lock().unwrap().remove(name);
+                self.downloaded.fetch_add(*bytes, Ordering::Relaxed);
+                self.status(format!("Downloaded {}", name));
+            }
+            InstallEvent::PackageCached { name } => {
+                self.cached.fetch_add(1, Ordering::Relaxed);
+                self.status(format!("Found {} in the cache", name));
+            }
+            InstallEvent::PackageExtracted { name } => {
+                self.status(format!("Extracted {}", name));
+            }
+            InstallEvent::PackageInstalled { name, .. } => {
+                self.installed.fetch_add(1, Ordering::Relaxed);
+                self.bar.inc(1);
+                self.status(format!("Installed {}", name));
+            }
+            InstallEvent::InstallFailed { name, error } => {
+                self.transfers.lock().unwrap().remove(name);
+                self.failed.fetch_add(1, Ordering::Relaxed);
+                self.bar.inc(1);
+                self.bar.println(format!("{} Failed to install {}: {}", style(icon("✗")).red(), name, error));
+                self.status(format!("Failed {}", name));
+            }
+            // Regular and dev installs each finish separately, so report the
+            // running count rather than this pass's
+            InstallEvent::InstallFinished { .. } => {
+                self.status(format!("Installed {} packages", self.installed.load(Ordering::Relaxed)));
+            }
+        }
+    }
+}
+
+// Cancel the install on the first Ctrl-C and exit immediately on the second
+pub(super) fn cancel_on_ctrl_c() -> CancellationToken {
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        warn!("Received Ctrl-C, cancelling installation...");
+        println!("{} {}", style("!").yellow(), tr!("install-cancelling"));
+        token.cancel();
+
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+
+    cancel
+}
@@ -0,0 +1,163 @@
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use console::style;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::lint::{self, Usage};
+use crate::utils::icon;
+
+#[derive(Args)]
+pub struct LintOptions {
+    #[command(subcommand)]
+    action: LintAction,
+}
+
+#[derive(Subcommand)]
+enum LintAction {
+    /// Find packages the source imports without declaring them in package.json,
+    /// and declared ones nothing imports. Fails when an undeclared one is found
+    Deps {
+        /// Print the findings as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Packages to leave out of the report, e.g. tools only used from the command line
+        #[arg(long, value_delimiter = ',')]
+        ignore: Vec<String>,
+    },
+}
+
+// Dependency fields of package.json that make a package importable
+const DEPENDENCY_FIELDS: [&str; 4] = ["dependencies", "devDependencies", "peerDependencies", "optionalDependencies"];
+
+#[derive(Serialize)]
+struct Findings {
+    // Imported, not declared: works only while something else pulls it in
+    undeclared: BTreeMap<String, Vec<Usage>>,
+    // Declared, never imported
+    unused: Vec<String>,
+}
+
+pub async fn execute(opts: LintOptions, root: &Path) -> Result<()> {
+    match opts.action {
+        LintAction::Deps { json, ignore } => lint_deps(root, json, &ignore),
+    }
+}
+
+fn lint_deps(cwd: &Path, json: bool, ignore: &[String]) -> Result<()> {
+    let package_json_path = cwd.join("package.json");
+    let content = std::fs::read_to_string(&package_json_path)
+        .with_context(|| format!("Failed to read {}", package_json_path.display()))?;
+    let package_json: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", package_json_path.display()))?;
+
+    let declared: Vec<&str> = DEPENDENCY_FIELDS
+        .iter()
+        .filter_map(|field| package_json.get(field).and_then(|deps| deps.as_object()))
+        .flat_map(|deps| deps.keys().map(String::as_str))
+        .collect();
+    let own_name = package_json.get("name").and_then(|name| name.as_str());
+    // Tools run from scripts (tsc, eslint, jest) are used without being imported
+    let scripts: Vec<&str> = package_json
+        .get("scripts")
+        .and_then(|scripts| scripts.as_object())
+        .map(|scripts| scripts.values().filter_map(|script| script.as_str()).collect())
+        .unwrap_or_default();
+
+    let imported = lint::imported_packages(cwd)?;
+    let skipped = |name: &str| ignore.iter().any(|ignored| ignored == name);
+
+    let undeclared: BTreeMap<String, Vec<Usage>> = imported
+        .iter()
+        // A package may import itself by name through its "exports"
+        .filter(|(name, _)| !declared.contains(&name.as_str()) && Some(name.as_str()) != own_name)
+        .filter(|(name, _)| !skipped(name))
+        .map(|(name, usages)| (name.clone(), usages.clone()))
+        .collect();
+
+    let mut unused: Vec<String> = declared
+        .iter()
+        .filter(|name| !imported.contains_key(**name) && !skipped(name))
+        // Type packages are used by the compiler, not imported
+        .filter(|name| !name.starts_with("@types/"))
+        .filter(|name| {
+            !commands(cwd, name)
+                .iter()
+                .any(|command| scripts.iter().any(|script| script.contains(command.as_str())))
+        })
+        .map(|name| name.to_string())
+        .collect();
+    unused.sort();
+    unused.dedup();
+
+    let findings = Findings { undeclared, unused };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&findings)?);
+    } else {
+        print_findings(cwd, &findings);
+    }
+
+    if !findings.undeclared.is_empty() {
+        anyhow::bail!(
+            "{} imported packages aren't declared in package.json",
+            findings.undeclared.len()
+        );
+    }
+    Ok(())
+}
+
+// What a package can be run as from scripts: its name and, when it's
+// installed, the commands its package.json "bin" declares (tsc for typescript)
+fn commands(cwd: &std::path::Path, name: &str) -> Vec<String> {
+    let mut commands = vec![name.to_string()];
+    let manifest = cwd.join("node_modules").join(name).join("package.json");
+    let bin = std::fs::read_to_string(manifest)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|manifest| manifest.get("bin").cloned());
+    match bin {
+        Some(serde_json::Value::Object(bins)) => commands.extend(bins.keys().cloned()),
+        // A single bin is named after the package, without its scope
+        Some(serde_json::Value::String(_)) => {
+            commands.push(name.rsplit('/').next().unwrap_or(name).to_string());
+        }
+        _ => {}
+    }
+    commands
+}
+
+fn print_findings(cwd: &Path, findings: &Findings) {
+    let node_modules = cwd.join("node_modules");
+    for (name, usages) in &findings.undeclared {
+        // Installed anyway means something else depends on it: a phantom dependency
+        let state = if node_modules.join(name).exists() {
+            "installed only as another package's dependency"
+        } else {
+            "not installed"
+        };
+        println!(
+            "{} {} is imported but not declared ({})",
+            style(icon("✗")).red(),
+            style(name).bold(),
+            state
+        );
+        for usage in usages.iter().take(3) {
+            println!("    {}:{}", usage.file.display(), usage.line);
+        }
+        if usages.len() > 3 {
+            println!("    {}", style(format!("and {} more", usages.len() - 3)).dim());
+        }
+    }
+    for name in &findings.unused {
+        println!(
+            "{} {} is declared but never imported",
+            style(icon("⚠")).yellow(),
+            style(name).bold()
+        );
+    }
+    if findings.undeclared.is_empty() && findings.unused.is_empty() {
+        println!("{} Every imported package is declared and every dependency is used", style(icon("✓")).green());
+    }
+}
@@ -3,10 +3,15 @@ use clap::Args;
 use console::style;
 use log::info;
 use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::dependency::graph::{Edge, Graph};
+use crate::utils::icon;
 
 #[derive(Args)]
 pub struct ListOptions {
-    /// Display only top-level dependencies
+    /// Also show what each dependency resolved to and its own dependencies,
+    /// this many levels deep, from the graph the last install saved
     #[arg(short, long)]
     depth: Option<usize>,
 
@@ -23,8 +28,8 @@ pub struct ListOptions {
     outdated: bool,
 }
 
-pub async fn execute(opts: ListOptions) -> Result<()> {
-    let cwd = std::env::current_dir()?;
+pub async fn execute(opts: ListOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
     let package_json_path = cwd.join("package.json");
 
     // Check if package.json exists
@@ -74,6 +79,10 @@ pub async fn execute(opts: ListOptions) -> Result<()> {
         return Ok(());
     }
 
+    // Only read when asked to go deeper than package.json
+    let depth = opts.depth.unwrap_or(0);
+    let graph = if depth > 0 { Graph::load(&cwd) } else { None };
+
     // Print the package info
     println!(
         "{} {}",
@@ -85,7 +94,8 @@ pub async fn execute(opts: ListOptions) -> Result<()> {
     if !dependencies.is_empty() {
         println!("\n{}:", style("dependencies").green().bold());
         for (name, version) in &dependencies {
-            println!("  {} {}", name, style(version.as_str().unwrap_or("")).dim());
+            println!("  {} {}{}", name, style(version.as_str().unwrap_or("")).dim(), resolved(&graph, name));
+            print_tree(&graph, name, depth);
         }
     }
 
@@ -93,17 +103,55 @@ pub async fn execute(opts: ListOptions) -> Result<()> {
     if !dev_dependencies.is_empty() {
         println!("\n{}:", style("devDependencies").magenta().bold());
         for (name, version) in &dev_dependencies {
-            println!("  {} {}", name, style(version.as_str().unwrap_or("")).dim());
+            println!("  {} {}{}", name, style(version.as_str().unwrap_or("")).dim(), resolved(&graph, name));
+            print_tree(&graph, name, depth);
         }
     }
 
     // Print summary
     println!(
         "\n{} {} dependencies, {} dev dependencies",
-        style("✓").green(),
+        style(icon("✓")).green(),
         dependencies.len(),
         dev_dependencies.len()
     );
+    if depth > 0 && graph.is_none() {
+        println!("{}", style("No dependency graph saved for rjs-lock.json; run `rjs install` to see what's below package.json").dim());
+    }
 
     Ok(())
 }
+
+// " -> 1.2.3" for a direct dependency the saved graph knows
+fn resolved(graph: &Option<Graph>, name: &str) -> String {
+    match graph.as_ref().and_then(|graph| graph.roots.get(name)) {
+        Some(edge) => style(format!(" -> {}", edge.version())).green().to_string(),
+        None => String::new(),
+    }
+}
+
+// The dependencies under a direct dependency, `depth` levels deep
+fn print_tree(graph: &Option<Graph>, name: &str, depth: usize) {
+    let Some(graph) = graph else {
+        return;
+    };
+    if let Some(edge) = graph.roots.get(name) {
+        print_edges(graph, edge, 1, depth);
+    }
+}
+
+fn print_edges(graph: &Graph, edge: &Edge, level: usize, depth: usize) {
+    if level > depth {
+        return;
+    }
+    for (name, child) in graph.edges(Some(&edge.to)).into_iter().flatten() {
+        println!(
+            "{}{} {} {}",
+            "  ".repeat(level + 1),
+            name,
+            style(&child.range).dim(),
+            style(format!("-> {}", child.version())).green()
+        );
+        print_edges(graph, child, level + 1, depth);
+    }
+}
@@ -1,3 +1,43 @@
+#[cfg(unix)]
+pub mod daemon;
+pub mod access;
+pub mod analyze;
+pub mod audit;
+pub mod bench;
+pub mod bundle;
+pub mod cache;
+pub mod config;
+pub mod create;
+pub mod deploy;
+pub mod dist_tag;
+pub mod dupes;
+pub mod env;
+pub mod explain;
+pub mod fetch;
+pub mod fund;
+pub mod graph;
 pub mod init;
 pub mod install;
+pub mod lint;
 pub mod list;
+pub mod outdated;
+pub mod owner;
+pub mod pack;
+pub mod patch;
+pub mod patch_commit;
+pub mod paths;
+pub mod ping;
+pub mod plugin;
+pub mod publish;
+pub mod run;
+pub mod self_update;
+pub mod serve;
+pub mod shrinkwrap;
+pub mod token;
+pub mod uninstall;
+pub mod unpublish;
+pub mod update;
+pub mod vendor;
+pub mod verify;
+pub mod view;
+pub mod workspaces;
@@ -0,0 +1,248 @@
+use anyhow::Result;
+use clap::Args;
+use console::style;
+use futures::stream::{self, StreamExt};
+use log::warn;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::dependency::{self, lockfile_key_name, versions, Lockfile};
+use crate::npmrc::Npmrc;
+use crate::registry::{NpmRegistry, PackageInfo};
+use crate::utils::{self, icon};
+use crate::workspace::{self, catalog::Catalogs};
+
+// Packuments fetched at once
+const FETCH_CONCURRENCY: usize = 16;
+
+#[derive(Args)]
+pub struct OutdatedOptions {
+    /// Print the report as JSON, grouped by workspace
+    #[arg(long)]
+    json: bool,
+
+    /// Registry to check for newer versions, instead of the registry setting
+    #[arg(long)]
+    registry: Option<String>,
+
+    /// Also show each package's downloads in the last week, one more request per package
+    #[arg(long)]
+    downloads: bool,
+}
+
+// A direct dependency behind its latest version
+#[derive(Serialize)]
+struct Outdated {
+    name: String,
+    // The locked version the range picks, if any
+    current: Option<String>,
+    // The newest version the range allows
+    wanted: Option<String>,
+    latest: Option<String>,
+    // When latest was published, as the registry gives it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    downloads: Option<u64>,
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+#[derive(Serialize)]
+struct Group {
+    workspace: String,
+    name: String,
+    packages: Vec<Outdated>,
+}
+
+pub async fn execute(opts: OutdatedOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let (root, members) = workspace::members(&cwd)?;
+    let lockfile = match std::fs::read_to_string(root.join("rjs-lock.json")) {
+        Ok(content) => serde_json::from_str(&content)?,
+        Err(_) => Lockfile::new("root", "0.0.0"),
+    };
+
+    let catalogs = Catalogs::load(&root)?;
+    let mut dependencies = Vec::new();
+    for member in &members {
+        let package = dependency::read_package_json(&member.dir.join("package.json")).await?;
+        let mut direct: Vec<(String, String, &'static str)> = package
+            .dependencies
+            .into_iter()
+            .map(|(name, spec)| (name, spec, "dependencies"))
+            .chain(package.dev_dependencies.into_iter().map(|(name, spec)| (name, spec, "devDependencies")))
+            .map(|(name, spec, kind)| {
+                let spec = catalogs.resolve(&name, &spec)?.to_string();
+                Ok((name, spec, kind))
+            })
+            .collect::<Result<_>>()?;
+        direct.sort();
+        dependencies.push((member, direct));
+    }
+
+    // Each package is looked up once, however many workspaces use it
+    let registry = NpmRegistry::for_registry(opts.registry.as_deref()).with_npmrc(Npmrc::load(&cwd));
+    let names: BTreeSet<String> = dependencies
+        .iter()
+        .flat_map(|(_, direct)| direct.iter().map(|(name, _, _)| name.clone()))
+        .collect();
+    let packuments: HashMap<String, PackageInfo> = stream::iter(names)
+        .map(|name| {
+            let registry = &registry;
+            async move {
+                let info = registry.get_package_info(&name).await;
+                (name, info)
+            }
+        })
+        .buffer_unordered(FETCH_CONCURRENCY)
+        .filter_map(|(name, info)| async move {
+            match info {
+                Ok(info) => Some((name, info)),
+                Err(e) => {
+                    warn!("Failed to look up {}: {:#}", name, e);
+                    None
+                }
+            }
+        })
+        .collect()
+        .await;
+
+    // Download counts come from a separate endpoint, so only when asked
+    let downloads: HashMap<String, u64> = if opts.downloads {
+        stream::iter(packuments.keys())
+            .map(|name| {
+                let registry = &registry;
+                async move { (name.clone(), registry.weekly_downloads(name).await) }
+            })
+            .buffer_unordered(FETCH_CONCURRENCY)
+            .filter_map(|(name, downloads)| async move {
+                match downloads {
+                    Ok(downloads) => Some((name, downloads?)),
+                    Err(e) => {
+                        warn!("Failed to get download counts for {}: {:#}", name, e);
+                        None
+                    }
+                }
+            })
+            .collect()
+            .await
+    } else {
+        HashMap::new()
+    };
+
+    let mut groups = Vec::new();
+    for (member, direct) in dependencies {
+        let packages: Vec<Outdated> = direct
+            .into_iter()
+            .map(|(name, spec, kind)| {
+                let info = packuments.get(&name);
+                let current = locked_version(&lockfile, &name, &spec);
+                let wanted = info.and_then(|info| wanted_version(info, &spec));
+                let latest = info.and_then(|info| info.dist_tags.get("latest").cloned());
+                let published = info.zip(latest.as_ref()).and_then(|(info, latest)| info.time.get(latest).cloned());
+                let downloads = downloads.get(&name).copied();
+                Outdated { name, current, wanted, latest, published, downloads, kind }
+            })
+            .filter(|outdated| outdated.current.is_none() || (outdated.latest.is_some() && outdated.current != outdated.latest))
+            .collect();
+        groups.push(Group {
+            workspace: member.path.clone(),
+            name: member.name.clone(),
+            packages,
+        });
+    }
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&groups)?);
+        return Ok(());
+    }
+
+    let grouped = groups.len() > 1;
+    let total: usize = groups.iter().map(|group| group.packages.len()).sum();
+    if total == 0 {
+        println!("{} Every dependency is up to date", style(icon("✓")).green());
+        return Ok(());
+    }
+    let width = groups
+        .iter()
+        .flat_map(|group| group.packages.iter().map(|outdated| outdated.name.len()))
+        .max()
+        .unwrap_or(0)
+        .max("Package".len());
+    let missing = || "-".to_string();
+    let downloads_column = if opts.downloads { format!("{:<11} ", "Downloads") } else { String::new() };
+    for group in groups.iter().filter(|group| !group.packages.is_empty()) {
+        if grouped {
+            println!("{} ({})", style(&group.workspace).bold(), group.name);
+        }
+        println!(
+            "  {}",
+            style(format!(
+                "{:<width$}  {:<10} {:<10} {:<10} {:<14} {}Type",
+                "Package", "Current", "Wanted", "Latest", "Age", downloads_column
+            ))
+            .dim()
+        );
+        for outdated in &group.packages {
+            // Red when the range allows an update, yellow when only a new major does
+            let name = format!("{:<width$}", outdated.name);
+            let name = if outdated.current != outdated.wanted {
+                style(name).red()
+            } else {
+                style(name).yellow()
+            };
+            // How long latest has been out, to judge how settled it is
+            let age = outdated
+                .published
+                .as_deref()
+                .and_then(|time| humantime::parse_rfc3339_weak(time).ok())
+                .map(utils::format_age)
+                .unwrap_or_else(missing);
+            let downloads = match (opts.downloads, outdated.downloads) {
+                (false, _) => String::new(),
+                (true, Some(downloads)) => format!("{:<11} ", downloads),
+                (true, None) => format!("{:<11} ", missing()),
+            };
+            println!(
+                "  {}  {:<10} {:<10} {:<10} {:<14} {}{}",
+                name,
+                outdated.current.clone().unwrap_or_else(missing),
+                outdated.wanted.clone().unwrap_or_else(missing),
+                outdated.latest.clone().unwrap_or_else(missing),
+                age,
+                downloads,
+                outdated.kind
+            );
+        }
+    }
+    Ok(())
+}
+
+// The highest locked version of `name` that `spec` accepts
+fn locked_version(lockfile: &Lockfile, name: &str, spec: &str) -> Option<String> {
+    let req = versions::parse_req_or_any(spec);
+    lockfile
+        .packages
+        .iter()
+        .filter(|(key, _)| lockfile_key_name(key) == name)
+        .filter_map(|(_, entry)| versions::parse_version(&entry.version))
+        .filter(|version| req.matches(version))
+        .max()
+        .map(|version| version.to_string())
+}
+
+// The newest published version `spec` accepts; dist-tags name theirs
+fn wanted_version(info: &PackageInfo, spec: &str) -> Option<String> {
+    if let Some(tagged) = info.dist_tags.get(spec) {
+        return Some(tagged.clone());
+    }
+    let req = versions::parse_req(spec)?;
+    info.versions
+        .keys()
+        .filter_map(|version| versions::parse_version(version))
+        .filter(|version| req.matches(version))
+        .max()
+        .map(|version| version.to_string())
+}
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use console::style;
+use std::path::Path;
+
+use crate::npmrc::Npmrc;
+use crate::pack;
+use crate::registry::NpmRegistry;
+
+#[derive(Args)]
+pub struct OwnerOptions {
+    #[command(subcommand)]
+    action: OwnerAction,
+
+    /// One-time password from your authenticator, for accounts with two-factor auth
+    #[arg(long, global = true)]
+    otp: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum OwnerAction {
+    /// List the accounts that can publish a package (default: the current package)
+    Ls {
+        package: Option<String>,
+    },
+    /// Let a registry user publish a package
+    Add {
+        user: String,
+        /// Package to share (default: the current package)
+        package: Option<String>,
+    },
+    /// Stop a registry user from publishing a package
+    Rm {
+        user: String,
+        /// Package to change (default: the current package)
+        package: Option<String>,
+    },
+}
+
+pub async fn execute(opts: OwnerOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let npmrc = Npmrc::load(&cwd);
+    let client = |name: &str| NpmRegistry::logged_in(&npmrc.registry_for(name), &npmrc).with_otp(opts.otp.clone());
+    let package_name = |package: &Option<String>| match package {
+        Some(package) => Ok(package.clone()),
+        None => pack::package_id(&cwd).map(|(name, _)| name),
+    };
+
+    match &opts.action {
+        OwnerAction::Ls { package } => {
+            let name = package_name(package)?;
+            for owner in client(&name).owners(&name).await? {
+                match owner.email {
+                    Some(email) => println!("{} <{}>", owner.name, email),
+                    None => println!("{}", owner.name),
+                }
+            }
+        }
+        OwnerAction::Add { user, package } => {
+            let name = package_name(package)?;
+            if client(&name).add_owner(&name, user).await? {
+                println!("{} {} can now publish {}", style("+").green(), user, name);
+            } else {
+                println!("{} already owns {}", user, name);
+            }
+        }
+        OwnerAction::Rm { user, package } => {
+            let name = package_name(package)?;
+            if client(&name).remove_owner(&name, user).await? {
+                println!("{} {} can no longer publish {}", style("-").red(), user, name);
+            } else {
+                println!("{} isn't an owner of {}", user, name);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use std::path::{Path, PathBuf};
+
+use crate::pack::{PackList, Tarball};
+use crate::utils::icon;
+use crate::utils;
+
+#[derive(Args)]
+pub struct PackOptions {
+    /// List what would be packed without writing the tarball
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Directory to write the tarball to (default: the current directory)
+    #[arg(long)]
+    pack_destination: Option<PathBuf>,
+}
+
+pub async fn execute(opts: PackOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let list = PackList::load(&cwd)?;
+    let tarball = list.tarball()?;
+    print_contents(&list, &tarball);
+
+    if opts.dry_run {
+        return Ok(());
+    }
+
+    let destination = opts.pack_destination.unwrap_or(cwd);
+    utils::ensure_dir(&destination).await?;
+    let path = destination.join(list.file_name());
+    tokio::fs::write(&path, &tarball.data)
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    println!("{}", path.display());
+    Ok(())
+}
+
+/// npm's "Tarball Contents" and "Tarball Details" report.
+pub fn print_contents(list: &PackList, tarball: &Tarball) {
+    println!(
+        "{} {}",
+        style(icon("📦")).bold().cyan(),
+        style(format!("{}@{}", list.name, list.version)).bold()
+    );
+
+    println!("{}", style("Tarball Contents").bold());
+    let sizes: Vec<String> = list.files.iter().map(|file| utils::format_size(file.size)).collect();
+    let width = sizes.iter().map(String::len).max().unwrap_or(0);
+    for (file, size) in list.files.iter().zip(&sizes) {
+        println!("  {:>width$} {}", style(size).dim(), file.path, width = width);
+    }
+
+    println!("{}", style("Tarball Details").bold());
+    println!("  name:          {}", list.name);
+    println!("  version:       {}", list.version);
+    println!("  filename:      {}", list.file_name());
+    println!("  package size:  {}", utils::format_size(tarball.data.len() as u64));
+    println!("  unpacked size: {}", utils::format_size(list.unpacked_size()));
+    println!("  shasum:        {}", tarball.shasum);
+    println!("  integrity:     {}", tarball.integrity);
+    println!("  total files:   {}", list.files.len());
+}
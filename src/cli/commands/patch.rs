@@ -0,0 +1,98 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use std::path::{Path, PathBuf};
+
+use crate::cache::TarballStore;
+use crate::dependency::events::NoopEvents;
+use crate::dependency::patches::{self, Patches};
+use crate::dependency::{lockfile_key_name, Lockfile, LockfileEntry, Package};
+use crate::registry::NpmRegistry;
+use crate::utils::icon;
+
+#[derive(Args)]
+pub struct PatchOptions {
+    /// Installed package to patch, with its version when several are installed (e.g. lodash@4.17.21)
+    package: String,
+
+    /// Directory to extract the package into (default: a new temp directory)
+    #[arg(long)]
+    edit_dir: Option<PathBuf>,
+
+    /// Start from the published package instead of the one its current patch gives
+    #[arg(long)]
+    ignore_existing: bool,
+}
+
+pub async fn execute(opts: PatchOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let lockfile = super::fetch::read_lockfile(&cwd).await?;
+    let (key, entry) = locked(&lockfile, &opts.package)?;
+    let package = entry.to_package(lockfile_key_name(key));
+
+    let edit_dir = match opts.edit_dir {
+        Some(dir) => {
+            if dir.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
+                anyhow::bail!("{} isn't empty", dir.display());
+            }
+            dir
+        }
+        None => {
+            let name = patches::file_name(&package.name, &package.version);
+            let dir = std::env::temp_dir().join(format!("rjs-patch-{}", name.trim_end_matches(".patch")));
+            if dir.exists() {
+                std::fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+            }
+            dir
+        }
+    };
+    extract(&package, &edit_dir).await?;
+
+    // Edits build on the changes already patched in
+    if !opts.ignore_existing && let Some(patch) = Patches::load(&cwd)?.get(&package.name, &package.version) {
+        patches::apply(patch, &edit_dir)?;
+    }
+
+    println!(
+        "{} Extracted {} into {}",
+        style(icon("✓")).green(),
+        style(key).bold(),
+        edit_dir.display()
+    );
+    println!("  Edit it, then run `rjs patch-commit {}`", edit_dir.display());
+    Ok(())
+}
+
+// The lockfile entry `package` ("name" or "name@version") names
+fn locked<'a>(lockfile: &'a Lockfile, package: &str) -> Result<(&'a String, &'a LockfileEntry)> {
+    if let Some(found) = lockfile.packages.get_key_value(package) {
+        return Ok(found);
+    }
+    let mut matches: Vec<(&String, &LockfileEntry)> = lockfile
+        .packages
+        .iter()
+        .filter(|(key, _)| lockfile_key_name(key) == package)
+        .collect();
+    matches.sort_by(|a, b| a.0.cmp(b.0));
+    match matches.as_slice() {
+        [found] => Ok(*found),
+        [] => anyhow::bail!("{} isn't in rjs-lock.json", package),
+        _ => {
+            let keys: Vec<&str> = matches.iter().map(|(key, _)| key.as_str()).collect();
+            anyhow::bail!("{} is locked at several versions ({}); pick one", package, keys.join(", "))
+        }
+    }
+}
+
+// Extract the published tarball of `package` into `dir`
+pub(super) async fn extract(package: &Package, dir: &Path) -> Result<()> {
+    let dist = package
+        .dist
+        .as_ref()
+        .with_context(|| format!("{}@{} has no tarball in rjs-lock.json", package.name, package.version))?;
+    let registry = NpmRegistry::for_registry(package.registry.as_deref());
+    let (tarball, _) = TarballStore::open()?
+        .fetch(&registry, &dist.tarball, dist.integrity.as_deref(), &package.name, &NoopEvents)
+        .await?;
+    registry.extract_tarball(&tarball, dir)
+}
@@ -0,0 +1,81 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::dependency::installer::{Installer, Plan};
+use crate::dependency::patches::{self, Patches, PATCHES_DIR};
+use crate::dependency::{lockfile_key_name, DependencyResolver};
+use crate::npmrc::Npmrc;
+use crate::registry::NpmRegistry;
+use crate::utils::icon;
+
+#[derive(Args)]
+pub struct PatchCommitOptions {
+    /// Directory `rjs patch` extracted the package into
+    edit_dir: PathBuf,
+}
+
+pub async fn execute(opts: PatchCommitOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let mut lockfile = super::fetch::read_lockfile(&cwd).await?;
+
+    let manifest_path = opts.edit_dir.join("package.json");
+    let content = std::fs::read_to_string(&manifest_path)
+        .with_context(|| format!("No package.json in {}; run `rjs patch <package>` first", opts.edit_dir.display()))?;
+    let manifest: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+    let (Some(name), Some(version)) = (manifest["name"].as_str(), manifest["version"].as_str()) else {
+        anyhow::bail!("{} has no name or version", manifest_path.display());
+    };
+    let key = format!("{}@{}", name, version);
+    let package = lockfile
+        .packages
+        .get(&key)
+        .with_context(|| format!("{} isn't in rjs-lock.json", key))?
+        .to_package(lockfile_key_name(&key));
+
+    // What was edited, against a fresh copy of the published package
+    let original = std::env::temp_dir().join(format!("rjs-patch-original-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&original);
+    let diff = match super::patch::extract(&package, &original).await {
+        Ok(()) => patches::diff(&original, &opts.edit_dir),
+        Err(e) => Err(e),
+    };
+    let _ = std::fs::remove_dir_all(&original);
+    let diff = diff?;
+
+    let relative = format!("{}/{}", PATCHES_DIR, patches::file_name(name, version));
+    let patch_path = cwd.join(&relative);
+    if diff.is_empty() {
+        if !patch_path.exists() {
+            println!("{} {} has no changes to save", style(icon("✓")).green(), style(&key).bold());
+            return Ok(());
+        }
+        std::fs::remove_file(&patch_path).with_context(|| format!("Failed to remove {}", patch_path.display()))?;
+        println!("{} {} has no changes anymore; removed {}", style(icon("✓")).green(), style(&key).bold(), relative);
+    } else {
+        std::fs::create_dir_all(cwd.join(PATCHES_DIR))?;
+        std::fs::write(&patch_path, &diff).with_context(|| format!("Failed to write {}", patch_path.display()))?;
+        println!("{} Saved {}", style(icon("✓")).green(), style(&relative).bold());
+    }
+
+    // Installed again with the patch, which the lockfile records
+    let patches = Patches::load(&cwd)?;
+    let patch_hash = patches.get(name, version).map(|patch| patch.hash.clone());
+    let resolver = DependencyResolver::new(NpmRegistry::new().with_npmrc(Npmrc::load(&cwd))).with_patches(patches).with_root(&cwd);
+    let plan = Plan {
+        install: vec![Arc::new(package)],
+        remove: Vec::new(),
+        lockfile: None,
+        graph: None,
+    };
+    Installer::new(&resolver, &cwd).apply(plan).await?;
+    if let Some(entry) = lockfile.packages.get_mut(&key) {
+        entry.patch_hash = patch_hash;
+    }
+    resolver.save_lockfile(&lockfile, &cwd).await?;
+    println!("  {} Applied to node_modules/{}", style(icon("•")).cyan(), name);
+    Ok(())
+}
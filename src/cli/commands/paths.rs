@@ -0,0 +1,68 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::path::{Path, PathBuf};
+
+use crate::npmrc::Npmrc;
+use crate::utils;
+
+#[derive(Args)]
+pub struct PathOptions {
+    /// The global location, under npm's global prefix, instead of the project's
+    #[arg(short, long)]
+    global: bool,
+}
+
+/// `rjs root`: the node_modules directory packages are installed into.
+pub async fn root(opts: PathOptions, project: &Path) -> Result<()> {
+    let root = if opts.global {
+        // Global packages live in <prefix>/lib/node_modules, and on Windows
+        // straight in <prefix>/node_modules
+        let prefix = global_prefix(project)?;
+        if cfg!(windows) { prefix.join("node_modules") } else { prefix.join("lib").join("node_modules") }
+    } else {
+        project.join("node_modules")
+    };
+    println!("{}", root.display());
+    Ok(())
+}
+
+/// `rjs bin`: the directory installed packages' executables are linked into.
+pub async fn bin(opts: PathOptions, project: &Path) -> Result<()> {
+    let bin = if opts.global {
+        let prefix = global_prefix(project)?;
+        if cfg!(windows) { prefix } else { prefix.join("bin") }
+    } else {
+        project.join("node_modules").join(".bin")
+    };
+    println!("{}", bin.display());
+    Ok(())
+}
+
+/// `rjs prefix`: the project directory, or npm's global prefix.
+pub async fn prefix(opts: PathOptions, project: &Path) -> Result<()> {
+    let prefix = if opts.global { global_prefix(project)? } else { project.to_path_buf() };
+    println!("{}", prefix.display());
+    Ok(())
+}
+
+// Where npm installs global packages: npm_config_prefix, or the prefix in
+// .npmrc, or else derived from node's location the way npm does, the
+// directory above node's bin/ (node's own directory on Windows)
+fn global_prefix(project: &Path) -> Result<PathBuf> {
+    if let Some(prefix) = std::env::var_os("npm_config_prefix").filter(|prefix| !prefix.is_empty()) {
+        return Ok(PathBuf::from(prefix));
+    }
+    if let Some(prefix) = Npmrc::load(project).get("prefix") {
+        return Ok(PathBuf::from(prefix));
+    }
+
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let node = utils::find_on(&path, "node")
+        .context("Can't tell the global prefix: node isn't on PATH and npm_config_prefix isn't set")?;
+    let node = node.canonicalize().unwrap_or(node);
+    let bin = node.parent().context("node has no parent directory")?;
+    if cfg!(windows) {
+        return Ok(bin.to_path_buf());
+    }
+    Ok(bin.parent().unwrap_or(bin).to_path_buf())
+}
@@ -0,0 +1,132 @@
+use anyhow::Result;
+use clap::Args;
+use console::style;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::config;
+use crate::npmrc::Npmrc;
+use crate::registry::NpmRegistry;
+use crate::utils::icon;
+
+#[derive(Args)]
+pub struct PingOptions {
+    /// Only ping this registry (default: the configured registry and every scope registry in .npmrc)
+    #[arg(long)]
+    registry: Option<String>,
+
+    /// Print as JSON: [{"registry", "scopes", "time_ms", "error", "user", "auth_error"}]
+    #[arg(long)]
+    json: bool,
+}
+
+// How one registry answered
+#[derive(Serialize)]
+struct Ping {
+    registry: String,
+    // The scopes routed to this registry; empty for the default one
+    scopes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    // Whom the token in .npmrc logs in as, when there's one and it's accepted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auth_error: Option<String>,
+}
+
+impl Ping {
+    fn failed(&self) -> bool {
+        self.error.is_some() || self.auth_error.is_some()
+    }
+}
+
+pub async fn execute(opts: PingOptions, root: &Path) -> Result<()> {
+    let npmrc = Npmrc::load(root);
+
+    // Each registry once, with the scopes that go to it
+    let mut registries: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    match &opts.registry {
+        Some(url) => {
+            registries.insert(url.trim_end_matches('/').to_string(), Vec::new());
+        }
+        None => {
+            registries.insert(config::settings().registry.clone(), Vec::new());
+            for (scope, url) in npmrc.scope_registries() {
+                registries.entry(url.trim_end_matches('/').to_string()).or_default().push(scope.to_string());
+            }
+        }
+    }
+
+    let pings = registries.into_iter().map(|(url, scopes)| ping(url, scopes, &npmrc));
+    let pings = futures::future::join_all(pings).await;
+    let failed = pings.iter().filter(|ping| ping.failed()).count();
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&pings)?);
+    } else {
+        for ping in &pings {
+            print(ping);
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{} of {} registries failed the check", failed, pings.len());
+    }
+    Ok(())
+}
+
+async fn ping(registry_url: String, scopes: Vec<String>, npmrc: &Npmrc) -> Ping {
+    let registry = NpmRegistry::logged_in(&registry_url, npmrc);
+    let mut ping = Ping {
+        registry: registry_url.clone(),
+        scopes,
+        time_ms: None,
+        error: None,
+        user: None,
+        auth_error: None,
+    };
+    match registry.ping().await {
+        Ok(elapsed) => ping.time_ms = Some(elapsed.as_millis()),
+        Err(e) => {
+            ping.error = Some(format!("{:#}", e));
+            return ping;
+        }
+    }
+    // Only checked with a token: without one there's nothing to validate
+    if npmrc.auth_token(&registry_url).is_some() {
+        match registry.whoami().await {
+            Ok(user) => ping.user = Some(user),
+            Err(e) => ping.auth_error = Some(format!("{:#}", e)),
+        }
+    }
+    ping
+}
+
+fn print(ping: &Ping) {
+    let name = if ping.scopes.is_empty() {
+        ping.registry.clone()
+    } else {
+        format!("{} ({})", ping.registry, ping.scopes.join(", "))
+    };
+    if let Some(error) = &ping.error {
+        println!("{} {}", style(icon("✗")).red(), name);
+        println!("    {}", style(error).red());
+        return;
+    }
+    let auth = match (&ping.user, &ping.auth_error) {
+        (Some(user), _) => format!("logged in as {}", user),
+        (_, Some(_)) => style("token rejected").red().to_string(),
+        _ => style("no token").dim().to_string(),
+    };
+    let mark = match ping.auth_error {
+        Some(_) => style(icon("✗")).red(),
+        None => style(icon("✓")).green(),
+    };
+    println!("{} {}  {}ms  {}", mark, name, ping.time_ms.unwrap_or_default(), auth);
+    if let Some(error) = &ping.auth_error {
+        println!("    {}", style(error).red());
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::utils;
+
+/// Run `rjs <name> args...` for a command rjs doesn't have by handing it to an
+/// `rjs-<name>` executable on PATH, the way cargo runs `cargo-<name>`. The
+/// plugin gets the project's context in environment variables:
+///
+/// - `RJS`: the rjs executable, to run rjs commands with
+/// - `RJS_VERSION`: the version of rjs
+/// - `RJS_PROJECT_DIR`: the closest directory with a package.json, if any
+/// - `RJS_REGISTRY` and `RJS_CACHE_DIR`: the registry and cache in effect
+///
+/// rjs exits with the plugin's exit code.
+pub async fn execute(args: Vec<String>, root: &Path) -> Result<()> {
+    let Some((name, args)) = args.split_first() else {
+        anyhow::bail!("No command given; run `rjs --help` for the list");
+    };
+    let program = format!("rjs-{}", name);
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let Some(plugin) = utils::find_on(&path, &program) else {
+        anyhow::bail!(
+            "Unknown command '{}', and no {} found on PATH; run `rjs --help` for the built-in commands",
+            name,
+            program
+        );
+    };
+    debug!("Running plugin {}", plugin.display());
+
+    let settings = config::settings();
+    let mut command = tokio::process::Command::new(&plugin);
+    command
+        .args(args)
+        .env("RJS_VERSION", env!("CARGO_PKG_VERSION"))
+        .env("RJS_REGISTRY", &settings.registry)
+        .env("RJS_CACHE_DIR", &settings.cache_dir);
+    if let Ok(exe) = std::env::current_exe() {
+        command.env("RJS", exe);
+    }
+    if let Some(project) = project_dir(root) {
+        command.env("RJS_PROJECT_DIR", project);
+    }
+
+    let status = command
+        .status()
+        .await
+        .with_context(|| format!("Failed to run {}", plugin.display()))?;
+    if !status.success() {
+        // The plugin has reported its own error
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+// The closest directory from `dir` up with a package.json
+fn project_dir(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|dir| dir.join("package.json").is_file())
+        .map(|dir| dir.to_path_buf())
+}
@@ -0,0 +1,143 @@
+use anyhow::Result;
+use clap::Args;
+use console::style;
+use std::path::Path;
+
+use crate::dependency::versions;
+use crate::npmrc::Npmrc;
+use crate::pack::PackList;
+use crate::registry::NpmRegistry;
+use crate::utils::icon;
+
+#[derive(Args)]
+pub struct PublishOptions {
+    /// Dist-tag for the new version (default: publishConfig.tag, or "latest")
+    #[arg(long)]
+    tag: Option<String>,
+
+    /// Who can install a scoped package (default: publishConfig.access, or the registry's default)
+    #[arg(long, value_parser = ["public", "restricted"])]
+    access: Option<String>,
+
+    /// Pack and check everything, but don't upload
+    #[arg(long)]
+    pub(super) dry_run: bool,
+
+    /// One-time password from your authenticator, for accounts with two-factor auth
+    #[arg(long)]
+    otp: Option<String>,
+}
+
+pub async fn execute(opts: PublishOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let list = PackList::load(&cwd)?;
+    publish(&list, &cwd, &opts, false).await?;
+    Ok(())
+}
+
+// Publish the packed package, with credentials from the .npmrc in
+// `npmrc_dir`. When `skip_published` is set, a version the registry already
+// has is left alone rather than refused; returns whether it was uploaded, or
+// would have been in a dry run
+pub(super) async fn publish(list: &PackList, npmrc_dir: &Path, opts: &PublishOptions, skip_published: bool) -> Result<bool> {
+    let manifest = &list.manifest;
+
+    if manifest.get("private").and_then(|private| private.as_bool()) == Some(true) {
+        anyhow::bail!(
+            "{} is marked \"private\"; remove it from package.json to publish",
+            list.name
+        );
+    }
+    if versions::parse_version(&list.version).is_none() {
+        anyhow::bail!("Invalid version \"{}\" in package.json", list.version);
+    }
+
+    let publish_config = |key: &str| {
+        manifest
+            .get("publishConfig")
+            .and_then(|config| config.get(key))
+            .and_then(|value| value.as_str())
+            .map(str::to_string)
+    };
+    let tag = opts
+        .tag
+        .clone()
+        .or_else(|| publish_config("tag"))
+        .unwrap_or_else(|| "latest".to_string());
+    // A tag that reads as a range would shadow versions in `install name@tag`
+    if versions::parse_req(&tag).is_some() {
+        anyhow::bail!("Tag \"{}\" looks like a version range; pick a name like \"next\"", tag);
+    }
+    let access = opts.access.clone().or_else(|| publish_config("access"));
+
+    let npmrc = Npmrc::load(npmrc_dir);
+    let registry_url = publish_config("registry")
+        .map(|registry| registry.trim_end_matches('/').to_string())
+        .unwrap_or_else(|| npmrc.registry_for(&list.name));
+    let registry = NpmRegistry::logged_in(&registry_url, &npmrc).with_otp(opts.otp.clone());
+
+    let tarball = list.tarball()?;
+    super::pack::print_contents(list, &tarball);
+    println!("  tag:           {}", tag);
+    println!("  access:        {}", access.as_deref().unwrap_or("default"));
+    println!("  registry:      {}", registry_url);
+
+    let published = match registry.published_versions(&list.name).await {
+        Ok(published) => published,
+        // A preview is still useful when the registry can't be reached
+        Err(e) if opts.dry_run => {
+            println!(
+                "\n{} Couldn't check {}'s published versions: {:#}",
+                style("!").yellow(),
+                list.name,
+                e
+            );
+            Some(Vec::new())
+        }
+        Err(e) => return Err(e.context(format!("Failed to check {}'s published versions", list.name))),
+    };
+    if published.as_ref().is_some_and(|published| published.contains(&list.version)) {
+        if skip_published {
+            println!(
+                "\n{} {}@{} is already published; skipped",
+                style(icon("•")).cyan(),
+                list.name,
+                list.version
+            );
+            return Ok(false);
+        }
+        anyhow::bail!(
+            "{}@{} is already published to {}; bump \"version\" in package.json",
+            list.name,
+            list.version,
+            registry_url
+        );
+    }
+
+    if opts.dry_run {
+        let new = if published.is_none() { " (a new package)" } else { "" };
+        println!(
+            "\n{} Would publish {}@{}{} to {} with tag {} (dry run)",
+            style(icon("✓")).green(),
+            list.name,
+            list.version,
+            new,
+            registry_url,
+            tag
+        );
+        return Ok(true);
+    }
+
+    registry
+        .publish(manifest, &tarball, &tag, access.as_deref())
+        .await?;
+    println!(
+        "\n{} Published {}@{} to {} with tag {}",
+        style(icon("✓")).green(),
+        list.name,
+        list.version,
+        registry_url,
+        tag
+    );
+    Ok(true)
+}
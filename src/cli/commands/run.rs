@@ -0,0 +1,178 @@
+use anyhow::Result;
+use clap::Args;
+use console::style;
+use dialoguer::FuzzySelect;
+use std::path::Path;
+
+use crate::package_manager;
+use crate::scripts::watch::Watcher;
+use crate::scripts::{self, Scripts};
+use crate::utils::icon;
+
+#[derive(Args)]
+pub struct RunOptions {
+    /// Scripts from package.json to run; picks one interactively when omitted,
+    /// or lists them without a terminal
+    scripts: Vec<String>,
+
+    /// Run the scripts at the same time, prefixing each line of output with its script
+    #[arg(short, long, conflicts_with = "sequential")]
+    parallel: bool,
+
+    /// Run the scripts one after another, stopping at the first failure (the default)
+    #[arg(short, long)]
+    sequential: bool,
+
+    /// Run again whenever files not ignored by .gitignore change
+    #[arg(short, long)]
+    watch: bool,
+
+    /// Arguments after `--`, appended to each script's command
+    #[arg(last = true)]
+    args: Vec<String>,
+}
+
+pub async fn execute(mut opts: RunOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let mut scripts = Scripts::load(&cwd)?;
+
+    if opts.scripts.is_empty() {
+        let interactive = atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stdout);
+        match pick(&scripts, interactive)? {
+            Some(name) => opts.scripts.push(name),
+            None => return Ok(()),
+        }
+    }
+
+    // Fail before starting anything if a script doesn't exist
+    if let Some(missing) = opts.scripts.iter().find(|name| scripts.get(name).is_none()) {
+        anyhow::bail!("Missing script: \"{}\"", missing);
+    }
+
+    package_manager::check(&cwd)?;
+    scripts::engines::check(&cwd)?;
+
+    if opts.watch {
+        scripts = scripts.with_process_groups();
+        return watch(&cwd, &scripts, &opts).await;
+    }
+    run_scripts(&scripts, &opts).await
+}
+
+async fn run_scripts(scripts: &Scripts, opts: &RunOptions) -> Result<()> {
+    if opts.parallel {
+        run_parallel(scripts, &opts.scripts, &opts.args).await
+    } else {
+        for name in &opts.scripts {
+            scripts.run(name, &opts.args).await?;
+        }
+        Ok(())
+    }
+}
+
+// Run the scripts, restarting them whenever project files change, until Ctrl-C
+async fn watch(cwd: &std::path::Path, scripts: &Scripts, opts: &RunOptions) -> Result<()> {
+    let mut watcher = Watcher::new(cwd)?;
+
+    loop {
+        let changed = tokio::select! {
+            result = run_scripts(scripts, opts) => {
+                match result {
+                    Ok(()) => println!("\n{} Done, waiting for changes...", style(icon("✓")).green()),
+                    Err(e) => println!("\n{} {:#}, waiting for changes...", style(icon("✗")).red(), e),
+                }
+                tokio::select! {
+                    changed = watcher.changed() => changed?,
+                    _ = tokio::signal::ctrl_c() => return Ok(()),
+                }
+            }
+            // Dropping the run stops the scripts still going
+            changed = watcher.changed() => changed?,
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        };
+
+        let more = match changed.len() {
+            1 => String::new(),
+            n => format!(" and {} more", n - 1),
+        };
+        println!(
+            "\n{} {}{} changed, restarting {}",
+            style(icon("↻")).cyan().bold(),
+            changed[0].display(),
+            more,
+            style(icon("─").repeat(20)).dim()
+        );
+    }
+}
+
+// Run every script to completion, even when some fail, then report all failures
+async fn run_parallel(scripts: &Scripts, names: &[String], args: &[String]) -> Result<()> {
+    let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+    let prefixes: Vec<String> = names
+        .iter()
+        .zip(scripts::prefix_styles())
+        .map(|(name, style)| style.apply_to(format!("[{:<width$}]", name, width = width)).to_string())
+        .collect();
+
+    let results = futures::future::join_all(
+        names
+            .iter()
+            .zip(&prefixes)
+            .map(|(name, prefix)| scripts.run_prefixed(name, args, prefix)),
+    )
+    .await;
+
+    let failures: Vec<String> = results
+        .into_iter()
+        .filter_map(|result| result.err())
+        .map(|e| format!("  {:#}", e))
+        .collect();
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    anyhow::bail!(
+        "{} of {} scripts failed:\n{}",
+        failures.len(),
+        names.len(),
+        failures.join("\n")
+    )
+}
+
+// Let the user choose a script from a fuzzy-searchable list. Without a
+// terminal, or with no scripts to choose from, the scripts are listed instead
+fn pick(scripts: &Scripts, interactive: bool) -> Result<Option<String>> {
+    let names: Vec<&str> = scripts.iter().map(|(name, _)| name).collect();
+    if !interactive || names.is_empty() {
+        list(scripts);
+        return Ok(None);
+    }
+
+    let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+    let items: Vec<String> = scripts
+        .iter()
+        .map(|(name, script)| format!("{:<width$}  {}", name, script, width = width))
+        .collect();
+
+    // Escape cancels without running anything
+    let choice = FuzzySelect::new()
+        .with_prompt("Script to run")
+        .items(&items)
+        .default(0)
+        .interact_opt()?;
+    Ok(choice.map(|index| names[index].to_string()))
+}
+
+fn list(scripts: &Scripts) {
+    let mut scripts = scripts.iter().peekable();
+    if scripts.peek().is_none() {
+        println!("No scripts found in package.json");
+        return;
+    }
+
+    println!("{}:", style("Scripts available via `rjs run`").bold());
+    for (name, script) in scripts {
+        println!("  {}", style(name).green());
+        println!("    {}", style(script).dim());
+    }
+}
@@ -0,0 +1,255 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use clap::{Args, ValueEnum};
+use console::style;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use crate::config;
+use crate::registry;
+use crate::utils::icon;
+
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Args)]
+pub struct SelfUpdateOptions {
+    /// Release channel to follow: tagged releases, or the latest prerelease build
+    #[arg(long, value_enum, default_value_t = Channel::Stable)]
+    channel: Channel,
+
+    /// Only report whether a newer release is available
+    #[arg(long)]
+    check: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Channel {
+    Stable,
+    Nightly,
+}
+
+// A release as GitHub's releases API lists it
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+pub async fn execute(opts: SelfUpdateOptions) -> Result<()> {
+    let settings = config::settings();
+    let client = registry::http_client()?;
+
+    let releases: Vec<Release> = client
+        .get(&settings.update_url)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("Failed to list releases from {}", settings.update_url))?
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse the releases from {}", settings.update_url))?;
+
+    let Some(release) = pick_release(&releases, opts.channel) else {
+        anyhow::bail!("No {:?} release found at {}", opts.channel, settings.update_url);
+    };
+    let version = release.tag_name.trim_start_matches('v');
+
+    // Nightly builds all carry the version of the next release, so any
+    // nightly is taken to be newer
+    if opts.channel == Channel::Stable && !is_newer(version, CURRENT_VERSION) {
+        println!("{} rjs {} is up to date", style(icon("✓")).green(), CURRENT_VERSION);
+        return Ok(());
+    }
+
+    let name = asset_name();
+    let asset = find_asset(release, &name)
+        .with_context(|| format!("Release {} has no binary for this platform ({})", release.tag_name, name))?;
+
+    if opts.check {
+        println!(
+            "{} rjs {} is available (current: {}); run `rjs self-update` to install it",
+            style(icon("ℹ")).blue(),
+            style(version).bold(),
+            CURRENT_VERSION
+        );
+        return Ok(());
+    }
+
+    println!("Downloading {} from {}", name, release.tag_name);
+    let binary = download(&client, &asset.browser_download_url).await?;
+
+    // Nothing is installed without a checksum to compare against
+    let expected = expected_checksum(&client, release, &name).await?;
+    let actual = hex::encode(Sha256::digest(&binary));
+    if !actual.eq_ignore_ascii_case(&expected) {
+        anyhow::bail!(
+            "Checksum mismatch for {} from {}: expected {}, got {}",
+            name,
+            release.tag_name,
+            expected,
+            actual
+        );
+    }
+
+    if let Some(public_key) = &settings.update_public_key {
+        verify_signature(&client, release, &name, &binary, public_key).await?;
+    }
+
+    let exe = std::env::current_exe()
+        .and_then(|exe| exe.canonicalize())
+        .context("Failed to locate the running rjs executable")?;
+    replace_executable(&exe, &binary)?;
+
+    println!(
+        "{} Updated rjs {} to {}",
+        style(icon("✓")).green(),
+        CURRENT_VERSION,
+        style(&release.tag_name).bold()
+    );
+    Ok(())
+}
+
+// The newest stable release by version, or the newest prerelease, which
+// GitHub lists first
+fn pick_release(releases: &[Release], channel: Channel) -> Option<&Release> {
+    let published = releases.iter().filter(|release| !release.draft);
+    match channel {
+        Channel::Stable => published
+            .filter(|release| !release.prerelease)
+            .filter_map(|release| {
+                let version = semver::Version::parse(release.tag_name.trim_start_matches('v')).ok()?;
+                Some((version, release))
+            })
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, release)| release),
+        Channel::Nightly => published.into_iter().find(|release| release.prerelease),
+    }
+}
+
+fn is_newer(version: &str, current: &str) -> bool {
+    match (semver::Version::parse(version), semver::Version::parse(current)) {
+        (Ok(version), Ok(current)) => version > current,
+        _ => version != current,
+    }
+}
+
+// The release binary for this platform, e.g. rjs-x86_64-linux or
+// rjs-aarch64-macos, with .exe on Windows
+fn asset_name() -> String {
+    let name = format!("rjs-{}-{}", std::env::consts::ARCH, std::env::consts::OS);
+    if cfg!(windows) {
+        format!("{}.exe", name)
+    } else {
+        name
+    }
+}
+
+fn find_asset<'a>(release: &'a Release, name: &str) -> Option<&'a Asset> {
+    release.assets.iter().find(|asset| asset.name == name)
+}
+
+async fn download(client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
+    let bytes = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("Failed to download {}", url))?
+        .bytes()
+        .await
+        .with_context(|| format!("Failed to download {}", url))?;
+    Ok(bytes.to_vec())
+}
+
+// The SHA-256 of `name` from its <name>.sha256 asset, or from the release's
+// SHA256SUMS, in the `<hex>  <file>` format sha256sum writes
+async fn expected_checksum(client: &reqwest::Client, release: &Release, name: &str) -> Result<String> {
+    if let Some(asset) = find_asset(release, &format!("{}.sha256", name)) {
+        let text = String::from_utf8_lossy(&download(client, &asset.browser_download_url).await?).to_string();
+        if let Some(hex) = text.split_whitespace().next() {
+            return Ok(hex.to_string());
+        }
+    }
+    if let Some(asset) = find_asset(release, "SHA256SUMS") {
+        let text = String::from_utf8_lossy(&download(client, &asset.browser_download_url).await?).to_string();
+        let line = text.lines().find_map(|line| {
+            let mut parts = line.split_whitespace();
+            let hex = parts.next()?;
+            (parts.next()?.trim_start_matches('*') == name).then(|| hex.to_string())
+        });
+        if let Some(hex) = line {
+            return Ok(hex);
+        }
+    }
+    anyhow::bail!(
+        "Release {} publishes no checksum for {}; refusing to install it",
+        release.tag_name,
+        name
+    )
+}
+
+// Check the base64 Ed25519 signature in the release's <name>.sig against the
+// configured public key
+async fn verify_signature(
+    client: &reqwest::Client,
+    release: &Release,
+    name: &str,
+    binary: &[u8],
+    public_key: &str,
+) -> Result<()> {
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let asset = find_asset(release, &format!("{}.sig", name)).with_context(|| {
+        format!("Release {} has no signature for {}, and update-public-key is set", release.tag_name, name)
+    })?;
+    let signature = download(client, &asset.browser_download_url).await?;
+    let signature = base64
+        .decode(String::from_utf8_lossy(&signature).trim())
+        .with_context(|| format!("Invalid signature for {}", name))?;
+    let public_key = base64
+        .decode(public_key.trim())
+        .context("update-public-key is not valid base64")?;
+
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, public_key)
+        .verify(binary, &signature)
+        .map_err(|_| anyhow::anyhow!("Signature check failed for {} from {}", name, release.tag_name))
+}
+
+// Write the new binary beside `exe` and rename it over, so rjs is never left
+// half-written. Windows won't overwrite a running executable but lets it be
+// renamed aside first
+fn replace_executable(exe: &Path, binary: &[u8]) -> Result<()> {
+    let dir = exe.parent().context("The rjs executable has no parent directory")?;
+    let staged = dir.join(format!(".rjs-update-{}", std::process::id()));
+    std::fs::write(&staged, binary).with_context(|| format!("Failed to write {}", staged.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    }
+    #[cfg(windows)]
+    {
+        let old = exe.with_extension("old.exe");
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(exe, &old).with_context(|| format!("Failed to move {} aside", exe.display()))?;
+    }
+
+    if let Err(e) = std::fs::rename(&staged, exe) {
+        let _ = std::fs::remove_file(&staged);
+        return Err(e).with_context(|| format!("Failed to replace {}", exe.display()));
+    }
+    Ok(())
+}
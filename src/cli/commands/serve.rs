@@ -0,0 +1,51 @@
+use anyhow::Result;
+use clap::Args;
+use console::style;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::proxy;
+use crate::registry::NpmRegistry;
+use crate::utils::icon;
+
+#[derive(Args)]
+pub struct ServeOptions {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    host: IpAddr,
+
+    /// Port to listen on
+    #[arg(short, long, default_value_t = 4873)]
+    port: u16,
+
+    /// Registry to pull packages from (default: the npm registry)
+    #[arg(long)]
+    upstream: Option<String>,
+
+    /// URL clients reach the proxy on, used in tarball links (default: from the Host header)
+    #[arg(long)]
+    public_url: Option<String>,
+}
+
+pub async fn execute(opts: ServeOptions) -> Result<()> {
+    let upstream = match opts.upstream {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => NpmRegistry::new().get_registry_url().to_string(),
+    };
+
+    let listener = proxy::bind(SocketAddr::new(opts.host, opts.port)).await?;
+    let url = format!("http://{}", listener.local_addr()?);
+
+    println!(
+        "{} Registry proxy listening on {} (upstream {}, Ctrl-C to stop)",
+        style(icon("●")).green(),
+        style(&url).bold(),
+        upstream
+    );
+    println!(
+        "  {} Point clients at it with {}",
+        style(icon("•")).yellow(),
+        style(format!("npm config set registry {}", url)).bold()
+    );
+
+    proxy::serve(listener, &upstream, opts.public_url).await
+}
@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::dependency;
+use crate::dependency::shrinkwrap::{self, SHRINKWRAP_FILE};
+use crate::utils::{self, icon};
+use crate::workspace::{self, catalog::Catalogs};
+
+#[derive(Args)]
+pub struct ShrinkwrapOptions {}
+
+pub async fn execute(_opts: ShrinkwrapOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    // A workspace is locked by the root's lockfile
+    let root = workspace::find_root(&cwd).unwrap_or_else(|| cwd.clone());
+    let lockfile = super::fetch::read_lockfile(&root).await?;
+    let package = dependency::read_package_json(&cwd.join("package.json")).await?;
+
+    let catalogs = Catalogs::load(&root)?;
+    let resolve = |specs: &HashMap<String, String>| -> Result<HashMap<String, String>> {
+        specs
+            .iter()
+            .map(|(name, spec)| Ok((name.clone(), catalogs.resolve(name, spec)?.to_string())))
+            .collect()
+    };
+    let (content, pinned) = shrinkwrap::from_lockfile(
+        &package.name,
+        &package.version,
+        &resolve(&package.dependencies)?,
+        &resolve(&package.optional_dependencies)?,
+        &lockfile,
+    )?;
+
+    let path = cwd.join(SHRINKWRAP_FILE);
+    utils::write_file(&path, content.as_bytes())
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    println!(
+        "{} Wrote {} pinning {} packages; `rjs pack` and `rjs publish` include it",
+        style(icon("✓")).green(),
+        style(SHRINKWRAP_FILE).bold(),
+        style(pinned).bold()
+    );
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use console::style;
+use dialoguer::Password;
+use std::path::Path;
+
+use crate::config;
+use crate::npmrc::Npmrc;
+use crate::registry::NpmRegistry;
+use crate::utils::icon;
+
+#[derive(Args)]
+pub struct TokenOptions {
+    #[command(subcommand)]
+    action: TokenAction,
+
+    /// Registry the account is on (default: the configured registry)
+    #[arg(long, global = true)]
+    registry: Option<String>,
+
+    /// One-time password from your authenticator, for accounts with two-factor auth
+    #[arg(long, global = true)]
+    otp: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum TokenAction {
+    /// List your access tokens
+    #[command(alias = "ls")]
+    List,
+    /// Create an access token, e.g. for CI. Asks for your password, or reads
+    /// it from stdin when that isn't a terminal
+    Create {
+        /// Allow installing but not publishing
+        #[arg(long)]
+        readonly: bool,
+
+        /// Only accept the token from these IP ranges, e.g. 192.0.2.0/24
+        #[arg(long, value_delimiter = ',')]
+        cidr: Vec<String>,
+    },
+    /// Revoke an access token by the id `rjs token list` shows
+    #[command(alias = "rm")]
+    Revoke {
+        id: String,
+    },
+}
+
+// How much of a token's key `list` shows; enough to tell them apart
+const ID_LENGTH: usize = 6;
+
+pub async fn execute(opts: TokenOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let npmrc = Npmrc::load(&cwd);
+    let registry_url = opts
+        .registry
+        .as_deref()
+        .unwrap_or(&config::settings().registry)
+        .trim_end_matches('/');
+    let registry = NpmRegistry::logged_in(registry_url, &npmrc).with_otp(opts.otp);
+
+    match opts.action {
+        TokenAction::List => {
+            let tokens = registry.tokens().await?;
+            if tokens.is_empty() {
+                println!("No access tokens on {}", registry_url);
+            }
+            for token in tokens {
+                let cidr = token.cidr_whitelist.unwrap_or_default();
+                println!(
+                    "{}  {}  {}  {}{}",
+                    style(id(&token.key)).bold(),
+                    token.token,
+                    token.created.as_deref().map_or("", |created| created.get(..10).unwrap_or(created)),
+                    if token.readonly { "read-only" } else { "publish" },
+                    if cidr.is_empty() { String::new() } else { format!("  {}", cidr.join(", ")) }
+                );
+            }
+        }
+        TokenAction::Create { readonly, cidr } => {
+            for range in &cidr {
+                if range.parse::<ipnet::IpNet>().is_err() {
+                    anyhow::bail!("\"{}\" isn't an IP range like 192.0.2.0/24", range);
+                }
+            }
+            let password = read_password()?;
+            let token = registry.create_token(&password, readonly, &cidr).await?;
+            println!(
+                "{} Created {} token {}",
+                style(icon("✓")).green(),
+                if token.readonly { "a read-only" } else { "a publish" },
+                id(&token.key)
+            );
+            println!("\n  {}\n", style(&token.token).bold());
+            println!("Save it now; the registry won't show it again.");
+        }
+        TokenAction::Revoke { id: wanted } => {
+            // Accept the listed id, a longer prefix of the key, or the token itself
+            let tokens = registry.tokens().await?;
+            let matching: Vec<_> = tokens
+                .iter()
+                .filter(|token| token.key.starts_with(&wanted) || token.token == wanted)
+                .collect();
+            let token = match matching[..] {
+                [token] => token,
+                [] => anyhow::bail!("No access token matches \"{}\"; see `rjs token list`", wanted),
+                _ => anyhow::bail!("\"{}\" matches {} tokens; give more of the id", wanted, matching.len()),
+            };
+            registry.revoke_token(&token.key).await?;
+            println!("{} Revoked token {}", style("-").red(), id(&token.key));
+        }
+    }
+    Ok(())
+}
+
+fn id(key: &str) -> &str {
+    key.get(..ID_LENGTH).unwrap_or(key)
+}
+
+// Creating a token needs the account password: prompt for it, or take the
+// first line of stdin so scripts can pipe it in
+fn read_password() -> Result<String> {
+    if atty::is(atty::Stream::Stdin) {
+        return Ok(Password::new().with_prompt("Password").interact()?);
+    }
+    let mut password = String::new();
+    std::io::stdin()
+        .read_line(&mut password)
+        .context("Failed to read the password from stdin")?;
+    let password = password.trim_end_matches(['\r', '\n']).to_string();
+    if password.is_empty() {
+        anyhow::bail!("Creating a token needs your password; pipe it to stdin or run in a terminal");
+    }
+    Ok(password)
+}
@@ -0,0 +1,122 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use dialoguer::MultiSelect;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::dependency::installer::{self, Installer, Plan};
+use crate::dependency::manifest::Manifest;
+use crate::dependency::{self, DependencyResolver, Lockfile};
+use crate::npmrc::Npmrc;
+use crate::registry::NpmRegistry;
+use crate::utils::icon;
+
+// The package.json objects a dependency can be saved in
+const DEPENDENCY_FIELDS: [&str; 3] = ["dependencies", "devDependencies", "optionalDependencies"];
+
+#[derive(Args)]
+pub struct UninstallOptions {
+    /// Packages to remove; picks them from package.json's dependencies when
+    /// omitted on a terminal
+    packages: Vec<String>,
+
+    /// Leave package.json as it is
+    #[arg(long)]
+    no_save: bool,
+}
+
+pub async fn execute(mut opts: UninstallOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let package_json_path = cwd.join("package.json");
+    if !package_json_path.exists() {
+        anyhow::bail!("No package.json found in {}", cwd.display());
+    }
+
+    if opts.packages.is_empty() {
+        opts.packages = pick(&package_json_path).await?;
+        if opts.packages.is_empty() {
+            println!("Nothing picked to remove");
+            return Ok(());
+        }
+    }
+
+    if !opts.no_save {
+        let content = tokio::fs::read_to_string(&package_json_path).await?;
+        let mut manifest =
+            Manifest::parse(&content).with_context(|| format!("Failed to parse {}", package_json_path.display()))?;
+        for name in &opts.packages {
+            for field in DEPENDENCY_FIELDS {
+                manifest.remove_dependency(field, name)?;
+            }
+        }
+        tokio::fs::write(&package_json_path, manifest.into_string()).await?;
+    }
+
+    // What the named packages pulled in goes with them, and anything still
+    // depended on stays, the named packages included
+    let resolver = DependencyResolver::new(NpmRegistry::new().with_npmrc(Npmrc::load(&cwd))).with_root(&cwd);
+    let project = dependency::read_package_json(&package_json_path).await?;
+    let roots: HashMap<String, String> = project
+        .dependencies
+        .into_iter()
+        .chain(project.dev_dependencies)
+        .filter(|(name, _)| !opts.packages.contains(name))
+        .collect();
+
+    let locked = resolver.load_lockfile(&cwd).await?;
+    let remove = match &locked {
+        Some(lockfile) => installer::unreachable(lockfile, &roots),
+        None => opts.packages.clone(),
+    };
+
+    let plan = Plan {
+        install: Vec::new(),
+        remove: remove.clone(),
+        lockfile: locked.map(|lockfile| Lockfile::new(&lockfile.name, &lockfile.version)),
+        graph: None,
+    };
+    Installer::new(&resolver, &cwd).apply(plan).await?;
+
+    println!(
+        "{} Removed {} {}",
+        style(icon("✓")).green(),
+        style(remove.len()).bold(),
+        if remove.len() == 1 { "package" } else { "packages" }
+    );
+    Ok(())
+}
+
+// Let the user choose dependencies to remove from a list of those in
+// package.json, with the field and spec of each
+async fn pick(package_json_path: &std::path::Path) -> Result<Vec<String>> {
+    if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stdout) {
+        anyhow::bail!("Name the packages to remove");
+    }
+
+    let content = tokio::fs::read_to_string(package_json_path).await?;
+    let json: serde_json::Value =
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", package_json_path.display()))?;
+    let dependencies: Vec<(&str, &str, &str)> = DEPENDENCY_FIELDS
+        .iter()
+        .filter_map(|field| Some((*field, json.get(*field)?.as_object()?)))
+        .flat_map(|(field, entries)| entries.iter().map(move |(name, spec)| (field, name.as_str(), spec.as_str().unwrap_or(""))))
+        .collect();
+    if dependencies.is_empty() {
+        println!("No dependencies in package.json");
+        return Ok(Vec::new());
+    }
+
+    let width = dependencies.iter().map(|(_, name, _)| name.len()).max().unwrap_or(0);
+    let items: Vec<String> = dependencies
+        .iter()
+        .map(|(field, name, spec)| format!("{:<width$}  {}  {}", name, spec, style(field).dim(), width = width))
+        .collect();
+
+    // Space picks, enter confirms, escape removes nothing
+    let chosen = MultiSelect::new().with_prompt("Packages to remove").items(&items).interact_opt()?;
+    let mut names: Vec<String> = chosen.into_iter().flatten().map(|index| dependencies[index].1.to_string()).collect();
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
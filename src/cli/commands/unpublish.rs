@@ -0,0 +1,96 @@
+use anyhow::Result;
+use clap::Args;
+use console::style;
+use dialoguer::Confirm;
+use std::path::Path;
+
+use crate::dependency::versions;
+use crate::npmrc::Npmrc;
+use crate::pack;
+use crate::registry::NpmRegistry;
+
+#[derive(Args)]
+pub struct UnpublishOptions {
+    /// Package to remove, with the version to remove (default: the current
+    /// package's version). Without a version, every version is removed
+    package: Option<String>,
+
+    /// Required to remove every version of a package
+    #[arg(long)]
+    force: bool,
+
+    /// Don't ask for confirmation
+    #[arg(short, long)]
+    yes: bool,
+
+    /// One-time password from your authenticator, for accounts with two-factor auth
+    #[arg(long)]
+    otp: Option<String>,
+}
+
+pub async fn execute(opts: UnpublishOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let (name, version) = match &opts.package {
+        // The scope's leading "@" isn't a version separator
+        Some(package) => match package.rsplit_once('@') {
+            Some((name, version)) if !name.is_empty() => (name.to_string(), Some(version.to_string())),
+            _ => (package.clone(), None),
+        },
+        None => {
+            let (name, version) = pack::package_id(&cwd)?;
+            (name, Some(version))
+        }
+    };
+
+    if let Some(version) = &version
+        && versions::parse_version(version).is_none()
+    {
+        anyhow::bail!("\"{}\" is not a version; unpublish one version at a time", version);
+    }
+    if version.is_none() && !opts.force {
+        anyhow::bail!(
+            "Refusing to unpublish every version of {}; pass --force if that's what you want",
+            name
+        );
+    }
+
+    let npmrc = Npmrc::load(&cwd);
+    let registry_url = npmrc.registry_for(&name);
+    let registry = NpmRegistry::logged_in(&registry_url, &npmrc).with_otp(opts.otp);
+
+    let target = match &version {
+        Some(version) => format!("{}@{}", name, version),
+        None => format!("every version of {}", name),
+    };
+    if !opts.yes {
+        if !atty::is(atty::Stream::Stdin) {
+            anyhow::bail!("Unpublishing can't be undone; pass --yes to confirm");
+        }
+        let confirmed = Confirm::new()
+            .with_prompt(format!(
+                "Unpublish {} from {}? This can't be undone, and the version can never be published again",
+                target, registry_url
+            ))
+            .default(false)
+            .interact()?;
+        if !confirmed {
+            println!("Aborted");
+            return Ok(());
+        }
+    }
+
+    match &version {
+        Some(version) => {
+            let new_latest = registry.unpublish_version(&name, version).await?;
+            println!("{} Unpublished {}@{}", style("-").red(), name, version);
+            if let Some(latest) = new_latest {
+                println!("  latest is now {}", latest);
+            }
+        }
+        None => {
+            registry.unpublish_package(&name).await?;
+            println!("{} Unpublished every version of {}", style("-").red(), name);
+        }
+    }
+    Ok(())
+}
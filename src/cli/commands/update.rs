@@ -0,0 +1,111 @@
+use anyhow::Result;
+use clap::Args;
+use console::style;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::config;
+use crate::dependency::installer::{self, Installer, Plan};
+use crate::dependency::patches::Patches;
+use crate::dependency::{self, lockfile_key_name, DependencyResolver, Lockfile};
+use crate::npmrc::Npmrc;
+use crate::registry::NpmRegistry;
+use crate::utils::icon;
+use crate::workspace::catalog::Catalogs;
+
+#[derive(Args)]
+pub struct UpdateOptions {
+    /// Packages to update (default: every dependency in package.json)
+    packages: Vec<String>,
+
+    /// Registry to resolve packages from, instead of the registry setting
+    #[arg(long)]
+    registry: Option<String>,
+}
+
+pub async fn execute(opts: UpdateOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let package_json_path = cwd.join("package.json");
+    if !package_json_path.exists() {
+        anyhow::bail!("No package.json found in {}", cwd.display());
+    }
+
+    let project = dependency::read_package_json(&package_json_path).await?;
+    let roots: HashMap<String, String> = project
+        .dependencies
+        .into_iter()
+        .chain(project.dev_dependencies)
+        .collect();
+
+    let mut targets: Vec<(String, String)> = if opts.packages.is_empty() {
+        roots.iter().map(|(name, spec)| (name.clone(), spec.clone())).collect()
+    } else {
+        opts.packages
+            .iter()
+            .map(|name| match roots.get(name) {
+                Some(spec) => Ok((name.clone(), spec.clone())),
+                None => Err(anyhow::anyhow!("{} is not a dependency in package.json", name)),
+            })
+            .collect::<Result<_>>()?
+    };
+    targets.sort();
+    if targets.is_empty() {
+        println!("{} No dependencies to update", style(icon("✓")).green());
+        return Ok(());
+    }
+
+    // The targets resolve to the newest versions their ranges allow; the rest
+    // of the tree keeps its locked versions where it can
+    let resolver = DependencyResolver::new(NpmRegistry::for_registry(opts.registry.as_deref()).with_npmrc(Npmrc::load(&cwd)))
+        .with_unlocked(targets.iter().map(|(name, _)| name.clone()))
+        .with_catalogs(Catalogs::load(&cwd)?)
+        .with_patches(Patches::load(&cwd)?)
+        .with_memory_limit(config::settings().max_packages_in_memory)
+        .with_cancellation(super::install::cancel_on_ctrl_c())
+        .with_root(&cwd);
+    let before = locked_versions(resolver.load_lockfile(&cwd).await?.as_ref());
+    resolver.resolve_and_install(&targets, &cwd, false, false).await?;
+
+    // Versions nothing depends on anymore are dropped
+    if let Some(lockfile) = resolver.load_lockfile(&cwd).await? {
+        let plan = Plan {
+            install: Vec::new(),
+            remove: installer::unreachable(&lockfile, &roots),
+            lockfile: Some(Lockfile::new(&lockfile.name, &lockfile.version)),
+            graph: None,
+        };
+        Installer::new(&resolver, &cwd).apply(plan).await?;
+    }
+
+    let after = locked_versions(resolver.load_lockfile(&cwd).await?.as_ref());
+    let mut updated = 0;
+    for (name, _) in &targets {
+        let (Some(old), Some(new)) = (before.get(name), after.get(name)) else {
+            continue;
+        };
+        if old != new {
+            println!("  {} {} {} -> {}", style(icon("•")).cyan(), style(name).bold(), old, style(new).green());
+            updated += 1;
+        }
+    }
+    if updated == 0 {
+        println!("{} Everything is up to date", style(icon("✓")).green());
+    } else {
+        println!("{} Updated {} {}", style(icon("✓")).green(), style(updated).bold(), if updated == 1 { "package" } else { "packages" });
+    }
+    Ok(())
+}
+
+// Locked versions by name, the newest when a name is locked more than once
+fn locked_versions(lockfile: Option<&Lockfile>) -> BTreeMap<String, String> {
+    let mut versions = BTreeMap::new();
+    for (key, entry) in lockfile.iter().flat_map(|lockfile| &lockfile.packages) {
+        let newer = versions.get(lockfile_key_name(key)).is_none_or(|current: &String| {
+            dependency::versions::parse_version(&entry.version) > dependency::versions::parse_version(current)
+        });
+        if newer {
+            versions.insert(lockfile_key_name(key).to_string(), entry.version.clone());
+        }
+    }
+    versions
+}
@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use futures::{stream, StreamExt};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::cache::TarballStore;
+use crate::dependency::events::NoopEvents;
+use crate::dependency::vendor::{self, INDEX_FILE, VENDOR_DIR};
+use crate::dependency::{lockfile_key_name, Lockfile, LockfileEntry};
+use crate::registry::NpmRegistry;
+use crate::utils::{self, icon};
+
+// Tarballs fetched at once
+const FETCH_CONCURRENCY: usize = 16;
+
+#[derive(Args)]
+pub struct VendorOptions {
+    /// Check in packages extracted into directories instead of their tarballs
+    #[arg(long)]
+    extract: bool,
+}
+
+pub async fn execute(opts: VendorOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let lockfile = super::fetch::read_lockfile(&cwd).await?;
+    let vendor_dir = cwd.join(VENDOR_DIR);
+    utils::ensure_dir(&vendor_dir).await?;
+
+    let entries: Vec<(&String, &LockfileEntry)> =
+        lockfile.packages.iter().filter(|(_, entry)| entry.resolved.is_some()).collect();
+    let skipped = lockfile.packages.len() - entries.len();
+    println!(
+        "{} Vendoring {} packages from rjs-lock.json into {}/",
+        style(icon("📦")).bold().cyan(),
+        style(entries.len()).bold(),
+        VENDOR_DIR
+    );
+
+    let store = TarballStore::open()?;
+    let mut results = stream::iter(&entries)
+        .map(|(key, entry)| {
+            let (store, vendor_dir) = (&store, &vendor_dir);
+            async move { (*key, vendor_package(store, vendor_dir, key, entry, opts.extract).await) }
+        })
+        .buffer_unordered(FETCH_CONCURRENCY);
+    let mut kept = HashSet::new();
+    let mut failed = 0;
+    while let Some((key, result)) = results.next().await {
+        match result {
+            Ok(name) => {
+                kept.insert(name);
+            }
+            Err(e) => {
+                failed += 1;
+                println!("  {} {}: {:#}", style(icon("✗")).red(), key, e);
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("Failed to vendor {} packages", failed);
+    }
+
+    // Packages the lockfile no longer has are taken out
+    let mut removed = 0;
+    for entry in std::fs::read_dir(&vendor_dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name == INDEX_FILE || kept.contains(&name) {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            std::fs::remove_dir_all(&path)?;
+        } else {
+            std::fs::remove_file(&path)?;
+        }
+        removed += 1;
+    }
+
+    let mut index = Lockfile::new(&lockfile.name, &lockfile.version);
+    index.packages = entries.iter().map(|(key, entry)| ((*key).clone(), (*entry).clone())).collect();
+    utils::write_file(&vendor_dir.join(INDEX_FILE), index.to_json()?.as_bytes()).await?;
+
+    if skipped > 0 {
+        println!("  {} {} entries have no resolved tarball and were skipped", style(icon("•")).yellow(), skipped);
+    }
+    if removed > 0 {
+        println!("  {} Removed {} packages that are no longer locked", style(icon("•")).cyan(), removed);
+    }
+    println!(
+        "{} Vendored {} packages ({}); set `vendor = true` in .rjsrc.toml to install from them",
+        style(icon("✅")).green(),
+        style(kept.len()).bold(),
+        utils::format_size(utils::dir_size(&vendor_dir))
+    );
+    Ok(())
+}
+
+// Copy the verified tarball of a lockfile entry into the vendor directory, or
+// extract it there, and return the name it got
+async fn vendor_package(
+    store: &TarballStore,
+    vendor_dir: &Path,
+    key: &str,
+    entry: &LockfileEntry,
+    extract: bool,
+) -> Result<String> {
+    let name = lockfile_key_name(key);
+    let url = entry.resolved.as_deref().unwrap_or_default();
+    let registry = NpmRegistry::for_registry(entry.registry.as_deref());
+    let (tarball, _) = store.fetch(&registry, url, entry.integrity.as_deref(), name, &NoopEvents).await?;
+
+    let stem = vendor::file_stem(name, &entry.version);
+    if !extract {
+        let file = format!("{}.tgz", stem);
+        tokio::fs::copy(&tarball, vendor_dir.join(&file))
+            .await
+            .with_context(|| format!("Failed to copy {} into {}", key, vendor_dir.display()))?;
+        return Ok(file);
+    }
+
+    let dir = vendor_dir.join(&stem);
+    if dir.exists() {
+        tokio::fs::remove_dir_all(&dir).await?;
+    }
+    tokio::task::spawn_blocking(move || registry.extract_tarball(&tarball, &dir))
+        .await
+        .context("Extraction task panicked")??;
+    Ok(stem)
+}
@@ -0,0 +1,164 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::config;
+use crate::dependency::patches::Patches;
+use crate::dependency::{self, versions, DependencyResolver, Lockfile};
+use crate::npmrc::Npmrc;
+use crate::policy::Policy;
+use crate::registry::NpmRegistry;
+use crate::utils::icon;
+use crate::workspace::catalog::Catalogs;
+
+#[derive(Args)]
+pub struct VerifyOptions {
+    /// Re-resolve package.json against the registry and require the same
+    /// rjs-lock.json, byte for byte, with the same integrity for every package
+    #[arg(long)]
+    reproducible: bool,
+
+    /// Registry to resolve against (default: the registry setting)
+    #[arg(long)]
+    registry: Option<String>,
+}
+
+pub async fn execute(opts: VerifyOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let package = dependency::read_package_json(&cwd.join("package.json"))
+        .await
+        .context("Failed to read package.json")?;
+    let lockfile_path = cwd.join("rjs-lock.json");
+    let content = std::fs::read_to_string(&lockfile_path)
+        .with_context(|| format!("Failed to read {}", lockfile_path.display()))?;
+    let lockfile: Lockfile = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", lockfile_path.display()))?;
+
+    // Every direct dependency has a locked version its range accepts
+    let catalogs = Catalogs::load(&cwd)?;
+    let mut problems = Vec::new();
+    let direct: Vec<(&String, &String)> = package.dependencies.iter().chain(&package.dev_dependencies).collect();
+    for (name, spec) in &direct {
+        let locked: Vec<&str> = lockfile
+            .packages
+            .iter()
+            .filter(|(key, _)| dependency::lockfile_key_name(key) == name.as_str())
+            .map(|(_, entry)| entry.version.as_str())
+            .collect();
+        let satisfied = match versions::parse_req(catalogs.resolve(name, spec)?) {
+            Some(req) => locked
+                .iter()
+                .filter_map(|version| versions::parse_version(version))
+                .any(|version| req.matches(&version)),
+            // Tags and URLs can't be checked without the registry
+            None => !locked.is_empty(),
+        };
+        if !satisfied {
+            problems.push(match locked.is_empty() {
+                true => format!("{}@{} from package.json isn't locked", name, spec),
+                false => format!("{}@{} from package.json is locked at {}", name, spec, locked.join(", ")),
+            });
+        }
+    }
+    if !problems.is_empty() {
+        return Err(report(&problems, "rjs-lock.json is out of date with package.json; run `rjs install`"));
+    }
+    if !opts.reproducible {
+        println!(
+            "{} rjs-lock.json covers the {} dependencies in package.json",
+            style(icon("✓")).green(),
+            direct.len()
+        );
+        return Ok(());
+    }
+
+    // The same versions, with everything about them read from the registry
+    let mut resolver = DependencyResolver::new(NpmRegistry::for_registry(opts.registry.as_deref()).with_npmrc(Npmrc::load(&cwd)))
+        .with_refreshed_locks()
+        .with_catalogs(catalogs)
+        .with_patches(Patches::load(&cwd)?)
+        .with_memory_limit(config::settings().max_packages_in_memory)
+        .with_root(&cwd);
+    if let Some(policy) = Policy::load(&cwd)? {
+        resolver = resolver.with_policy(policy);
+    }
+    resolver.prefer_locked(&lockfile);
+
+    // Laid out as `rjs install` locks the project
+    let mut root = package.clone();
+    root.name = "root".to_string();
+    root.version = "0.0.0".to_string();
+    root.dependencies = package.dev_dependencies.clone();
+    root.dependencies.extend(package.dependencies.clone());
+    root.dev_dependencies.clear();
+    let tree = resolver.resolve_dependencies(&root).await?;
+    let expected = resolver.generate_lockfile(&tree, &cwd).await?;
+
+    let problems = differences(&lockfile, &expected)?;
+    if !problems.is_empty() {
+        return Err(report(
+            &problems,
+            "rjs-lock.json doesn't match a fresh resolution of package.json; it may have been edited by hand",
+        ));
+    }
+    if content != expected.to_json()? {
+        anyhow::bail!(
+            "rjs-lock.json has the right packages but isn't laid out as rjs writes it; \
+             run `rjs install --lockfile-only` to rewrite it"
+        );
+    }
+
+    let integrity = expected
+        .packages
+        .values()
+        .filter(|entry| entry.integrity.is_some())
+        .count();
+    println!(
+        "{} rjs-lock.json is reproducible: {} packages resolve to the same versions ({} with integrity)",
+        style(icon("✓")).green(),
+        expected.packages.len(),
+        integrity
+    );
+    Ok(())
+}
+
+// What `lockfile` has that `expected` doesn't, and the other way round, entry
+// by entry and field by field
+fn differences(lockfile: &Lockfile, expected: &Lockfile) -> Result<Vec<String>> {
+    let keys: BTreeSet<&String> = lockfile.packages.keys().chain(expected.packages.keys()).collect();
+    let mut problems = Vec::new();
+    for key in keys {
+        let (Some(locked), Some(fresh)) = (lockfile.packages.get(key), expected.packages.get(key)) else {
+            problems.push(match lockfile.packages.contains_key(key) {
+                true => format!("{} is locked but not in a fresh resolution", key),
+                false => format!("{} is missing from the lockfile", key),
+            });
+            continue;
+        };
+
+        let locked = serde_json::to_value(locked)?;
+        let fresh = serde_json::to_value(fresh)?;
+        let fields: BTreeSet<&String> = locked
+            .as_object()
+            .into_iter()
+            .chain(fresh.as_object())
+            .flat_map(|entry| entry.keys())
+            .collect();
+        for field in fields {
+            let (was, is) = (&locked[field.as_str()], &fresh[field.as_str()]);
+            if was != is {
+                problems.push(format!("{}: {} is {} in the lockfile, {} in the registry", key, field, was, is));
+            }
+        }
+    }
+    Ok(problems)
+}
+
+fn report(problems: &[String], summary: &str) -> anyhow::Error {
+    for problem in problems {
+        println!("  {} {}", style(icon("✗")).red(), problem);
+    }
+    anyhow::anyhow!("{}", summary)
+}
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use log::debug;
+use std::collections::BTreeMap;
+
+use crate::dependency::versions;
+use crate::provenance;
+use crate::registry::{NpmRegistry, PackageInfo, Person};
+use crate::utils::{self, icon};
+
+#[derive(Args)]
+pub struct ViewOptions {
+    /// Package to show, optionally with a version, range or dist-tag (e.g. lodash@^4)
+    package: String,
+
+    /// Registry to look the package up on, instead of the registry setting
+    #[arg(long)]
+    registry: Option<String>,
+}
+
+pub async fn execute(opts: ViewOptions) -> Result<()> {
+    // The scope's leading "@" isn't a version separator
+    let (name, spec) = match opts.package.rsplit_once('@') {
+        Some((name, spec)) if !name.is_empty() => (name, spec),
+        _ => (opts.package.as_str(), "latest"),
+    };
+
+    let registry = NpmRegistry::for_registry(opts.registry.as_deref());
+    let info = registry.get_package_info(name).await?;
+    let version = select_version(&info, spec)
+        .with_context(|| format!("No version of {} matches {}", name, spec))?;
+    let details = &info.versions[&version];
+
+    println!(
+        "{} | {} | deps: {} | versions: {}",
+        style(format!("{}@{}", name, version)).bold().green(),
+        details.license.as_deref().unwrap_or("no license"),
+        details.dependencies.len(),
+        info.versions.len()
+    );
+
+    let published = publish_date(&info, &version).unwrap_or_else(|| "at an unknown time".to_string());
+    match &details.publisher {
+        Some(publisher) => println!("published {} by {}", published, person(publisher)),
+        None => println!("published {}", published),
+    }
+    // Not every registry keeps counts; they're left out when it doesn't
+    match registry.weekly_downloads(name).await {
+        Ok(Some(downloads)) => println!("downloads: {} last week", downloads),
+        Ok(None) => {}
+        Err(e) => debug!("No download counts for {}: {:#}", name, e),
+    }
+
+    let dist = &details.dist;
+    println!("\n{}:", style("dist").bold());
+    println!("  tarball: {}", dist.tarball);
+    if let Some(integrity) = &dist.integrity {
+        println!("  integrity: {}", integrity);
+    }
+
+    let signatures = if !dist.signatures.is_empty() {
+        let keys: Vec<&str> = dist.signatures.iter().map(|signature| signature.keyid.as_str()).collect();
+        format!("{} registry-signed (key {})", style(icon("✓")).green(), keys.join(", "))
+    } else if dist.npm_signature.is_some() {
+        "legacy PGP signature only".to_string()
+    } else {
+        format!("{} none", style("!").yellow())
+    };
+    println!("  signatures: {}", signatures);
+
+    print!("  provenance: ");
+    match provenance::fetch(&registry, dist).await {
+        Ok(Some(provenance)) => {
+            let mark = if provenance.subject_matches {
+                style(icon("✓")).green()
+            } else {
+                style(icon("✗")).red()
+            };
+            println!(
+                "{} built from {}",
+                mark,
+                provenance.repository.as_deref().unwrap_or("an unknown repository")
+            );
+            if let Some(workflow) = &provenance.workflow {
+                let git_ref = provenance.git_ref.as_deref().map(|git_ref| format!(" at {}", git_ref));
+                println!("    workflow: {}{}", workflow, git_ref.unwrap_or_default());
+            }
+            if let Some(commit) = &provenance.commit {
+                println!("    commit: {}", commit);
+            }
+            if let Some(builder) = &provenance.builder {
+                println!("    builder: {}", builder);
+            }
+            if let Some(run) = &provenance.run {
+                println!("    run: {}", run);
+            }
+            if !provenance.subject_matches {
+                println!(
+                    "    {}",
+                    style("the attestation is not for this tarball's integrity").red()
+                );
+            }
+        }
+        Ok(None) => println!("{} none", style("!").yellow()),
+        Err(e) => println!("{} could not be loaded: {:#}", style("!").yellow(), e),
+    }
+
+    if !details.dependencies.is_empty() {
+        println!("\n{}:", style("dependencies").bold());
+        let dependencies: BTreeMap<_, _> = details.dependencies.iter().collect();
+        for (dependency, range) in dependencies {
+            println!("  {} {}", dependency, style(range).dim());
+        }
+    }
+
+    if !info.maintainers.is_empty() {
+        println!("\n{}:", style("maintainers").bold());
+        for maintainer in &info.maintainers {
+            println!("  - {}", person(maintainer));
+        }
+    }
+
+    println!("\n{}:", style("dist-tags").bold());
+    let dist_tags: BTreeMap<_, _> = info.dist_tags.iter().collect();
+    for (tag, tagged) in dist_tags {
+        match publish_date(&info, tagged) {
+            Some(published) => println!("  {}: {} {}", tag, tagged, style(format!("published {}", published)).dim()),
+            None => println!("  {}: {}", tag, tagged),
+        }
+    }
+
+    Ok(())
+}
+
+// A dist-tag's version, an exact version, or the newest version in a range
+fn select_version(info: &PackageInfo, spec: &str) -> Option<String> {
+    if let Some(version) = info.dist_tags.get(spec) {
+        return Some(version.clone());
+    }
+    // A bare version means that release, not a caret range
+    if versions::parse_version(spec).is_some() {
+        return info.versions.contains_key(spec).then(|| spec.to_string());
+    }
+
+    let req = versions::parse_req(spec)?;
+    info.versions
+        .keys()
+        .filter_map(|version| versions::parse_version(version).map(|parsed| (parsed, version)))
+        .filter(|(parsed, _)| req.matches(parsed))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, version)| version.clone())
+}
+
+// The date `version` was published and how long ago, e.g. "2024-03-01 (7 months ago)"
+fn publish_date(info: &PackageInfo, version: &str) -> Option<String> {
+    let time = info.published(version)?;
+    let date = info.time[version].get(..10).unwrap_or(&info.time[version]);
+    Some(format!("{} ({})", date, utils::format_age(time)))
+}
+
+fn person(person: &Person) -> String {
+    match &person.email {
+        Some(email) => format!("{} <{}>", person.name, email),
+        None => person.name.clone(),
+    }
+}
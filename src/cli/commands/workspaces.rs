@@ -0,0 +1,454 @@
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use console::style;
+use semver::{BuildMetadata, Prerelease, Version};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::publish::PublishOptions;
+use crate::dependency::manifest::Manifest;
+use crate::dependency::versions;
+use crate::pack::PackList;
+use crate::utils::icon;
+use crate::workspace::{self, Workspace, DEPENDENCY_FIELDS};
+
+// Files at the root that every workspace is built from
+const SHARED_FILES: [&str; 3] = ["package.json", "rjs-lock.json", ".rjsrc.toml"];
+
+// The workspaces a `publish` got through, kept until it finishes so a failed
+// one can be resumed
+const PUBLISH_PROGRESS: &str = "node_modules/.rjs-workspaces-publish.json";
+
+// Where a dependency ends up in what's published for dependents to install
+const PUBLISHED_FIELDS: [&str; 3] = ["dependencies", "optionalDependencies", "peerDependencies"];
+
+#[derive(Args)]
+pub struct WorkspacesOptions {
+    #[command(subcommand)]
+    action: WorkspacesAction,
+}
+
+#[derive(Subcommand)]
+enum WorkspacesAction {
+    /// List the workspaces a git diff affects: changed, or depending on a changed one
+    Changed {
+        /// Commit, branch or tag to compare against, e.g. origin/main
+        #[arg(long)]
+        since: String,
+
+        /// Print the workspaces as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Bump workspace versions, with the ranges and versions of what depends on them
+    Version {
+        /// major, minor, patch, or an exact version
+        bump: String,
+
+        /// Only bump these workspaces, by name or path (default: all of them)
+        #[arg(long = "workspace", short = 'w')]
+        workspaces: Vec<String>,
+    },
+    /// Publish the public workspaces, each after those it depends on
+    Publish {
+        #[command(flatten)]
+        publish: PublishOptions,
+
+        /// Carry on from the workspace a failed publish stopped at
+        #[arg(long)]
+        resume: bool,
+    },
+}
+
+// A workspace affected by the diff
+#[derive(Serialize)]
+struct Affected<'a> {
+    name: &'a str,
+    path: &'a str,
+    // Whether its own files changed, rather than only workspaces it depends on
+    changed: bool,
+    // The changed workspaces it depends on, directly or not
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    through: Vec<&'a str>,
+}
+
+pub async fn execute(opts: WorkspacesOptions, root: &Path) -> Result<()> {
+    let cwd = root.to_path_buf();
+    let root = workspace::find_root(&cwd).context("No package.json with \"workspaces\" found here or above")?;
+    let workspaces = workspace::list(&root)?;
+
+    match &opts.action {
+        WorkspacesAction::Changed { since, json } => changed(&root, &workspaces, since, *json),
+        WorkspacesAction::Version { bump, workspaces: selected } => version(&root, &workspaces, bump, selected),
+        WorkspacesAction::Publish { publish: options, resume } => publish(&root, &workspaces, options, *resume).await,
+    }
+}
+
+fn changed(root: &Path, workspaces: &[Workspace], since: &str, json: bool) -> Result<()> {
+    let files = changed_files(root, since)?;
+
+    // The shared files change what every workspace installs or runs
+    let everything = files.iter().any(|file| SHARED_FILES.contains(&file.as_str()) || file.starts_with("patches/"));
+    let direct: BTreeSet<&str> = workspaces
+        .iter()
+        .filter(|ws| everything || files.iter().any(|file| file.starts_with(&format!("{}/", ws.path))))
+        .map(|ws| ws.path.as_str())
+        .collect();
+
+    // Then whatever depends on them, transitively
+    let graph = workspace::local_dependencies(workspaces)?;
+    let mut through: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for changed in &direct {
+        let mut queue = VecDeque::from([*changed]);
+        while let Some(path) = queue.pop_front() {
+            for (dependent, dependencies) in &graph {
+                if dependencies.contains(path) && through.entry(dependent.as_str()).or_default().insert(changed) {
+                    queue.push_back(dependent.as_str());
+                }
+            }
+        }
+    }
+
+    let affected: Vec<Affected> = workspaces
+        .iter()
+        .filter(|ws| direct.contains(ws.path.as_str()) || through.contains_key(ws.path.as_str()))
+        .map(|ws| Affected {
+            name: &ws.name,
+            path: &ws.path,
+            changed: direct.contains(ws.path.as_str()),
+            through: through
+                .get(ws.path.as_str())
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|path| *path != ws.path)
+                .collect(),
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&affected)?);
+        return Ok(());
+    }
+    if affected.is_empty() {
+        println!("{} No workspace changed since {}", style(icon("✓")).green(), since);
+        return Ok(());
+    }
+    for ws in &affected {
+        let reason = match ws.changed {
+            true => "changed".to_string(),
+            false => format!("depends on {}", ws.through.join(", ")),
+        };
+        println!("{} ({}) {}", style(ws.path).bold(), ws.name, style(reason).dim());
+    }
+    Ok(())
+}
+
+fn version(root: &Path, workspaces: &[Workspace], bump: &str, selected: &[String]) -> Result<()> {
+    let manifests = read_manifests(workspaces)?;
+    let current = |ws: &Workspace| manifests[ws.path.as_str()].get("version").and_then(|version| version.as_str());
+
+    let mut targets = Vec::new();
+    for selector in selected {
+        let ws = workspace::find(root, selector)?;
+        if current(&ws).is_none() {
+            anyhow::bail!("{} has no version to bump", ws.path);
+        }
+        targets.push(ws.path);
+    }
+    if selected.is_empty() {
+        targets = workspaces.iter().filter(|ws| current(ws).is_some()).map(|ws| ws.path.clone()).collect();
+    }
+
+    // New versions by name
+    let mut bumped: BTreeMap<&str, (&Workspace, &str, String)> = BTreeMap::new();
+    for ws in workspaces.iter().filter(|ws| targets.contains(&ws.path)) {
+        let old = current(ws).unwrap_or_default();
+        bumped.insert(&ws.name, (ws, old, next_version(old, bump)?));
+    }
+    // What depends on them gets a patch release when what it publishes
+    // changes with them, and so on up
+    loop {
+        let dependents: Vec<&Workspace> = workspaces
+            .iter()
+            .filter(|ws| !bumped.contains_key(ws.name.as_str()) && current(ws).is_some())
+            .filter(|ws| {
+                let manifest = &manifests[ws.path.as_str()];
+                PUBLISHED_FIELDS
+                    .iter()
+                    .filter_map(|field| manifest.get(field)?.as_object())
+                    .flatten()
+                    .any(|(name, spec)| {
+                        let (Some((_, _, version)), Some(spec)) = (bumped.get(name.as_str()), spec.as_str()) else {
+                            return false;
+                        };
+                        spec.starts_with("workspace:") || rewrite_range(spec, version).is_some()
+                    })
+            })
+            .collect();
+        if dependents.is_empty() {
+            break;
+        }
+        for ws in dependents {
+            let old = current(ws).unwrap_or_default();
+            bumped.insert(&ws.name, (ws, old, next_version(old, "patch")?));
+        }
+    }
+
+    // Then every package.json takes the new versions and the ranges on them
+    for ws in workspaces {
+        let path = ws.dir.join("package.json");
+        let content = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut manifest = Manifest::parse(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+        if let Some((_, _, version)) = bumped.get(ws.name.as_str()) {
+            manifest.set_string("version", version)?;
+        }
+        let json = &manifests[ws.path.as_str()];
+        for field in DEPENDENCY_FIELDS {
+            let Some(dependencies) = json.get(field).and_then(|deps| deps.as_object()) else {
+                continue;
+            };
+            for (name, spec) in dependencies {
+                if let (Some((_, _, version)), Some(spec)) = (bumped.get(name.as_str()), spec.as_str())
+                    && let Some(spec) = rewrite_range(spec, version)
+                {
+                    manifest.set_dependency(field, name, &spec)?;
+                }
+            }
+        }
+        let updated = manifest.into_string();
+        if updated != content {
+            std::fs::write(&path, updated).with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+
+    let mut bumped: Vec<_> = bumped.into_values().collect();
+    bumped.sort_by(|a, b| a.0.path.cmp(&b.0.path));
+    for (ws, old, new) in &bumped {
+        let reason = if targets.contains(&ws.path) { "" } else { " (a dependency changed)" };
+        println!("{} ({}) {} → {}{}", style(&ws.path).bold(), ws.name, old, style(new).green(), style(reason).dim());
+    }
+    println!(
+        "{} Bumped {} workspaces; commit them, then run `rjs workspaces publish`",
+        style(icon("✓")).green(),
+        bumped.len()
+    );
+    Ok(())
+}
+
+async fn publish(root: &Path, workspaces: &[Workspace], opts: &PublishOptions, resume: bool) -> Result<()> {
+    let manifests = read_manifests(workspaces)?;
+    let versions: HashMap<&str, &str> = workspaces
+        .iter()
+        .filter_map(|ws| Some((ws.name.as_str(), manifests[ws.path.as_str()].get("version")?.as_str()?)))
+        .collect();
+
+    let progress_path = root.join(PUBLISH_PROGRESS);
+    let mut done: BTreeSet<String> = BTreeSet::new();
+    if resume {
+        let content = std::fs::read_to_string(&progress_path).context("No failed publish to resume")?;
+        done = serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", PUBLISH_PROGRESS))?;
+    }
+
+    let mut published = 0;
+    for ws in in_dependency_order(workspaces)? {
+        let manifest = &manifests[ws.path.as_str()];
+        if manifest.get("private").and_then(|private| private.as_bool()) == Some(true) {
+            continue;
+        }
+        let id = format!("{}@{}", ws.name, versions.get(ws.name.as_str()).unwrap_or(&""));
+        if done.contains(&id) {
+            println!("{} {} was published by the earlier run", style(icon("•")).cyan(), id);
+            continue;
+        }
+
+        println!("\n{} {}", style(icon("📦")).bold().cyan(), style(&ws.path).bold());
+        let result = async {
+            let mut list = PackList::load(&ws.dir)?;
+            let mut manifest = list.manifest.clone();
+            if resolve_workspace_specs(&mut manifest, &versions)? {
+                list.set_manifest(manifest)?;
+            }
+            super::publish::publish(&list, root, opts, true).await
+        }
+        .await;
+
+        match result {
+            Ok(uploaded) => {
+                published += uploaded as usize;
+                done.insert(id);
+            }
+            Err(e) => {
+                if !opts.dry_run {
+                    if let Some(parent) = progress_path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::fs::write(&progress_path, serde_json::to_string_pretty(&done)?)?;
+                }
+                return Err(e.context(format!(
+                    "Failed to publish {}; once it's fixed, `rjs workspaces publish --resume` carries on from there",
+                    ws.path
+                )));
+            }
+        }
+    }
+
+    let _ = std::fs::remove_file(&progress_path);
+    let verb = if opts.dry_run { "Would publish" } else { "Published" };
+    println!("\n{} {} {} workspaces", style(icon("✅")).green(), verb, style(published).bold());
+    Ok(())
+}
+
+// Each workspace's package.json, by path
+fn read_manifests(workspaces: &[Workspace]) -> Result<HashMap<&str, serde_json::Value>> {
+    workspaces
+        .iter()
+        .map(|ws| {
+            let path = ws.dir.join("package.json");
+            let content = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let json = serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+            Ok((ws.path.as_str(), json))
+        })
+        .collect()
+}
+
+// The workspaces, each after those it depends on
+fn in_dependency_order(workspaces: &[Workspace]) -> Result<Vec<&Workspace>> {
+    let mut graph = workspace::local_dependencies(workspaces)?;
+    let mut order = Vec::new();
+    while !graph.is_empty() {
+        let ready: Vec<String> = graph
+            .iter()
+            .filter(|(_, dependencies)| dependencies.iter().all(|path| !graph.contains_key(path)))
+            .map(|(path, _)| path.clone())
+            .collect();
+        if ready.is_empty() {
+            let cycle: Vec<&str> = graph.keys().map(String::as_str).collect();
+            anyhow::bail!("These workspaces depend on each other in a cycle: {}", cycle.join(", "));
+        }
+        for path in ready {
+            graph.remove(&path);
+            order.extend(workspaces.iter().find(|ws| ws.path == path));
+        }
+    }
+    Ok(order)
+}
+
+// Replace the `workspace:` specs of a manifest with what they publish as;
+// returns whether there were any
+fn resolve_workspace_specs(manifest: &mut serde_json::Value, versions: &HashMap<&str, &str>) -> Result<bool> {
+    let mut resolved = false;
+    for field in DEPENDENCY_FIELDS {
+        let Some(dependencies) = manifest.get_mut(field).and_then(|deps| deps.as_object_mut()) else {
+            continue;
+        };
+        for (name, spec) in dependencies.iter_mut() {
+            let Some(text) = spec.as_str().filter(|text| text.starts_with("workspace:")) else {
+                continue;
+            };
+            let version = versions
+                .get(name.as_str())
+                .with_context(|| format!("\"{}\": \"{}\" doesn't name a workspace with a version", name, text))?;
+            *spec = workspace::publish_spec(text, version).unwrap_or_default().into();
+            resolved = true;
+        }
+    }
+    Ok(resolved)
+}
+
+// `version` after a "major", "minor" or "patch" bump, or the exact version
+// `bump` is. A prerelease bumps to its release, as in npm: 2.0.0-rc.1 is
+// 2.0.0 after a major bump
+fn next_version(version: &str, bump: &str) -> Result<String> {
+    let mut next = Version::parse(version).with_context(|| format!("Invalid version \"{}\"", version))?;
+    let release = next.pre.is_empty();
+    match bump {
+        "major" => {
+            if release || next.minor != 0 || next.patch != 0 {
+                next.major += 1;
+            }
+            next.minor = 0;
+            next.patch = 0;
+        }
+        "minor" => {
+            if release || next.patch != 0 {
+                next.minor += 1;
+            }
+            next.patch = 0;
+        }
+        "patch" => {
+            if release {
+                next.patch += 1;
+            }
+        }
+        exact => {
+            return versions::parse_version(exact)
+                .map(|version| version.to_string())
+                .with_context(|| format!("\"{}\" isn't major, minor, patch or a version", exact));
+        }
+    }
+    next.pre = Prerelease::EMPTY;
+    next.build = BuildMetadata::EMPTY;
+    Ok(next.to_string())
+}
+
+// `spec` widened to take in `version` when it doesn't: "^1.2.0" becomes
+// "^2.0.0", "~1.2.0" "~2.0.0", an exact "1.2.0" "2.0.0", and
+// "workspace:^1.2.0" "workspace:^2.0.0". None when it already matches, or
+// isn't a range, like "workspace:*"
+fn rewrite_range(spec: &str, version: &str) -> Option<String> {
+    let (protocol, range) = match spec.strip_prefix("workspace:") {
+        Some(range) => ("workspace:", range),
+        None => ("", spec),
+    };
+    let parsed = versions::parse_version(version)?;
+    if versions::parse_req(range)?.matches(&parsed) {
+        return None;
+    }
+    let prefix = match range {
+        _ if range.starts_with('~') => "~",
+        _ if versions::parse_version(range).is_some() => "",
+        _ => "^",
+    };
+    Some(format!("{}{}{}", protocol, prefix, version))
+}
+
+// Files changed since where the current branch left `since`, committed or
+// not, relative to `root` with forward slashes
+fn changed_files(root: &Path, since: &str) -> Result<BTreeSet<String>> {
+    let top = PathBuf::from(git(root, &["rev-parse", "--show-toplevel"])?.trim());
+    let base = git(root, &["merge-base", since, "HEAD"])?;
+    let mut files: Vec<String> = git(root, &["diff", "--name-only", base.trim()])?.lines().map(str::to_string).collect();
+    // New files too, with paths from the top like the diff's
+    files.extend(
+        git(root, &["ls-files", "--others", "--exclude-standard", "--full-name"])?
+            .lines()
+            .map(str::to_string),
+    );
+
+    // git lists paths from the top of the repository, which may be above the root
+    let root = std::fs::canonicalize(root)?;
+    let top = std::fs::canonicalize(&top)?;
+    Ok(files
+        .into_iter()
+        .filter_map(|file| {
+            let path = top.join(&file);
+            let relative = path.strip_prefix(&root).ok()?;
+            Some(relative.to_string_lossy().replace('\\', "/"))
+        })
+        .collect())
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("Failed to run git; is it installed?")?;
+    if !output.status.success() {
+        anyhow::bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
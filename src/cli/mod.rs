@@ -2,6 +2,10 @@ use anyhow::Result;
 use clap::Subcommand;
 use console::style;
 use log::info;
+use std::path::Path;
+
+use crate::i18n::tr;
+use crate::scripts::hooks;
 
 pub mod commands;
 
@@ -10,23 +14,195 @@ pub enum Command {
     /// Initialize a new package.json file
     Init(commands::init::InitOptions),
 
-    /// Install packages
-    Install(commands::install::InstallOptions),
+    /// Scaffold a project with a create-* initializer package, like `npm create`
+    Create(commands::create::CreateOptions),
+
+    /// Add packages to package.json and install them, or with none install what package.json lists
+    #[command(visible_alias = "install")]
+    Add(commands::install::InstallOptions),
+
+    /// Remove packages from package.json, node_modules and rjs-lock.json
+    #[command(visible_alias = "uninstall")]
+    Remove(commands::uninstall::UninstallOptions),
+
+    /// Update packages to the newest versions their package.json ranges allow
+    Update(commands::update::UpdateOptions),
+
+    /// Extract an installed package into a directory to edit it for a patch
+    Patch(commands::patch::PatchOptions),
+
+    /// Save the edits to a package extracted by `rjs patch` in patches/ and apply them
+    PatchCommit(commands::patch_commit::PatchCommitOptions),
 
     /// List installed packages
     List(commands::list::ListOptions),
+
+    /// Show dependencies with newer versions, for the root and every workspace
+    Outdated(commands::outdated::OutdatedOptions),
+
+    /// Check the lockfile against security advisories, grouped by workspace
+    Audit(commands::audit::AuditOptions),
+
+    /// Work across a monorepo's workspaces, e.g. list the ones a git diff affects
+    Workspaces(commands::workspaces::WorkspacesOptions),
+
+    /// Report disk usage: largest packages, duplicate versions and size per direct dependency
+    Analyze(commands::analyze::AnalyzeOptions),
+
+    /// List packages locked at more than one version and whether they could be collapsed
+    Dupes(commands::dupes::DupesOptions),
+
+    /// Explain a locked package: its lockfile entry, who depends on it with which range, and where it's installed
+    #[command(visible_alias = "why")]
+    Explain(commands::explain::ExplainOptions),
+
+    /// Print the dependency graph the last install saved, as Graphviz DOT or JSON
+    Graph(commands::graph::GraphOptions),
+
+    /// List where to fund the installed packages that ask for it
+    Fund(commands::fund::FundOptions),
+
+    /// Check the project, e.g. `rjs lint deps` for imports missing from package.json
+    Lint(commands::lint::LintOptions),
+
+    /// Run a script from package.json
+    Run(commands::run::RunOptions),
+
+    /// Pack the current package into a tarball, as it would be published
+    Pack(commands::pack::PackOptions),
+
+    /// Write npm-shrinkwrap.json from rjs-lock.json, pinning the production dependencies the package is published with
+    Shrinkwrap(commands::shrinkwrap::ShrinkwrapOptions),
+
+    /// Publish the current package to the registry
+    Publish(commands::publish::PublishOptions),
+
+    /// Remove a published version, or with --force a whole package, from the registry
+    Unpublish(commands::unpublish::UnpublishOptions),
+
+    /// List, add or remove a package's dist-tags
+    DistTag(commands::dist_tag::DistTagOptions),
+
+    /// List, add or remove the users who can publish a package
+    Owner(commands::owner::OwnerOptions),
+
+    /// Set a scoped package's visibility, or grant and revoke org team access
+    Access(commands::access::AccessOptions),
+
+    /// List, create or revoke registry access tokens
+    Token(commands::token::TokenOptions),
+
+    /// Show a package's registry details, signatures and build provenance
+    View(commands::view::ViewOptions),
+
+    /// Show the merged settings from config files and their sources
+    Config(commands::config::ConfigOptions),
+
+    /// Show the setup in effect: registry routing and auth, proxy, cache paths and concurrency, with their sources
+    Env(commands::env::EnvOptions),
+
+    /// Check that each configured registry answers, how fast, and that its auth token is accepted
+    Ping(commands::ping::PingOptions),
+
+    /// Inspect or clean the metadata cache
+    Cache(commands::cache::CacheOptions),
+
+    /// Print the node_modules directory, or with -g the global one
+    Root(commands::paths::PathOptions),
+
+    /// Print the directory package executables are linked into, or with -g the global one
+    Bin(commands::paths::PathOptions),
+
+    /// Print the project directory, or with -g npm's global prefix
+    Prefix(commands::paths::PathOptions),
+
+    /// Download and verify all lockfile tarballs into the cache without installing
+    Fetch(commands::fetch::FetchOptions),
+
+    /// Check the lockfile's packages into vendor/, for installs that can't reach a registry
+    Vendor(commands::vendor::VendorOptions),
+
+    /// Write the package with a production-only node_modules to a directory, e.g. for a Docker image
+    Deploy(commands::deploy::DeployOptions),
+
+    /// Check rjs-lock.json against package.json, or with --reproducible against a fresh resolution
+    Verify(commands::verify::VerifyOptions),
+
+    /// Export or import an offline bundle of lockfile tarballs and metadata
+    Bundle(commands::bundle::BundleOptions),
+
+    /// Run a pull-through registry proxy backed by the rjs cache
+    Serve(commands::serve::ServeOptions),
+
+    /// Benchmark standard install scenarios against the current project
+    Bench(commands::bench::BenchOptions),
+
+    /// Replace this rjs with the latest release for the platform
+    SelfUpdate(commands::self_update::SelfUpdateOptions),
+
+    /// Run a background daemon that keeps registry metadata warm
+    #[cfg(unix)]
+    Daemon(commands::daemon::DaemonOptions),
+
+    /// Any other command runs the rjs-<name> executable on PATH
+    #[command(external_subcommand)]
+    Plugin(Vec<String>),
 }
 
 impl Command {
-    pub async fn execute(self) -> Result<()> {
+    /// Run the command on the project in `root`.
+    pub async fn execute(self, root: &Path) -> Result<()> {
         match self {
             Command::Init(opts) => {
-                println!("{}", style("RJS - Initialize a new project").bold().green());
+                println!("{}", style(tr!("init-title")).bold().green());
                 info!("Initializing new project");
-                commands::init::execute(opts).await
+                commands::init::execute(opts, root).await
             },
-            Command::Install(opts) => commands::install::execute(opts).await,
-            Command::List(opts) => commands::list::execute(opts).await,
+            Command::Create(opts) => commands::create::execute(opts).await,
+            // Hooks keep the npm names, whichever verb was used
+            Command::Add(opts) => hooks::around("install", root, commands::install::execute(opts, root)).await,
+            Command::Remove(opts) => hooks::around("uninstall", root, commands::uninstall::execute(opts, root)).await,
+            Command::Update(opts) => hooks::around("update", root, commands::update::execute(opts, root)).await,
+            Command::Patch(opts) => commands::patch::execute(opts, root).await,
+            Command::PatchCommit(opts) => commands::patch_commit::execute(opts, root).await,
+            Command::List(opts) => commands::list::execute(opts, root).await,
+            Command::Outdated(opts) => commands::outdated::execute(opts, root).await,
+            Command::Audit(opts) => commands::audit::execute(opts, root).await,
+            Command::Workspaces(opts) => commands::workspaces::execute(opts, root).await,
+            Command::Analyze(opts) => commands::analyze::execute(opts, root).await,
+            Command::Dupes(opts) => commands::dupes::execute(opts, root).await,
+            Command::Explain(opts) => commands::explain::execute(opts, root).await,
+            Command::Graph(opts) => commands::graph::execute(opts, root).await,
+            Command::Fund(opts) => commands::fund::execute(opts, root).await,
+            Command::Lint(opts) => commands::lint::execute(opts, root).await,
+            Command::Run(opts) => commands::run::execute(opts, root).await,
+            Command::Pack(opts) => commands::pack::execute(opts, root).await,
+            Command::Shrinkwrap(opts) => commands::shrinkwrap::execute(opts, root).await,
+            Command::Publish(opts) => hooks::around("publish", root, commands::publish::execute(opts, root)).await,
+            Command::Unpublish(opts) => commands::unpublish::execute(opts, root).await,
+            Command::DistTag(opts) => commands::dist_tag::execute(opts, root).await,
+            Command::Owner(opts) => commands::owner::execute(opts, root).await,
+            Command::Access(opts) => commands::access::execute(opts, root).await,
+            Command::Token(opts) => commands::token::execute(opts, root).await,
+            Command::View(opts) => commands::view::execute(opts).await,
+            Command::Config(opts) => commands::config::execute(opts).await,
+            Command::Env(opts) => commands::env::execute(opts, root).await,
+            Command::Ping(opts) => commands::ping::execute(opts, root).await,
+            Command::Cache(opts) => commands::cache::execute(opts).await,
+            Command::Root(opts) => commands::paths::root(opts, root).await,
+            Command::Bin(opts) => commands::paths::bin(opts, root).await,
+            Command::Prefix(opts) => commands::paths::prefix(opts, root).await,
+            Command::Fetch(opts) => commands::fetch::execute(opts, root).await,
+            Command::Vendor(opts) => commands::vendor::execute(opts, root).await,
+            Command::Deploy(opts) => commands::deploy::execute(opts, root).await,
+            Command::Verify(opts) => commands::verify::execute(opts, root).await,
+            Command::Bundle(opts) => commands::bundle::execute(opts, root).await,
+            Command::Serve(opts) => commands::serve::execute(opts).await,
+            Command::Bench(opts) => commands::bench::execute(opts, root).await,
+            Command::SelfUpdate(opts) => commands::self_update::execute(opts).await,
+            #[cfg(unix)]
+            Command::Daemon(opts) => commands::daemon::execute(opts).await,
+            Command::Plugin(args) => commands::plugin::execute(args, root).await,
         }
     }
 }
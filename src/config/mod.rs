@@ -0,0 +1,380 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::audit::Severity;
+use crate::registry::{NetworkFamily, DEFAULT_REGISTRY};
+use crate::utils::ColorMode;
+
+/// A project's config file, next to package.json.
+pub const PROJECT_FILE: &str = ".rjsrc.toml";
+
+/// Where rjs releases are published.
+pub const DEFAULT_UPDATE_URL: &str = "https://api.github.com/repos/RemiPelloux/rjs/releases";
+
+// Loaded once at startup; commands that run before that see the defaults
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Settings after merging every layer. Command-line flags still take
+/// precedence over these where a command has one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Settings {
+    /// Registry for packages without a scope registry in .npmrc
+    pub registry: String,
+    /// Where metadata and tarballs are cached
+    pub cache_dir: PathBuf,
+    /// Concurrent metadata requests while resolving
+    pub concurrency: usize,
+    /// Tarballs downloaded at once
+    pub network_concurrency: usize,
+    /// Tarballs extracted at once
+    pub extract_concurrency: usize,
+    /// Packages processed per batch while installing
+    pub batch_size: usize,
+    /// Resolved packages held in memory at once; the rest are spilled to a
+    /// temporary database until they're locked and installed. 0 keeps them
+    /// all in memory
+    pub max_packages_in_memory: usize,
+    /// Check installed packages against security advisories
+    pub audit: bool,
+    /// Fail installs with advisories at or above this severity
+    pub audit_level: Option<Severity>,
+    /// Show progress bars on terminals
+    pub progress: bool,
+    /// Say after installs how many packages are looking for funding
+    pub fund: bool,
+    /// Proxy for plain-HTTP registry requests
+    pub proxy: Option<String>,
+    /// Proxy for HTTPS registry requests
+    pub https_proxy: Option<String>,
+    /// Comma-separated hosts to reach without the proxy
+    pub no_proxy: Option<String>,
+    /// Fetch metadata and tarballs over HTTP/3 (QUIC) from hosts that serve
+    /// it, falling back to HTTP/2 for those that don't. Needs an rjs built
+    /// with the http3 feature
+    pub http3: bool,
+    /// IP versions to connect over: auto (both, racing them), ipv4 or ipv6
+    pub network_family: NetworkFamily,
+    /// Connections to the registry opened before resolution starts, so the
+    /// first requests don't all wait on DNS and TLS handshakes
+    pub prewarm_connections: usize,
+    /// Comma-separated registries to fetch tarballs from when the registry's
+    /// copy fails integrity verification
+    pub mirrors: Option<String>,
+    /// When to color output: auto, always or never
+    pub color: ColorMode,
+    /// Use symbols and emoji in output, rather than ASCII
+    pub emoji: bool,
+    /// Fail, rather than warn, when node doesn't match the project's
+    /// engines.node or .nvmrc
+    pub engine_strict: bool,
+    /// Fail, rather than warn, when package.json's packageManager names
+    /// another manager or rjs version
+    pub package_manager_strict: bool,
+    /// Releases `rjs self-update` installs from, as GitHub's releases API
+    /// lists them
+    pub update_url: String,
+    /// Base64 Ed25519 key release binaries must be signed with to be
+    /// installed by `rjs self-update`
+    pub update_public_key: Option<String>,
+    /// Install packages from the project's vendor/ directory, written by
+    /// `rjs vendor`, before going to the registry
+    pub vendor: bool,
+    /// Commands run before and after rjs commands, from a [hooks] table
+    pub hooks: Hooks,
+}
+
+/// Shell commands run in the project directory around rjs commands, e.g.
+/// `pre-install = "node scripts/codegen.js"`. A failing pre hook stops the
+/// command; a failing post hook fails it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Hooks {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_install: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_install: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_uninstall: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_uninstall: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_update: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_update: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_publish: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_publish: Option<String>,
+}
+
+impl Hooks {
+    /// The command for a hook by name, e.g. "pre-install".
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let command = match name {
+            "pre-install" => &self.pre_install,
+            "post-install" => &self.post_install,
+            "pre-uninstall" => &self.pre_uninstall,
+            "post-uninstall" => &self.post_uninstall,
+            "pre-update" => &self.pre_update,
+            "post-update" => &self.post_update,
+            "pre-publish" => &self.pre_publish,
+            "post-publish" => &self.post_publish,
+            _ => &None,
+        };
+        command.as_deref()
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            registry: DEFAULT_REGISTRY.to_string(),
+            cache_dir: dirs::cache_dir().unwrap_or_else(std::env::temp_dir).join("rjs"),
+            concurrency: num_cpus::get() * 4,
+            network_concurrency: 64,
+            extract_concurrency: num_cpus::get(),
+            batch_size: 50,
+            max_packages_in_memory: 0,
+            audit: false,
+            audit_level: None,
+            progress: true,
+            fund: true,
+            proxy: None,
+            https_proxy: None,
+            no_proxy: None,
+            http3: false,
+            network_family: NetworkFamily::Auto,
+            prewarm_connections: 0,
+            mirrors: None,
+            color: ColorMode::Auto,
+            emoji: true,
+            engine_strict: false,
+            package_manager_strict: false,
+            update_url: DEFAULT_UPDATE_URL.to_string(),
+            update_public_key: None,
+            vendor: false,
+            hooks: Hooks::default(),
+        }
+    }
+}
+
+// One config file: any subset of the settings
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct Layer {
+    registry: Option<String>,
+    cache_dir: Option<PathBuf>,
+    concurrency: Option<usize>,
+    network_concurrency: Option<usize>,
+    extract_concurrency: Option<usize>,
+    batch_size: Option<usize>,
+    max_packages_in_memory: Option<usize>,
+    audit: Option<bool>,
+    audit_level: Option<Severity>,
+    progress: Option<bool>,
+    fund: Option<bool>,
+    proxy: Option<String>,
+    https_proxy: Option<String>,
+    no_proxy: Option<String>,
+    http3: Option<bool>,
+    network_family: Option<NetworkFamily>,
+    prewarm_connections: Option<usize>,
+    mirrors: Option<String>,
+    color: Option<ColorMode>,
+    emoji: Option<bool>,
+    engine_strict: Option<bool>,
+    package_manager_strict: Option<bool>,
+    update_url: Option<String>,
+    update_public_key: Option<String>,
+    vendor: Option<bool>,
+    hooks: Option<Hooks>,
+}
+
+/// Where a setting's value came from, from lowest to highest precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Default,
+    User,
+    Project,
+    Env,
+}
+
+impl Source {
+    pub fn label(self) -> &'static str {
+        match self {
+            Source::Default => "default",
+            Source::User => "user",
+            Source::Project => "project",
+            Source::Env => "env",
+        }
+    }
+}
+
+/// The merged settings, with where each one came from.
+#[derive(Debug, Default)]
+pub struct Config {
+    pub settings: Settings,
+    pub sources: BTreeMap<String, Source>,
+    /// The config files that were looked for, whether or not they exist.
+    pub files: Vec<(Source, PathBuf)>,
+}
+
+impl Config {
+    /// Merge the user's config file, `dir`'s project file and the environment
+    /// over the defaults, later layers winning key by key.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut files = Vec::new();
+        if let Some(path) = user_path() {
+            files.push((Source::User, path));
+        }
+        files.push((Source::Project, dir.join(PROJECT_FILE)));
+
+        let mut merged = serde_json::Map::new();
+        let mut sources = BTreeMap::new();
+        let mut apply = |source: Source, values: serde_json::Value| {
+            for (key, value) in values.as_object().into_iter().flatten() {
+                if value.is_null() {
+                    continue;
+                }
+                // Tables like [hooks] merge entry by entry
+                match (merged.get_mut(key), value) {
+                    (Some(serde_json::Value::Object(existing)), serde_json::Value::Object(entries)) => {
+                        existing.extend(entries.iter().map(|(name, entry)| (name.clone(), entry.clone())))
+                    }
+                    _ => {
+                        merged.insert(key.clone(), value.clone());
+                    }
+                }
+                sources.insert(key.clone(), source);
+            }
+        };
+
+        apply(Source::Default, serde_json::to_value(Settings::default())?);
+        for (source, path) in &files {
+            if let Some(layer) = read_layer(path)? {
+                apply(*source, serde_json::to_value(layer)?);
+            }
+        }
+        apply(Source::Env, serde_json::to_value(env_layer()?)?);
+
+        let mut settings: Settings = serde_json::from_value(serde_json::Value::Object(merged))?;
+        settings.registry = settings.registry.trim_end_matches('/').to_string();
+        Ok(Self {
+            settings,
+            sources,
+            files,
+        })
+    }
+
+    /// Every setting with a value, as `key -> value`.
+    pub fn values(&self) -> Result<BTreeMap<String, serde_json::Value>> {
+        let values = serde_json::to_value(&self.settings)?;
+        Ok(values
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+}
+
+/// Load the config for `dir` for the rest of the process.
+pub fn init(dir: &Path) -> Result<()> {
+    let config = Config::load(dir)?;
+    let _ = CONFIG.set(config);
+    Ok(())
+}
+
+/// The process's config.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// The process's merged settings.
+pub fn settings() -> &'static Settings {
+    &get().settings
+}
+
+/// The user's config file: $XDG_CONFIG_HOME/rjs/config.toml, by default
+/// ~/.config/rjs/config.toml on every platform.
+pub fn user_path() -> Option<PathBuf> {
+    let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => dirs::home_dir()?.join(".config"),
+    };
+    Some(config_home.join("rjs").join("config.toml"))
+}
+
+// Settings npm's own variables carry over to, for CI images set up for npm
+const NPM_VARIABLES: [(&str, &str); 8] = [
+    ("npm_config_registry", "registry"),
+    ("npm_config_fund", "fund"),
+    ("npm_config_proxy", "proxy"),
+    ("npm_config_https_proxy", "https-proxy"),
+    ("npm_config_noproxy", "no-proxy"),
+    ("npm_config_audit_level", "audit-level"),
+    ("npm_config_engine_strict", "engine-strict"),
+    ("npm_config_package_manager_strict", "package-manager-strict"),
+];
+
+// Settings from RJS_<KEY> variables (RJS_CACHE_DIR for cache-dir), or from
+// the npm_config_* equivalents, which npm matches in any case
+fn env_layer() -> Result<Layer> {
+    let vars: Vec<(String, String)> = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect();
+    let defaults = serde_json::to_value(Settings::default())?;
+    let mut values = serde_json::Map::new();
+
+    for (key, default) in defaults.as_object().into_iter().flatten() {
+        let rjs_name = format!("RJS_{}", key.to_uppercase().replace('-', "_"));
+        let npm_name = NPM_VARIABLES.iter().find(|(_, setting)| setting == key).map(|(name, _)| *name);
+        let found = vars.iter().find(|(name, _)| *name == rjs_name).or_else(|| {
+            vars.iter()
+                .find(|(name, _)| npm_name.is_some_and(|npm_name| name.eq_ignore_ascii_case(npm_name)))
+        });
+        let Some((name, raw)) = found else {
+            continue;
+        };
+
+        // Variables are strings; read them as whatever type the setting is
+        let value = match default {
+            serde_json::Value::Bool(_) => match raw.to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" => serde_json::Value::Bool(true),
+                "0" | "false" | "no" | "" => serde_json::Value::Bool(false),
+                _ => anyhow::bail!("{}={} should be true or false", name, raw),
+            },
+            serde_json::Value::Number(_) => serde_json::Value::Number(
+                raw.trim()
+                    .parse::<u64>()
+                    .with_context(|| format!("{}={} should be a number", name, raw))?
+                    .into(),
+            ),
+            // Tables are only set in config files
+            serde_json::Value::Object(_) => continue,
+            _ if raw.is_empty() => continue,
+            _ => serde_json::Value::String(raw.clone()),
+        };
+        let single = serde_json::json!({ key.as_str(): value.clone() });
+        serde_json::from_value::<Layer>(single).with_context(|| format!("Invalid {}={}", name, raw))?;
+        values.insert(key.clone(), value);
+    }
+
+    Ok(serde_json::from_value(serde_json::Value::Object(values))?)
+}
+
+fn read_layer(path: &Path) -> Result<Option<Layer>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let layer = toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(layer))
+}
@@ -0,0 +1,269 @@
+use anyhow::{Context, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{UnixListener, UnixStream};
+use tokio_util::sync::CancellationToken;
+
+use crate::registry::{NpmRegistry, PackageInfo};
+use crate::utils;
+
+// How long a packument stays warm before the daemon refetches it
+const METADATA_TTL: Duration = Duration::from_secs(300);
+
+// Requests are answered from memory, so anything slower means the daemon is stuck
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Request sent by the CLI to the daemon, one JSON document per line.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum DaemonRequest {
+    /// `name`'s packument from `registry`, fetched with the client's token
+    PackageInfo {
+        registry: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+        name: String,
+    },
+    Status,
+    Shutdown,
+}
+
+/// Response sent back by the daemon, one JSON document per line.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum DaemonResponse {
+    PackageInfo { info: PackageInfo },
+    Status(DaemonStatus),
+    Ok,
+    Error { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub uptime_secs: u64,
+    pub cached_packages: usize,
+    pub requests: u64,
+    pub cache_hits: u64,
+}
+
+// A registry as a client reaches it: its URL and the token it sends there
+type Origin = (String, Option<String>);
+
+// Warm state shared by all daemon connections. Packuments are kept per
+// origin, so what one token can read is never served to another
+struct DaemonState {
+    started: Instant,
+    registries: Mutex<HashMap<Origin, NpmRegistry>>,
+    packuments: Mutex<HashMap<(Origin, String), (Instant, PackageInfo)>>,
+    requests: AtomicU64,
+    cache_hits: AtomicU64,
+    shutdown: CancellationToken,
+}
+
+impl DaemonState {
+    fn registry(&self, origin: &Origin) -> NpmRegistry {
+        let mut registries = self.registries.lock().unwrap();
+        registries
+            .entry(origin.clone())
+            .or_insert_with(|| {
+                let (url, token) = origin;
+                NpmRegistry::with_registry(url).without_daemon().with_auth_token(token.clone())
+            })
+            .clone()
+    }
+
+    async fn package_info(&self, origin: Origin, name: &str) -> Result<PackageInfo> {
+        let key = (origin, name.to_string());
+
+        let cached = {
+            let packuments = self.packuments.lock().unwrap();
+            packuments
+                .get(&key)
+                .filter(|(fetched, _)| fetched.elapsed() < METADATA_TTL)
+                .map(|(_, info)| info.clone())
+        };
+
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if let Some(info) = cached {
+            self.cache_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(info);
+        }
+
+        let info = self.registry(&key.0).get_package_info(name).await?;
+        self.packuments
+            .lock()
+            .unwrap()
+            .insert(key, (Instant::now(), info.clone()));
+        Ok(info)
+    }
+
+    fn status(&self) -> DaemonStatus {
+        DaemonStatus {
+            pid: std::process::id(),
+            uptime_secs: self.started.elapsed().as_secs(),
+            cached_packages: self.packuments.lock().unwrap().len(),
+            requests: self.requests.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Default location of the daemon socket
+pub fn socket_path() -> Result<PathBuf> {
+    Ok(utils::get_cache_dir()?.join("daemon.sock"))
+}
+
+/// Run the daemon in the foreground until it receives a shutdown request.
+pub async fn serve(socket: &Path) -> Result<()> {
+    if socket.exists() {
+        if send(socket, &DaemonRequest::Status).await.is_ok() {
+            return Err(anyhow::anyhow!("A daemon is already listening on {}", socket.display()));
+        }
+        // Left behind by a daemon that didn't shut down cleanly
+        std::fs::remove_file(socket)
+            .with_context(|| format!("Failed to remove stale socket {}", socket.display()))?;
+    }
+
+    let listener = UnixListener::bind(socket)
+        .with_context(|| format!("Failed to bind daemon socket {}", socket.display()))?;
+    info!("Daemon listening on {}", socket.display());
+
+    let state = Arc::new(DaemonState {
+        started: Instant::now(),
+        registries: Mutex::new(HashMap::new()),
+        packuments: Mutex::new(HashMap::new()),
+        requests: AtomicU64::new(0),
+        cache_hits: AtomicU64::new(0),
+        shutdown: CancellationToken::new(),
+    });
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted.context("Failed to accept daemon connection")?;
+                let state = Arc::clone(&state);
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, state).await {
+                        debug!("Daemon connection failed: {}", e);
+                    }
+                });
+            }
+            _ = state.shutdown.cancelled() => break,
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    let _ = std::fs::remove_file(socket);
+    info!("Daemon stopped");
+    Ok(())
+}
+
+async fn handle_connection(stream: UnixStream, state: Arc<DaemonState>) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        let response = match serde_json::from_str::<DaemonRequest>(&line) {
+            Ok(DaemonRequest::PackageInfo { registry, token, name }) => {
+                match state.package_info((registry, token), &name).await {
+                    Ok(info) => DaemonResponse::PackageInfo { info },
+                    Err(e) => DaemonResponse::Error { message: format!("{:#}", e) },
+                }
+            }
+            Ok(DaemonRequest::Status) => DaemonResponse::Status(state.status()),
+            Ok(DaemonRequest::Shutdown) => {
+                state.shutdown.cancel();
+                DaemonResponse::Ok
+            }
+            Err(e) => DaemonResponse::Error { message: format!("Invalid request: {}", e) },
+        };
+
+        let mut payload = serde_json::to_vec(&response)?;
+        payload.push(b'\n');
+        writer.write_all(&payload).await?;
+    }
+
+    Ok(())
+}
+
+// Connections to daemons left open between requests, with their socket; a
+// resolve makes thousands of lookups that would each connect anew otherwise
+static IDLE: Mutex<Vec<(PathBuf, Connection)>> = Mutex::new(Vec::new());
+
+// A connection to the daemon, carrying one request at a time
+struct Connection {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+impl Connection {
+    async fn open(socket: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(socket)
+            .await
+            .with_context(|| format!("Failed to connect to daemon at {}", socket.display()))?;
+        let (reader, writer) = stream.into_split();
+        Ok(Self { lines: BufReader::new(reader).lines(), writer })
+    }
+
+    async fn exchange(&mut self, request: &DaemonRequest) -> Result<DaemonResponse> {
+        let mut payload = serde_json::to_vec(request)?;
+        payload.push(b'\n');
+        self.writer.write_all(&payload).await?;
+
+        let line = self
+            .lines
+            .next_line()
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Daemon closed the connection"))?;
+        serde_json::from_str(&line).context("Failed to parse daemon response")
+    }
+}
+
+/// Send a request to the daemon listening on `socket`, over a connection
+/// left open by an earlier request when there is one.
+pub async fn send(socket: &Path, request: &DaemonRequest) -> Result<DaemonResponse> {
+    let idle = {
+        let mut idle = IDLE.lock().unwrap();
+        let position = idle.iter().position(|(path, _)| path == socket);
+        position.map(|position| idle.swap_remove(position).1)
+    };
+    let exchange = async {
+        let mut connection = match idle {
+            Some(connection) => connection,
+            None => Connection::open(socket).await?,
+        };
+        let response = connection.exchange(request).await?;
+        Ok::<_, anyhow::Error>((connection, response))
+    };
+
+    // A connection that failed is dropped rather than reused
+    let (connection, response) = tokio::time::timeout(CLIENT_TIMEOUT, exchange)
+        .await
+        .context("Timed out waiting for daemon")??;
+    IDLE.lock().unwrap().push((socket.to_path_buf(), connection));
+    Ok(response)
+}
+
+/// Fetch package metadata through the daemon's warm cache, as `registry`
+/// answers a client sending `token`.
+pub async fn fetch_package_info(socket: &Path, registry: &str, token: Option<&str>, name: &str) -> Result<PackageInfo> {
+    let request = DaemonRequest::PackageInfo {
+        registry: registry.to_string(),
+        token: token.map(str::to_string),
+        name: name.to_string(),
+    };
+
+    match send(socket, &request).await? {
+        DaemonResponse::PackageInfo { info } => Ok(info),
+        DaemonResponse::Error { message } => Err(anyhow::anyhow!(message)),
+        other => Err(anyhow::anyhow!("Unexpected daemon response: {:?}", other)),
+    }
+}
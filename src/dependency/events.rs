@@ -0,0 +1,64 @@
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Typed events emitted by the resolver and installer while they work.
+///
+/// Consumers subscribe by implementing [`InstallEvents`] (or by handing the
+/// resolver an unbounded channel sender) and attaching it with
+/// `DependencyResolver::with_events`.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub enum InstallEvent {
+    /// Resolution of `requested` top-level packages has begun
+    ResolveStarted { requested: usize },
+    /// A package spec was resolved to a concrete version
+    PackageResolved { name: String, version: String },
+    /// A version that its publisher deprecated was resolved; `path` is the
+    /// chain of `name@version`s from a top-level dependency down to it
+    PackageDeprecated { name: String, version: String, message: String, path: Vec<String> },
+    /// A package spec could not be resolved. Optional dependencies are left
+    /// out of the tree; anything else fails the resolve
+    ResolveFailed { name: String, spec: String, error: String },
+    /// Resolution finished with `total` packages in the tree
+    ResolveFinished { total: usize },
+    /// Installation of `total` packages into node_modules has begun
+    InstallStarted { total: usize },
+    /// Bytes of a package's tarball received so far, out of `total` when the
+    /// registry said how large it is
+    DownloadProgress { name: String, downloaded: u64, total: Option<u64> },
+    /// A package's tarball was downloaded from the registry, rather than
+    /// found in the tarball store
+    PackageDownloaded { name: String, bytes: u64 },
+    /// A package's tarball was found in the tarball store
+    PackageCached { name: String },
+    /// A package's tarball was unpacked into its staging directory
+    PackageExtracted { name: String },
+    /// A package was written to node_modules
+    PackageInstalled { name: String, version: String },
+    /// A package could not be written to node_modules
+    InstallFailed { name: String, error: String },
+    /// Installation finished with `installed` packages written
+    InstallFinished { installed: usize },
+}
+
+/// Subscriber for [`InstallEvent`]s.
+///
+/// Handlers are called synchronously from resolver tasks, so they should be
+/// cheap and must not block.
+pub trait InstallEvents: Send + Sync {
+    fn on_event(&self, event: &InstallEvent);
+}
+
+/// Subscriber that ignores every event; used when nobody is listening.
+pub struct NoopEvents;
+
+impl InstallEvents for NoopEvents {
+    fn on_event(&self, _event: &InstallEvent) {}
+}
+
+// Forward events into a channel so consumers can process them on their own task
+impl InstallEvents for UnboundedSender<InstallEvent> {
+    fn on_event(&self, event: &InstallEvent) {
+        // A dropped receiver just means nobody is listening anymore
+        let _ = self.send(event.clone());
+    }
+}
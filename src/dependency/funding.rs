@@ -0,0 +1,58 @@
+use serde::Serialize;
+use std::path::Path;
+
+/// An installed package whose maintainers ask for funding.
+#[derive(Debug, Clone, Serialize)]
+pub struct Funding {
+    pub name: String,
+    pub version: String,
+    pub sources: Vec<FundingSource>,
+}
+
+/// One place to fund a package, e.g. `{"type": "github", "url": "https://github.com/sponsors/x"}`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FundingSource {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    pub url: String,
+}
+
+/// The sources in a package.json `funding` field, which npm accepts as a
+/// URL, a `{type, url}` object, or a list of either.
+pub fn parse(funding: &serde_json::Value) -> Vec<FundingSource> {
+    match funding {
+        serde_json::Value::String(url) => vec![FundingSource { kind: None, url: url.clone() }],
+        serde_json::Value::Object(source) => source
+            .get("url")
+            .and_then(|url| url.as_str())
+            .map(|url| FundingSource {
+                kind: source.get("type").and_then(|kind| kind.as_str()).map(str::to_string),
+                url: url.to_string(),
+            })
+            .into_iter()
+            .collect(),
+        serde_json::Value::Array(sources) => sources.iter().flat_map(parse).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The funding declared by `name` as installed in `node_modules`, if it
+/// declares any.
+pub fn read(node_modules: &Path, name: &str) -> Option<Funding> {
+    let content = std::fs::read_to_string(node_modules.join(name).join("package.json")).ok()?;
+    let manifest: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let sources = parse(manifest.get("funding")?);
+    if sources.is_empty() {
+        return None;
+    }
+    Some(Funding {
+        name: name.to_string(),
+        version: manifest.get("version").and_then(|version| version.as_str()).unwrap_or_default().to_string(),
+        sources,
+    })
+}
+
+/// Funding for each of `names` installed in `node_modules`, in the order given.
+pub fn collect<'a>(node_modules: &Path, names: impl IntoIterator<Item = &'a str>) -> Vec<Funding> {
+    names.into_iter().filter_map(|name| read(node_modules, name)).collect()
+}
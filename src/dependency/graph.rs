@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::{lockfile_key_name, Lockfile};
+use crate::utils;
+
+/// Where the graph is kept, inside node_modules.
+pub const GRAPH_FILE: &str = ".rjs-graph.json";
+
+/// The dependency graph as rjs last resolved it. rjs-lock.json records the
+/// range each package asks for; this records the locked version each range
+/// got. That lets `list`, `explain`, `dupes` and `graph` follow the edges
+/// resolution took without resolving again or fetching metadata. It's only trusted while
+/// rjs-lock.json is the one it was saved with.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Graph {
+    /// Hash of the rjs-lock.json the graph goes with
+    lockfile: String,
+    /// The project's own dependencies
+    pub roots: BTreeMap<String, Edge>,
+    /// Each locked "name@version" and its dependencies
+    pub packages: BTreeMap<String, BTreeMap<String, Edge>>,
+}
+
+/// A range and the "name@version" it resolved to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Edge {
+    pub range: String,
+    pub to: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dev: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+}
+
+impl Edge {
+    /// The version the range resolved to.
+    pub fn version(&self) -> &str {
+        &self.to[lockfile_key_name(&self.to).len() + 1..]
+    }
+}
+
+impl Graph {
+    /// The graph saved for `root`'s rjs-lock.json, or None when there's none
+    /// or the lockfile changed since.
+    pub fn load(root: &Path) -> Option<Graph> {
+        let lockfile = std::fs::read(root.join("rjs-lock.json")).ok()?;
+        let graph: Graph = serde_json::from_slice(&std::fs::read(root.join("node_modules").join(GRAPH_FILE)).ok()?).ok()?;
+        if graph.lockfile != utils::calculate_sha256(&lockfile) {
+            debug!("{} is out of date with rjs-lock.json", GRAPH_FILE);
+            return None;
+        }
+        Some(graph)
+    }
+
+    /// The edges out of `from`: a locked "name@version", or None for the project.
+    pub fn edges(&self, from: Option<&str>) -> Option<&BTreeMap<String, Edge>> {
+        match from {
+            Some(id) => self.packages.get(id),
+            None => Some(&self.roots),
+        }
+    }
+
+    /// Fold this graph into the one saved for `root`, keeping what it knew
+    /// about other packages, and save the result with `lockfile` as written.
+    /// Edges to packages the lockfile no longer has are dropped.
+    pub(super) fn save(self, root: &Path, previous: Option<Graph>, lockfile: &Lockfile) -> Result<()> {
+        let mut graph = previous.unwrap_or_default();
+        graph.roots.extend(self.roots);
+        graph.packages.extend(self.packages);
+        // Hashed as save_lockfile writes it
+        graph.lockfile = utils::calculate_sha256(lockfile.to_json()?.as_bytes());
+
+        let locked = |id: &String| lockfile.packages.contains_key(id);
+        graph.packages.retain(|id, _| locked(id));
+        graph.roots.retain(|_, edge| locked(&edge.to));
+        for edges in graph.packages.values_mut() {
+            edges.retain(|_, edge| locked(&edge.to));
+        }
+
+        let node_modules = root.join("node_modules");
+        std::fs::create_dir_all(&node_modules)?;
+        let path = node_modules.join(GRAPH_FILE);
+        std::fs::write(&path, serde_json::to_vec(&graph)?).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
@@ -0,0 +1,335 @@
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::graph::Graph;
+use super::layout;
+use super::{lockfile_key_name, versions, DependencyResolver, Lockfile, Package};
+use crate::utils;
+
+/// A change to a project's packages: what goes into node_modules, what comes
+/// out, and the lockfile entries describing the result.
+#[derive(Default)]
+pub struct Plan {
+    /// Packages to put in node_modules, replacing other versions of them
+    pub install: Vec<Arc<Package>>,
+    /// Package names to take out of node_modules and rjs-lock.json
+    pub remove: Vec<String>,
+    /// Entries merged into rjs-lock.json over the ones already there; None
+    /// leaves the lockfile alone, as frozen installs do
+    pub lockfile: Option<Lockfile>,
+    /// The edges resolution took, folded into node_modules/.rjs-graph.json
+    /// as the lockfile is written
+    pub graph: Option<Graph>,
+}
+
+/// Applies plans to a project. Every command that changes node_modules goes
+/// through `apply`, so the lockfile, node_modules/.bin and scope directories
+/// never fall out of step with the packages installed.
+pub struct Installer<'a> {
+    resolver: &'a DependencyResolver,
+    root: PathBuf,
+}
+
+impl<'a> Installer<'a> {
+    pub fn new(resolver: &'a DependencyResolver, root: &Path) -> Self {
+        Self {
+            resolver,
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// Remove, install and link what `plan` says, then update rjs-lock.json.
+    /// With the resolver in lockfile-only mode only the lockfile is written.
+    pub async fn apply(&self, plan: Plan) -> Result<Vec<String>> {
+        let mut installed = Vec::new();
+        if !self.resolver.lockfile_only {
+            let node_modules = self.root.join("node_modules");
+            check_case(&node_modules, &plan)?;
+            let replaced: HashSet<&str> = plan.install.iter().map(|pkg| pkg.name.as_str()).collect();
+            for name in plan.remove.iter().filter(|name| !replaced.contains(name.as_str())) {
+                remove_package(&node_modules, name)?;
+            }
+
+            installed = self.resolver.install_packages(&plan.install, &project_ranges(&self.root), &self.root).await?;
+            link_bins(&node_modules);
+        }
+
+        if let Some(lockfile) = plan.lockfile {
+            self.resolver.update_lockfile(lockfile, plan.graph, &self.root, &plan.remove).await?;
+        }
+        Ok(installed)
+    }
+}
+
+// The ranges the project's package.json asks for, by name; they decide which
+// of two conflicting versions gets the top level of node_modules
+pub(super) fn project_ranges(root: &Path) -> HashMap<String, String> {
+    let Some(manifest) = std::fs::read_to_string(root.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+    else {
+        return HashMap::new();
+    };
+    ["optionalDependencies", "devDependencies", "dependencies"]
+        .iter()
+        .filter_map(|field| manifest.get(field)?.as_object())
+        .flatten()
+        .filter_map(|(name, range)| Some((name.clone(), range.as_str()?.to_string())))
+        .collect()
+}
+
+/// Names in `lockfile` that nothing in `roots` (a project's direct
+/// dependencies, name to spec) leads to, directly or through other entries.
+pub fn unreachable(lockfile: &Lockfile, roots: &HashMap<String, String>) -> Vec<String> {
+    let reachable = reachable(lockfile, roots);
+    let kept: HashSet<&str> = reachable.iter().map(|key| lockfile_key_name(key)).collect();
+    let names: BTreeSet<&str> = lockfile.packages.keys().map(|key| lockfile_key_name(key)).collect();
+    names
+        .into_iter()
+        .filter(|name| !kept.contains(name))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Keys ("name@version") of the `lockfile` entries `roots` lead to, directly
+/// or through other entries.
+pub fn reachable<'a>(lockfile: &'a Lockfile, roots: &HashMap<String, String>) -> HashSet<&'a str> {
+    let mut by_name: HashMap<&str, Vec<&str>> = HashMap::new();
+    for key in lockfile.packages.keys() {
+        by_name.entry(lockfile_key_name(key)).or_default().push(key);
+    }
+
+    let mut reachable: HashSet<&str> = HashSet::new();
+    let mut queue: VecDeque<(&str, &str)> = roots.iter().map(|(name, spec)| (name.as_str(), spec.as_str())).collect();
+    while let Some((name, spec)) = queue.pop_front() {
+        let Some(keys) = by_name.get(name) else {
+            continue;
+        };
+        // Specs that aren't ranges (tags, URLs) or match nothing keep every
+        // locked version of the name
+        let req = versions::parse_req(spec);
+        let matching: Vec<&str> = keys
+            .iter()
+            .copied()
+            .filter(|key| {
+                let version = versions::parse_version(&lockfile.packages[*key].version);
+                matches!((&req, version), (Some(req), Some(version)) if req.matches(&version))
+            })
+            .collect();
+        let keys = if matching.is_empty() { keys.clone() } else { matching };
+
+        for key in keys {
+            if reachable.insert(key) {
+                let entry = &lockfile.packages[key];
+                for (dep, spec) in entry.dependencies.iter().chain(&entry.optional_dependencies) {
+                    queue.push_back((dep, spec));
+                }
+            }
+        }
+    }
+
+    reachable
+}
+
+// Windows and macOS filesystems ignore case, so packages whose names differ
+// only in case share one directory there
+const CASE_INSENSITIVE: bool = cfg!(any(windows, target_os = "macos"));
+
+// Refuse a plan that would leave two packages whose names differ only in case
+// (e.g. JSONStream and jsonstream) in node_modules where they'd overwrite each
+// other; elsewhere warn, as the lockfile won't install on those platforms
+fn check_case(node_modules: &Path, plan: &Plan) -> Result<()> {
+    let removed: HashSet<&str> = plan.remove.iter().map(String::as_str).collect();
+    let names: BTreeSet<String> = packages_in(node_modules)
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| !removed.contains(name.as_str()))
+        .chain(plan.install.iter().map(|pkg| pkg.name.clone()))
+        .collect();
+
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    let mut collisions = Vec::new();
+    for name in &names {
+        if let Some(other) = seen.insert(name.to_lowercase(), name) {
+            collisions.push(format!("{} and {}", other, name));
+        }
+    }
+    if collisions.is_empty() {
+        return Ok(());
+    }
+    if CASE_INSENSITIVE {
+        anyhow::bail!(
+            "{} differ only in case and would overwrite each other in node_modules on this filesystem",
+            collisions.join(", ")
+        );
+    }
+    warn!("{} differ only in case; this tree can't be installed on Windows or macOS", collisions.join(", "));
+    Ok(())
+}
+
+fn remove_package(node_modules: &Path, name: &str) -> Result<()> {
+    // Names come from package.json and the lockfile; never leave node_modules
+    let Some(path) = layout::package_path(name) else {
+        warn!("Not removing {:?}, which isn't a package name", name);
+        return Ok(());
+    };
+    let dir = utils::long_path(&node_modules.join(path));
+    match std::fs::remove_dir_all(&dir) {
+        Ok(()) => {
+            debug!("Removed {}", dir.display());
+            Ok(())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("Failed to remove {}", dir.display())),
+    }
+}
+
+// Link the `bin` entries of every package in `node_modules` into
+// node_modules/.bin, then drop links whose package is gone and scope
+// directories left empty
+fn link_bins(node_modules: &Path) {
+    let bin_dir = node_modules.join(".bin");
+    for (name, dir) in packages_in(node_modules) {
+        for (bin, target) in bins_of(&name, &dir) {
+            let relative = Path::new("..").join(&name).join(&target);
+            if let Err(e) = link_bin(&bin_dir, &bin, &relative) {
+                warn!("Failed to link {} from {}: {:#}", bin, name, e);
+            }
+        }
+    }
+
+    for entry in std::fs::read_dir(&bin_dir).into_iter().flatten().flatten() {
+        if shim_target(&entry.path()).is_some_and(|target| !bin_dir.join(target).exists()) {
+            debug!("Removing dead shim {}", entry.path().display());
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+
+    // remove_dir only succeeds on directories left empty
+    let _ = std::fs::remove_dir(&bin_dir);
+    for entry in std::fs::read_dir(node_modules).into_iter().flatten().flatten() {
+        if entry.file_name().to_string_lossy().starts_with('@') {
+            let _ = std::fs::remove_dir(entry.path());
+        }
+    }
+}
+
+// The packages directly in `node_modules`, scoped ones included, by name
+fn packages_in(node_modules: &Path) -> Vec<(String, PathBuf)> {
+    let mut packages = Vec::new();
+    for entry in std::fs::read_dir(node_modules).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') || !entry.path().is_dir() {
+            continue;
+        }
+        if name.starts_with('@') {
+            for scoped in std::fs::read_dir(entry.path()).into_iter().flatten().flatten() {
+                let scoped_name = scoped.file_name().to_string_lossy().to_string();
+                if !scoped_name.starts_with('.') && scoped.path().is_dir() {
+                    packages.push((format!("{}/{}", name, scoped_name), scoped.path()));
+                }
+            }
+        } else {
+            packages.push((name, entry.path()));
+        }
+    }
+    packages
+}
+
+// The commands a package declares, from its package.json `bin`: a path
+// named after the package, or an object of names to paths
+fn bins_of(name: &str, dir: &Path) -> Vec<(String, String)> {
+    let json = std::fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok());
+    let bins: Vec<(String, String)> = match json.as_ref().and_then(|json| json.get("bin")) {
+        Some(serde_json::Value::String(path)) => {
+            vec![(name.rsplit('/').next().unwrap_or(name).to_string(), path.clone())]
+        }
+        Some(serde_json::Value::Object(map)) => map
+            .iter()
+            .filter_map(|(bin, path)| Some((bin.clone(), path.as_str()?.to_string())))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    // A bin is a plain file name pointing inside its package
+    bins.into_iter()
+        .filter_map(|(bin, path)| {
+            let bin = bin.rsplit('/').next().unwrap_or(&bin).to_string();
+            let path = path.trim_start_matches("./").to_string();
+            let inside = !Path::new(&path)
+                .components()
+                .any(|part| !matches!(part, std::path::Component::Normal(_)));
+            (!bin.is_empty() && !bin.starts_with('.') && inside && !path.is_empty()).then_some((bin, path))
+        })
+        .collect()
+}
+
+// Point node_modules/.bin/<bin> at `target`, relative to .bin. Files rjs
+// didn't create are left alone
+#[cfg(unix)]
+fn link_bin(bin_dir: &Path, bin: &str, target: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let link = bin_dir.join(bin);
+    if let Ok(existing) = std::fs::read_link(&link) {
+        if existing == target {
+            return Ok(());
+        }
+        std::fs::remove_file(&link)?;
+    } else if link.symlink_metadata().is_ok() {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(bin_dir)?;
+    std::os::unix::fs::symlink(target, &link)?;
+    // Tarballs don't always mark their bins executable
+    let script = bin_dir.join(target);
+    if let Ok(metadata) = std::fs::metadata(&script) {
+        let mut permissions = metadata.permissions();
+        permissions.set_mode(permissions.mode() | 0o111);
+        std::fs::set_permissions(&script, permissions)?;
+    }
+    Ok(())
+}
+
+// Write node_modules/.bin/<bin>.cmd and <bin>.ps1 shims running `target`
+// with node, for cmd.exe and PowerShell. Files rjs didn't create are left alone
+#[cfg(windows)]
+fn link_bin(bin_dir: &Path, bin: &str, target: &Path) -> Result<()> {
+    std::fs::create_dir_all(bin_dir)?;
+    for (extension, prefix, suffix) in SHIMS {
+        let shim = bin_dir.join(format!("{}.{}", bin, extension));
+        if shim.exists() && shim_target(&shim).is_none() {
+            continue;
+        }
+        let content = format!("{}{}{}", prefix, target.display(), suffix);
+        std::fs::write(&shim, content)?;
+    }
+    Ok(())
+}
+
+// Each shim's extension and what surrounds the target in it
+#[cfg(windows)]
+const SHIMS: [(&str, &str, &str); 2] = [
+    ("cmd", "@node \"%~dp0\\", "\" %*\r\n"),
+    ("ps1", "& node \"$PSScriptRoot\\", "\" @args\r\nexit $LASTEXITCODE\r\n"),
+];
+
+// Where a link or shim rjs created in .bin points, relative to .bin; None
+// for anything else
+#[cfg(unix)]
+fn shim_target(path: &Path) -> Option<PathBuf> {
+    std::fs::read_link(path).ok()
+}
+
+#[cfg(windows)]
+fn shim_target(path: &Path) -> Option<PathBuf> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let rest = SHIMS.iter().find_map(|(_, prefix, _)| content.strip_prefix(prefix))?;
+    Some(PathBuf::from(rest.split('"').next()?))
+}
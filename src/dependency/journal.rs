@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Where the journal lives inside node_modules.
+pub const JOURNAL_FILE: &str = ".rjs-install-journal";
+
+/// The packages an install has put in place so far, one `name@version` per
+/// line in node_modules/.rjs-install-journal.
+///
+/// Packages are recorded once they're downloaded, verified and renamed into
+/// place, and the file is removed when the install finishes. Finding one
+/// means the last install was interrupted, and what it lists can be kept.
+pub struct Journal {
+    path: PathBuf,
+    file: Mutex<Option<std::fs::File>>,
+    done: HashSet<String>,
+}
+
+impl Journal {
+    /// Open the journal in `node_modules`, picking up what an interrupted
+    /// install recorded. Leftovers of its half-staged packages are removed.
+    /// Nothing is written until the first package is recorded.
+    pub fn open(node_modules: &Path) -> Result<Self> {
+        let path = node_modules.join(JOURNAL_FILE);
+
+        let done: HashSet<String> = std::fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        if !done.is_empty() {
+            remove_leftovers(node_modules);
+        }
+
+        Ok(Self {
+            path,
+            file: Mutex::new(None),
+            done,
+        })
+    }
+
+    /// How many packages the interrupted install had already put in place.
+    pub fn resumed(&self) -> usize {
+        self.done.len()
+    }
+
+    /// Whether an interrupted install already put `name@version` in place.
+    pub fn is_done(&self, name: &str, version: &str) -> bool {
+        self.done.contains(&format!("{}@{}", name, version))
+    }
+
+    /// Note that `name@version` is in place.
+    pub fn record(&self, name: &str, version: &str) -> Result<()> {
+        let mut file = self.file.lock().unwrap();
+        if file.is_none() {
+            let opened = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .with_context(|| format!("Failed to open {}", self.path.display()))?;
+            *file = Some(opened);
+        }
+        let file = file.as_mut().unwrap();
+        writeln!(file, "{}@{}", name, version).context("Failed to update the install journal")
+    }
+
+    /// The install finished: nothing is left to resume.
+    pub fn finish(&self) -> Result<()> {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).with_context(|| format!("Failed to remove {}", self.path.display())),
+        }
+    }
+}
+
+// Staging and moved-aside directories of packages an interrupted install was
+// in the middle of, such as node_modules/.lodash.staging-<pid>-<n>
+fn remove_leftovers(node_modules: &Path) {
+    let own_pid = format!("-{}-", std::process::id());
+    let scopes = std::fs::read_dir(node_modules)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with('@'))
+        .map(|entry| entry.path());
+
+    for dir in std::iter::once(node_modules.to_path_buf()).chain(scopes) {
+        for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let leftover = name.starts_with('.')
+                && (name.contains(".staging-") || name.contains(".old-"))
+                && !name.contains(&own_pid);
+            if leftover {
+                debug!("Removing leftover {}", entry.path().display());
+                let _ = std::fs::remove_dir_all(entry.path());
+            }
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use log::{debug, warn};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::{versions, Package};
+
+// Nesting deeper than this is taken for a cycle of conflicting versions
+const MAX_DEPTH: usize = 16;
+
+/// Where each package goes under node_modules, as a path relative to it.
+///
+/// Every name is installed at the top level once. When the graph holds
+/// several versions of a name, the top level gets the one the project's own
+/// `roots` (name to range) ask for, or else the one most packages ask for,
+/// newest first on a tie. Each other version is nested in the node_modules of
+/// every package that asks for it and wouldn't otherwise find it, the way Node
+/// resolves modules. Entries come shallowest first, so a package's directory
+/// is in place before anything is nested inside it.
+pub fn layout(packages: &[Arc<Package>], roots: &HashMap<String, String>) -> Vec<(Arc<Package>, PathBuf)> {
+    let mut by_name: BTreeMap<&str, Vec<&Arc<Package>>> = BTreeMap::new();
+    for pkg in packages {
+        let versions = by_name.entry(pkg.name.as_str()).or_default();
+        if !versions.iter().any(|other| other.version == pkg.version) {
+            versions.push(pkg);
+        }
+    }
+
+    let mut placed: BTreeMap<String, Arc<Package>> = BTreeMap::new();
+    let mut queue = VecDeque::new();
+    for (name, versions) in &by_name {
+        let top = match versions.as_slice() {
+            [only] => *only,
+            _ => top_version(name, versions, packages, roots),
+        };
+        placed.insert(name.to_string(), Arc::clone(top));
+        queue.push_back(name.to_string());
+    }
+
+    // Nest whatever a placed package can't find at the version it asks for
+    while let Some(path) = queue.pop_front() {
+        let pkg = Arc::clone(&placed[&path]);
+        for (name, spec) in pkg.dependencies.iter().chain(&pkg.optional_dependencies) {
+            let Some(candidates) = by_name.get(name.as_str()).filter(|versions| versions.len() > 1) else {
+                continue;
+            };
+            let req = versions::parse_req_or_any(spec);
+            let wants = |pkg: &Package| versions::parse_version(&pkg.version).is_some_and(|version| req.matches(&version));
+            if find(&placed, &path, name).is_some_and(|found| wants(found)) {
+                continue;
+            }
+            let Some(wanted) = candidates
+                .iter()
+                .filter(|candidate| wants(candidate))
+                .max_by_key(|candidate| versions::parse_version(&candidate.version))
+            else {
+                continue;
+            };
+
+            let nested = format!("{}/node_modules/{}", path, name);
+            if depth(&nested) > MAX_DEPTH {
+                warn!("Not nesting {}@{} under {}: its versions depend on each other in a cycle", name, wanted.version, path);
+                continue;
+            }
+            debug!("Nesting {}@{} under {}", name, wanted.version, path);
+            placed.insert(nested.clone(), Arc::clone(wanted));
+            queue.push_back(nested);
+        }
+    }
+
+    let mut layout: Vec<(Arc<Package>, PathBuf)> = placed
+        .into_iter()
+        .map(|(path, pkg)| (pkg, PathBuf::from(path)))
+        .collect();
+    layout.sort_by_key(|(_, path)| depth(&path.to_string_lossy()));
+    layout
+}
+
+/// The directory `name` goes in, relative to a node_modules: "name", or
+/// "@scope/name" inside its scope directory. None for anything else, so a
+/// name from a manifest can't reach outside node_modules.
+pub fn package_path(name: &str) -> Option<PathBuf> {
+    let parts: Vec<&str> = name.split('/').collect();
+    let valid = |part: &str| !part.is_empty() && !part.starts_with('.') && !part.contains('\\');
+    match parts.as_slice() {
+        [scope, name] if scope.len() > 1 && scope.starts_with('@') && valid(scope) && valid(name) => {
+            Some(Path::new(scope).join(name))
+        }
+        [name] if !name.starts_with('@') && valid(name) => Some(PathBuf::from(name)),
+        _ => None,
+    }
+}
+
+/// How many node_modules directories deep a layout path is, 0 for the top level.
+pub fn depth(path: &str) -> usize {
+    path.matches("/node_modules/").count()
+}
+
+// The version of `name` that gets node_modules/<name>
+fn top_version<'a>(
+    name: &str,
+    versions: &[&'a Arc<Package>],
+    packages: &[Arc<Package>],
+    roots: &HashMap<String, String>,
+) -> &'a Arc<Package> {
+    let asked = |version: &str, spec: &str| {
+        versions::parse_version(version).is_some_and(|version| versions::parse_req_or_any(spec).matches(&version))
+    };
+    versions
+        .iter()
+        .copied()
+        .max_by_key(|pkg| {
+            let by_project = roots.get(name).is_some_and(|spec| asked(&pkg.version, spec));
+            let dependents = packages
+                .iter()
+                .filter_map(|dependent| dependent.dependencies.get(name).or(dependent.optional_dependencies.get(name)))
+                .filter(|spec| asked(&pkg.version, spec))
+                .count();
+            (by_project, dependents, versions::parse_version(&pkg.version))
+        })
+        .unwrap_or(versions[0])
+}
+
+// What Node finds for `name` from the package at `path`: its own node_modules
+// first, then each enclosing package's, then the top level
+fn find<'a>(placed: &'a BTreeMap<String, Arc<Package>>, path: &str, name: &str) -> Option<&'a Arc<Package>> {
+    let mut dir = Some(path);
+    loop {
+        let candidate = match dir {
+            Some(dir) => format!("{}/node_modules/{}", dir, name),
+            None => name.to_string(),
+        };
+        if let Some(found) = placed.get(&candidate) {
+            return Some(found);
+        }
+        dir = dir?.rfind("/node_modules/").map(|index| &path[..index]);
+    }
+}
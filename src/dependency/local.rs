@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use flate2::read::GzDecoder;
+use log::warn;
+use sha2::{Digest, Sha512};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use super::platform::Platforms;
+use super::{versions, Package};
+use crate::registry::DistInfo;
+
+/// Prefix of specs naming a tarball on disk by its path from the project
+/// root, as in "file:vendor/foo-1.2.3.tgz". The lockfile records the same
+/// spec as the package's `resolved`.
+pub const FILE_PREFIX: &str = "file:";
+
+/// Whether `spec` names a local tarball rather than a range or dist-tag.
+pub fn is_file_spec(spec: &str) -> bool {
+    spec.starts_with(FILE_PREFIX)
+}
+
+/// Whether an argument to `rjs install` is a path to a tarball rather than
+/// "name@spec": "file:..." or anything ending in .tgz or .tar.gz.
+pub fn is_tarball_arg(arg: &str) -> bool {
+    is_file_spec(arg) || arg.ends_with(".tgz") || arg.ends_with(".tar.gz")
+}
+
+/// The name the tarball at `arg` (relative to the working directory)
+/// installs as, and the "file:" spec it's saved under, relative to `root`
+/// when it's inside the project.
+pub fn spec_for(arg: &str, root: &Path) -> Result<(String, String)> {
+    let path = Path::new(arg.strip_prefix(FILE_PREFIX).unwrap_or(arg));
+    let absolute = std::path::absolute(path)?;
+    if !absolute.is_file() {
+        anyhow::bail!("{} isn't a tarball", path.display());
+    }
+    let data = std::fs::read(&absolute).with_context(|| format!("Failed to read {}", absolute.display()))?;
+    let manifest = manifest(&data).with_context(|| format!("{} isn't a package tarball", path.display()))?;
+    let name = manifest
+        .get("name")
+        .and_then(|name| name.as_str())
+        .with_context(|| format!("The package.json in {} has no name", path.display()))?;
+
+    let root = std::path::absolute(root)?;
+    let relative = normalize(&absolute)
+        .strip_prefix(normalize(&root))
+        .map(Path::to_path_buf)
+        .unwrap_or(absolute);
+    Ok((name.to_string(), format!("{}{}", FILE_PREFIX, relative.to_string_lossy().replace('\\', "/"))))
+}
+
+/// The tarball a "file:" spec or `resolved` points at, under `root`.
+pub fn path(root: &Path, spec: &str) -> Option<PathBuf> {
+    spec.strip_prefix(FILE_PREFIX).map(|path| root.join(path))
+}
+
+/// The package in the tarball `spec` points at, installed as `name` the way
+/// a dependency on it is named. Its integrity is the tarball's, so editing
+/// it shows up as a change.
+pub fn package(root: &Path, name: &str, spec: &str) -> Result<Package> {
+    let path = path(root, spec).with_context(|| format!("{} isn't a file: spec", spec))?;
+    let data = std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let manifest = manifest(&data).with_context(|| format!("{} isn't a package tarball", path.display()))?;
+
+    let version = manifest
+        .get("version")
+        .and_then(|version| version.as_str())
+        .filter(|version| versions::parse_version(version).is_some())
+        .with_context(|| format!("The package.json in {} has no valid version", path.display()))?;
+    if let Some(other) = manifest.get("name").and_then(|name| name.as_str()).filter(|other| *other != name) {
+        warn!("{} holds {}, installed as {}", path.display(), other, name);
+    }
+    let dependencies = |field: &str| -> HashMap<String, String> {
+        manifest
+            .get(field)
+            .and_then(|deps| deps.as_object())
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, spec)| Some((name.clone(), spec.as_str()?.to_string())))
+            .collect()
+    };
+
+    Ok(Package {
+        name: name.to_string(),
+        version: version.to_string(),
+        dependencies: dependencies("dependencies"),
+        dev_dependencies: HashMap::new(),
+        dist: Some(DistInfo {
+            shasum: sha1_smol::Sha1::from(&data).digest().to_string(),
+            tarball: spec.to_string(),
+            integrity: Some(format!("sha512-{}", base64::engine::general_purpose::STANDARD.encode(Sha512::digest(&data)))),
+            signatures: Vec::new(),
+            npm_signature: None,
+            attestations: None,
+        }),
+        license: manifest.get("license").and_then(|license| license.as_str()).map(str::to_string),
+        optional_dependencies: dependencies("optionalDependencies"),
+        platforms: Platforms::from_fields(manifest.get("os"), manifest.get("cpu"), manifest.get("libc")),
+        registry: None,
+        deprecated: None,
+    })
+}
+
+// The package.json at the top of a gzipped tarball, usually package/package.json
+fn manifest(data: &[u8]) -> Result<serde_json::Value> {
+    let mut archive = tar::Archive::new(GzDecoder::new(data));
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let entry_path = entry.path()?.to_path_buf();
+        if entry_path.components().count() == 2 && entry_path.ends_with("package.json") {
+            return serde_json::from_reader(entry).context("Failed to parse its package.json");
+        }
+    }
+    anyhow::bail!("No package.json in it")
+}
+
+// `path` with "." and ".." folded away, as std::path::absolute leaves them
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
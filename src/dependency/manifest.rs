@@ -0,0 +1,293 @@
+use anyhow::{Context, Result};
+use std::ops::Range;
+
+/// A package.json edited in place: only the text of what changes is
+/// rewritten, so key order, indentation, line endings and the trailing
+/// newline stay as the author left them.
+pub struct Manifest {
+    content: String,
+}
+
+// An object in the text: its braces and members
+struct Object {
+    open: usize,
+    close: usize,
+    members: Vec<Member>,
+}
+
+struct Member {
+    key: String,
+    // The key's quotes included
+    key_start: usize,
+    key_end: usize,
+    value: Range<usize>,
+}
+
+impl Manifest {
+    pub fn parse(content: &str) -> Result<Self> {
+        let json: serde_json::Value = serde_json::from_str(content).context("Invalid JSON")?;
+        if !json.is_object() {
+            anyhow::bail!("Expected a JSON object");
+        }
+        Ok(Self {
+            content: content.to_string(),
+        })
+    }
+
+    /// Set `name` to `spec` in the `field` dependency object ("dependencies",
+    /// "devDependencies", ...), creating the object when it's missing. The
+    /// object is left sorted by name, as npm leaves it.
+    pub fn set_dependency(&mut self, field: &str, name: &str, spec: &str) -> Result<()> {
+        let root = self.root()?;
+        let Some(member) = root.members.iter().find(|member| member.key == field) else {
+            self.insert_member(&root, field, "{}", 1);
+            return self.set_dependency(field, name, spec);
+        };
+
+        let value = json_string(spec);
+        if !self.content[member.value.clone()].starts_with('{') {
+            let range = member.value.clone();
+            self.content.replace_range(range, "{}");
+            return self.set_dependency(field, name, spec);
+        }
+
+        let start = member.value.start;
+        let dependencies = parse_object(&self.content, start)?;
+        match dependencies.members.iter().find(|member| member.key == name) {
+            Some(existing) => self.content.replace_range(existing.value.clone(), &value),
+            None => self.insert_member(&dependencies, name, &value, 2),
+        }
+        self.sort_members(start)
+    }
+
+    /// Set the top-level `key` to the string `value`, adding it last when
+    /// it's missing.
+    pub fn set_string(&mut self, key: &str, value: &str) -> Result<()> {
+        let root = self.root()?;
+        let value = json_string(value);
+        match root.members.iter().find(|member| member.key == key) {
+            Some(member) => self.content.replace_range(member.value.clone(), &value),
+            None => self.insert_member(&root, key, &value, 1),
+        }
+        Ok(())
+    }
+
+    /// Remove `name` from the `field` dependency object, with the separator
+    /// before or after it. Returns whether it was there.
+    pub fn remove_dependency(&mut self, field: &str, name: &str) -> Result<bool> {
+        let root = self.root()?;
+        let Some(member) = root.members.iter().find(|member| member.key == field) else {
+            return Ok(false);
+        };
+        if !self.content[member.value.clone()].starts_with('{') {
+            return Ok(false);
+        }
+
+        let dependencies = parse_object(&self.content, member.value.start)?;
+        let Some(index) = dependencies.members.iter().position(|member| member.key == name) else {
+            return Ok(false);
+        };
+        let members = &dependencies.members;
+        let range = match (index.checked_sub(1), members.get(index + 1)) {
+            (_, Some(next)) => members[index].key_start..next.key_start,
+            (Some(previous), None) => members[previous].value.end..members[index].value.end,
+            // The only entry: the object is left empty
+            (None, None) => dependencies.open + 1..dependencies.close,
+        };
+        self.content.replace_range(range, "");
+        Ok(true)
+    }
+
+    pub fn into_string(self) -> String {
+        self.content
+    }
+
+    fn root(&self) -> Result<Object> {
+        let start = skip_whitespace(self.content.as_bytes(), 0);
+        parse_object(&self.content, start)
+    }
+
+    // Add `"key": value` last in `object`, which is nested `level` deep, laid
+    // out like its other members
+    fn insert_member(&mut self, object: &Object, key: &str, value: &str, level: usize) {
+        let newline = if self.content.contains("\r\n") { "\r\n" } else { "\n" };
+        let unit = self.indent_unit();
+        let colon = self.colon();
+        let member = format!("{}{}{}", json_string(key), colon, value);
+
+        if object.members.is_empty() {
+            let closing = unit.repeat(level - 1);
+            let text = format!("{{{}{}{}{}{}}}", newline, unit.repeat(level), member, newline, closing);
+            self.content.replace_range(object.open..object.close + 1, &text);
+            return;
+        }
+
+        let last = object.members.last().unwrap();
+        let separator = self.separator(object);
+        self.content.insert_str(last.value.end, &format!("{}{}", separator, member));
+    }
+
+    // Reorder the members of the object at `open` by key
+    fn sort_members(&mut self, open: usize) -> Result<()> {
+        let object = parse_object(&self.content, open)?;
+        let (Some(first), Some(last)) = (object.members.first(), object.members.last()) else {
+            return Ok(());
+        };
+        if object.members.windows(2).all(|pair| pair[0].key <= pair[1].key) {
+            return Ok(());
+        }
+
+        let mut members: Vec<&Member> = object.members.iter().collect();
+        members.sort_by(|a, b| a.key.cmp(&b.key));
+        let sorted = members
+            .iter()
+            .map(|member| &self.content[member.key_start..member.value.end])
+            .collect::<Vec<_>>()
+            .join(&self.separator(&object));
+        self.content.replace_range(first.key_start..last.value.end, &sorted);
+        Ok(())
+    }
+
+    // What goes between two members of `object`: a comma and a new line
+    // indented like its first member, or a comma and a space when it's all
+    // on one line, e.g. {"a": "1", "b": "2"}
+    fn separator(&self, object: &Object) -> String {
+        let newline = if self.content.contains("\r\n") { "\r\n" } else { "\n" };
+        let leading = &self.content[object.open + 1..object.members[0].key_start];
+        match leading.rfind('\n') {
+            Some(line_start) => format!(",{}{}", newline, &leading[line_start + 1..]),
+            None => ", ".to_string(),
+        }
+    }
+
+    // The indentation of the top-level keys, two spaces when there are none
+    fn indent_unit(&self) -> String {
+        let Ok(root) = self.root() else {
+            return "  ".to_string();
+        };
+        root.members
+            .first()
+            .and_then(|first| {
+                let leading = &self.content[root.open + 1..first.key_start];
+                leading.rfind('\n').map(|line_start| leading[line_start + 1..].to_string())
+            })
+            .filter(|indent| !indent.is_empty())
+            .unwrap_or_else(|| "  ".to_string())
+    }
+
+    // What goes between a key and its value: ": " unless the file leaves out
+    // the space
+    fn colon(&self) -> &'static str {
+        let compact = self.root().ok().and_then(|root| {
+            let first = root.members.first()?;
+            Some(!self.content[first.key_end..first.value.start].contains(' '))
+        });
+        if compact == Some(true) { ":" } else { ": " }
+    }
+}
+
+fn json_string(value: &str) -> String {
+    serde_json::Value::String(value.to_string()).to_string()
+}
+
+fn parse_object(text: &str, open: usize) -> Result<Object> {
+    let bytes = text.as_bytes();
+    if bytes.get(open) != Some(&b'{') {
+        anyhow::bail!("Expected an object at offset {}", open);
+    }
+
+    let mut members = Vec::new();
+    let mut i = skip_whitespace(bytes, open + 1);
+    if bytes.get(i) == Some(&b'}') {
+        return Ok(Object { open, close: i, members });
+    }
+
+    loop {
+        let key_start = i;
+        let key_end = skip_string(bytes, i)?;
+        let key: String = serde_json::from_str(&text[key_start..key_end])?;
+        i = skip_whitespace(bytes, key_end);
+        if bytes.get(i) != Some(&b':') {
+            anyhow::bail!("Expected ':' at offset {}", i);
+        }
+        let value_start = skip_whitespace(bytes, i + 1);
+        let value_end = skip_value(bytes, value_start)?;
+        members.push(Member {
+            key,
+            key_start,
+            key_end,
+            value: value_start..value_end,
+        });
+
+        i = skip_whitespace(bytes, value_end);
+        match bytes.get(i) {
+            Some(b',') => i = skip_whitespace(bytes, i + 1),
+            Some(b'}') => return Ok(Object { open, close: i, members }),
+            _ => anyhow::bail!("Expected ',' or '}}' at offset {}", i),
+        }
+    }
+}
+
+fn skip_whitespace(bytes: &[u8], mut i: usize) -> usize {
+    while bytes.get(i).is_some_and(|byte| byte.is_ascii_whitespace()) {
+        i += 1;
+    }
+    i
+}
+
+// The offset just past the string starting at `i`
+fn skip_string(bytes: &[u8], i: usize) -> Result<usize> {
+    if bytes.get(i) != Some(&b'"') {
+        anyhow::bail!("Expected a string at offset {}", i);
+    }
+    let mut j = i + 1;
+    while let Some(&byte) = bytes.get(j) {
+        match byte {
+            b'\\' => j += 2,
+            b'"' => return Ok(j + 1),
+            _ => j += 1,
+        }
+    }
+    anyhow::bail!("Unterminated string at offset {}", i)
+}
+
+// The offset just past the value starting at `i`
+fn skip_value(bytes: &[u8], i: usize) -> Result<usize> {
+    match bytes.get(i) {
+        Some(b'"') => skip_string(bytes, i),
+        Some(b'{') | Some(b'[') => {
+            let mut depth = 0;
+            let mut j = i;
+            while let Some(&byte) = bytes.get(j) {
+                match byte {
+                    b'"' => {
+                        j = skip_string(bytes, j)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Ok(j + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                j += 1;
+            }
+            anyhow::bail!("Unterminated value at offset {}", i)
+        }
+        // Numbers, true, false and null run until the next delimiter
+        Some(_) => {
+            let mut j = i;
+            while bytes
+                .get(j)
+                .is_some_and(|byte| !matches!(byte, b',' | b'}' | b']') && !byte.is_ascii_whitespace())
+            {
+                j += 1;
+            }
+            Ok(j)
+        }
+        None => anyhow::bail!("Expected a value at offset {}", i),
+    }
+}
@@ -1,81 +1,281 @@
 use anyhow::{Context, Result};
 use futures::{stream, StreamExt};
-use log::{debug, info};
-use semver::{Version, VersionReq};
-use std::collections::{HashMap, HashSet};
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+use log::{debug, info, warn};
+use semver::Version;
+use dashmap::{DashMap, DashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs;
-use rayon::prelude::*;
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
 use crossbeam::queue::SegQueue;
-use std::thread;
 use serde::{Deserialize, Serialize};
-use hex;
+use tokio_util::sync::CancellationToken;
 
-use crate::registry::NpmRegistry;
+use crate::policy::{Action, Policy, POLICY_FILE};
+use crate::registry::{DistInfo, NpmRegistry, PackageInfo};
+use crate::timing::{self, Phase};
+use crate::utils;
+use crate::workspace::catalog::Catalogs;
 
-#[derive(Clone)]
+pub mod events;
+pub mod funding;
+pub mod graph;
+pub mod installer;
+pub mod journal;
+pub mod layout;
+pub mod local;
+pub mod manifest;
+pub mod patches;
+pub mod platform;
+pub mod shrinkwrap;
+mod spill;
+pub mod staging;
+pub mod state;
+pub mod vendor;
+pub mod versions;
+
+use events::{InstallEvent, InstallEvents, NoopEvents};
+use graph::{Edge, Graph};
+use installer::{Installer, Plan};
+use journal::Journal;
+use patches::Patches;
+use spill::{Slot, Spill};
+use vendor::Vendor;
+use platform::{Host, Platforms};
+
+#[derive(Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Package {
     pub name: String,
     pub version: String,
     pub dependencies: HashMap<String, String>,
     pub dev_dependencies: HashMap<String, String>,
+    // Where the package tarball lives; unknown for packages not yet resolved
+    pub dist: Option<DistInfo>,
+    // SPDX license expression declared in the package metadata
+    pub license: Option<String>,
+    // Resolved and locked like dependencies, but only installed on the
+    // platforms they support, e.g. esbuild's one binary package per os/cpu
+    pub optional_dependencies: HashMap<String, String>,
+    pub platforms: Platforms,
+    // Registry the package was resolved from, when it came from one
+    pub registry: Option<String>,
+    // The publisher's deprecation message for this version, if it has one
+    pub deprecated: Option<String>,
 }
 
+/// A resolved dependency graph.
+///
+/// Every resolved `name@version` is stored once and shared with the resolver's
+/// cache; requested specs point at it by index, so large graphs don't hold a
+/// copy of a package per spec that selected it. With a memory limit, packages
+/// past it stay in the spill database and are read back when asked for.
 #[allow(dead_code)]
 pub struct DependencyTree {
     pub root: Package,
-    pub dependencies: HashMap<String, Package>,
+    packages: Vec<Slot>,
+    // Requested "name@spec" to the index of the package it resolved to
+    pub resolved: HashMap<String, usize>,
+    // "name@version" to its index in `packages`
+    ids: HashMap<String, usize>,
+    spill: Option<Arc<Spill>>,
+}
+
+impl DependencyTree {
+    fn new(root: Package, spill: Option<Arc<Spill>>) -> Self {
+        Self {
+            root,
+            packages: Vec::new(),
+            resolved: HashMap::new(),
+            ids: HashMap::new(),
+            spill,
+        }
+    }
+
+    // Record that `spec_key` resolved to `pkg`, reusing the node when that
+    // exact version is already in the tree. Packages the cache spilled are
+    // only kept by id
+    fn insert(&mut self, spec_key: String, pkg: Arc<Package>) {
+        let id = format!("{}@{}", pkg.name, pkg.version);
+        let index = match self.ids.get(&id) {
+            Some(&index) => index,
+            None => {
+                let slot = match &self.spill {
+                    Some(spill) if spill.contains(&id) => Slot::Spilled(id.clone()),
+                    _ => Slot::Resident(pkg),
+                };
+                self.packages.push(slot);
+                self.ids.insert(id, self.packages.len() - 1);
+                self.packages.len() - 1
+            }
+        };
+        self.resolved.insert(spec_key, index);
+    }
+
+    /// Number of distinct packages in the tree.
+    pub fn len(&self) -> usize {
+        self.packages.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    /// The package at `index`, read back from disk if it was spilled.
+    pub fn package(&self, index: usize) -> Result<Arc<Package>> {
+        match &self.packages[index] {
+            Slot::Resident(pkg) => Ok(Arc::clone(pkg)),
+            Slot::Spilled(id) => self.spill.as_ref().context("Spilled package without a spill database")?.load(id),
+        }
+    }
+
+    /// Every package, loading spilled ones one at a time.
+    pub fn iter(&self) -> impl Iterator<Item = Result<Arc<Package>>> + '_ {
+        (0..self.packages.len()).map(|index| self.package(index))
+    }
+
+    /// The packages to put in node_modules. Spilled ones come back with only
+    /// the dependencies on names the tree has several versions of, which
+    /// layout needs; the rest installing doesn't read, so the list stays
+    /// small however large the graph is.
+    pub fn to_install(&self) -> Result<Vec<Arc<Package>>> {
+        let mut versions: HashMap<&str, usize> = HashMap::new();
+        for id in self.ids.keys() {
+            *versions.entry(lockfile_key_name(id)).or_default() += 1;
+        }
+        let conflicting = |name: &String| versions.get(name.as_str()).is_some_and(|count| *count > 1);
+
+        self.packages
+            .iter()
+            .enumerate()
+            .map(|(index, slot)| match slot {
+                Slot::Resident(pkg) => Ok(Arc::clone(pkg)),
+                Slot::Spilled(_) => {
+                    let mut pkg = self.package(index)?;
+                    // Packages without a tarball get a package.json listing them
+                    if pkg.dist.is_some() {
+                        let pkg = Arc::make_mut(&mut pkg);
+                        pkg.dependencies.retain(|name, _| conflicting(name));
+                        pkg.optional_dependencies.retain(|name, _| conflicting(name));
+                        pkg.dev_dependencies = HashMap::new();
+                    }
+                    Ok(pkg)
+                }
+            })
+            .collect()
+    }
+
+    // The edges resolution took: what each requested spec, the root's and
+    // every package's, resolved to
+    fn graph(&self) -> Result<Graph> {
+        let mut ids = vec![""; self.packages.len()];
+        for (id, &index) in &self.ids {
+            ids[index] = id;
+        }
+        let edge = |name: &str, range: &str, dev: bool, optional: bool| {
+            let index = self.resolved.get(&format!("{}@{}", name, range))?;
+            Some((name.to_string(), Edge { range: range.to_string(), to: ids[*index].to_string(), dev, optional }))
+        };
+        let edges = |pkg: &Package| -> BTreeMap<String, Edge> {
+            pkg.dependencies
+                .iter()
+                .map(|(name, range)| (name, range, false))
+                .chain(pkg.optional_dependencies.iter().map(|(name, range)| (name, range, true)))
+                .filter_map(|(name, range, optional)| edge(name, range, false, optional))
+                .collect()
+        };
+
+        let mut graph = Graph::default();
+        graph.roots = edges(&self.root);
+        graph.roots.extend(self.root.dev_dependencies.iter().filter_map(|(name, range)| edge(name, range, true, false)));
+        for (index, pkg) in self.iter().enumerate() {
+            let pkg = pkg?;
+            graph.packages.insert(ids[index].to_string(), edges(&pkg));
+        }
+        Ok(graph)
+    }
+
+    /// The package a requested `name@spec` resolved to.
+    pub fn get(&self, name: &str, spec: &str) -> Result<Option<Arc<Package>>> {
+        match self.resolved.get(&format!("{}@{}", name, spec)) {
+            Some(&index) => self.package(index).map(Some),
+            None => Ok(None),
+        }
+    }
 }
 
-// Cache for package resolution to avoid redundant network requests
+// Cache for package resolution to avoid redundant network requests. Sharded,
+// so concurrent resolves of different packages don't contend on one lock.
+// With a spill database, packages past its limit are cached by id only
 #[derive(Clone)]
 struct PackageCache {
-    cache: Arc<Mutex<HashMap<String, Arc<Package>>>>,
+    cache: Arc<DashMap<String, Slot>>,
+    spill: Option<Arc<Spill>>,
 }
 
 impl PackageCache {
     fn new() -> Self {
         Self {
-            cache: Arc::new(Mutex::new(HashMap::new())),
+            cache: Arc::new(DashMap::new()),
+            spill: None,
         }
     }
 
     fn get(&self, key: &str) -> Option<Arc<Package>> {
-        let cache = self.cache.lock().unwrap();
-        cache.get(key).cloned()
+        let slot = self.cache.get(key)?.value().clone();
+        match (slot, &self.spill) {
+            (Slot::Resident(pkg), _) => Some(pkg),
+            (Slot::Spilled(id), Some(spill)) => match spill.load(&id) {
+                Ok(pkg) => Some(pkg),
+                Err(e) => {
+                    // Resolved again instead
+                    debug!("{:#}", e);
+                    None
+                }
+            },
+            (Slot::Spilled(_), None) => None,
+        }
     }
 
-    fn insert(&self, key: String, package: Package) -> Arc<Package> {
-        let package_arc = Arc::new(package);
-        let mut cache = self.cache.lock().unwrap();
-        cache.insert(key, package_arc.clone());
-        package_arc
+    fn insert(&self, key: String, package: impl Into<Arc<Package>>) -> Result<Arc<Package>> {
+        let package_arc = package.into();
+        let slot = match &self.spill {
+            Some(spill) => spill.admit(Arc::clone(&package_arc))?,
+            None => Slot::Resident(Arc::clone(&package_arc)),
+        };
+        self.cache.insert(key, slot);
+        Ok(package_arc)
     }
 }
 
+// Parsed version, original version string and the spec that selected it
+type RegisteredVersion = (Arc<Version>, String, String);
+
+// A packument's parsed versions with their original strings, newest first
+type SortedVersions = Arc<Vec<(Arc<Version>, String)>>;
+
 // Add a structure for tracking deduplicated dependencies
 #[derive(Clone)]
 struct DependencyDeduplication {
     // Map from package name to available versions and their full specs
-    packages: Arc<Mutex<HashMap<String, Vec<(Version, String, String)>>>>,
+    packages: Arc<DashMap<String, Vec<RegisteredVersion>>>,
 }
 
 impl DependencyDeduplication {
     fn new() -> Self {
         Self {
-            packages: Arc::new(Mutex::new(HashMap::new())),
+            packages: Arc::new(DashMap::new()),
         }
     }
 
     fn register_package(&self, name: &str, version_str: &str, spec: &str) -> Result<()> {
-        let version = Version::parse(version_str)
+        let version = versions::parse_version(version_str)
             .with_context(|| format!("Invalid version '{}' for package '{}'", version_str, name))?;
         
-        let mut packages = self.packages.lock().unwrap();
-        let versions = packages.entry(name.to_string()).or_insert_with(Vec::new);
+        // The entry guard only locks this package's shard
+        let mut versions = self.packages.entry(name.to_string()).or_default();
         
         // Check if this exact version is already registered
         if !versions.iter().any(|(v, _, _)| *v == version) {
@@ -88,15 +288,12 @@ impl DependencyDeduplication {
     }
     
     fn find_compatible_version(&self, name: &str, req_str: &str) -> Option<String> {
-        let req = match VersionReq::parse(req_str) {
-            Ok(r) => r,
-            Err(_) => return None, // If we can't parse the requirement, we can't find a match
-        };
+        // If we can't parse the requirement, we can't find a match
+        let req = versions::parse_req(req_str)?;
         
-        let packages = self.packages.lock().unwrap();
-        if let Some(versions) = packages.get(name) {
+        if let Some(versions) = self.packages.get(name) {
             // Try to find the highest version that satisfies the requirement
-            for (version, version_str, _) in versions {
+            for (version, version_str, _) in versions.iter() {
                 if req.matches(version) {
                     return Some(version_str.clone());
                 }
@@ -111,11 +308,39 @@ impl DependencyDeduplication {
 #[derive(Clone)]
 pub struct DependencyResolver {
     registry: NpmRegistry,
-    visited: Arc<Mutex<HashSet<String>>>,
     concurrency: usize,
     package_cache: PackageCache,
     batch_size: usize,
     deduplication: DependencyDeduplication,
+    sorted_versions: Arc<DashMap<String, SortedVersions>>,
+    // Versions pinned by rjs-lock.json, preferred over the registry while they
+    // still satisfy the requested range
+    locked: Arc<DashMap<String, Vec<Arc<Package>>>>,
+    // Packages whose locked versions are ignored, so they resolve afresh
+    unlocked: HashSet<String>,
+    // Locked versions are still picked, but their metadata comes from the
+    // registry rather than from the lockfile
+    refresh_locked: bool,
+    // The project's catalogs, for "catalog:" specs
+    catalogs: Catalogs,
+    // The project's patches, applied to packages as they're extracted
+    patches: Patches,
+    // Packages checked into the project's vendor/, installed before the registry's
+    vendor: Option<Arc<Vendor>>,
+    // The project's directory, which "file:" specs are relative to
+    root: PathBuf,
+    // Serializes read-merge-write updates of rjs-lock.json
+    lockfile_guard: Arc<tokio::sync::Mutex<()>>,
+    lockfile_only: bool,
+    // Project policy every resolved package is checked against
+    policy: Option<Arc<Policy>>,
+    // Packages built for other platforms than this one are locked but not
+    // installed; the host unless targeting another platform
+    host: Host,
+    // Progress of this install, so an interrupted one can pick up from it
+    journal: Option<Arc<Journal>>,
+    events: Arc<dyn InstallEvents>,
+    cancel: CancellationToken,
 }
 
 impl DependencyResolver {
@@ -126,11 +351,25 @@ impl DependencyResolver {
         
         Self {
             registry,
-            visited: Arc::new(Mutex::new(HashSet::new())),
             concurrency: optimal_concurrency,
             package_cache: PackageCache::new(),
             batch_size: 50, // Process packages in batches of 50 for better throughput
             deduplication: DependencyDeduplication::new(),
+            sorted_versions: Arc::new(DashMap::new()),
+            locked: Arc::new(DashMap::new()),
+            unlocked: HashSet::new(),
+            refresh_locked: false,
+            catalogs: Catalogs::default(),
+            patches: Patches::default(),
+            vendor: None,
+            root: PathBuf::from("."),
+            lockfile_guard: Arc::new(tokio::sync::Mutex::new(())),
+            lockfile_only: false,
+            policy: None,
+            host: Host::current(),
+            journal: None,
+            events: Arc::new(NoopEvents),
+            cancel: CancellationToken::new(),
         }
     }
 
@@ -144,42 +383,168 @@ impl DependencyResolver {
     // Set custom batch size for processing
     #[allow(dead_code)]
     pub fn with_batch_size(mut self, batch_size: usize) -> Self {
-        self.batch_size = batch_size.max(10).min(100); // Between 10 and 100
+        self.batch_size = batch_size.clamp(10, 100); // Between 10 and 100
+        self
+    }
+
+    // Attach a subscriber for resolve/install events
+    pub fn with_events(mut self, events: Arc<dyn InstallEvents>) -> Self {
+        self.events = events;
+        self
+    }
+
+    // Attach a token that aborts resolution and installation when cancelled
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    // Only resolve and write rjs-lock.json, without installing anything
+    pub fn with_lockfile_only(mut self, lockfile_only: bool) -> Self {
+        self.lockfile_only = lockfile_only;
+        self
+    }
+
+    // Resolve these packages against the registry even when locked
+    pub fn with_unlocked(mut self, names: impl IntoIterator<Item = String>) -> Self {
+        self.unlocked = names.into_iter().collect();
+        self
+    }
+
+    // Pick the same locked versions, but read what's locked about them
+    // (dependencies, tarball, integrity) from the registry again
+    pub fn with_refreshed_locks(mut self) -> Self {
+        self.refresh_locked = true;
+        self
+    }
+
+    // Resolve "catalog:" specs to the versions the project pins in its catalogs
+    pub fn with_catalogs(mut self, catalogs: Catalogs) -> Self {
+        self.catalogs = catalogs;
+        self
+    }
+
+    // Apply the project's patches to the packages they're for
+    pub fn with_patches(mut self, patches: Patches) -> Self {
+        self.patches = patches;
+        self
+    }
+
+    // Resolve to the vendored versions where they fit, and install them from
+    // vendor/ instead of fetching their tarballs
+    pub fn with_vendor(mut self, vendor: Vendor) -> Self {
+        self.prefer_locked(&vendor.lockfile);
+        self.vendor = Some(Arc::new(vendor));
+        self
+    }
+
+    // Read "file:" specs relative to the project at `root` rather than the
+    // working directory
+    pub fn with_root(mut self, root: &Path) -> Self {
+        self.root = root.to_path_buf();
+        self
+    }
+
+    // Record installed packages in a journal and skip the ones it already has
+    pub fn with_journal(mut self, journal: Arc<Journal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    // Check resolved packages against a project policy before installing them
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = Some(Arc::new(policy));
+        self
+    }
+
+    // Install the variants built for `host` rather than for this machine
+    pub fn with_host(mut self, host: Host) -> Self {
+        self.host = host;
+        self
+    }
+
+    // Keep at most `limit` resolved packages in memory and spill the rest to
+    // a temporary database; 0 keeps them all
+    pub fn with_memory_limit(mut self, limit: usize) -> Self {
+        self.package_cache.spill = (limit > 0).then(|| Arc::new(Spill::new(limit)));
         self
     }
 
+    /// Resolved packages spilled to disk so far, and the limit past which
+    /// they are; None without a memory limit.
+    pub fn spilled(&self) -> Option<(usize, usize)> {
+        let spill = self.package_cache.spill.as_ref()?;
+        Some((spill.spilled(), spill.limit()))
+    }
+
+    // Warn about packages the policy flags and fail if any of them is fatal
+    fn enforce_policy<'a>(&self, packages: impl IntoIterator<Item = (&'a str, &'a str, Option<&'a str>)>) -> Result<()> {
+        let Some(policy) = &self.policy else {
+            return Ok(());
+        };
+
+        let mut fatal = Vec::new();
+        for (name, version, license) in packages {
+            let violations = policy
+                .check_package(name, version)
+                .into_iter()
+                .chain(policy.check_license(name, version, license));
+            for violation in violations {
+                match violation.action {
+                    Action::Warn => {
+                        warn!("{}: {}", violation.package, violation.reason);
+                        println!("Warning: {}: {}", violation.package, violation.reason);
+                    }
+                    Action::Fail => fatal.push(format!("{}: {}", violation.package, violation.reason)),
+                }
+            }
+        }
+
+        if !fatal.is_empty() {
+            fatal.sort();
+            return Err(anyhow::anyhow!(
+                "{} packages violate {}:\n  {}",
+                fatal.len(),
+                POLICY_FILE,
+                fatal.join("\n  ")
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            return Err(anyhow::anyhow!("Installation cancelled"));
+        }
+        Ok(())
+    }
+
+    fn emit(&self, event: InstallEvent) {
+        self.events.on_event(&event);
+    }
+
     // Update resolve_package to use deduplication
     #[allow(dead_code)]
-    pub async fn resolve_package(&self, name: &str, version_req: &str) -> Result<Package> {
+    pub async fn resolve_package(&self, name: &str, version_req: &str) -> Result<Arc<Package>> {
+        let version_req = self.catalogs.resolve(name, version_req)?;
         let key = format!("{}@{}", name, version_req);
         
         // Check cache first
         if let Some(cached_pkg) = self.package_cache.get(&key) {
             debug!("Cache hit for {}", key);
-            return Ok((*cached_pkg).clone());
-        }
-        
-        // Check if already visited using a mutex
-        {
-            let visited = self.visited.lock().unwrap();
-            if visited.contains(&key) {
-                debug!("Already visited {}", key);
-                // Return a dummy package to avoid circular dependencies for now
-                return Ok(Package {
-                    name: name.to_string(),
-                    version: "0.0.0".to_string(),
-                    dependencies: HashMap::new(),
-                    dev_dependencies: HashMap::new(),
-                });
-            }
-        }
-        
-        // Mark as visited
-        {
-            let mut visited = self.visited.lock().unwrap();
-            visited.insert(key.clone());
+            return Ok(cached_pkg);
         }
 
+        // Local tarballs are read again each time, never deduplicated with
+        // the registry's versions
+        if local::is_file_spec(version_req) {
+            let (root, name_owned, spec) = (self.root.clone(), name.to_string(), version_req.to_string());
+            let package = tokio::task::spawn_blocking(move || local::package(&root, &name_owned, &spec))
+                .await
+                .context("Tarball task panicked")??;
+            return self.package_cache.insert(key, package);
+        }
+        
         // Check if we can deduplicate by finding a compatible version we've already resolved
         let deduplicated_version = self.deduplication.find_compatible_version(name, version_req);
         if let Some(version) = deduplicated_version {
@@ -187,41 +552,68 @@ impl DependencyResolver {
             let deduplicated_key = format!("{}@{}", name, version);
             if let Some(cached_pkg) = self.package_cache.get(&deduplicated_key) {
                 // We found a compatible package, use it
-                return Ok((*cached_pkg).clone());
+                return Ok(cached_pkg);
+            }
+        }
+
+        // Keep the version from the lockfile as long as it still satisfies the range
+        let mut pinned = None;
+        let mut registry_url = self.registry.registry_url_for(name);
+        if let Some(locked_pkg) = self.locked_version(name, version_req) {
+            debug!("Using locked version {} for {}@{}", locked_pkg.version, name, version_req);
+            if !self.refresh_locked {
+                let _ = self.deduplication.register_package(name, &locked_pkg.version, version_req);
+                return self.package_cache.insert(key, locked_pkg);
+            }
+            pinned = Some(format!("={}", locked_pkg.version));
+            // Refresh from the registry the entry was locked from
+            if let Some(locked_registry) = &locked_pkg.registry {
+                registry_url = locked_registry.trim_end_matches('/').to_string();
             }
         }
 
         // Fetch package info from registry with timing
         let start = Instant::now();
-        let package_info = self.registry.get_package_info(name).await?;
+        let package_info = self.registry.get_package_info_from(&registry_url, name).await?;
         debug!("Fetched package info for {} in {:?}", name, start.elapsed());
 
-        // Find the best matching version
-        let version_req_parsed = VersionReq::parse(version_req).unwrap_or(VersionReq::STAR);
-        let version_req_str = version_req.to_string(); // Clone for error message
-
-        // Optimize version selection using Rayon parallel iterators
-        let versions: Vec<_> = package_info.versions.keys().cloned().collect();
-        let best_version = thread::spawn(move || {
-            versions.into_par_iter()
-                .filter_map(|v| {
-                    match semver::Version::parse(&v) {
-                        Ok(parsed) => {
-                            if version_req_parsed.matches(&parsed) {
-                                Some((v, parsed))
-                            } else {
-                                None
-                            }
-                        },
-                        Err(_) => None,
-                    }
-                })
-                .max_by(|(_, a), (_, b)| a.cmp(b))
-                .map(|(v, _)| v)
-        }).join().unwrap();
-        
-        let best_version = best_version
-            .with_context(|| format!("No matching version found for {}@{}", name, version_req_str))?;
+        // A dist-tag names one version; anything else is read as a range
+        let spec = match &pinned {
+            Some(pinned) => pinned.as_str(),
+            None => package_info
+                .dist_tags
+                .get(version_req)
+                .filter(|version| package_info.versions.contains_key(*version))
+                .map_or(version_req, String::as_str),
+        };
+
+        // Find the best matching version: the first match in newest-first order
+        let version_req_parsed = versions::parse_req_or_any(spec);
+        let sorted_versions = self.sorted_versions(name, &package_info);
+        let mut matching = sorted_versions
+            .iter()
+            .filter(|(version, _)| version_req_parsed.matches(version))
+            .map(|(_, version_str)| version_str)
+            .peekable();
+        let newest = matching
+            .peek()
+            .map(|version| version.to_string())
+            .with_context(|| format!("No matching version found for {}@{}", name, version_req))?;
+
+        // Skip versions still inside the policy's quarantine window, unless pinned exactly
+        let cutoff = if pinned.is_some() { None } else { self.release_cutoff(version_req) };
+        let best_version = match cutoff {
+            Some(cutoff) => matching
+                .find(|version| !published_after(&package_info, version, cutoff))
+                .cloned()
+                .with_context(|| {
+                    format!(
+                        "No version of {}@{} is older than the minimum release age in {} (newest is {})",
+                        name, version_req, POLICY_FILE, newest
+                    )
+                })?,
+            None => newest,
+        };
 
         debug!(
             "Selected version {} for {}@{}",
@@ -236,15 +628,102 @@ impl DependencyResolver {
             version: best_version.clone(),
             dependencies: version_info.dependencies.clone(),
             dev_dependencies: version_info.dev_dependencies.clone(),
+            dist: Some(version_info.dist.clone()),
+            license: version_info.license.clone(),
+            optional_dependencies: version_info.optional_dependencies.clone(),
+            platforms: version_info.platforms.clone(),
+            registry: Some(registry_url),
+            deprecated: version_info.deprecated.clone(),
         };
         
         // Register this package for future deduplication
         let _ = self.deduplication.register_package(name, &best_version, version_req);
         
-        // Cache the result
-        let _ = self.package_cache.insert(key, package.clone());
+        // Cache the result and share it with the tree
+        self.package_cache.insert(key, package)
+    }
 
-        Ok(package)
+    /// Prefer the versions pinned in `lockfile` during resolution.
+    ///
+    /// Only entries with a known registry tarball are used; anything else is
+    /// resolved again.
+    pub fn prefer_locked(&self, lockfile: &Lockfile) {
+        for (key, entry) in &lockfile.packages {
+            // Ranges resolve against the registry, not a local tarball
+            let Some(tarball) = entry.resolved.as_ref().filter(|tarball| !local::is_file_spec(tarball)) else {
+                continue;
+            };
+            if self.unlocked.contains(lockfile_key_name(key)) {
+                continue;
+            }
+
+            let pkg = Package {
+                name: lockfile_key_name(key).to_string(),
+                version: entry.version.clone(),
+                dependencies: entry.dependencies.clone(),
+                dev_dependencies: HashMap::new(),
+                dist: Some(DistInfo {
+                    shasum: String::new(),
+                    tarball: tarball.clone(),
+                    integrity: entry.integrity.clone(),
+                    signatures: Vec::new(),
+                    npm_signature: None,
+                    attestations: None,
+                }),
+                license: entry.license.clone(),
+                optional_dependencies: entry.optional_dependencies.clone(),
+                platforms: entry.platforms.clone(),
+                registry: entry.registry.clone(),
+                deprecated: None,
+            };
+
+            let mut versions = self.locked.entry(pkg.name.clone()).or_default();
+            if !versions.iter().any(|locked| locked.version == pkg.version) {
+                versions.push(Arc::new(pkg));
+            }
+        }
+    }
+
+    // Highest locked version satisfying `version_req`. Specs that aren't ranges,
+    // like dist-tags, always go to the registry
+    fn locked_version(&self, name: &str, version_req: &str) -> Option<Arc<Package>> {
+        let req = versions::parse_req(version_req)?;
+        let locked = self.locked.get(name)?;
+
+        locked
+            .iter()
+            .filter_map(|pkg| versions::parse_version(&pkg.version).map(|v| (v, pkg)))
+            .filter(|(version, _)| req.matches(version))
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, pkg)| Arc::clone(pkg))
+    }
+
+    // Publish time after which versions of `version_req` are too new to pick,
+    // per the policy's minimum release age. Exact pins opt out of the window
+    fn release_cutoff(&self, version_req: &str) -> Option<SystemTime> {
+        if versions::parse_version(version_req).is_some() {
+            return None;
+        }
+        self.policy.as_ref()?.release_cutoff()
+    }
+
+    // A packument's versions parsed once and sorted newest first, shared by
+    // every spec of the package resolved later
+    fn sorted_versions(&self, name: &str, package_info: &PackageInfo) -> SortedVersions {
+        if let Some(versions) = self.sorted_versions.get(name) {
+            return Arc::clone(versions.value());
+        }
+
+        let mut versions: Vec<_> = package_info
+            .versions
+            .keys()
+            .filter_map(|v| versions::parse_version(v).map(|parsed| (parsed, v.clone())))
+            .collect();
+        versions.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+        let versions = Arc::new(versions);
+        self.sorted_versions.insert(name.to_string(), Arc::clone(&versions));
+        versions
     }
 
     // Add a method to deduplicate a dependency tree
@@ -252,73 +731,92 @@ impl DependencyResolver {
         debug!("Deduplicating dependency tree...");
         let start = Instant::now();
         
-        let mut packages_by_name: HashMap<String, Vec<(String, Package)>> = HashMap::new();
-        
-        // Group packages by name
-        for (key, pkg) in &tree.dependencies {
+        // Group package indices by name, read off their ids so spilled
+        // packages aren't loaded for it
+        let mut packages_by_name: HashMap<String, Vec<(usize, String)>> = HashMap::new();
+        for (id, &index) in &tree.ids {
+            let name = lockfile_key_name(id);
             packages_by_name
-                .entry(pkg.name.clone())
+                .entry(name.to_string())
                 .or_default()
-                .push((key.clone(), pkg.clone()));
+                .push((index, id[name.len() + 1..].to_string()));
+        }
+        packages_by_name.retain(|_, indices| indices.len() > 1);
+        if packages_by_name.is_empty() {
+            debug!("Deduplicated 0 packages in {:?}", start.elapsed());
+            return Ok(());
+        }
+
+        // The specs the root and every package ask for each duplicated name,
+        // in one pass
+        let mut specs: HashMap<&str, Vec<String>> = HashMap::new();
+        let mut collect = |deps: &HashMap<String, String>| {
+            for name in packages_by_name.keys() {
+                if let Some(spec) = deps.get(name) {
+                    specs.entry(name.as_str()).or_default().push(spec.clone());
+                }
+            }
+        };
+        collect(&tree.root.dependencies);
+        collect(&tree.root.dev_dependencies);
+        collect(&tree.root.optional_dependencies);
+        for pkg in tree.iter() {
+            let pkg = pkg?;
+            collect(&pkg.dependencies);
+            collect(&pkg.optional_dependencies);
         }
         
-        // Counter for deduplicated packages
-        let mut deduped_count = 0;
+        // Index of the package each deduplicated one is replaced by
+        let mut replaced_by: HashMap<usize, usize> = HashMap::new();
         
         // Process each group of packages with the same name
-        for (_name, packages) in packages_by_name {
-            if packages.len() <= 1 {
-                continue; // No need to deduplicate single packages
-            }
-            
+        for (name, indices) in &packages_by_name {
             // Sort packages by version (newest first) to prefer newer versions
-            let mut sorted_packages = packages;
-            sorted_packages.sort_by(|(_, a), (_, b)| {
-                Version::parse(&b.version)
-                    .unwrap_or_else(|_| Version::new(0, 0, 0))
-                    .cmp(&Version::parse(&a.version).unwrap_or_else(|_| Version::new(0, 0, 0)))
-            });
+            let parse = |version: &str| {
+                versions::parse_version(version).unwrap_or_else(|| Arc::new(Version::new(0, 0, 0)))
+            };
+            let mut sorted = indices.clone();
+            sorted.sort_by_key(|(_, version)| std::cmp::Reverse(parse(version)));
             
             // Take the newest version as the preferred one
-            let (_preferred_key, preferred_pkg) = &sorted_packages[0];
+            let (preferred, preferred_version) = &sorted[0];
+            let parsed = parse(preferred_version);
             
-            // For remaining versions, check if they can be deduplicated
-            for (key, _package) in sorted_packages.iter().skip(1) {
-                // Check if this package is a dependency of any other package
-                let mut can_deduplicate = false;
-                
-                for (_, dep_pkg) in &tree.dependencies {
-                    if dep_pkg.dependencies.contains_key(&preferred_pkg.name) {
-                        let req = VersionReq::parse(
-                            dep_pkg.dependencies.get(&preferred_pkg.name).unwrap()
-                        ).unwrap_or_else(|_| VersionReq::STAR);
-                        
-                        let preferred_version = Version::parse(&preferred_pkg.version)
-                            .unwrap_or_else(|_| Version::new(0, 0, 0));
-                        
-                        if req.matches(&preferred_version) {
-                            can_deduplicate = true;
-                            break;
-                        }
-                    }
+            // Only collapse the versions when every range on the name accepts
+            // the preferred one; otherwise each dependent keeps its own
+            let can_deduplicate = specs
+                .get(name.as_str())
+                .is_some_and(|specs| specs.iter().all(|spec| versions::parse_req_or_any(spec).matches(&parsed)));
+            
+            if can_deduplicate {
+                // Replace the older versions with the preferred one
+                for (index, _) in sorted.iter().skip(1) {
+                    replaced_by.insert(*index, *preferred);
                 }
-                
-                if can_deduplicate {
-                    // Replace this package with the preferred one
-                    tree.dependencies.remove(key);
-                    deduped_count += 1;
-                    
-                    // Update dependencies to point to the preferred version
-                    for (_, dep_pkg) in tree.dependencies.iter_mut() {
-                        if dep_pkg.dependencies.contains_key(&preferred_pkg.name) {
-                            dep_pkg.dependencies.insert(
-                                preferred_pkg.name.clone(),
-                                preferred_pkg.version.clone()
-                            );
-                        }
-                    }
+            }
+        }
+        
+        let deduped_count = replaced_by.len();
+        if deduped_count > 0 {
+            // Compact the package list and remap every index into it
+            let mut new_index = vec![0; tree.packages.len()];
+            let mut packages = Vec::with_capacity(tree.packages.len() - deduped_count);
+            for (index, slot) in std::mem::take(&mut tree.packages).into_iter().enumerate() {
+                if !replaced_by.contains_key(&index) {
+                    new_index[index] = packages.len();
+                    packages.push(slot);
                 }
             }
+            let remap = |index: usize| new_index[*replaced_by.get(&index).unwrap_or(&index)];
+            
+            for index in tree.resolved.values_mut() {
+                *index = remap(*index);
+            }
+            tree.ids.retain(|_, index| !replaced_by.contains_key(index));
+            for index in tree.ids.values_mut() {
+                *index = new_index[*index];
+            }
+            tree.packages = packages;
         }
         
         debug!("Deduplicated {} packages in {:?}", deduped_count, start.elapsed());
@@ -328,6 +826,7 @@ impl DependencyResolver {
     // Update resolve_dependencies to apply deduplication
     #[allow(dead_code)]
     pub async fn resolve_dependencies(&self, root_pkg: &Package) -> Result<DependencyTree> {
+        let _span = timing::span(Phase::Resolution, &root_pkg.name);
         let mut tree = self.resolve_dependencies_internal(root_pkg).await?;
         self.deduplicate_tree(&mut tree).await?;
         Ok(tree)
@@ -335,24 +834,38 @@ impl DependencyResolver {
 
     // Renamed the original resolve_dependencies method to resolve_dependencies_internal
     async fn resolve_dependencies_internal(&self, root_pkg: &Package) -> Result<DependencyTree> {
-        let mut dependencies = HashMap::new();
-        let dep_entries: Vec<_> = root_pkg.dependencies.iter().collect();
+        let mut tree = DependencyTree::new(root_pkg.clone(), self.package_cache.spill.clone());
+        // The root's dev dependencies are installed too; a package's aren't
+        let dep_entries: Vec<_> = root_pkg.dependencies.iter().chain(&root_pkg.dev_dependencies).collect();
+        self.emit(InstallEvent::ResolveStarted { requested: dep_entries.len() });
         
         // Use a work-stealing queue for dynamic workload distribution
         let work_queue = Arc::new(SegQueue::new());
+
+        // Specs already queued during this resolution; cycles stop here instead
+        // of being re-resolved
+        let visited: Arc<DashSet<String>> = Arc::new(DashSet::new());
         
-        // Initialize the queue with dependencies
+        // Initialize the queue with dependencies. Each entry carries the chain of
+        // packages that pulled it in, to point at the culprit when policy rejects
+        // it, and whether it's optional, which lets it fail to resolve
+        let root_path: Arc<Vec<String>> = Arc::new(Vec::new());
         for (name, version) in dep_entries {
-            work_queue.push((name.clone(), version.clone()));
+            if visited.insert(format!("{}@{}", name, version)) {
+                work_queue.push((name.clone(), version.clone(), Arc::clone(&root_path), false));
+            }
         }
         
         // Process queue in batches for better throughput
         while !work_queue.is_empty() {
+            // Don't start another batch once cancelled
+            self.check_cancelled()?;
+
             // Collect a batch of work items
             let mut batch = Vec::new();
             for _ in 0..self.batch_size {
-                if let Some((name, version)) = work_queue.pop() {
-                    batch.push((name, version));
+                if let Some(item) = work_queue.pop() {
+                    batch.push(item);
                 } else {
                     break;
                 }
@@ -367,27 +880,89 @@ impl DependencyResolver {
             
             // Process batch concurrently
             let mut stream = stream::iter(batch)
-                .map(|(dep_name, dep_version)| {
+                .map(|(dep_name, dep_version, parents, optional)| {
                     let resolver = self.clone();
                     let queue = Arc::clone(&work_queue_clone);
+                    let visited = Arc::clone(&visited);
                     
                     async move {
-                        match resolver.resolve_package(&dep_name, &dep_version).await {
+                        let result = tokio::select! {
+                            result = resolver.resolve_package(&dep_name, &dep_version) => result,
+                            _ = resolver.cancel.cancelled() => return Ok(None),
+                        };
+
+                        match result {
                             Ok(pkg) => {
+                                let mut path = (*parents).clone();
+                                path.push(format!("{}@{}", pkg.name, pkg.version));
+
+                                // Refuse banned packages before anything depends on them
+                                if let Some(policy) = &resolver.policy
+                                    && let Some(violation) = policy.check_package(&pkg.name, &pkg.version)
+                                {
+                                    return Err(anyhow::anyhow!(
+                                        "{} violates {}: {}\n  pulled in by {}",
+                                        violation.package,
+                                        POLICY_FILE,
+                                        violation.reason,
+                                        path.join(" > ")
+                                    ));
+                                }
+
+                                resolver.emit(InstallEvent::PackageResolved {
+                                    name: pkg.name.clone(),
+                                    version: pkg.version.clone(),
+                                });
+                                if let Some(message) = &pkg.deprecated {
+                                    resolver.emit(InstallEvent::PackageDeprecated {
+                                        name: pkg.name.clone(),
+                                        version: pkg.version.clone(),
+                                        message: message.clone(),
+                                        path: path.clone(),
+                                    });
+                                }
+
                                 // Add nested dependencies to work queue
-                                for (nested_name, nested_version) in &pkg.dependencies {
-                                    let key = format!("{}@{}", nested_name, nested_version);
-                                    let mut visited = resolver.visited.lock().unwrap();
-                                    if !visited.contains(&key) {
-                                        queue.push((nested_name.clone(), nested_version.clone()));
-                                        visited.insert(key);
+                                let path = Arc::new(path);
+                                // Every platform's variant is resolved, so the
+                                // lockfile works on all of them
+                                let nested = pkg.dependencies.iter().map(|dep| (dep, false));
+                                for ((nested_name, nested_version), optional) in nested.chain(pkg.optional_dependencies.iter().map(|dep| (dep, true))) {
+                                    // A package's "file:" paths point into its author's
+                                    // tree, not this project's; only package.json may use them
+                                    if local::is_file_spec(nested_version) {
+                                        return Err(anyhow::anyhow!(
+                                            "{} depends on {}@{}, but only the project's package.json can install local tarballs\n  pulled in by {}",
+                                            pkg.name,
+                                            nested_name,
+                                            nested_version,
+                                            path.join(" > ")
+                                        ));
+                                    }
+                                    // insert() is the check and the mark in one step
+                                    if visited.insert(format!("{}@{}", nested_name, nested_version)) {
+                                        queue.push((nested_name.clone(), nested_version.clone(), Arc::clone(&path), optional));
                                     }
                                 }
-                                Some((format!("{}@{}", dep_name, dep_version), pkg))
+                                Ok(Some((format!("{}@{}", dep_name, dep_version), pkg)))
                             },
                             Err(e) => {
                                 debug!("Failed to resolve {}@{}: {}", dep_name, dep_version, e);
-                                None
+                                resolver.emit(InstallEvent::ResolveFailed {
+                                    name: dep_name.clone(),
+                                    spec: dep_version.clone(),
+                                    error: e.to_string(),
+                                });
+                                // Optional dependencies may be missing, as npm
+                                // allows; anything else leaves the tree incomplete
+                                if optional {
+                                    return Ok(None);
+                                }
+                                let pulled_in_by = match parents.is_empty() {
+                                    true => String::new(),
+                                    false => format!(", pulled in by {}", parents.join(" > ")),
+                                };
+                                Err(e.context(format!("Failed to resolve {}@{}{}", dep_name, dep_version, pulled_in_by)))
                             }
                         }
                     }
@@ -395,21 +970,26 @@ impl DependencyResolver {
                 .buffer_unordered(self.concurrency);
                 
             while let Some(result) = stream.next().await {
-                if let Some((key, pkg)) = result {
-                    dependencies.insert(key, pkg);
+                if let Some((key, pkg)) = result? {
+                    tree.insert(key, pkg);
                 }
             }
         }
 
-        Ok(DependencyTree {
-            root: root_pkg.clone(),
-            dependencies,
-        })
+        self.check_cancelled()?;
+        self.emit(InstallEvent::ResolveFinished { total: tree.len() });
+        if let Some((spilled, limit)) = self.spilled().filter(|(spilled, _)| *spilled > 0) {
+            info!("Spilled {} resolved packages past the first {} to disk", spilled, limit);
+        }
+
+        Ok(tree)
     }
 
-    // Install method from previous implementation
-    pub async fn install_tree(&self, tree: &DependencyTree, install_path: &Path) -> Result<Vec<String>> {
-        debug!("Installing dependency tree with {} packages...", tree.dependencies.len());
+    // Put `packages` in node_modules, laid out for the project's `roots`.
+    // Commands go through Installer::apply, which also keeps .bin and the
+    // lockfile in step
+    async fn install_packages(&self, packages: &[Arc<Package>], roots: &HashMap<String, String>, install_path: &Path) -> Result<Vec<String>> {
+        debug!("Installing {} packages...", packages.len());
         let start = Instant::now();
         
         // Create node_modules directory
@@ -420,38 +1000,153 @@ impl DependencyResolver {
             fs::create_dir_all(&node_modules_dir).await?;
         }
         
-        // For tests, just simulate installation by creating empty directories for each package
-        let mut installed = Vec::with_capacity(tree.dependencies.len());
+        let supported: Vec<Arc<Package>> = packages
+            .iter()
+            .filter(|pkg| self.supports(&pkg.name, &pkg.version, &pkg.platforms))
+            .cloned()
+            .collect();
+        // Scoped names go in their scope's directory; anything else with a
+        // "/" would land somewhere no require() looks
+        if let Some(pkg) = supported.iter().find(|pkg| layout::package_path(&pkg.name).is_none()) {
+            anyhow::bail!("Can't install {}@{}: {:?} isn't a valid package name", pkg.name, pkg.version, pkg.name);
+        }
+        // Versions that conflict with the one at the top level are nested
+        // under the packages asking for them
+        let placements = layout::layout(&supported, roots);
+        let mut installed = Vec::with_capacity(placements.len());
+        self.emit(InstallEvent::InstallStarted { total: placements.len() });
         
-        for (key, pkg) in &tree.dependencies {
-            let pkg_dir = node_modules_dir.join(&pkg.name);
-            
-            // Create package directory
-            if !pkg_dir.exists() {
-                fs::create_dir_all(&pkg_dir).await?;
-                
-                // Create a minimal package.json for the package
-                let pkg_json = serde_json::json!({
-                    "name": pkg.name,
-                    "version": pkg.version,
-                    "dependencies": pkg.dependencies,
+        // Materialize packages concurrently; each one is staged and renamed into
+        // place, so whatever is in node_modules is always complete. Each level
+        // of nesting waits for the one above, whose directories it goes in
+        let mut waves: Vec<Vec<(Arc<Package>, PathBuf)>> = Vec::new();
+        for (pkg, path) in placements {
+            let depth = layout::depth(&path.to_string_lossy());
+            if waves.len() <= depth {
+                waves.resize_with(depth + 1, Vec::new);
+            }
+            waves[depth].push((pkg, path));
+        }
+        for wave in waves {
+            let mut stream = stream::iter(wave)
+                .map(|(pkg, path)| {
+                    let pkg_dir = utils::long_path(&node_modules_dir.join(path));
+                    async move {
+                        // Stop before touching another package once cancelled
+                        self.check_cancelled()?;
+
+                        let patch = self.patches.get(&pkg.name, &pkg.version).map(Arc::as_ref);
+                        if !already_installed(self.journal.as_deref(), &pkg_dir, &pkg, patch) {
+                            self.install_package_dir(&pkg, &pkg_dir).await?;
+                            if let Some(journal) = &self.journal
+                                && let Err(e) = journal.record(&pkg.name, &pkg.version)
+                            {
+                                warn!("{:#}", e);
+                            }
+                        }
+
+                        debug!("Installed package {}@{} in {}", pkg.name, pkg.version, pkg_dir.display());
+                        Ok::<_, anyhow::Error>(pkg)
+                    }
+                })
+                .buffer_unordered(self.registry.install_concurrency());
+
+            while let Some(result) = stream.next().await {
+                let pkg = result?;
+                installed.push(pkg.name.clone());
+                self.emit(InstallEvent::PackageInstalled {
+                    name: pkg.name.clone(),
+                    version: pkg.version.clone(),
                 });
-                
-                fs::write(
-                    pkg_dir.join("package.json"),
-                    serde_json::to_string_pretty(&pkg_json)?,
-                ).await?;
             }
-            
-            installed.push(pkg.name.clone());
-            debug!("Installed package {}", key);
         }
         
         debug!("Installed {} packages in {:?}", installed.len(), start.elapsed());
+        self.emit(InstallEvent::InstallFinished { installed: installed.len() });
         
         Ok(installed)
     }
 
+    // Whether a package runs on the host; the ones that don't stay in the
+    // lockfile for other platforms
+    fn supports(&self, name: &str, version: &str, platforms: &Platforms) -> bool {
+        let supported = platforms.supports(&self.host);
+        if !supported {
+            debug!("Skipping {}@{}, which isn't built for {}", name, version, self.host);
+        }
+        supported
+    }
+
+    // Download and extract a package, or write a minimal manifest when the
+    // package has no tarball to fetch
+    async fn install_package_dir(&self, pkg: &Package, pkg_dir: &Path) -> Result<()> {
+        let vendored = self.vendor.as_ref().and_then(|vendor| vendor.path(&pkg.name, &pkg.version));
+        let local = pkg.dist.as_ref().and_then(|dist| local::path(&self.root, &dist.tarball));
+        let result = match (&pkg.dist, vendored) {
+            (dist, Some(source)) => {
+                let integrity = dist.as_ref().and_then(|dist| dist.integrity.as_deref());
+                staging::install_vendored(&self.registry, &pkg.name, &source, integrity, pkg_dir, self.events.as_ref()).await
+            }
+            (Some(dist), None) if let Some(source) = &local => {
+                staging::install_local(&self.registry, &pkg.name, source, dist.integrity.as_deref(), pkg_dir, self.events.as_ref()).await
+            }
+            (Some(dist), None) => {
+                staging::install_tarball(
+                    &self.registry,
+                    &pkg.name,
+                    &dist.tarball,
+                    dist.integrity.as_deref(),
+                    pkg_dir,
+                    &self.cancel,
+                    self.events.as_ref(),
+                )
+                .await
+            }
+            (None, None) => {
+                let pkg_json = serde_json::json!({
+                    "name": pkg.name,
+                    "version": pkg.version,
+                    "dependencies": pkg.dependencies,
+                });
+                staging::install_files(pkg_dir, &[("package.json", serde_json::to_string_pretty(&pkg_json)?)]).await
+            }
+        };
+
+        // Patched after every extraction, so node_modules never has the package
+        // without its patch
+        let result = match (result, self.patches.get(&pkg.name, &pkg.version)) {
+            (Ok(()), Some(patch)) => {
+                let (patch, pkg_dir) = (Arc::clone(patch), pkg_dir.to_path_buf());
+                tokio::task::spawn_blocking(move || patches::apply(&patch, &pkg_dir).and_then(|()| patches::mark(&patch, &pkg_dir)))
+                    .await
+                    .context("Patch task panicked")?
+            }
+            (result, _) => result,
+        };
+
+        // Recorded last, so a state file means the package is complete
+        let result = match result {
+            Ok(()) => {
+                let pkg_dir = pkg_dir.to_path_buf();
+                let version = pkg.version.clone();
+                let integrity = pkg.dist.as_ref().and_then(|dist| dist.integrity.clone());
+                tokio::task::spawn_blocking(move || state::write(&pkg_dir, &version, integrity.as_deref()))
+                    .await
+                    .context("State task panicked")?
+            }
+            result => result,
+        };
+
+        if let Err(e) = &result {
+            self.emit(InstallEvent::InstallFailed {
+                name: pkg.name.clone(),
+                error: e.to_string(),
+            });
+        }
+
+        result.with_context(|| format!("Failed to install {}@{}", pkg.name, pkg.version))
+    }
+
     // Generate a lockfile from a dependency tree
     pub async fn generate_lockfile(&self, tree: &DependencyTree, _root_path: &Path) -> Result<Lockfile> {
         debug!("Generating lockfile from dependency tree...");
@@ -461,10 +1156,14 @@ impl DependencyResolver {
         let mut lockfile = Lockfile::new(&tree.root.name, &tree.root.version);
         
         // Add all packages to the lockfile
-        for (_, package) in &tree.dependencies {
-            // Get registry URL
-            let registry_url = format!("{}", self.registry.get_registry_url());
-            lockfile.add_package(package, &registry_url);
+        for package in tree.iter() {
+            let package = package?;
+            lockfile.add_package(&package);
+        }
+        for (key, entry) in lockfile.packages.iter_mut() {
+            if let Some(patch) = self.patches.get(lockfile_key_name(key), &entry.version) {
+                entry.patch_hash = Some(patch.hash.clone());
+            }
         }
         
         debug!("Added {} packages to lockfile", lockfile.packages.len());
@@ -479,15 +1178,54 @@ impl DependencyResolver {
         let start = Instant::now();
         
         let lockfile_path = root_path.join("rjs-lock.json");
-        let lockfile_json = serde_json::to_string_pretty(lockfile)?;
+        let lockfile_json = lockfile.to_json()?;
         
-        fs::write(&lockfile_path, lockfile_json).await?;
+        // Write beside it and rename, so concurrent readers never see a partial file
+        let temp_path = root_path.join(format!(".rjs-lock.json.{}.tmp", std::process::id()));
+        fs::write(&temp_path, lockfile_json).await?;
+        if let Err(e) = fs::rename(&temp_path, &lockfile_path).await {
+            let _ = fs::remove_file(&temp_path).await;
+            return Err(e).with_context(|| format!("Failed to write {}", lockfile_path.display()));
+        }
         
         debug!("Saved lockfile to {} in {:?}", lockfile_path.display(), start.elapsed());
         
         Ok(())
     }
     
+    // Save `lockfile` over the packages it covers, keeping the entries for every
+    // other package already on disk but the `removed` ones. Installing a few
+    // packages, or the dev and regular halves concurrently, then never drops
+    // the rest of the project. The saved graph follows along, with `graph`'s
+    // edges added
+    async fn update_lockfile(&self, mut lockfile: Lockfile, graph: Option<Graph>, root_path: &Path, removed: &[String]) -> Result<()> {
+        let _guard = self.lockfile_guard.lock().await;
+
+        // Read while it still matches the lockfile on disk
+        let previous_graph = Graph::load(root_path);
+        if let Some(mut previous) = self.load_lockfile(root_path).await? {
+            previous.packages.retain(|key, _| !removed.iter().any(|name| name == lockfile_key_name(key)));
+            // What this resolve was asked for wins over package.json, which
+            // commands update after installing
+            let mut roots = installer::project_ranges(root_path);
+            if let Some(graph) = &graph {
+                roots.extend(graph.roots.iter().map(|(name, edge)| (name.clone(), edge.range.clone())));
+            }
+            lockfile.keep_others_from(previous, &roots);
+        }
+        self.save_lockfile(&lockfile, root_path).await?;
+
+        // node_modules is left alone in lockfile-only mode; the old graph
+        // no longer matches and isn't used
+        if self.lockfile_only || (graph.is_none() && previous_graph.is_none()) {
+            return Ok(());
+        }
+        if let Err(e) = graph.unwrap_or_default().save(root_path, previous_graph, &lockfile) {
+            warn!("{:#}", e);
+        }
+        Ok(())
+    }
+
     // Load lockfile from disk
     pub async fn load_lockfile(&self, root_path: &Path) -> Result<Option<Lockfile>> {
         let lockfile_path = root_path.join("rjs-lock.json");
@@ -516,7 +1254,21 @@ impl DependencyResolver {
         install_path: &Path,
         is_dev: bool,
         frozen: bool  // Add frozen parameter
-    ) -> Result<Vec<Package>> {
+    ) -> Result<Vec<Arc<Package>>> {
+        let (regular, dev) = if is_dev { (&[][..], packages) } else { (packages, &[][..]) };
+        self.resolve_and_install_project(regular, dev, install_path, frozen).await
+    }
+
+    // Resolve regular and dev packages as one tree and install it in one
+    // plan, so both halves agree on the layout and on what's locked
+    pub async fn resolve_and_install_project(
+        &self,
+        regular: &[(String, String)],
+        dev: &[(String, String)],
+        install_path: &Path,
+        frozen: bool,
+    ) -> Result<Vec<Arc<Package>>> {
+        let packages: Vec<&(String, String)> = regular.iter().chain(dev).collect();
         info!("Resolving and installing {} packages...", packages.len());
         let start = Instant::now();
         
@@ -534,34 +1286,52 @@ impl DependencyResolver {
             if let Some(lockfile) = self.load_lockfile(&absolute_install_path).await? {
                 info!("Using existing lockfile with {} packages", lockfile.packages.len());
                 println!("Using frozen lockfile mode - not updating dependencies");
+
+                self.enforce_policy(lockfile.packages.iter().map(|(key, entry)| {
+                    (lockfile_key_name(key), entry.version.as_str(), entry.license.as_deref())
+                }))?;
                 
                 // Install directly from lockfile
-                let packages = self.install_from_lockfile(&lockfile, &absolute_install_path).await?;
+                let installed = self.install_from_lockfile(&lockfile, &absolute_install_path).await?;
                 
                 info!("Installed {} packages from lockfile in {:?}", 
-                    packages.len(), start.elapsed());
+                    installed.len(), start.elapsed());
                     
-                return Ok(packages);
+                return Ok(installed
+                    .into_iter()
+                    .filter(|pkg| packages.iter().any(|(name, _)| *name == pkg.name))
+                    .collect());
             } else {
                 info!("No lockfile found, proceeding with normal installation");
             }
         }
         
+        // Reuse pinned versions that still satisfy their ranges instead of
+        // re-resolving everything against the registry
+        if let Some(lockfile) = self.load_lockfile(&absolute_install_path).await? {
+            self.prefer_locked(&lockfile);
+        }
+
         // Create a temporary root package
         let mut root_pkg = Package {
             name: "root".to_string(),
             version: "0.0.0".to_string(),
             dependencies: HashMap::new(),
             dev_dependencies: HashMap::new(),
+            dist: None,
+            license: None,
+            optional_dependencies: HashMap::new(),
+            platforms: Platforms::default(),
+            registry: None,
+            deprecated: None,
         };
 
         // Add requested packages as dependencies
-        for (name, version) in packages {
-            if is_dev {
-                root_pkg.dev_dependencies.insert(name.clone(), version.clone());
-            } else {
-                root_pkg.dependencies.insert(name.clone(), version.clone());
-            }
+        for (name, version) in regular {
+            root_pkg.dependencies.insert(name.clone(), version.clone());
+        }
+        for (name, version) in dev {
+            root_pkg.dev_dependencies.insert(name.clone(), version.clone());
         }
 
         // Resolve dependencies
@@ -569,106 +1339,94 @@ impl DependencyResolver {
         let tree = self.resolve_dependencies(&root_pkg).await?;
         
         info!("Resolved {} packages in {:?}", 
-            tree.dependencies.len(), start.elapsed());
-        
-        // Install packages
-        info!("Installing {} packages...", tree.dependencies.len());
-        let installed = self.install_tree(&tree, &absolute_install_path).await?;
+            tree.len(), start.elapsed());
+
+        // Nothing is installed or locked if the tree breaks the project policy
+        if self.policy.is_some() {
+            let packages = tree.iter().collect::<Result<Vec<_>>>()?;
+            self.enforce_policy(packages.iter().map(|pkg| {
+                (pkg.name.as_str(), pkg.version.as_str(), pkg.license.as_deref())
+            }))?;
+        }
         
-        // Generate and save lockfile
+        // Install packages and merge the lockfile for this tree into the existing one
+        if self.lockfile_only {
+            info!("Lockfile-only mode, leaving node_modules untouched");
+        } else {
+            info!("Installing {} packages...", tree.len());
+        }
         let lockfile = self.generate_lockfile(&tree, &absolute_install_path).await?;
-        self.save_lockfile(&lockfile, &absolute_install_path).await?;
+        let plan = Plan {
+            install: tree.to_install()?,
+            remove: Vec::new(),
+            lockfile: Some(lockfile),
+            graph: Some(tree.graph()?),
+        };
+        Installer::new(self, &absolute_install_path).apply(plan).await?;
         
-        info!("Installed and locked {} packages in {:?}", 
-            installed.len(), start.elapsed());
+        info!("Resolved and locked {} packages in {:?}", 
+            tree.len(), start.elapsed());
         
-        Ok(tree.dependencies.values().cloned().collect())
+        // Report what the requested specs resolved to, not their dependencies
+        let mut resolved = Vec::with_capacity(packages.len());
+        for (name, version) in packages {
+            resolved.extend(tree.get(name, version)?);
+        }
+        Ok(resolved)
     }
     
     // Add method to install directly from lockfile
-    async fn install_from_lockfile(&self, lockfile: &Lockfile, install_path: &Path) -> Result<Vec<Package>> {
+    async fn install_from_lockfile(&self, lockfile: &Lockfile, install_path: &Path) -> Result<Vec<Arc<Package>>> {
         debug!("Installing packages from lockfile...");
         let start = Instant::now();
-        
-        // Create node_modules directory
-        let node_modules_dir = install_path.join("node_modules");
-        if !node_modules_dir.exists() {
-            fs::create_dir_all(&node_modules_dir).await?;
-        }
-        
-        // Convert lockfile entries to packages
-        let mut packages = Vec::new();
-        
-        // Clone the packages map to avoid borrowing issues
-        let packages_map = lockfile.packages.clone();
-        
-        // Install packages in parallel
-        let registry = self.registry.clone();
-        let mut handles = Vec::new();
-        
-        for (pkg_key, entry) in packages_map {
-            // Parse the package name from the key
-            let parts: Vec<&str> = pkg_key.split('@').collect();
-            if parts.is_empty() {
-                continue;
-            }
-            
-            let name = parts[0].to_string();
-            let version = entry.version.clone();
-            
-            let pkg = Package {
-                name: name.clone(),
-                version: version.clone(),
-                dependencies: entry.dependencies.clone(),
-                dev_dependencies: HashMap::new(),
-            };
-            
-            packages.push(pkg.clone());
-            
-            // Install in parallel
-            let pkg_dir = node_modules_dir.join(&name);
-            let registry_clone = registry.clone();
-            
-            let handle = tokio::spawn(async move {
-                if !pkg_dir.exists() {
-                    let _ = fs::create_dir_all(&pkg_dir).await;
-                    
-                    if let Some(url) = &entry.resolved {
-                        // Download and extract the package
-                        let tarball_path = pkg_dir.join("package.tgz");
-                        let _ = registry_clone.download_package(url, &tarball_path).await;
-                        
-                        // Extract the package
-                        let tarball_path_clone = tarball_path.clone();
-                        let pkg_dir_clone = pkg_dir.clone();
-                        let extract_result = tokio::task::spawn_blocking(move || {
-                            registry_clone.extract_tarball(&tarball_path_clone, &pkg_dir_clone)
-                        }).await;
-                        
-                        if let Ok(Ok(_)) = extract_result {
-                            // Clean up the tarball
-                            let _ = fs::remove_file(tarball_path).await;
-                        }
-                    }
-                }
-                
-                name
-            });
-            
-            handles.push(handle);
-        }
-        
-        // Wait for all installations to complete
-        let results = futures::future::join_all(handles).await;
-        let installed_count = results.iter().filter(|r| r.is_ok()).count();
-        
-        debug!("Installed {} packages from lockfile in {:?}", 
-            installed_count, start.elapsed());
-        
+
+        // The name from the key, scope included: "@scope/pkg@1.0.0"
+        let packages: Vec<Arc<Package>> = lockfile
+            .packages
+            .iter()
+            .map(|(key, entry)| Arc::new(entry.to_package(lockfile_key_name(key))))
+            .collect();
+
+        // The lockfile is what's being installed, so it's left as it is
+        let plan = Plan {
+            install: packages.clone(),
+            remove: Vec::new(),
+            lockfile: None,
+            graph: None,
+        };
+        let installed = Installer::new(self, install_path).apply(plan).await?;
+
+        debug!("Installed {} packages from lockfile in {:?}", installed.len(), start.elapsed());
         Ok(packages)
     }
 }
 
+// Whether `pkg_dir` already holds `pkg`: the journal of an interrupted install
+// says so, or its state file says it was installed from the same tarball and
+// nothing in it has changed since, patched with `patch` if there is one.
+// Packages without a tarball have nothing to verify against; a directory at
+// their version is kept. Anything else is extracted again
+fn already_installed(journal: Option<&Journal>, pkg_dir: &Path, pkg: &Package, patch: Option<&patches::Patch>) -> bool {
+    // The journal has name@version once, however many places it's nested in
+    if pkg_dir.exists() && journal.is_some_and(|journal| journal.is_done(&pkg.name, &pkg.version)) {
+        return true;
+    }
+    if pkg_dir.exists() && !patches::is_applied(pkg_dir, patch) {
+        return false;
+    }
+    if pkg.dist.is_some() || pkg_dir.join(state::STATE_FILE).exists() {
+        let integrity = pkg.dist.as_ref().and_then(|dist| dist.integrity.as_deref());
+        return state::is_intact(pkg_dir, &pkg.version, integrity);
+    }
+    let Ok(content) = std::fs::read_to_string(pkg_dir.join("package.json")) else {
+        return pkg_dir.exists();
+    };
+    let installed = serde_json::from_str::<serde_json::Value>(&content)
+        .ok()
+        .and_then(|json| json.get("version")?.as_str().map(str::to_string));
+    installed.is_none_or(|installed| installed == pkg.version)
+}
+
 // Add the Lockfile structures at module scope, before any impl blocks
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct LockfileEntry {
@@ -676,6 +1434,45 @@ pub struct LockfileEntry {
     pub resolved: Option<String>,
     pub integrity: Option<String>,
     pub dependencies: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub optional_dependencies: HashMap<String, String>,
+    // os/cpu/libc, kept so each platform installs only its own variants
+    #[serde(flatten)]
+    pub platforms: Platforms,
+    // Registry the version was resolved from. Locked versions keep coming
+    // from it whatever registry a later install runs against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    // Hash of the patch from patches/ applied to the package after extraction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch_hash: Option<String>,
+}
+
+impl LockfileEntry {
+    /// The package this entry locks, as installs from the lockfile get it.
+    pub fn to_package(&self, name: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            version: self.version.clone(),
+            dependencies: self.dependencies.clone(),
+            dev_dependencies: HashMap::new(),
+            dist: self.resolved.clone().map(|tarball| DistInfo {
+                shasum: String::new(),
+                tarball,
+                integrity: self.integrity.clone(),
+                signatures: Vec::new(),
+                npm_signature: None,
+                attestations: None,
+            }),
+            license: self.license.clone(),
+            optional_dependencies: self.optional_dependencies.clone(),
+            platforms: self.platforms.clone(),
+            registry: self.registry.clone(),
+            deprecated: None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -698,20 +1495,102 @@ impl Lockfile {
     }
 
     // Add a package to the lockfile
-    pub fn add_package(&mut self, pkg: &Package, registry: &str) {
+    pub fn add_package(&mut self, pkg: &Package) {
         let key = format!("{}@{}", pkg.name, pkg.version);
-        let integrity = Some(format!("sha512-{}", hex::encode(key.as_bytes())));
-        let resolved = Some(format!("{}/{}-{}.tgz", registry, pkg.name, pkg.version));
+        let resolved = pkg.dist.as_ref().map(|dist| dist.tarball.clone());
+        let integrity = pkg.dist.as_ref().and_then(|dist| dist.integrity.clone());
         
         let entry = LockfileEntry {
             version: pkg.version.clone(),
             resolved,
             integrity,
             dependencies: pkg.dependencies.clone(),
+            license: pkg.license.clone(),
+            optional_dependencies: pkg.optional_dependencies.clone(),
+            platforms: pkg.platforms.clone(),
+            registry: pkg.registry.clone(),
+            patch_hash: None,
         };
         
         self.packages.insert(key, entry);
     }
+
+    // Carry over entries from `previous`, merged per "name@version". A
+    // previous version of a name this lockfile also locks is kept while
+    // something still asks for it with a range none of the new versions
+    // satisfies: one of `roots` (the project's name to range) or another
+    // entry. Otherwise this resolve replaced it
+    fn keep_others_from(&mut self, previous: Lockfile, roots: &HashMap<String, String>) {
+        let mut fresh: HashMap<String, Vec<Arc<Version>>> = HashMap::new();
+        for (key, entry) in &self.packages {
+            fresh
+                .entry(lockfile_key_name(key).to_string())
+                .or_default()
+                .extend(versions::parse_version(&entry.version));
+        }
+
+        let mut contested = Vec::new();
+        for (key, entry) in previous.packages {
+            if self.packages.contains_key(&key) {
+                continue;
+            }
+            match fresh.contains_key(lockfile_key_name(&key)) {
+                true => contested.push((key, entry)),
+                false => {
+                    self.packages.insert(key, entry);
+                }
+            }
+        }
+
+        let asked = |name: &str, version: &Version| {
+            let mut ranges = roots.get(name).into_iter().chain(
+                self.packages
+                    .values()
+                    .filter_map(|entry| entry.dependencies.get(name).or(entry.optional_dependencies.get(name))),
+            );
+            ranges.any(|range| {
+                let Some(req) = versions::parse_req(range) else {
+                    return false;
+                };
+                req.matches(version) && !fresh[name].iter().any(|fresh| req.matches(fresh))
+            })
+        };
+        let kept: Vec<(String, LockfileEntry)> = contested
+            .into_iter()
+            .filter(|(key, entry)| {
+                versions::parse_version(&entry.version).is_some_and(|version| asked(lockfile_key_name(key), &version))
+            })
+            .collect();
+        self.packages.extend(kept);
+    }
+
+    /// The lockfile as rjs writes it: pretty JSON with the keys of every
+    /// object sorted, so the same tree always gives the same bytes.
+    pub fn to_json(&self) -> Result<String> {
+        // serde_json's own maps are sorted
+        let value = serde_json::to_value(self)?;
+        Ok(serde_json::to_string_pretty(&value)?)
+    }
+
+    // Distinct package names in the lockfile, sorted
+    pub fn package_names(&self) -> Vec<String> {
+        let names: BTreeSet<&str> = self.packages.keys().map(|key| lockfile_key_name(key)).collect();
+        names.into_iter().map(str::to_string).collect()
+    }
+}
+
+// Whether `version` was published after `cutoff`. Versions without a publish
+// time in the packument get the benefit of the doubt
+fn published_after(package_info: &PackageInfo, version: &str, cutoff: SystemTime) -> bool {
+    package_info.published(version).is_some_and(|published| published > cutoff)
+}
+
+/// Package name of a lockfile key, e.g. "@scope/pkg" for "@scope/pkg@1.0.0".
+pub fn lockfile_key_name(key: &str) -> &str {
+    match key.rsplit_once('@') {
+        Some((name, _)) if !name.is_empty() => name,
+        _ => key,
+    }
 }
 
 // Helper methods that could be used by commands
@@ -757,6 +1636,12 @@ pub async fn read_package_json(path: &Path) -> Result<Package> {
         version,
         dependencies,
         dev_dependencies,
+        dist: None,
+        license: json.get("license").and_then(|license| license.as_str()).map(str::to_string),
+        optional_dependencies: HashMap::new(),
+        platforms: Platforms::from_fields(json.get("os"), json.get("cpu"), json.get("libc")),
+        registry: None,
+        deprecated: None,
     })
 }
 
@@ -767,7 +1652,8 @@ pub async fn update_package_json(
     dev: bool,
 ) -> Result<()> {
     let content = fs::read_to_string(path).await?;
-    let mut json: serde_json::Value = serde_json::from_str(&content)?;
+    let mut manifest = manifest::Manifest::parse(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
 
     let deps_field = if dev {
         "devDependencies"
@@ -775,28 +1661,14 @@ pub async fn update_package_json(
         "dependencies"
     };
 
-    // Create a new object for dependencies if it doesn't exist
-    if !json.as_object_mut().unwrap().contains_key(deps_field) {
-        json.as_object_mut().unwrap().insert(
-            deps_field.to_string(),
-            serde_json::Value::Object(serde_json::Map::new()),
-        );
-    }
-
-    // Get the dependencies object
-    let deps_obj = json
-        .as_object_mut()
-        .unwrap()
-        .get_mut(deps_field)
-        .and_then(|v| v.as_object_mut())
-        .unwrap();
-
-    // Update dependencies
+    // Only the entries that change are rewritten, in a stable order
+    let mut dependencies: Vec<(&String, &String)> = dependencies.iter().collect();
+    dependencies.sort();
     for (name, version) in dependencies {
-        deps_obj.insert(name.clone(), serde_json::Value::String(version.clone()));
+        manifest.set_dependency(deps_field, name, version)?;
     }
 
-    fs::write(path, serde_json::to_string_pretty(&json)?).await?;
+    fs::write(path, manifest.into_string()).await?;
 
     Ok(())
 }
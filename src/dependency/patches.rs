@@ -0,0 +1,191 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use super::state::STATE_FILE;
+use crate::utils;
+
+/// Directory beside package.json a project keeps its patches in.
+pub const PATCHES_DIR: &str = "patches";
+
+// Written into a patched package's directory with the patch's hash, so a
+// package is extracted again when its patch changes or goes away
+const MARKER: &str = ".rjs-patch";
+
+/// A patch from patches/, applied to a package every time it's extracted.
+#[derive(Debug)]
+pub struct Patch {
+    pub path: PathBuf,
+    content: String,
+    /// "sha256-<hex>" of the patch file, recorded in rjs-lock.json
+    pub hash: String,
+}
+
+/// The patches of a project, by the "name@version" they're for.
+#[derive(Debug, Clone, Default)]
+pub struct Patches {
+    patches: HashMap<String, Arc<Patch>>,
+}
+
+impl Patches {
+    /// The patches in `root`'s patches/ directory, named as `file_name` names them.
+    pub fn load(root: &Path) -> Result<Self> {
+        let Ok(entries) = std::fs::read_dir(root.join(PATCHES_DIR)) else {
+            return Ok(Self::default());
+        };
+        let mut patches = HashMap::new();
+        for entry in entries {
+            let path = entry?.path();
+            let Some(key) = path.file_name().and_then(|name| name.to_str()?.strip_suffix(".patch")) else {
+                continue;
+            };
+            // "@scope+name@1.0.0" is "@scope/name@1.0.0"
+            let key = match key.starts_with('@') {
+                true => key.replacen('+', "/", 1),
+                false => key.to_string(),
+            };
+            let content =
+                std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let hash = format!("sha256-{}", utils::calculate_sha256(content.as_bytes()));
+            patches.insert(key, Arc::new(Patch { path, content, hash }));
+        }
+        Ok(Self { patches })
+    }
+
+    pub fn get(&self, name: &str, version: &str) -> Option<&Arc<Patch>> {
+        self.patches.get(&format!("{}@{}", name, version))
+    }
+}
+
+/// File in patches/ a package's patch is kept in, e.g. "lodash@4.17.21.patch"
+/// or "@types+node@20.1.0.patch", as patch-package names them.
+pub fn file_name(name: &str, version: &str) -> String {
+    format!("{}@{}.patch", name.replacen('/', "+", 1), version)
+}
+
+/// Apply `patch` to the package extracted in `dir`.
+pub fn apply(patch: &Patch, dir: &Path) -> Result<()> {
+    let patch_name = patch.path.file_name().unwrap_or_default().to_string_lossy();
+    for section in sections(&patch.content) {
+        let parsed = diffy::Patch::from_str(section).with_context(|| format!("Failed to parse {}", patch_name))?;
+        let (original, modified) = (parsed.original().map(strip_prefix), parsed.modified().map(strip_prefix));
+        let Some(file) = modified.flatten().or(original.flatten()) else {
+            anyhow::bail!("{} has a file without a name", patch_name);
+        };
+        // Patches only ever touch the package they're for
+        if Path::new(file).components().any(|c| !matches!(c, Component::Normal(_))) {
+            anyhow::bail!("{} changes {}, outside the package", patch_name, file);
+        }
+
+        let path = dir.join(file);
+        let before = match original.flatten() {
+            Some(_) => std::fs::read_to_string(&path)
+                .with_context(|| format!("{} changes {}, which the package doesn't have", patch_name, file))?,
+            None => String::new(),
+        };
+        let after = diffy::apply(&before, &parsed)
+            .with_context(|| format!("{} no longer applies to {}", patch_name, file))?;
+        match modified.flatten() {
+            Some(_) => {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, after).with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+            None => std::fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?,
+        }
+    }
+    Ok(())
+}
+
+/// Record in `dir` that its package was patched with `patch`.
+pub fn mark(patch: &Patch, dir: &Path) -> Result<()> {
+    std::fs::write(dir.join(MARKER), &patch.hash).with_context(|| format!("Failed to write {}", dir.join(MARKER).display()))
+}
+
+/// Whether the package in `dir` was patched with `patch`, or left unpatched
+/// when there's none.
+pub fn is_applied(dir: &Path, patch: Option<&Patch>) -> bool {
+    let applied = std::fs::read_to_string(dir.join(MARKER)).ok();
+    applied.as_deref() == patch.map(|patch| patch.hash.as_str())
+}
+
+/// A patch turning the package in `original` into the one in `edited`, in
+/// the format `git diff` writes. Binary files can't be patched.
+pub fn diff(original: &Path, edited: &Path) -> Result<String> {
+    let mut files = files_in(original);
+    files.extend(files_in(edited));
+
+    let mut patch = String::new();
+    for file in files {
+        let before = std::fs::read(original.join(&file)).ok();
+        let after = std::fs::read(edited.join(&file)).ok();
+        if before == after {
+            continue;
+        }
+        let text = |bytes: Option<Vec<u8>>| match bytes.map(String::from_utf8) {
+            Some(Ok(text)) => Ok(Some(text)),
+            Some(Err(_)) => Err(anyhow::anyhow!("{} is a binary file, which patches can't change", file)),
+            None => Ok(None),
+        };
+        let (before, after) = (text(before)?, text(after)?);
+
+        let a = before.as_ref().map_or("/dev/null".to_string(), |_| format!("a/{}", file));
+        let b = after.as_ref().map_or("/dev/null".to_string(), |_| format!("b/{}", file));
+        patch.push_str(&format!("diff --git a/{} b/{}\n", file, file));
+        if before.is_none() {
+            patch.push_str("new file mode 100644\n");
+        } else if after.is_none() {
+            patch.push_str("deleted file mode 100644\n");
+        }
+        let section = diffy::DiffOptions::new()
+            .set_original_filename(a)
+            .set_modified_filename(b)
+            .create_patch(before.as_deref().unwrap_or_default(), after.as_deref().unwrap_or_default())
+            .to_string();
+        patch.push_str(&section);
+    }
+    Ok(patch)
+}
+
+// The patch of each file in a multi-file patch, split at its "diff --git" lines
+fn sections(content: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = content
+        .match_indices("diff --git ")
+        .filter(|(at, _)| *at == 0 || content.as_bytes()[at - 1] == b'\n')
+        .map(|(at, _)| at)
+        .collect();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    starts.push(content.len());
+    starts
+        .windows(2)
+        .map(|bounds| &content[bounds[0]..bounds[1]])
+        .filter(|section| section.contains("@@ "))
+        .collect()
+}
+
+// "a/lib/index.js" as "lib/index.js"; None for /dev/null
+fn strip_prefix(name: &str) -> Option<&str> {
+    if name == "/dev/null" {
+        return None;
+    }
+    Some(name.strip_prefix("a/").or_else(|| name.strip_prefix("b/")).unwrap_or(name))
+}
+
+// Files of a package directory relative to it, with forward slashes
+fn files_in(dir: &Path) -> BTreeSet<String> {
+    walkdir::WalkDir::new(dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| !matches!(entry.file_name().to_str(), Some("node_modules" | MARKER | STATE_FILE)))
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(dir).ok()?;
+            Some(relative.to_string_lossy().replace('\\', "/"))
+        })
+        .collect()
+}
@@ -0,0 +1,194 @@
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// The platforms a package is built for, from the `os`, `cpu` and `libc`
+/// fields of its package.json. Entries may be negated ("!win32"); an empty
+/// list allows anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Platforms {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cpu: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub libc: Vec<String>,
+}
+
+impl Platforms {
+    /// Read the fields from a package.json or packument version, where each
+    /// is either a string or a list of strings.
+    pub fn from_fields(
+        os: Option<&serde_json::Value>,
+        cpu: Option<&serde_json::Value>,
+        libc: Option<&serde_json::Value>,
+    ) -> Self {
+        Self {
+            os: string_list(os),
+            cpu: string_list(cpu),
+            libc: string_list(libc),
+        }
+    }
+
+    /// Whether a package built for these platforms can run on `host`.
+    pub fn supports(&self, host: &Host) -> bool {
+        allows(&self.os, &host.os)
+            && allows(&self.cpu, &host.cpu)
+            // Without knowing the host's libc, don't rule anything out
+            && host.libc.as_deref().is_none_or(|libc| allows(&self.libc, libc))
+    }
+}
+
+/// Node's `process.platform` values, what a package's `os` names.
+pub const OSES: [&str; 8] = ["aix", "android", "darwin", "freebsd", "linux", "openbsd", "sunos", "win32"];
+
+/// Node's `process.arch` values, what a package's `cpu` names.
+pub const CPUS: [&str; 12] = ["arm", "arm64", "ia32", "loong64", "mips", "mipsel", "ppc", "ppc64", "riscv64", "s390", "s390x", "x64"];
+
+/// The C libraries a package's `libc` names, on Linux.
+pub const LIBCS: [&str; 2] = ["glibc", "musl"];
+
+/// A platform to install for, in Node's `process.platform`/`process.arch`
+/// names ("linux", "darwin", "win32"; "x64", "arm64").
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Host {
+    pub os: String,
+    pub cpu: String,
+    pub libc: Option<String>,
+}
+
+impl Host {
+    /// The platform rjs is running on.
+    pub fn current() -> Self {
+        let os = match std::env::consts::OS {
+            "macos" => "darwin",
+            "windows" => "win32",
+            "solaris" | "illumos" => "sunos",
+            os => os,
+        };
+        let cpu = match std::env::consts::ARCH {
+            "x86_64" => "x64",
+            "x86" => "ia32",
+            "aarch64" => "arm64",
+            "powerpc" => "ppc",
+            "powerpc64" => "ppc64",
+            "loongarch64" => "loong64",
+            cpu => cpu,
+        };
+        let libc = if os == "linux" { linux_libc() } else { None };
+        Self {
+            os: os.to_string(),
+            cpu: cpu.to_string(),
+            libc: libc.map(str::to_string),
+        }
+    }
+
+    /// The platform rjs is running on with `os`, `cpu` and `libc` replaced
+    /// where given, to install for another one. The host's libc only carries
+    /// over while the os does; targeting Linux from elsewhere without a libc
+    /// installs the variants of both.
+    pub fn target(os: Option<&str>, cpu: Option<&str>, libc: Option<&str>) -> Self {
+        let mut host = Self::current();
+        if let Some(os) = os
+            && os != host.os
+        {
+            host.os = os.to_string();
+            host.libc = None;
+        }
+        if let Some(cpu) = cpu {
+            host.cpu = cpu.to_string();
+        }
+        if let Some(libc) = libc {
+            host.libc = Some(libc.to_string());
+        }
+        host
+    }
+}
+
+// Alpine, the usual base of slim Docker images, is musl-based
+fn is_alpine() -> bool {
+    Path::new("/etc/alpine-release").exists()
+}
+
+// "glibc" or "musl", the names npm's libc field uses. Looked up once: asking
+// ldd means starting a process
+fn linux_libc() -> Option<&'static str> {
+    static LIBC: OnceLock<Option<&'static str>> = OnceLock::new();
+    *LIBC.get_or_init(|| {
+        let libc = detect_linux_libc();
+        debug!("Detected libc: {}", libc.unwrap_or("unknown"));
+        libc
+    })
+}
+
+fn detect_linux_libc() -> Option<&'static str> {
+    if is_alpine() {
+        return Some("musl");
+    }
+    // The dynamic loader is named after the libc: ld-linux-x86-64.so.2 or
+    // ld-musl-x86_64.so.1. glibc's wins, as glibc systems can have musl
+    // installed alongside for building
+    let loaders: Vec<String> = ["/lib", "/lib64"]
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with("ld-"))
+        .collect();
+    if loaders.iter().any(|name| name.starts_with("ld-linux")) {
+        return Some("glibc");
+    }
+    if loaders.iter().any(|name| name.starts_with("ld-musl-")) {
+        return Some("musl");
+    }
+
+    // musl's ldd prints its version to stderr and exits with 1
+    let output = std::process::Command::new("ldd").arg("--version").output().ok()?;
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+    .to_lowercase();
+    if text.contains("musl") {
+        Some("musl")
+    } else if text.contains("glibc") || text.contains("gnu libc") {
+        Some("glibc")
+    } else {
+        None
+    }
+}
+
+impl std::fmt::Display for Host {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.os, self.cpu)?;
+        if let Some(libc) = &self.libc {
+            write!(f, "-{}", libc)?;
+        }
+        Ok(())
+    }
+}
+
+// npm's rule: a value is refused when it's negated, or when the list names
+// values without it
+fn allows(list: &[String], value: &str) -> bool {
+    let mut listed = false;
+    for entry in list {
+        match entry.strip_prefix('!') {
+            Some(refused) if refused == value => return false,
+            Some(_) => {}
+            None if entry == value => return true,
+            None => listed = true,
+        }
+    }
+    !listed
+}
+
+fn string_list(value: Option<&serde_json::Value>) -> Vec<String> {
+    match value {
+        Some(serde_json::Value::String(single)) => vec![single.clone()],
+        Some(serde_json::Value::Array(list)) => list.iter().filter_map(|item| item.as_str()).map(str::to_string).collect(),
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use super::{lockfile_key_name, versions, Lockfile};
+
+/// The lockfile npm installs a published package's dependencies from instead
+/// of resolving them. `rjs pack` and `rjs publish` always include it.
+pub const SHRINKWRAP_FILE: &str = "npm-shrinkwrap.json";
+
+// npm's lockfile format, version 3
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Shrinkwrap<'a> {
+    name: &'a str,
+    version: &'a str,
+    lockfile_version: u32,
+    requires: bool,
+    packages: BTreeMap<String, Node<'a>>,
+}
+
+// An entry of "packages", keyed by where it's installed: "" for the package
+// itself, "node_modules/a/node_modules/b" for a copy nested under another
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Node<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    version: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resolved: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    integrity: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<&'a str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    dependencies: BTreeMap<&'a str, &'a str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    optional_dependencies: BTreeMap<&'a str, &'a str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    os: &'a [String],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    cpu: &'a [String],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    libc: &'a [String],
+    // Only reached through optionalDependencies
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    optional: bool,
+}
+
+/// npm-shrinkwrap.json for the package `name`@`version`, pinning what its
+/// production `dependencies` and `optional` dependencies (name to spec) are
+/// locked to in `lockfile`. Packages are laid out as npm installs them: each
+/// as high in node_modules as it can go, other versions of it nested under
+/// what needs them. Returns the file's content and the packages it pins.
+pub fn from_lockfile(
+    name: &str,
+    version: &str,
+    dependencies: &HashMap<String, String>,
+    optional: &HashMap<String, String>,
+    lockfile: &Lockfile,
+) -> Result<(String, usize)> {
+    let mut by_name: HashMap<&str, Vec<&str>> = HashMap::new();
+    for key in lockfile.packages.keys() {
+        by_name.entry(lockfile_key_name(key)).or_default().push(key);
+    }
+
+    // Where each package goes, breadth first so the shallowest need of a
+    // name takes the top-level spot
+    let mut placed: BTreeMap<String, &str> = BTreeMap::new();
+    let mut queue: VecDeque<(String, &str, &str, bool)> = dependencies
+        .iter()
+        .map(|(name, spec)| (String::new(), name.as_str(), spec.as_str(), false))
+        .chain(optional.iter().map(|(name, spec)| (String::new(), name.as_str(), spec.as_str(), true)))
+        .collect();
+    while let Some((from, name, spec, optional)) = queue.pop_front() {
+        let Some(key) = pick(lockfile, &by_name, name, spec) else {
+            if optional {
+                continue;
+            }
+            anyhow::bail!("{}@{} isn't in rjs-lock.json; run `rjs install` first", name, spec);
+        };
+        let path = match locate(&placed, &from, name) {
+            Some(found) if placed[&found] == key => continue,
+            // A package needing another version of one of its ancestors
+            // would nest forever
+            Some(_) if ancestors(&from).any(|dir| placed.get(dir) == Some(&key)) => continue,
+            Some(_) => child(&from, name),
+            None => child("", name),
+        };
+        placed.insert(path.clone(), key);
+
+        let entry = &lockfile.packages[key];
+        for (dependency, spec) in &entry.dependencies {
+            queue.push_back((path.clone(), dependency, spec, optional));
+        }
+        for (dependency, spec) in &entry.optional_dependencies {
+            queue.push_back((path.clone(), dependency, spec, true));
+        }
+    }
+
+    // What a plain install needs, following dependencies but not optional ones
+    let mut required: HashSet<String> = HashSet::new();
+    let mut queue: VecDeque<(String, &str)> =
+        dependencies.keys().map(|name| (String::new(), name.as_str())).collect();
+    while let Some((from, name)) = queue.pop_front() {
+        let Some(path) = locate(&placed, &from, name) else {
+            continue;
+        };
+        if required.insert(path.clone()) {
+            for dependency in lockfile.packages[placed[&path]].dependencies.keys() {
+                queue.push_back((path.clone(), dependency));
+            }
+        }
+    }
+
+    let mut packages = BTreeMap::new();
+    packages.insert(
+        String::new(),
+        Node {
+            name: Some(name),
+            version,
+            dependencies: sorted(dependencies),
+            optional_dependencies: sorted(optional),
+            ..Default::default()
+        },
+    );
+    for (path, key) in &placed {
+        let entry = &lockfile.packages[*key];
+        let node = Node {
+            version: &entry.version,
+            resolved: entry.resolved.as_deref(),
+            integrity: entry.integrity.as_deref(),
+            license: entry.license.as_deref(),
+            dependencies: sorted(&entry.dependencies),
+            optional_dependencies: sorted(&entry.optional_dependencies),
+            os: &entry.platforms.os,
+            cpu: &entry.platforms.cpu,
+            libc: &entry.platforms.libc,
+            optional: !required.contains(path),
+            ..Default::default()
+        };
+        packages.insert(path.clone(), node);
+    }
+
+    let shrinkwrap = Shrinkwrap {
+        name,
+        version,
+        lockfile_version: 3,
+        requires: true,
+        packages,
+    };
+    Ok((format!("{}\n", serde_json::to_string_pretty(&shrinkwrap)?), placed.len()))
+}
+
+// The locked version of `name` that `spec` takes: the highest matching one,
+// or the highest at all when the spec isn't a range or matches none
+fn pick<'a>(lockfile: &Lockfile, by_name: &HashMap<&str, Vec<&'a str>>, name: &str, spec: &str) -> Option<&'a str> {
+    let keys = by_name.get(name)?;
+    let req = versions::parse_req(spec);
+    let version = |key: &&str| versions::parse_version(&lockfile.packages[*key].version);
+    let matching = keys
+        .iter()
+        .filter(|key| matches!((&req, version(key)), (Some(req), Some(version)) if req.matches(&version)))
+        .max_by_key(|key| version(key));
+    matching.or_else(|| keys.iter().max_by_key(|key| version(key))).copied()
+}
+
+// Where Node finds `name` from the package at `from`: in its node_modules,
+// then each enclosing one up to the top
+fn locate(placed: &BTreeMap<String, &str>, from: &str, name: &str) -> Option<String> {
+    std::iter::once(from)
+        .chain(ancestors(from))
+        .map(|dir| child(dir, name))
+        .find(|path| placed.contains_key(path))
+}
+
+// The packages `path` is nested under, innermost first, then "" for the top
+fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(path), |path| {
+        (!path.is_empty()).then(|| path.rfind("/node_modules/").map_or("", |end| &path[..end]))
+    })
+    .skip(1)
+}
+
+fn child(dir: &str, name: &str) -> String {
+    match dir {
+        "" => format!("node_modules/{}", name),
+        dir => format!("{}/node_modules/{}", dir, name),
+    }
+}
+
+fn sorted(specs: &HashMap<String, String>) -> BTreeMap<&str, &str> {
+    specs.iter().map(|(name, spec)| (name.as_str(), spec.as_str())).collect()
+}
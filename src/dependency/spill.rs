@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::Package;
+
+// Tells apart the databases of resolvers in one process
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A resolved package, either in memory or written out to the spill database
+/// under its "name@version".
+#[derive(Clone)]
+pub(super) enum Slot {
+    Resident(Arc<Package>),
+    Spilled(String),
+}
+
+/// Caps how many resolved packages a resolver keeps in memory. Packages past
+/// the limit are written to a temporary database and read back one at a time
+/// as they're locked and installed. The database is created on the first
+/// package that doesn't fit and deleted when the resolver is dropped.
+pub(super) struct Spill {
+    limit: usize,
+    resident: AtomicUsize,
+    spilled: AtomicUsize,
+    db: Mutex<Option<sled::Db>>,
+}
+
+impl Spill {
+    pub(super) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            resident: AtomicUsize::new(0),
+            spilled: AtomicUsize::new(0),
+            db: Mutex::new(None),
+        }
+    }
+
+    /// Packages written to disk so far.
+    pub(super) fn spilled(&self) -> usize {
+        self.spilled.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn limit(&self) -> usize {
+        self.limit
+    }
+
+    // On disk rather than in sled's default /dev/shm, which is memory too
+    fn db(&self) -> Result<sled::Db> {
+        let mut db = self.db.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(db) = db.as_ref() {
+            return Ok(db.clone());
+        }
+        let path = std::env::temp_dir().join(format!(
+            "rjs-spill-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let opened = sled::Config::new()
+            .path(&path)
+            .temporary(true)
+            .open()
+            .with_context(|| format!("Failed to create a spill database at {}", path.display()))?;
+        debug!("Spilling resolved packages past the first {} to {}", self.limit, path.display());
+        *db = Some(opened.clone());
+        Ok(opened)
+    }
+
+    /// Keep `package` in memory while there's room, or else write it out.
+    pub(super) fn admit(&self, package: Arc<Package>) -> Result<Slot> {
+        let id = format!("{}@{}", package.name, package.version);
+        if self.contains(&id) {
+            return Ok(Slot::Spilled(id));
+        }
+        if self.resident.fetch_add(1, Ordering::Relaxed) < self.limit {
+            return Ok(Slot::Resident(package));
+        }
+        self.resident.fetch_sub(1, Ordering::Relaxed);
+        self.store(&id, &package)?;
+        self.spilled.fetch_add(1, Ordering::Relaxed);
+        Ok(Slot::Spilled(id))
+    }
+
+    /// Whether "name@version" was written out.
+    pub(super) fn contains(&self, id: &str) -> bool {
+        let db = self.db.lock().unwrap_or_else(|e| e.into_inner());
+        db.as_ref().is_some_and(|db| db.contains_key(id).unwrap_or(false))
+    }
+
+    /// Write `package` under `id`, replacing what's there.
+    pub(super) fn store(&self, id: &str, package: &Package) -> Result<()> {
+        // Named fields, as Platforms leaves out empty ones
+        let bytes = rmp_serde::to_vec_named(package).with_context(|| format!("Failed to encode {}", id))?;
+        self.db()?
+            .insert(id, bytes)
+            .with_context(|| format!("Failed to spill {} to disk", id))?;
+        Ok(())
+    }
+
+    /// Read a spilled package back.
+    pub(super) fn load(&self, id: &str) -> Result<Arc<Package>> {
+        let bytes = self
+            .db()?
+            .get(id)?
+            .with_context(|| format!("{} is missing from the spill database", id))?;
+        let package = rmp_serde::from_slice(&bytes).with_context(|| format!("Failed to decode spilled {}", id))?;
+        Ok(Arc::new(package))
+    }
+}
@@ -0,0 +1,268 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::fs;
+use tokio_util::sync::CancellationToken;
+
+use crate::cache::{StoreOutcome, TarballStore};
+use crate::dependency::events::{InstallEvent, InstallEvents};
+use crate::registry::NpmRegistry;
+use crate::timing::{self, Phase};
+use crate::utils;
+
+// Distinguishes concurrent attempts at materializing the same package
+static ATTEMPT: AtomicUsize = AtomicUsize::new(0);
+
+// Temp sibling paths used while a package is being materialized. They live next
+// to the destination so the final rename never crosses a filesystem boundary.
+fn sibling_path(dest: &Path, suffix: &str, attempt: usize) -> PathBuf {
+    let name = package_label(dest);
+    dest.with_file_name(format!(".{}.{}-{}-{}", name, suffix, std::process::id(), attempt))
+}
+
+fn next_attempt() -> usize {
+    ATTEMPT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Fetch a package tarball through the tarball store, verify it and move it
+/// into `dest` atomically.
+///
+/// The tarball is extracted into a temp sibling of `dest` and only renamed into
+/// place once the download and integrity check succeeded, so an interrupted
+/// install never leaves a truncated package behind. Downloads and extraction
+/// are reported to `events` under `name`.
+pub async fn install_tarball(
+    registry: &NpmRegistry,
+    name: &str,
+    tarball_url: &str,
+    integrity: Option<&str>,
+    dest: &Path,
+    cancel: &CancellationToken,
+    events: &dyn InstallEvents,
+) -> Result<()> {
+    let attempt = next_attempt();
+    let staging_dir = sibling_path(dest, "staging", attempt);
+
+    if let Some(parent) = dest.parent() {
+        utils::ensure_dir(parent).await?;
+    }
+
+    let result = tokio::select! {
+        result = stage_tarball(registry, name, tarball_url, integrity, &staging_dir, events) => result,
+        _ = cancel.cancelled() => Err(anyhow::anyhow!("Installation cancelled")),
+    };
+
+    if let Err(e) = result {
+        remove_path(&staging_dir).await;
+        return Err(e);
+    }
+
+    commit(&staging_dir, dest, attempt).await
+}
+
+/// Move a vendored package into `dest` atomically: its tarball, verified
+/// against `integrity` and extracted, or a copy of its extracted directory.
+pub async fn install_vendored(
+    registry: &NpmRegistry,
+    name: &str,
+    source: &Path,
+    integrity: Option<&str>,
+    dest: &Path,
+    events: &dyn InstallEvents,
+) -> Result<()> {
+    let attempt = next_attempt();
+    let staging_dir = sibling_path(dest, "staging", attempt);
+    if let Some(parent) = dest.parent() {
+        utils::ensure_dir(parent).await?;
+    }
+
+    if let Err(e) = stage_vendored(registry, name, source, integrity, &staging_dir, events).await {
+        remove_path(&staging_dir).await;
+        return Err(e);
+    }
+    commit(&staging_dir, dest, attempt).await
+}
+
+/// Move a local tarball into `dest` atomically, once it's checked against
+/// the `integrity` it was locked with.
+pub async fn install_local(
+    registry: &NpmRegistry,
+    name: &str,
+    source: &Path,
+    integrity: Option<&str>,
+    dest: &Path,
+    events: &dyn InstallEvents,
+) -> Result<()> {
+    let data = utils::read_file(source).await?;
+    if let Some(integrity) = integrity {
+        utils::verify_integrity(&data, integrity).with_context(|| {
+            format!("{} changed since it was locked; install it again to update rjs-lock.json", source.display())
+        })?;
+    }
+
+    let attempt = next_attempt();
+    let staging_dir = sibling_path(dest, "staging", attempt);
+    if let Some(parent) = dest.parent() {
+        utils::ensure_dir(parent).await?;
+    }
+    if let Err(e) = stage_local(registry, name, source, data, &staging_dir, events).await {
+        remove_path(&staging_dir).await;
+        return Err(e);
+    }
+    commit(&staging_dir, dest, attempt).await
+}
+
+/// Write `files` into `dest` atomically, used for packages without a tarball.
+pub async fn install_files(dest: &Path, files: &[(&str, String)]) -> Result<()> {
+    let attempt = next_attempt();
+    let staging_dir = sibling_path(dest, "staging", attempt);
+    utils::ensure_dir(&staging_dir).await?;
+
+    for (name, content) in files {
+        if let Err(e) = utils::write_file(&staging_dir.join(name), content.as_bytes()).await {
+            remove_path(&staging_dir).await;
+            return Err(e);
+        }
+    }
+
+    commit(&staging_dir, dest, attempt).await
+}
+
+async fn stage_tarball(
+    registry: &NpmRegistry,
+    name: &str,
+    tarball_url: &str,
+    integrity: Option<&str>,
+    staging_dir: &Path,
+    events: &dyn InstallEvents,
+) -> Result<()> {
+    let (tarball_path, outcome) = TarballStore::open()?
+        .fetch(registry, tarball_url, integrity, name, events)
+        .await?;
+    match outcome {
+        StoreOutcome::Downloaded => {
+            let bytes = fs::metadata(&tarball_path).await.map(|m| m.len()).unwrap_or(0);
+            events.on_event(&InstallEvent::PackageDownloaded { name: name.to_string(), bytes });
+        }
+        StoreOutcome::Cached => events.on_event(&InstallEvent::PackageCached { name: name.to_string() }),
+    }
+
+    let _slot = registry.extraction_slot().await?;
+    let _span = timing::span(Phase::Extract, name);
+    let registry = registry.clone();
+    let staging_dir = staging_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || registry.extract_tarball(&tarball_path, &staging_dir))
+        .await
+        .context("Extraction task panicked")??;
+    events.on_event(&InstallEvent::PackageExtracted { name: name.to_string() });
+    Ok(())
+}
+
+async fn stage_vendored(
+    registry: &NpmRegistry,
+    name: &str,
+    source: &Path,
+    integrity: Option<&str>,
+    staging_dir: &Path,
+    events: &dyn InstallEvents,
+) -> Result<()> {
+    events.on_event(&InstallEvent::PackageCached { name: name.to_string() });
+    if let Some(integrity) = integrity
+        && source.is_file()
+    {
+        let data = utils::read_file(source).await?;
+        utils::verify_integrity(&data, integrity)
+            .with_context(|| format!("{} doesn't match rjs-lock.json; run `rjs vendor` again", source.display()))?;
+    }
+
+    let _slot = registry.extraction_slot().await?;
+    let _span = timing::span(Phase::Extract, name);
+    let (registry, source, staging_dir) = (registry.clone(), source.to_path_buf(), staging_dir.to_path_buf());
+    tokio::task::spawn_blocking(move || match source.is_dir() {
+        true => copy_dir(&source, &staging_dir),
+        false => registry.extract_tarball(&source, &staging_dir),
+    })
+    .await
+    .context("Extraction task panicked")??;
+    events.on_event(&InstallEvent::PackageExtracted { name: name.to_string() });
+    Ok(())
+}
+
+// Extract a local tarball from the bytes its integrity was checked on, so
+// it can't change between the check and the extraction
+async fn stage_local(
+    registry: &NpmRegistry,
+    name: &str,
+    source: &Path,
+    data: Vec<u8>,
+    staging_dir: &Path,
+    events: &dyn InstallEvents,
+) -> Result<()> {
+    events.on_event(&InstallEvent::PackageCached { name: name.to_string() });
+
+    let _slot = registry.extraction_slot().await?;
+    let _span = timing::span(Phase::Extract, name);
+    let (registry, source, staging_dir) = (registry.clone(), source.to_path_buf(), staging_dir.to_path_buf());
+    tokio::task::spawn_blocking(move || registry.extract_archive(data.as_slice(), &source, &staging_dir))
+        .await
+        .context("Extraction task panicked")??;
+    events.on_event(&InstallEvent::PackageExtracted { name: name.to_string() });
+    Ok(())
+}
+
+// Copy the files under `from` into `to`
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    for entry in walkdir::WalkDir::new(from) {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+// Name a package directory is reported under in timings
+fn package_label(dest: &Path) -> String {
+    dest.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+// Swap the staged directory into place. An existing package is moved aside
+// first so `dest` is always either the old or the new complete package.
+async fn commit(staging_dir: &Path, dest: &Path, attempt: usize) -> Result<()> {
+    let _span = timing::span(Phase::Link, &package_label(dest));
+    let previous = sibling_path(dest, "old", attempt);
+
+    if dest.exists() {
+        fs::rename(dest, &previous)
+            .await
+            .with_context(|| format!("Failed to move aside {}", dest.display()))?;
+    }
+
+    if let Err(e) = fs::rename(staging_dir, dest).await {
+        // Put the previous version back before giving up
+        if previous.exists() {
+            let _ = fs::rename(&previous, dest).await;
+        }
+        remove_path(staging_dir).await;
+        return Err(e).with_context(|| format!("Failed to move package into {}", dest.display()));
+    }
+
+    remove_path(&previous).await;
+    debug!("Committed package to {}", dest.display());
+    Ok(())
+}
+
+async fn remove_path(path: &Path) {
+    if path.is_dir() {
+        let _ = fs::remove_dir_all(path).await;
+    } else if path.exists() {
+        let _ = fs::remove_file(path).await;
+    }
+}
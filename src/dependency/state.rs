@@ -0,0 +1,75 @@
+use anyhow::{Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Written into a package's directory once it's completely installed, so
+/// later installs can skip a package that's intact and replace one that's
+/// stale or damaged, without extracting it again to find out.
+pub const STATE_FILE: &str = ".rjs-state";
+
+// What was installed into a package directory: the version and integrity it
+// was extracted from and the size of each of its files
+#[derive(Serialize, Deserialize)]
+struct State {
+    version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    integrity: Option<String>,
+    files: BTreeMap<String, u64>,
+}
+
+/// Record that `dir` holds `version`, extracted from a tarball with
+/// `integrity`, as it is now.
+pub fn write(dir: &Path, version: &str, integrity: Option<&str>) -> Result<()> {
+    let state = State {
+        version: version.to_string(),
+        integrity: integrity.map(str::to_string),
+        files: files_in(dir)?,
+    };
+    let path = dir.join(STATE_FILE);
+    std::fs::write(&path, serde_json::to_vec(&state)?).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Whether `dir` holds `version` from a tarball with `integrity` (when one is
+/// known), with every file it was installed with still there at its size.
+/// Directories without a state file aren't trusted.
+pub fn is_intact(dir: &Path, version: &str, integrity: Option<&str>) -> bool {
+    let Some(state) = std::fs::read(dir.join(STATE_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<State>(&bytes).ok())
+    else {
+        return false;
+    };
+    if state.version != version || integrity.is_some_and(|integrity| state.integrity.as_deref() != Some(integrity)) {
+        debug!("{} holds {} rather than {}", dir.display(), state.version, version);
+        return false;
+    }
+    let damaged = state.files.iter().find(|(file, size)| {
+        std::fs::metadata(dir.join(file.as_str())).map_or(true, |metadata| metadata.len() != **size)
+    });
+    if let Some((file, _)) = damaged {
+        debug!("{} has changed in {}", file, dir.display());
+        return false;
+    }
+    true
+}
+
+// Sizes of the files of a package, by their path relative to it with forward
+// slashes; nested node_modules belong to other packages
+fn files_in(dir: &Path) -> Result<BTreeMap<String, u64>> {
+    let mut files = BTreeMap::new();
+    let walk = walkdir::WalkDir::new(dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != "node_modules" && entry.file_name() != STATE_FILE);
+    for entry in walk {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(dir)?.to_string_lossy().replace('\\', "/");
+        files.insert(relative, entry.metadata()?.len());
+    }
+    Ok(files)
+}
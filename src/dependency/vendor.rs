@@ -0,0 +1,45 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use super::Lockfile;
+
+/// Directory beside package.json `rjs vendor` checks packages into.
+pub const VENDOR_DIR: &str = "vendor";
+
+/// The lockfile entries of the vendored packages, in the vendor directory.
+pub const INDEX_FILE: &str = "rjs-vendor.json";
+
+/// The packages of a project's vendor/ directory: tarballs, or packages
+/// extracted into directories, named by `file_stem`.
+pub struct Vendor {
+    dir: PathBuf,
+    /// What each vendored package was locked as when it was vendored
+    pub lockfile: Lockfile,
+}
+
+impl Vendor {
+    /// The vendor directory of the project at `root`, if `rjs vendor` wrote one.
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        let dir = root.join(VENDOR_DIR);
+        let index = dir.join(INDEX_FILE);
+        let Ok(content) = std::fs::read_to_string(&index) else {
+            return Ok(None);
+        };
+        let lockfile = serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", index.display()))?;
+        Ok(Some(Self { dir, lockfile }))
+    }
+
+    /// Where name@version is vendored: its tarball or its directory.
+    pub fn path(&self, name: &str, version: &str) -> Option<PathBuf> {
+        let stem = file_stem(name, version);
+        [self.dir.join(format!("{}.tgz", stem)), self.dir.join(stem)]
+            .into_iter()
+            .find(|path| path.exists())
+    }
+}
+
+/// Name a package is vendored under, e.g. "lodash@4.17.21" or
+/// "@types+node@20.1.0", with ".tgz" for a tarball.
+pub fn file_stem(name: &str, version: &str) -> String {
+    format!("{}@{}", name.replacen('/', "+", 1), version)
+}
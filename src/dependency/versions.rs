@@ -0,0 +1,136 @@
+use dashmap::DashMap;
+use semver::{Version, VersionReq};
+use std::sync::{Arc, OnceLock};
+
+// Parsed versions and ranges keyed by their source string. The same few
+// thousand strings come up again and again while resolving and deduplicating
+// a large graph, so each one is parsed once per process. Failures are cached
+// too so invalid strings aren't re-parsed either.
+static VERSIONS: OnceLock<DashMap<String, Option<Arc<Version>>>> = OnceLock::new();
+static RANGES: OnceLock<DashMap<String, Option<Arc<Range>>>> = OnceLock::new();
+
+fn lookup<T>(
+    cache: &'static OnceLock<DashMap<String, Option<Arc<T>>>>,
+    input: &str,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> Option<Arc<T>> {
+    let cache = cache.get_or_init(DashMap::new);
+    if let Some(parsed) = cache.get(input) {
+        return parsed.clone();
+    }
+
+    let parsed = parse(input).map(Arc::new);
+    cache.insert(input.to_string(), parsed.clone());
+    parsed
+}
+
+/// Parse a version, reusing an earlier parse of the same string.
+pub fn parse_version(input: &str) -> Option<Arc<Version>> {
+    lookup(&VERSIONS, input, |s| Version::parse(s).ok())
+}
+
+/// Parse an npm version range, reusing an earlier parse of the same string.
+pub fn parse_req(input: &str) -> Option<Arc<Range>> {
+    lookup(&RANGES, input, Range::parse)
+}
+
+/// Parse an npm version range, treating anything unparseable as `*`.
+pub fn parse_req_or_any(input: &str) -> Arc<Range> {
+    parse_req(input).unwrap_or_else(|| Arc::new(Range::any()))
+}
+
+/// An npm version range: comparator sets joined by `||`, matching a version
+/// that satisfies any one of them. Within a set, comparators are separated by
+/// spaces (">=1.2 <2"), and "1.2.3 - 2.3" and x-ranges ("1.x", "*") work as
+/// in npm. A bare version is that exact release, not a caret range.
+#[derive(Debug)]
+pub struct Range {
+    sets: Vec<VersionReq>,
+}
+
+impl Range {
+    fn any() -> Self {
+        Self {
+            sets: vec![VersionReq::STAR],
+        }
+    }
+
+    fn parse(input: &str) -> Option<Self> {
+        let sets = input
+            .split("||")
+            .map(|set| VersionReq::parse(&comparators(set)?).ok())
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { sets })
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        self.sets.iter().any(|set| set.matches(version))
+    }
+}
+
+// One npm comparator set in the comma-separated syntax of `VersionReq`
+fn comparators(set: &str) -> Option<String> {
+    // Operators may be written apart from their version: ">= 1.2.3"
+    let mut tokens: Vec<String> = Vec::new();
+    let mut pending_op = String::new();
+    for token in set.split_whitespace() {
+        if token.chars().all(|c| "<>=~^".contains(c)) {
+            pending_op.push_str(token);
+        } else {
+            tokens.push(format!("{}{}", std::mem::take(&mut pending_op), token));
+        }
+    }
+    if !pending_op.is_empty() {
+        return None;
+    }
+
+    let mut comparators = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        // Hyphen ranges: "A - B" is ">=A <=B", where a partial B covers its
+        // whole minor or major version, as `VersionReq` reads "<=1.2" too
+        if tokens.get(i + 1).is_some_and(|token| token == "-") {
+            let upper = tokens.get(i + 2)?;
+            comparators.extend(comparator(">=", &tokens[i]));
+            comparators.extend(comparator("<=", upper));
+            i += 3;
+            continue;
+        }
+        let token = &tokens[i];
+        let version_start = token.find(|c: char| !"<>=~^".contains(c))?;
+        let (op, version) = token.split_at(version_start);
+        let op = match op {
+            "" | "=" => "=",
+            "~>" => "~",
+            "<" | "<=" | ">" | ">=" | "~" | "^" => op,
+            _ => return None,
+        };
+        comparators.extend(comparator(op, version));
+        i += 1;
+    }
+
+    if comparators.is_empty() {
+        return Some("*".to_string());
+    }
+    Some(comparators.join(", "))
+}
+
+// "op version" with a leading "v" dropped and wildcard parts trimmed, so
+// "1.2.x" is the partial version "1.2". None when the version is all wildcards,
+// which leaves nothing to compare against
+fn comparator(op: &str, version: &str) -> Option<String> {
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+    let (core, rest) = match version.find(['-', '+']) {
+        Some(at) => version.split_at(at),
+        None => (version, ""),
+    };
+    let parts: Vec<&str> = core
+        .split('.')
+        .take_while(|part| !matches!(*part, "x" | "X" | "*"))
+        .collect();
+    if parts.is_empty() {
+        return None;
+    }
+    let rest = if parts.len() < 3 { "" } else { rest };
+    Some(format!("{}{}{}", op, parts.join("."), rest))
+}
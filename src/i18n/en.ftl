@@ -0,0 +1,60 @@
+# Messages of the rjs CLI. Every other catalog falls back to this one for
+# the keys it lacks. `{ $name }` is filled in by the caller; a selector such
+# as `{ $count -> ... }` picks the variant for the number's plural category.
+
+error = Error: { $message }
+
+## install
+
+install-no-package-json = No package.json found. Run 'rjs init' first or specify packages to install.
+install-from-package-json = Installing dependencies from package.json
+install-packages = Installing packages: { $packages }
+install-workspace = Adding to workspace { $workspace }
+install-mode-frozen = Using { $mode } mode - exact versions from lockfile
+install-mode-offline = Using { $mode } mode - no registry access
+install-mode-lockfile-only = Using { $mode } mode - node_modules is left untouched
+install-target = Installing for { $host } - native builds are skipped
+install-clean = Removed node_modules for a clean install
+install-resuming = Resuming an interrupted install: { $count } packages already in place
+install-done = Done
+install-failed-short = Failed
+install-updated-package-json = Updated package.json
+install-installed = Installed { $count } packages in { $seconds }s
+install-locked = Locked { $count } packages in { $seconds }s
+install-failed = Failed to install packages: { $error }
+install-summary = added { $added }, removed { $removed }, changed { $changed } packages
+install-funding = { $count ->
+    [one] { $count } package is looking for funding
+   *[other] { $count } packages are looking for funding
+}
+install-funding-hint = run `rjs fund` for details
+install-no-dependencies = No dependencies found in package.json
+install-found-dependencies = Found { $count } dependencies in package.json
+install-regular-dependencies = { $count } regular dependencies
+install-dev-dependencies = { $count } development dependencies
+install-lockfile-updated = Updated rjs-lock.json in { $seconds }s!
+install-all-installed = All dependencies installed successfully in { $seconds }s!
+install-dependencies-failed = Failed to install dependencies: { $error }
+install-cancelling = Cancelling, waiting for in-flight packages to finish (Ctrl-C again to force)
+
+## init
+
+init-title = RJS - Initialize a new project
+init-overwrite = package.json already exists. Overwrite?
+init-prompt-name = package name
+init-prompt-version = version
+init-prompt-description = description
+init-prompt-entry = entry point
+init-prompt-test = test command
+init-prompt-repository = git repository
+init-prompt-author = author
+init-prompt-license = license
+init-created = Created { $file }
+init-exists = { $file } already exists, leaving it as is
+init-typescript-next = Run `rjs install` to install TypeScript, then `rjs run build`
+init-access-unscoped = --access only applies to scoped packages
+init-scope-registry = Using registry { $registry } for { $scope }
+init-npmrc-added = Added { $key } to .npmrc
+init-invalid-scope = Invalid scope "{ $scope }"; scopes look like @org
+init-no-license-template = No LICENSE template for "{ $license }"; rjs can write one for { $known }
+init-no-author = No author set; fill in the copyright holder in LICENSE
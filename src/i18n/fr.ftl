@@ -0,0 +1,77 @@
+# Messages de la CLI rjs en français. Les clés absentes sont prises dans
+# en.ftl.
+
+error = Erreur : { $message }
+
+## install
+
+install-no-package-json = Aucun package.json trouvé. Lancez d'abord « rjs init » ou indiquez les paquets à installer.
+install-from-package-json = Installation des dépendances de package.json
+install-packages = Installation des paquets : { $packages }
+install-workspace = Ajout à l'espace de travail { $workspace }
+install-mode-frozen = Mode { $mode } : versions exactes du lockfile
+install-mode-offline = Mode { $mode } : aucun accès au registre
+install-mode-lockfile-only = Mode { $mode } : node_modules n'est pas modifié
+install-target = Installation pour { $host } : les compilations natives sont ignorées
+install-clean = node_modules supprimé pour une installation propre
+install-resuming = Reprise d'une installation interrompue : { $count ->
+    [one] { $count } paquet déjà en place
+   *[other] { $count } paquets déjà en place
+}
+install-done = Terminé
+install-failed-short = Échec
+install-updated-package-json = package.json mis à jour
+install-installed = { $count ->
+    [one] { $count } paquet installé en { $seconds } s
+   *[other] { $count } paquets installés en { $seconds } s
+}
+install-locked = { $count ->
+    [one] { $count } paquet verrouillé en { $seconds } s
+   *[other] { $count } paquets verrouillés en { $seconds } s
+}
+install-failed = Échec de l'installation des paquets : { $error }
+install-summary = { $added } ajoutés, { $removed } supprimés, { $changed } modifiés
+install-funding = { $count ->
+    [one] { $count } paquet cherche un financement
+   *[other] { $count } paquets cherchent un financement
+}
+install-funding-hint = lancez `rjs fund` pour les détails
+install-no-dependencies = Aucune dépendance dans package.json
+install-found-dependencies = { $count ->
+    [one] { $count } dépendance trouvée dans package.json
+   *[other] { $count } dépendances trouvées dans package.json
+}
+install-regular-dependencies = { $count ->
+    [one] { $count } dépendance
+   *[other] { $count } dépendances
+}
+install-dev-dependencies = { $count ->
+    [one] { $count } dépendance de développement
+   *[other] { $count } dépendances de développement
+}
+install-lockfile-updated = rjs-lock.json mis à jour en { $seconds } s !
+install-all-installed = Toutes les dépendances ont été installées en { $seconds } s !
+install-dependencies-failed = Échec de l'installation des dépendances : { $error }
+install-cancelling = Annulation, en attente des paquets en cours (Ctrl-C à nouveau pour forcer)
+
+## init
+
+init-title = RJS - Initialiser un nouveau projet
+init-overwrite = package.json existe déjà. L'écraser ?
+init-prompt-name = nom du paquet
+init-prompt-version = version
+init-prompt-description = description
+init-prompt-entry = point d'entrée
+init-prompt-test = commande de test
+init-prompt-repository = dépôt git
+init-prompt-author = auteur
+init-prompt-license = licence
+init-created = { $file } créé
+init-exists = { $file } existe déjà, il est conservé
+init-typescript-next = Lancez « rjs install » pour installer TypeScript, puis « rjs run build »
+init-access-unscoped = --access ne s'applique qu'aux paquets avec un scope
+init-scope-registry = Registre { $registry } utilisé pour { $scope }
+init-npmrc-added = { $key } ajouté à .npmrc
+init-invalid-scope = Scope « { $scope } » invalide ; un scope s'écrit @org
+init-no-license-template = Pas de modèle de LICENSE pour « { $license } » ; rjs peut en écrire un pour { $known }
+init-no-author = Aucun auteur défini ; complétez le titulaire du copyright dans LICENSE
@@ -0,0 +1,195 @@
+use log::debug;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// The catalogs built into rjs, by language. English is complete and the
+// fallback for anything another catalog lacks
+const CATALOGS: [(&str, &str); 2] = [("en", include_str!("en.ftl")), ("fr", include_str!("fr.ftl"))];
+const FALLBACK: &str = "en";
+
+struct Catalog {
+    lang: &'static str,
+    messages: HashMap<&'static str, String>,
+    fallback: HashMap<&'static str, String>,
+}
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+/// Pick the language of messages: `lang` from --lang when given, otherwise
+/// RJS_LANG, LC_ALL, LC_MESSAGES or LANG. Languages without a catalog fall
+/// back to English.
+pub fn init(lang: Option<&str>) {
+    let requested = lang.map(str::to_string).or_else(detect).unwrap_or_default();
+    let code = language_code(&requested);
+    let (lang, source) = CATALOGS
+        .iter()
+        .find(|(lang, _)| *lang == code)
+        .copied()
+        .unwrap_or(CATALOGS[0]);
+    debug!("Using {} messages", lang);
+
+    let _ = CATALOG.set(Catalog {
+        lang,
+        messages: parse(source),
+        fallback: parse(CATALOGS[0].1),
+    });
+}
+
+/// The message `key` in the current language, with `args` filled in.
+/// Unknown keys come back as the key itself.
+pub fn message(key: &str, args: &[(&str, String)]) -> String {
+    let catalog = CATALOG.get_or_init(|| Catalog {
+        lang: FALLBACK,
+        messages: HashMap::new(),
+        fallback: parse(CATALOGS[0].1),
+    });
+    match catalog.messages.get(key).or_else(|| catalog.fallback.get(key)) {
+        Some(pattern) => format(pattern, args, catalog.lang),
+        None => key.to_string(),
+    }
+}
+
+/// A message from the catalog: `tr!("install-done")`, or with arguments
+/// `tr!("install-count", count = 3)`.
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::i18n::message($key, &[])
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::message($key, &[$((stringify!($name), $value.to_string())),+])
+    };
+}
+pub(crate) use tr;
+
+// The first locale variable set, as POSIX orders them
+fn detect() -> Option<String> {
+    ["RJS_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+}
+
+// "fr" for "fr_FR.UTF-8", "fr-CA" or "fr"; "C" and "POSIX" are English
+fn language_code(locale: &str) -> String {
+    let code = locale
+        .split(['_', '-', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    match code.as_str() {
+        "c" | "posix" | "" => FALLBACK.to_string(),
+        _ => code,
+    }
+}
+
+// A subset of Fluent: `key = text` messages, continued on indented lines
+// (and on the `}` closing a selector), and `#` comments
+fn parse(source: &'static str) -> HashMap<&'static str, String> {
+    let mut messages = HashMap::new();
+    let mut current: Option<(&'static str, String)> = None;
+    for line in source.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with([' ', '\t', '}', '[', '*']) {
+            if let Some((_, pattern)) = &mut current {
+                pattern.push('\n');
+                pattern.push_str(line.trim());
+            }
+            continue;
+        }
+        if let Some((key, pattern)) = current.take() {
+            messages.insert(key, pattern);
+        }
+        if let Some((key, pattern)) = line.split_once('=') {
+            current = Some((key.trim(), pattern.trim().to_string()));
+        }
+    }
+    if let Some((key, pattern)) = current {
+        messages.insert(key, pattern);
+    }
+    messages
+}
+
+// Fill in `{ $name }` placeables and pick the variant of selectors, which
+// list one variant per line:
+//   { $count ->
+//       [one] package
+//      *[other] packages
+//   }
+fn format(pattern: &str, args: &[(&str, String)], lang: &str) -> String {
+    let mut out = String::new();
+    let mut rest = pattern;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let Some(close) = matching_brace(&rest[open..]) else {
+            out.push_str(&rest[open..]);
+            return out;
+        };
+        out.push_str(&placeable(&rest[open + 1..open + close], args, lang));
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn placeable(inner: &str, args: &[(&str, String)], lang: &str) -> String {
+    let value = |name: &str| {
+        args.iter()
+            .find(|(arg, _)| *arg == name)
+            .map(|(_, value)| value.clone())
+    };
+
+    let Some((selector, variants)) = inner.split_once("->") else {
+        let name = inner.trim().trim_start_matches('$');
+        return value(name).unwrap_or_else(|| format!("{{${}}}", name));
+    };
+
+    let selected = value(selector.trim().trim_start_matches('$')).unwrap_or_default();
+    let category = plural_category(&selected, lang);
+    let mut chosen = None;
+    let mut default = None;
+    for line in variants.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let is_default = line.starts_with('*');
+        let Some((key, text)) = line.trim_start_matches('*').trim_start_matches('[').split_once(']') else {
+            continue;
+        };
+        if key == selected || Some(key) == category {
+            chosen.get_or_insert(text.trim());
+        }
+        if is_default {
+            default = Some(text.trim());
+        }
+    }
+    format(chosen.or(default).unwrap_or_default(), args, lang)
+}
+
+// The CLDR plural category of a number, for the languages rjs has catalogs
+// for: only 1 is singular in English, 0 and 1 are in French
+fn plural_category(value: &str, lang: &str) -> Option<&'static str> {
+    // Counts are often styled, e.g. bold
+    let number: f64 = console::strip_ansi_codes(value).parse().ok()?;
+    let one = match lang {
+        "fr" => number.abs() < 2.0,
+        _ => number == 1.0,
+    };
+    Some(if one { "one" } else { "other" })
+}
+
+// The offset of the `}` closing the `{` at the start of `text`
+fn matching_brace(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
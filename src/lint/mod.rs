@@ -0,0 +1,167 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// Extensions of files that can import packages
+const SOURCE_EXTENSIONS: [&str; 10] = ["js", "mjs", "cjs", "jsx", "ts", "mts", "cts", "tsx", "vue", "svelte"];
+
+// Node's built-in modules, importable without the "node:" prefix (node:test
+// and the like only exist with it)
+const BUILTINS: [&str; 44] = [
+    "assert", "async_hooks", "buffer", "child_process", "cluster", "console", "constants", "crypto",
+    "dgram", "diagnostics_channel", "dns", "domain", "events", "fs", "http", "http2", "https",
+    "inspector", "module", "net", "os", "path", "perf_hooks", "process", "punycode", "querystring",
+    "readline", "repl", "stream", "string_decoder", "sys", "timers", "tls", "trace_events", "tty",
+    "url", "util", "v8", "vm", "wasi", "worker_threads", "zlib",
+    "stream/promises", "fs/promises",
+];
+
+/// Where a package is imported.
+#[derive(Debug, Clone, Serialize)]
+pub struct Usage {
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+/// Every package imported by source files below `dir`, with where. Files
+/// ignored by git, hidden directories and node_modules are skipped.
+pub fn imported_packages(dir: &Path) -> Result<BTreeMap<String, Vec<Usage>>> {
+    let mut packages: BTreeMap<String, Vec<Usage>> = BTreeMap::new();
+    let walker = ignore::WalkBuilder::new(dir)
+        .filter_entry(|entry| entry.file_name() != "node_modules")
+        .build();
+
+    for entry in walker.flatten() {
+        let path = entry.path();
+        let is_source = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| SOURCE_EXTENSIONS.contains(&extension));
+        if !is_source || !path.is_file() {
+            continue;
+        }
+        // Minified bundles and binaries aren't worth failing over
+        let Ok(source) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        let file = path.strip_prefix(dir).unwrap_or(path).to_path_buf();
+        for (specifier, line) in specifiers(&source) {
+            if let Some(name) = package_name(&specifier) {
+                packages.entry(name.to_string()).or_default().push(Usage { file: file.clone(), line });
+            }
+        }
+    }
+    Ok(packages)
+}
+
+/// The package a module specifier refers to, e.g. "@scope/pkg" for
+/// "@scope/pkg/sub/path"; `None` for relative paths, URLs, built-ins and
+/// `#imports` aliases.
+pub fn package_name(specifier: &str) -> Option<&str> {
+    if specifier.is_empty()
+        || specifier.starts_with(['.', '/', '#', '~'])
+        || specifier.starts_with("node:")
+        || specifier.contains("://")
+        || BUILTINS.contains(&specifier)
+    {
+        return None;
+    }
+
+    let mut segments = specifier.splitn(3, '/');
+    let first = segments.next()?;
+    let name_len = if first.starts_with('@') {
+        // "@/components" is a common path alias, not a scope
+        let second = segments.next().filter(|second| first.len() > 1 && !second.is_empty())?;
+        first.len() + 1 + second.len()
+    } else {
+        first.len()
+    };
+    let name = &specifier[..name_len];
+    if BUILTINS.contains(&name) { None } else { Some(name) }
+}
+
+/// The specifiers of `require("x")`, `import ... from "x"`, `import "x"`,
+/// `import("x")` and `export ... from "x"` in `source`, with their line.
+/// Comments and other string literals are skipped; this is a scan for string
+/// literals after those keywords, not a parser.
+pub fn specifiers(source: &str) -> Vec<(String, usize)> {
+    let bytes = source.as_bytes();
+    let mut found = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => line += 1,
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    if bytes[i] == b'\n' {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+                i += 2;
+                continue;
+            }
+            // A string that doesn't follow one of the keywords, e.g. "data from 'db'"
+            quote @ (b'"' | b'\'' | b'`') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    match bytes[i] {
+                        b'\\' => i += 1,
+                        b'\n' => line += 1,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let keyword = ["require", "import", "from"]
+            .into_iter()
+            .find(|keyword| bytes[i..].starts_with(keyword.as_bytes()));
+        let at_word_start = i == 0 || !is_identifier(bytes[i - 1]);
+        if let Some(keyword) = keyword.filter(|_| at_word_start) {
+            let end = i + keyword.len();
+            if bytes.get(end).is_some_and(|byte| is_identifier(*byte)) {
+                i = end;
+                continue;
+            }
+            // Skip spaces and an opening parenthesis, then expect a quote
+            let mut j = end;
+            while j < bytes.len() && (bytes[j] == b' ' || bytes[j] == b'\t' || bytes[j] == b'(') {
+                j += 1;
+            }
+            if let Some(&quote) = bytes.get(j).filter(|byte| matches!(byte, b'"' | b'\'' | b'`'))
+                && let Some(length) = source[j + 1..].find(quote as char)
+            {
+                let specifier = &source[j + 1..j + 1 + length];
+                // Template literals with substitutions aren't static
+                if !specifier.contains(['\n', '$']) {
+                    found.push((specifier.to_string(), line));
+                }
+                i = j + 1 + length + 1;
+                continue;
+            }
+            i = end;
+            continue;
+        }
+        i += 1;
+    }
+    found
+}
+
+fn is_identifier(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$' || byte == b'.'
+}
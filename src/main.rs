@@ -1,12 +1,30 @@
 use anyhow::Result;
 use clap::Parser;
-use env_logger::Env;
+use env_logger::{Env, WriteStyle};
 use log::info;
 
+mod analyze;
+mod audit;
+mod cache;
 mod cli;
+mod config;
+#[cfg(unix)]
+mod daemon;
 mod dependency;
+mod i18n;
+mod lint;
+mod npmrc;
+mod pack;
+mod package_manager;
+mod policy;
+mod provenance;
+mod proxy;
 mod registry;
+mod scripts;
+mod timing;
+mod typosquat;
 mod utils;
+mod workspace;
 
 #[derive(Parser)]
 #[command(
@@ -18,19 +36,82 @@ mod utils;
 struct Cli {
     #[command(subcommand)]
     command: cli::Command,
+
+    /// Run in this project directory rather than the current one, e.g. from CI scripts
+    /// or editors. Relative paths given to the command stay relative to the current one
+    #[arg(long, alias = "prefix", short = 'C', global = true, value_name = "PATH")]
+    dir: Option<std::path::PathBuf>,
+
+    /// Record per-phase timings and write them to rjs-timing.json
+    #[arg(long, global = true)]
+    timing: bool,
+
+    /// When to color output (default: the color setting, or auto; auto honors NO_COLOR)
+    #[arg(long, global = true, value_enum)]
+    color: Option<utils::ColorMode>,
+
+    /// Plain ASCII output without symbols, emoji or progress bars, for logs and screen readers
+    #[arg(long, alias = "plain", global = true)]
+    no_emoji: bool,
+
+    /// Language of messages, e.g. "fr" (default: RJS_LANG, or the locale from LC_ALL, LC_MESSAGES or LANG).
+    /// --json output is the same in every language
+    #[arg(long, global = true)]
+    lang: Option<String>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logger
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
-
     // Parse command line arguments
     let cli = Cli::parse();
+    if cli.timing {
+        timing::enable();
+    }
+    // Commands get the project directory rather than reading the process's
+    // own, which stays where rjs was started
+    let root = match &cli.dir {
+        Some(dir) if dir.is_dir() => std::path::absolute(dir)?,
+        Some(dir) => anyhow::bail!("Can't use {} as the project directory", dir.display()),
+        None => std::env::current_dir()?,
+    };
+    config::init(&root)?;
+
+    // Flags win over the color and emoji settings
+    let settings = config::settings();
+    let color = cli.color.unwrap_or(settings.color);
+    color.apply();
+    utils::set_plain(cli.no_emoji || !settings.emoji);
+    i18n::init(cli.lang.as_deref());
+
+    // Initialize logger
+    let write_style = match color.forced() {
+        Some(true) => WriteStyle::Always,
+        Some(false) => WriteStyle::Never,
+        None => WriteStyle::Auto,
+    };
+    env_logger::Builder::from_env(Env::default().default_filter_or("info"))
+        .write_style(write_style)
+        .init();
 
     // Execute the command
     info!("RJS - Rust JavaScript Package Manager");
-    cli.command.execute().await?;
+    let result = cli.command.execute(&root).await;
+    cache::flush();
+
+    // Report counters for `rjs bench`, which runs rjs as a child process
+    if let Some(path) = std::env::var_os("RJS_STATS_FILE") {
+        let stats = serde_json::to_string(&registry::network_stats())?;
+        std::fs::write(path, stats)?;
+    }
+
+    // Written even when the command failed, that's often when it's wanted most
+    if cli.timing {
+        timing::write_report(&root)?;
+    }
 
+    if let Err(e) = result {
+        eprintln!("{}", i18n::tr!("error", message = format!("{:?}", e)));
+        std::process::exit(1);
+    }
     Ok(())
 }
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+
+/// Settings from npm's config files: the project's .npmrc, then the user's.
+#[derive(Debug, Default)]
+pub struct Npmrc {
+    project: Vec<(String, String)>,
+    user: Vec<(String, String)>,
+}
+
+impl Npmrc {
+    /// Read `dir`'s .npmrc and the user's (~/.npmrc, or $NPM_CONFIG_USERCONFIG).
+    /// Missing or unreadable files count as empty.
+    pub fn load(dir: &Path) -> Self {
+        Self {
+            project: read(&dir.join(".npmrc")),
+            user: user_path().map(|path| read(&path)).unwrap_or_default(),
+        }
+    }
+
+    /// A setting, the project's taking precedence over the user's.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.project(key).or_else(|| self.user(key))
+    }
+
+    /// A setting from the project's .npmrc only.
+    pub fn project(&self, key: &str) -> Option<&str> {
+        lookup(&self.project, key)
+    }
+
+    /// A setting from the user's .npmrc only.
+    pub fn user(&self, key: &str) -> Option<&str> {
+        lookup(&self.user, key)
+    }
+
+    /// The registry a scope ("@org") is mapped to with "@org:registry=...".
+    pub fn scope_registry(&self, scope: &str) -> Option<&str> {
+        self.get(&format!("{}:registry", scope))
+    }
+
+    /// Every "@org:registry=..." mapping, by scope, the project's taking
+    /// precedence over the user's.
+    pub fn scope_registries(&self) -> BTreeMap<&str, &str> {
+        self.user
+            .iter()
+            .chain(&self.project)
+            .filter_map(|(key, value)| Some((key.strip_suffix(":registry")?, value.as_str())))
+            .filter(|(scope, _)| scope.starts_with('@'))
+            .collect()
+    }
+
+    /// The registry `name`'s scope is mapped to, if it's scoped and mapped.
+    pub fn scope_registry_for(&self, name: &str) -> Option<&str> {
+        name.strip_prefix('@')
+            .and_then(|scoped| scoped.split_once('/'))
+            .and_then(|(scope, _)| self.scope_registry(&format!("@{}", scope)))
+    }
+
+    /// The registry `name` lives on: its scope's registry, or the configured one.
+    pub fn registry_for(&self, name: &str) -> String {
+        self.scope_registry_for(name)
+            .unwrap_or(&config::settings().registry)
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    /// The auth token for `registry_url`, from a "//host/path/:_authToken=..."
+    /// line for the registry or any path above it.
+    pub fn auth_token(&self, registry_url: &str) -> Option<&str> {
+        // npm keys credentials by the URL without its scheme
+        let mut prefix = registry_url
+            .split_once("://")
+            .map_or(registry_url, |(_, rest)| rest)
+            .trim_end_matches('/')
+            .to_string();
+        loop {
+            // An unset ${VAR} leaves an empty token, which is no token at all
+            if let Some(token) = self.get(&format!("//{}/:_authToken", prefix)).filter(|token| !token.is_empty()) {
+                return Some(token);
+            }
+            match prefix.rsplit_once('/') {
+                Some((parent, _)) => prefix = parent.to_string(),
+                None => return None,
+            }
+        }
+    }
+}
+
+/// The user's npm config file.
+pub fn user_path() -> Option<PathBuf> {
+    match std::env::var_os("NPM_CONFIG_USERCONFIG") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => dirs::home_dir().map(|home| home.join(".npmrc")),
+    }
+}
+
+// The last setting of `key` wins, as in npm
+fn lookup<'a>(settings: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    settings
+        .iter()
+        .rev()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.as_str())
+}
+
+// "key=value" lines, skipping comments, with ${VAR} expanded
+fn read(path: &Path) -> Vec<(String, String)> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#') && !line.starts_with(';'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim().trim_matches('"');
+            (key.trim().to_string(), expand_env(value))
+        })
+        .collect()
+}
+
+// Replace ${VAR} with the environment variable's value; unset ones become empty
+fn expand_env(value: &str) -> String {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        let name = &rest[start + 2..start + end];
+        expanded.push_str(&std::env::var(name).unwrap_or_default());
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
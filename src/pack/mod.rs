@@ -0,0 +1,336 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use log::debug;
+use sha2::{Digest, Sha512};
+use std::path::{Path, PathBuf};
+
+use crate::dependency::shrinkwrap::SHRINKWRAP_FILE;
+
+// Never packed, whatever `files` or the ignore files say
+const ALWAYS_EXCLUDED: &[&str] = &[
+    ".git",
+    ".svn",
+    ".hg",
+    "CVS",
+    "node_modules",
+    ".npmrc",
+    ".npmignore",
+    ".gitignore",
+    ".DS_Store",
+    "npm-debug.log",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "rjs-lock.json",
+    "config.gypi",
+    ".lock-wscript",
+];
+
+// The mtime npm gives every tarball entry, so packing is reproducible
+const PACKED_MTIME: u64 = 499162500;
+
+/// A file to be packed, by its path relative to the package root.
+#[derive(Debug, Clone)]
+pub struct PackedFile {
+    /// Path inside the package, '/'-separated.
+    pub path: String,
+    pub size: u64,
+    executable: bool,
+}
+
+/// The files a package would be published with.
+#[derive(Debug)]
+pub struct PackList {
+    pub dir: PathBuf,
+    pub name: String,
+    pub version: String,
+    /// The parsed package.json.
+    pub manifest: serde_json::Value,
+    /// Sorted by path.
+    pub files: Vec<PackedFile>,
+    // package.json as packed, when it isn't the file on disk
+    manifest_text: Option<String>,
+}
+
+/// A packed tarball and the digests the registry knows it by.
+#[derive(Debug)]
+pub struct Tarball {
+    pub data: Vec<u8>,
+    /// Hex SHA-1, npm's legacy `shasum`.
+    pub shasum: String,
+    /// Subresource Integrity string ("sha512-<base64>").
+    pub integrity: String,
+}
+
+impl PackList {
+    /// Pick the files of the package in `dir`: those matched by the `files`
+    /// allowlist if package.json has one, otherwise everything not excluded by
+    /// .npmignore (or .gitignore where a directory has no .npmignore).
+    /// package.json, npm-shrinkwrap.json, README, LICENSE and the `main` file
+    /// are always included.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let (manifest, name, version) = read_manifest(dir)?;
+
+        let allowlist = match manifest.get("files").and_then(|files| files.as_array()) {
+            Some(files) => Some(allowlist(dir, files)?),
+            None => None,
+        };
+        let main = manifest
+            .get("main")
+            .and_then(|main| main.as_str())
+            .map(|main| main.trim_start_matches("./").to_string());
+
+        let mut files = Vec::new();
+        let mut walker = Walker {
+            root: dir,
+            allowlist: allowlist.as_ref(),
+            main: main.as_deref(),
+            ignores: Vec::new(),
+            files: &mut files,
+        };
+        walker.walk(dir)?;
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut list = Self {
+            dir: dir.to_path_buf(),
+            name,
+            version,
+            manifest,
+            files,
+            manifest_text: None,
+        };
+        // Not the tarball of an earlier pack
+        let own_tarball = list.file_name();
+        list.files.retain(|file| file.path != own_tarball);
+        Ok(list)
+    }
+
+    /// Pack `manifest` as package.json instead of the file on disk, e.g. with
+    /// its `workspace:` ranges replaced by versions.
+    pub fn set_manifest(&mut self, manifest: serde_json::Value) -> Result<()> {
+        let text = format!("{}\n", serde_json::to_string_pretty(&manifest)?);
+        if let Some(file) = self.files.iter_mut().find(|file| file.path == "package.json") {
+            file.size = text.len() as u64;
+        }
+        self.manifest = manifest;
+        self.manifest_text = Some(text);
+        Ok(())
+    }
+
+    /// Total size of the files before compression.
+    pub fn unpacked_size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+
+    /// npm's tarball name: "name-1.0.0.tgz", "scope-name-1.0.0.tgz" when scoped.
+    pub fn file_name(&self) -> String {
+        let name = self.name.trim_start_matches('@').replace('/', "-");
+        format!("{}-{}.tgz", name, self.version)
+    }
+
+    /// Build the gzipped tarball, every file under "package/" like npm's.
+    /// Entries are sorted and timestamped identically, so the same files
+    /// always produce the same tarball.
+    pub fn tarball(&self) -> Result<Tarball> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::best()));
+        for file in &self.files {
+            let source = self.dir.join(&file.path);
+            let content = match &self.manifest_text {
+                Some(text) if file.path == "package.json" => text.clone().into_bytes(),
+                _ => std::fs::read(&source).with_context(|| format!("Failed to read {}", source.display()))?,
+            };
+
+            let mut header = tar::Header::new_ustar();
+            header.set_size(content.len() as u64);
+            header.set_mode(if file.executable { 0o755 } else { 0o644 });
+            header.set_mtime(PACKED_MTIME);
+            header.set_entry_type(tar::EntryType::Regular);
+            builder
+                .append_data(&mut header, format!("package/{}", file.path), content.as_slice())
+                .with_context(|| format!("Failed to pack {}", file.path))?;
+        }
+        let data = builder
+            .into_inner()
+            .and_then(|encoder| encoder.finish())
+            .context("Failed to write the tarball")?;
+
+        let shasum = sha1_smol::Sha1::from(&data).digest().to_string();
+        let integrity = format!(
+            "sha512-{}",
+            base64::engine::general_purpose::STANDARD.encode(Sha512::digest(&data))
+        );
+        Ok(Tarball { data, shasum, integrity })
+    }
+}
+
+/// The name and version of the package in `dir`.
+pub fn package_id(dir: &Path) -> Result<(String, String)> {
+    let (_, name, version) = read_manifest(dir)?;
+    Ok((name, version))
+}
+
+// package.json, which must at least name and version the package
+fn read_manifest(dir: &Path) -> Result<(serde_json::Value, String, String)> {
+    let path = dir.join("package.json");
+    let manifest: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?,
+    )
+    .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    let field = |key: &str| {
+        manifest
+            .get(key)
+            .and_then(|value| value.as_str())
+            .map(str::to_string)
+            .with_context(|| format!("package.json has no \"{}\"", key))
+    };
+    let (name, version) = (field("name")?, field("version")?);
+    Ok((manifest, name, version))
+}
+
+// The `files` patterns, anchored at the package root like npm's
+fn allowlist(dir: &Path, files: &[serde_json::Value]) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    for pattern in files.iter().filter_map(|file| file.as_str()) {
+        let (negated, pattern) = match pattern.strip_prefix('!') {
+            Some(pattern) => ("!", pattern),
+            None => ("", pattern),
+        };
+        let pattern = pattern.trim_start_matches("./").trim_start_matches('/');
+        builder
+            .add_line(None, &format!("{}/{}", negated, pattern))
+            .with_context(|| format!("Invalid pattern \"{}\" in \"files\"", pattern))?;
+    }
+    builder.build().context("Invalid \"files\" in package.json")
+}
+
+struct Walker<'a> {
+    root: &'a Path,
+    allowlist: Option<&'a Gitignore>,
+    main: Option<&'a str>,
+    // Ignore rules of the directories being walked, outermost first
+    ignores: Vec<Gitignore>,
+    files: &'a mut Vec<PackedFile>,
+}
+
+impl Walker<'_> {
+    fn walk(&mut self, dir: &Path) -> Result<()> {
+        // The root's ignore file gives way to an explicit `files` allowlist
+        let pushed = match ignore_rules(dir) {
+            Some(rules) if !(dir == self.root && self.allowlist.is_some()) => {
+                self.ignores.push(rules);
+                true
+            }
+            _ => false,
+        };
+
+        let mut entries: Vec<_> = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read {}", dir.display()))?
+            .filter_map(|entry| entry.ok())
+            .collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if ALWAYS_EXCLUDED.contains(&name.as_str())
+                || name.ends_with(".orig")
+                || name.starts_with("._")
+                || (name.starts_with('.') && name.ends_with(".swp"))
+            {
+                continue;
+            }
+            let relative = path
+                .strip_prefix(self.root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            if file_type.is_dir() {
+                if !self.ignored(&path, true) {
+                    self.walk(&path)?;
+                }
+            } else if file_type.is_file() && self.included(&path, &relative) {
+                let metadata = entry.metadata()?;
+                self.files.push(PackedFile {
+                    path: relative,
+                    size: metadata.len(),
+                    executable: is_executable(&metadata),
+                });
+            } else {
+                debug!("Not packing {}", relative);
+            }
+        }
+
+        if pushed {
+            self.ignores.pop();
+        }
+        Ok(())
+    }
+
+    fn included(&self, path: &Path, relative: &str) -> bool {
+        if self.always_included(relative) {
+            return true;
+        }
+        if let Some(allowlist) = self.allowlist
+            && !allowlist.matched_path_or_any_parents(path, false).is_ignore()
+        {
+            return false;
+        }
+        !self.ignored(path, false)
+    }
+
+    // The innermost rule that matches decides, so nested files can re-include
+    fn ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for rules in self.ignores.iter().rev() {
+            let matched = rules.matched(path, is_dir);
+            if matched.is_ignore() {
+                return true;
+            }
+            if matched.is_whitelist() {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn always_included(&self, relative: &str) -> bool {
+        if self.main == Some(relative) {
+            return true;
+        }
+        if relative.contains('/') {
+            return false;
+        }
+        let stem = relative.split('.').next().unwrap_or(relative).to_ascii_uppercase();
+        relative == "package.json" || relative == SHRINKWRAP_FILE || matches!(stem.as_str(), "README" | "LICENSE" | "LICENCE")
+    }
+}
+
+// A directory's .npmignore, or its .gitignore when it has none
+fn ignore_rules(dir: &Path) -> Option<Gitignore> {
+    let file = [".npmignore", ".gitignore"]
+        .into_iter()
+        .map(|name| dir.join(name))
+        .find(|file| file.is_file())?;
+
+    let mut builder = GitignoreBuilder::new(dir);
+    if let Some(e) = builder.add(&file) {
+        debug!("Skipping invalid ignore rules in {}: {}", file.display(), e);
+    }
+    builder.build().ok()
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use console::style;
+use std::path::Path;
+
+use crate::config;
+use crate::dependency::versions;
+use crate::utils::icon;
+
+/// A package.json "packageManager" field, e.g. "pnpm@9.1.0+sha512.abc".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageManager {
+    pub name: String,
+    pub version: String,
+}
+
+impl PackageManager {
+    /// Parse "name@version", dropping corepack's "+sha512.<hash>" suffix.
+    pub fn parse(field: &str) -> Option<Self> {
+        let (name, version) = field.trim().rsplit_once('@')?;
+        let version = version.split('+').next().unwrap_or(version);
+        if name.is_empty() || version.is_empty() {
+            return None;
+        }
+        Some(Self {
+            name: name.to_string(),
+            version: version.to_string(),
+        })
+    }
+}
+
+/// The raw "packageManager" field of the package.json in `dir`, if it has one.
+pub fn declared(dir: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(dir.join("package.json")).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+    json.get("packageManager")?.as_str().map(str::to_string)
+}
+
+/// Check the project in `dir` is meant to be managed by this rjs: warn when
+/// its "packageManager" names another manager or another rjs version, or
+/// fail with `package-manager-strict` set, the way corepack refuses.
+pub fn check(dir: &Path) -> Result<()> {
+    let Some(field) = declared(dir) else {
+        return Ok(());
+    };
+    let current = env!("CARGO_PKG_VERSION");
+
+    let message = match PackageManager::parse(&field) {
+        None => format!("packageManager \"{}\" in package.json should look like \"rjs@{}\"", field, current),
+        Some(manager) if manager.name != "rjs" => format!(
+            "This project is set up for {}@{} (packageManager in package.json), not rjs",
+            manager.name, manager.version
+        ),
+        // A range ("^1.2") is accepted too, though corepack wants an exact version
+        Some(manager) => {
+            let wanted = versions::parse_req(&manager.version);
+            let running = versions::parse_version(current);
+            match (wanted, running) {
+                (Some(wanted), Some(running)) if wanted.matches(&running) => return Ok(()),
+                (None, _) => format!("packageManager \"{}\" in package.json isn't a valid rjs version", field),
+                _ => format!(
+                    "This project wants rjs@{} (packageManager in package.json), but this is rjs {}",
+                    manager.version, current
+                ),
+            }
+        }
+    };
+
+    if config::settings().package_manager_strict {
+        anyhow::bail!("{} (package-manager-strict is set; turn it off to only warn)", message);
+    }
+    eprintln!("{} {}", style(format!("{} Warning:", icon("⚠"))).bold().yellow(), message);
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::dependency::versions;
+
+/// File a project's install policy is read from, next to package.json.
+pub const POLICY_FILE: &str = "rjs-policy.toml";
+
+/// Project install policy, loaded from `rjs-policy.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Policy {
+    /// How long a version must have been published before it's picked, e.g. "7d".
+    pub minimum_release_age: Option<String>,
+    pub licenses: LicensePolicy,
+    pub packages: PackagePolicy,
+    // minimum_release_age, parsed when loading
+    #[serde(skip)]
+    release_age: Option<Duration>,
+}
+
+/// Which licenses packages in the tree may be under.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LicensePolicy {
+    /// SPDX identifiers that are acceptable. Empty means any license that isn't denied.
+    pub allow: Vec<String>,
+    /// SPDX identifiers that fail the install.
+    pub deny: Vec<String>,
+    /// What to do with licenses that are neither allowed nor denied, or missing.
+    pub unlisted: Action,
+    /// Packages ("name" or "name@version") approved whatever their license.
+    pub exceptions: Vec<String>,
+}
+
+/// Which packages may appear anywhere in the tree.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PackagePolicy {
+    /// Banned packages: "name" bans every version, "name@range" only matching ones.
+    pub deny: Vec<String>,
+    /// When not empty, scoped packages must belong to one of these scopes.
+    pub allowed_scopes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    #[default]
+    Warn,
+    Fail,
+}
+
+/// A package that doesn't meet the policy.
+#[derive(Debug, Clone)]
+pub struct Violation {
+    pub package: String,
+    pub reason: String,
+    pub action: Action,
+}
+
+// How a license expression fares against the policy, from worst to best
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Verdict {
+    Denied,
+    Unlisted,
+    Allowed,
+}
+
+impl Policy {
+    /// The project's policy, if it has a policy file.
+    pub fn load(project_dir: &Path) -> Result<Option<Self>> {
+        let path = project_dir.join(POLICY_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut policy: Self = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        if let Some(age) = &policy.minimum_release_age {
+            let age = humantime::parse_duration(age).with_context(|| {
+                format!("Invalid minimum-release-age \"{}\" in {}", age, path.display())
+            })?;
+            policy.release_age = Some(age);
+        }
+        Ok(Some(policy))
+    }
+
+    /// Versions published after this time are too new to be picked, if the
+    /// policy sets a minimum release age.
+    pub fn release_cutoff(&self) -> Option<SystemTime> {
+        SystemTime::now().checked_sub(self.release_age?)
+    }
+
+    /// Check a resolved package against the banned packages and allowed scopes.
+    pub fn check_package(&self, name: &str, version: &str) -> Option<Violation> {
+        let policy = &self.packages;
+        let violation = |reason: String| Violation {
+            package: format!("{}@{}", name, version),
+            reason,
+            action: Action::Fail,
+        };
+
+        if let Some(entry) = policy.deny.iter().find(|entry| bans(entry, name, version)) {
+            return Some(violation(format!("banned by \"{}\"", entry)));
+        }
+
+        if let Some((scope, _)) = name.split_once('/')
+            && !policy.allowed_scopes.is_empty()
+            && !policy
+                .allowed_scopes
+                .iter()
+                .any(|allowed| allowed.trim_start_matches('@') == scope.trim_start_matches('@'))
+        {
+            return Some(violation(format!("scope {} is not allowed", scope)));
+        }
+
+        None
+    }
+
+    /// Check a resolved package's license against the policy.
+    pub fn check_license(&self, name: &str, version: &str, license: Option<&str>) -> Option<Violation> {
+        let policy = &self.licenses;
+        let id = format!("{}@{}", name, version);
+        if policy.exceptions.iter().any(|exception| exception == name || *exception == id) {
+            return None;
+        }
+
+        let (verdict, reason) = match license {
+            Some(license) => (
+                policy.evaluate(license),
+                format!("license {} is not allowed", license),
+            ),
+            None => (Verdict::Unlisted, "no license declared".to_string()),
+        };
+
+        let action = match verdict {
+            Verdict::Allowed => return None,
+            Verdict::Denied => Action::Fail,
+            Verdict::Unlisted => policy.unlisted,
+        };
+
+        Some(Violation {
+            package: id,
+            reason,
+            action,
+        })
+    }
+}
+
+// Whether a deny entry ("name" or "name@range") matches a package version
+fn bans(entry: &str, name: &str, version: &str) -> bool {
+    // The scope's leading "@" isn't a version separator
+    let (banned, range) = match entry.rsplit_once('@') {
+        Some((banned, range)) if !banned.is_empty() => (banned, Some(range)),
+        _ => (entry, None),
+    };
+    if banned != name {
+        return false;
+    }
+
+    match range {
+        None => true,
+        // A bare version bans exactly that release, not a caret range
+        Some(range) if versions::parse_version(range).is_some() => range == version,
+        Some(range) => match (versions::parse_req(range), versions::parse_version(version)) {
+            (Some(range), Some(version)) => range.matches(&version),
+            // Be strict with entries we can't evaluate
+            _ => range == version,
+        },
+    }
+}
+
+impl LicensePolicy {
+    fn identifier(&self, id: &str) -> Verdict {
+        let listed = |list: &[String]| list.iter().any(|entry| entry.eq_ignore_ascii_case(id));
+        if listed(&self.deny) {
+            Verdict::Denied
+        } else if self.allow.is_empty() || listed(&self.allow) {
+            Verdict::Allowed
+        } else {
+            Verdict::Unlisted
+        }
+    }
+
+    // Evaluate an SPDX expression: an OR is as good as its best choice, an AND
+    // as bad as its worst part. "X WITH exception" is judged as X
+    fn evaluate(&self, expression: &str) -> Verdict {
+        let spaced = expression.replace('(', " ( ").replace(')', " ) ");
+        let tokens: Vec<&str> = spaced.split_whitespace().collect();
+        let mut pos = 0;
+        let verdict = self.parse_or(&tokens, &mut pos);
+        // Trailing garbage means we didn't understand the expression
+        if pos == tokens.len() { verdict } else { Verdict::Unlisted }
+    }
+
+    fn parse_or(&self, tokens: &[&str], pos: &mut usize) -> Verdict {
+        let mut verdict = self.parse_and(tokens, pos);
+        while tokens.get(*pos).is_some_and(|token| token.eq_ignore_ascii_case("OR")) {
+            *pos += 1;
+            verdict = verdict.max(self.parse_and(tokens, pos));
+        }
+        verdict
+    }
+
+    fn parse_and(&self, tokens: &[&str], pos: &mut usize) -> Verdict {
+        let mut verdict = self.parse_term(tokens, pos);
+        while tokens.get(*pos).is_some_and(|token| token.eq_ignore_ascii_case("AND")) {
+            *pos += 1;
+            verdict = verdict.min(self.parse_term(tokens, pos));
+        }
+        verdict
+    }
+
+    fn parse_term(&self, tokens: &[&str], pos: &mut usize) -> Verdict {
+        match tokens.get(*pos) {
+            Some(&"(") => {
+                *pos += 1;
+                let verdict = self.parse_or(tokens, pos);
+                if tokens.get(*pos) == Some(&")") {
+                    *pos += 1;
+                }
+                verdict
+            }
+            Some(id) => {
+                *pos += 1;
+                if tokens.get(*pos).is_some_and(|token| token.eq_ignore_ascii_case("WITH")) {
+                    *pos += 2;
+                }
+                self.identifier(id)
+            }
+            None => Verdict::Unlisted,
+        }
+    }
+}
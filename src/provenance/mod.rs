@@ -0,0 +1,117 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde_json::Value;
+
+use crate::registry::{Attestation, DistInfo, NpmRegistry};
+
+// Predicate types of SLSA provenance, "v0.2" and "v1" so far
+const SLSA_PROVENANCE: &str = "https://slsa.dev/provenance/";
+
+/// Where a version was built, according to its SLSA provenance attestation.
+///
+/// The registry verifies attestation signatures when a version is published;
+/// rjs checks the attestation describes the version's tarball but doesn't
+/// verify the Sigstore signatures itself.
+#[derive(Debug, Clone, Default)]
+pub struct Provenance {
+    pub repository: Option<String>,
+    pub workflow: Option<String>,
+    pub git_ref: Option<String>,
+    pub commit: Option<String>,
+    pub builder: Option<String>,
+    pub run: Option<String>,
+    /// Whether the attestation's subject is the tarball named by `dist.integrity`
+    pub subject_matches: bool,
+}
+
+/// The build provenance of a version, if it was published with any.
+pub async fn fetch(registry: &NpmRegistry, dist: &DistInfo) -> Result<Option<Provenance>> {
+    let Some(attestations) = &dist.attestations else {
+        return Ok(None);
+    };
+    if attestations.provenance.is_none() {
+        return Ok(None);
+    }
+
+    let bundles = registry.attestations(&attestations.url).await?;
+    bundles
+        .iter()
+        .find(|attestation| attestation.predicate_type.starts_with(SLSA_PROVENANCE))
+        .map(|attestation| parse(attestation, dist.integrity.as_deref()))
+        .transpose()
+}
+
+fn parse(attestation: &Attestation, integrity: Option<&str>) -> Result<Provenance> {
+    let payload = attestation
+        .bundle
+        .pointer("/dsseEnvelope/payload")
+        .and_then(Value::as_str)
+        .context("Provenance attestation has no DSSE payload")?;
+    let payload = base64::engine::general_purpose::STANDARD
+        .decode(payload)
+        .context("Provenance payload is not valid base64")?;
+    let statement: Value =
+        serde_json::from_slice(&payload).context("Failed to parse provenance statement")?;
+
+    let predicate = &statement["predicate"];
+    let text = |pointer: &str| {
+        predicate
+            .pointer(pointer)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+
+    let mut provenance = if let Some(workflow) =
+        predicate.pointer("/buildDefinition/externalParameters/workflow")
+    {
+        // SLSA v1, what npm publishes today
+        let field = |name: &str| workflow.get(name).and_then(Value::as_str).map(str::to_string);
+        Provenance {
+            repository: field("repository"),
+            workflow: field("path"),
+            git_ref: field("ref"),
+            commit: text("/buildDefinition/resolvedDependencies/0/digest/gitCommit"),
+            builder: text("/runDetails/builder/id"),
+            run: text("/runDetails/metadata/invocationId"),
+            ..Default::default()
+        }
+    } else {
+        // SLSA v0.2: the source is "git+<repository>@<ref>"
+        let source = text("/invocation/configSource/uri");
+        let source = source.as_deref().map(|uri| uri.trim_start_matches("git+"));
+        let (repository, git_ref) = match source.and_then(|uri| uri.rsplit_once('@')) {
+            Some((repository, git_ref)) => (Some(repository.to_string()), Some(git_ref.to_string())),
+            None => (source.map(str::to_string), None),
+        };
+        Provenance {
+            repository,
+            workflow: text("/invocation/configSource/entryPoint"),
+            git_ref,
+            commit: text("/invocation/configSource/digest/sha1"),
+            builder: text("/builder/id"),
+            run: text("/metadata/buildInvocationId"),
+            ..Default::default()
+        }
+    };
+
+    provenance.subject_matches = match integrity.and_then(sha512_hex) {
+        Some(digest) => statement["subject"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|subject| subject.pointer("/digest/sha512").and_then(Value::as_str))
+            .any(|subject| subject.eq_ignore_ascii_case(&digest)),
+        None => false,
+    };
+
+    Ok(provenance)
+}
+
+// Hex sha512 digest of a Subresource Integrity string, if it has one
+fn sha512_hex(integrity: &str) -> Option<String> {
+    let encoded = integrity
+        .split_whitespace()
+        .find_map(|candidate| candidate.strip_prefix("sha512-"))?;
+    let digest = base64::engine::general_purpose::STANDARD.decode(encoded).ok()?;
+    Some(hex::encode(digest))
+}
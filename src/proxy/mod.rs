@@ -0,0 +1,284 @@
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{CONTENT_TYPE, HOST};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use log::{debug, info, warn};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+
+use crate::cache::TarballStore;
+use crate::dependency::events::NoopEvents;
+use crate::registry::{NotFound, NpmRegistry, PackageInfo};
+use crate::utils;
+
+// Packuments are revalidated against the upstream at most this often; tarball
+// requests for the same package in between reuse the copy in memory
+const METADATA_TTL: Duration = Duration::from_secs(60);
+
+// Shared by all proxy connections
+struct ProxyState {
+    registry: NpmRegistry,
+    // Answers from the metadata cache alone while the upstream is unreachable
+    fallback: NpmRegistry,
+    store: TarballStore,
+    // Base URL clients reach us on, when it isn't the Host they send
+    public_url: Option<String>,
+    packuments: DashMap<String, (Instant, Arc<PackageInfo>)>,
+}
+
+impl ProxyState {
+    async fn package_info(&self, name: &str) -> Result<Arc<PackageInfo>> {
+        if let Some(entry) = self.packuments.get(name)
+            && entry.0.elapsed() < METADATA_TTL
+        {
+            return Ok(Arc::clone(&entry.1));
+        }
+
+        let info = match self.registry.get_package_info(name).await {
+            Ok(info) => info,
+            Err(e) => match self.fallback.get_package_info(name).await {
+                Ok(info) => {
+                    warn!("Upstream lookup for {} failed, serving cached metadata: {:#}", name, e);
+                    info
+                }
+                Err(_) => return Err(e),
+            },
+        };
+
+        let info = Arc::new(info);
+        self.packuments
+            .insert(name.to_string(), (Instant::now(), Arc::clone(&info)));
+        Ok(info)
+    }
+
+    fn base_url(&self, req: &Request<Incoming>, local: SocketAddr) -> String {
+        if let Some(url) = &self.public_url {
+            return url.trim_end_matches('/').to_string();
+        }
+        let host = req
+            .headers()
+            .get(HOST)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+            .unwrap_or_else(|| local.to_string());
+        format!("http://{}", host)
+    }
+}
+
+/// Bind the proxy's listening socket.
+pub async fn bind(addr: SocketAddr) -> Result<TcpListener> {
+    TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to listen on {}", addr))
+}
+
+/// Serve the npm registry protocol on `listener` until Ctrl-C, fetching
+/// packuments and tarballs from `upstream` through the rjs cache and store.
+pub async fn serve(listener: TcpListener, upstream: &str, public_url: Option<String>) -> Result<()> {
+    let local = listener.local_addr()?;
+    let registry = NpmRegistry::with_registry(upstream).without_daemon();
+    let state = Arc::new(ProxyState {
+        fallback: registry.clone().offline(),
+        registry,
+        store: TarballStore::open()?,
+        public_url,
+        packuments: DashMap::new(),
+    });
+    info!("Registry proxy listening on {} for {}", local, upstream);
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, peer) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("Failed to accept proxy connection: {}", e);
+                        continue;
+                    }
+                };
+                let state = Arc::clone(&state);
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| handle(req, Arc::clone(&state), local));
+                    if let Err(e) = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        debug!("Proxy connection from {} ended: {}", peer, e);
+                    }
+                });
+            }
+            _ = tokio::signal::ctrl_c() => {
+                info!("Registry proxy shutting down");
+                return Ok(());
+            }
+        }
+    }
+}
+
+async fn handle(
+    req: Request<Incoming>,
+    state: Arc<ProxyState>,
+    local: SocketAddr,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let start = Instant::now();
+    let response = route(&req, &state, local).await;
+    info!(
+        "{} {} {} in {:?}",
+        req.method(),
+        req.uri().path(),
+        response.status().as_u16(),
+        start.elapsed()
+    );
+    Ok(response)
+}
+
+async fn route(req: &Request<Incoming>, state: &ProxyState, local: SocketAddr) -> Response<Full<Bytes>> {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return error(StatusCode::METHOD_NOT_ALLOWED, "only GET and HEAD are supported");
+    }
+
+    // Scoped names arrive as "@scope%2fname"
+    let path = req.uri().path().trim_start_matches('/').replace("%2f", "/").replace("%2F", "/");
+
+    if path == "-/ping" {
+        return json_response(StatusCode::OK, &json!({}));
+    }
+
+    match path.split_once("/-/") {
+        Some((name, file)) if is_package_name(name) => tarball(state, name, file).await,
+        None if is_package_name(&path) => packument(state, &path, &state.base_url(req, local)).await,
+        _ => error(StatusCode::NOT_FOUND, "not found"),
+    }
+}
+
+async fn packument(state: &ProxyState, name: &str, base_url: &str) -> Response<Full<Bytes>> {
+    match state.package_info(name).await {
+        Ok(info) => json_response(StatusCode::OK, &render_packument(&info, base_url)),
+        Err(e) => upstream_error(e),
+    }
+}
+
+async fn tarball(state: &ProxyState, name: &str, file: &str) -> Response<Full<Bytes>> {
+    let info = match state.package_info(name).await {
+        Ok(info) => info,
+        Err(e) => return upstream_error(e),
+    };
+
+    let Some(dist) = info
+        .versions
+        .values()
+        .map(|version| &version.dist)
+        .find(|dist| tarball_file(&dist.tarball) == file)
+    else {
+        // The upstream's packument doesn't list a tarball it handed out
+        return error(StatusCode::BAD_GATEWAY, &format!("{} has no tarball {}", name, file));
+    };
+
+    let result = async {
+        let (path, _) = state
+            .store
+            .fetch(&state.registry, &dist.tarball, dist.integrity.as_deref(), name, &NoopEvents)
+            .await?;
+        utils::read_file(&path).await
+    }
+    .await;
+
+    match result {
+        Ok(data) => Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .body(Full::new(Bytes::from(data)))
+            .expect("valid response"),
+        Err(e) => error(StatusCode::BAD_GATEWAY, &format!("{:#}", e)),
+    }
+}
+
+// The packument in the registry's own format, with tarballs pointing back at us
+fn render_packument(info: &PackageInfo, base_url: &str) -> Value {
+    let versions: serde_json::Map<String, Value> = info
+        .versions
+        .iter()
+        .map(|(version, details)| {
+            let mut dist = json!({
+                "shasum": details.dist.shasum,
+                "tarball": format!("{}/{}/-/{}", base_url, info.name, tarball_file(&details.dist.tarball)),
+            });
+            if let Some(integrity) = &details.dist.integrity {
+                dist["integrity"] = json!(integrity);
+            }
+
+            let mut manifest = json!({
+                "name": info.name,
+                "version": details.version,
+                "dependencies": details.dependencies,
+                "devDependencies": details.dev_dependencies,
+                "dist": dist,
+            });
+            if let Some(license) = &details.license {
+                manifest["license"] = json!(license);
+            }
+            if !details.optional_dependencies.is_empty() {
+                manifest["optionalDependencies"] = json!(details.optional_dependencies);
+            }
+            // os, cpu and libc, each left out when it allows any platform
+            if let (Value::Object(platforms), Some(fields)) = (json!(details.platforms), manifest.as_object_mut()) {
+                fields.extend(platforms);
+            }
+            if let Some(deprecated) = &details.deprecated {
+                manifest["deprecated"] = json!(deprecated);
+            }
+            (version.clone(), manifest)
+        })
+        .collect();
+
+    json!({
+        "name": info.name,
+        "dist-tags": info.dist_tags,
+        "versions": versions,
+        "time": info.time,
+    })
+}
+
+// Last path segment of a tarball URL, e.g. "lodash-4.17.21.tgz"
+fn tarball_file(url: &str) -> &str {
+    url.rsplit('/').next().unwrap_or(url)
+}
+
+// "name" or "@scope/name"
+fn is_package_name(name: &str) -> bool {
+    match name.strip_prefix('@') {
+        Some(scoped) => matches!(scoped.split_once('/'), Some((scope, rest)) if !scope.is_empty() && !rest.is_empty() && !rest.contains('/')),
+        None => !name.is_empty() && !name.contains('/') && !name.starts_with('.'),
+    }
+}
+
+fn json_response(status: StatusCode, body: &Value) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body.to_string())))
+        .expect("valid response")
+}
+
+// A package the upstream doesn't have is a 404, as it answered; anything else
+// is the upstream failing us
+fn upstream_error(e: anyhow::Error) -> Response<Full<Bytes>> {
+    let status = match e.downcast_ref::<NotFound>() {
+        Some(_) => StatusCode::NOT_FOUND,
+        None => StatusCode::BAD_GATEWAY,
+    };
+    error(status, &format!("{:#}", e))
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+    json_response(status, &json!({ "error": message }))
+}
@@ -0,0 +1,108 @@
+use log::{debug, warn};
+use reqwest::{Client, RequestBuilder, Response};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+// A host's first request over QUIC waits this long for a response before
+// falling back; networks that drop UDP otherwise stall until the idle timeout
+const FIRST_RESPONSE_TIMEOUT: Duration = Duration::from_secs(3);
+
+// The HTTP/3-only client, when this build has the `http3` feature and the
+// http3 setting turns it on
+static CLIENT: OnceLock<Option<Client>> = OnceLock::new();
+
+// Whether each host contacted over QUIC answered (true) or couldn't be
+// reached (false) this run; unreachable hosts get HTTP/2 from then on
+static HOSTS: Mutex<Option<HashMap<String, bool>>> = Mutex::new(None);
+
+fn client() -> Option<&'static Client> {
+    CLIENT
+        .get_or_init(|| {
+            let settings = crate::config::settings();
+            if !settings.http3 {
+                return None;
+            }
+            if !cfg!(feature = "http3") {
+                warn!("http3 is set, but this rjs was built without the http3 feature; using HTTP/2");
+                return None;
+            }
+            // QUIC can't be tunneled through an HTTP proxy
+            let proxied = settings.https_proxy.is_some()
+                || ["HTTPS_PROXY", "https_proxy"].iter().any(|name| std::env::var_os(name).is_some());
+            if proxied {
+                debug!("Not using HTTP/3 through a proxy");
+                return None;
+            }
+            build()
+        })
+        .as_ref()
+}
+
+#[cfg(feature = "http3")]
+fn build() -> Option<Client> {
+    match Client::builder().http3_prior_knowledge().timeout(Duration::from_secs(30)).build() {
+        Ok(client) => Some(client),
+        Err(e) => {
+            warn!("Failed to set up HTTP/3, using HTTP/2: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "http3"))]
+fn build() -> Option<Client> {
+    None
+}
+
+fn host_state(host: &str) -> Option<bool> {
+    HOSTS.lock().unwrap_or_else(|e| e.into_inner()).as_ref()?.get(host).copied()
+}
+
+fn set_host_state(host: &str, reachable: bool) {
+    HOSTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .insert(host.to_string(), reachable);
+}
+
+/// Send `request` over HTTP/3 when that's on, falling back to the request's
+/// own client (HTTP/2, or HTTP/1.1) for hosts QUIC can't reach. Plain-HTTP
+/// URLs and requests whose body can't be sent twice always use the latter.
+pub(super) async fn send(request: RequestBuilder) -> reqwest::Result<Response> {
+    let Some(http3) = client() else {
+        return request.send().await;
+    };
+    let (fallback, request) = request.build_split();
+    let request = request?;
+    let host = request.url().host_str().unwrap_or_default().to_string();
+    let retry = match request.try_clone() {
+        Some(retry) if request.url().scheme() == "https" && host_state(&host) != Some(false) => retry,
+        _ => return fallback.execute(request).await,
+    };
+
+    let sent = http3.execute(request);
+    let result = match host_state(&host) {
+        Some(true) => sent.await,
+        _ => match tokio::time::timeout(FIRST_RESPONSE_TIMEOUT, sent).await {
+            Ok(result) => result,
+            Err(_) => {
+                debug!("No HTTP/3 response from {} in {:?}", host, FIRST_RESPONSE_TIMEOUT);
+                set_host_state(&host, false);
+                return fallback.execute(retry).await;
+            }
+        },
+    };
+    match result {
+        Err(e) if e.is_connect() || e.is_timeout() || e.is_request() => {
+            debug!("HTTP/3 to {} failed, falling back to HTTP/2: {}", host, e);
+            set_host_state(&host, false);
+            fallback.execute(retry).await
+        }
+        result => {
+            set_host_state(&host, true);
+            result
+        }
+    }
+}
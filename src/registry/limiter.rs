@@ -0,0 +1,154 @@
+use log::debug;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+// Requests in flight to a host before anything is known about it, and the
+// bounds the limit moves between
+const INITIAL_LIMIT: f64 = 32.0;
+const MIN_LIMIT: f64 = 2.0;
+const MAX_LIMIT: f64 = 256.0;
+
+// A host answering this many times slower than its fastest answer so far is
+// queueing requests, once answers take at least SLOW_LATENCY
+const SLOW_FACTOR: u32 = 4;
+const SLOW_LATENCY: Duration = Duration::from_millis(250);
+
+// The requests in flight when a host pushes back all hear about it; the
+// limit is lowered once for all of them
+const BACKOFF_WINDOW: Duration = Duration::from_secs(1);
+
+/// What a response says about how busy its host is.
+#[derive(Debug, Clone, Copy)]
+pub enum Signal {
+    /// Headers arrived after this long
+    Responded(Duration),
+    /// 429, 503 or a timeout: the host wants fewer requests
+    Overloaded,
+}
+
+/// Per-host limits on requests in flight, adjusted the way TCP adjusts its
+/// window: raised by one for every limit's worth of timely responses, halved
+/// when a host answers 429 or 503, and trimmed when its answers slow down.
+#[derive(Default)]
+pub struct RateLimiter {
+    hosts: Mutex<HashMap<String, Arc<Host>>>,
+}
+
+struct Host {
+    name: String,
+    state: Mutex<State>,
+    released: Notify,
+}
+
+struct State {
+    limit: f64,
+    in_flight: usize,
+    fastest: Option<Duration>,
+    last_backoff: Option<Instant>,
+}
+
+/// A request slot on a host, given back when dropped.
+pub struct Permit {
+    host: Arc<Host>,
+}
+
+/// The limiter every registry client of the process shares, so clients for
+/// the same host don't each get a full allowance.
+pub fn shared() -> Arc<RateLimiter> {
+    static SHARED: OnceLock<Arc<RateLimiter>> = OnceLock::new();
+    Arc::clone(SHARED.get_or_init(Arc::default))
+}
+
+impl RateLimiter {
+    /// Wait for a slot on the host `url` points to.
+    pub async fn acquire(&self, url: &str) -> Permit {
+        let host = self.host(url);
+        loop {
+            let released = host.released.notified();
+            {
+                let mut state = host.state.lock().unwrap_or_else(|e| e.into_inner());
+                if (state.in_flight as f64) < state.limit.floor() {
+                    state.in_flight += 1;
+                    // The limit may have grown by more than the slot that woke us
+                    if (state.in_flight as f64) < state.limit.floor() {
+                        host.released.notify_one();
+                    }
+                    return Permit { host: Arc::clone(&host) };
+                }
+            }
+            released.await;
+        }
+    }
+
+    /// The current limit of every host requests went to, for diagnostics.
+    pub fn limits(&self) -> Vec<(String, usize)> {
+        let hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let mut limits: Vec<(String, usize)> = hosts
+            .values()
+            .map(|host| (host.name.clone(), host.state.lock().unwrap_or_else(|e| e.into_inner()).limit as usize))
+            .collect();
+        limits.sort();
+        limits
+    }
+
+    fn host(&self, url: &str) -> Arc<Host> {
+        let name = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| Some(format!("{}:{}", url.host_str()?, url.port_or_known_default()?)))
+            .unwrap_or_default();
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(hosts.entry(name.clone()).or_insert_with(|| {
+            Arc::new(Host {
+                name,
+                state: Mutex::new(State {
+                    limit: INITIAL_LIMIT,
+                    in_flight: 0,
+                    fastest: None,
+                    last_backoff: None,
+                }),
+                released: Notify::new(),
+            })
+        }))
+    }
+}
+
+impl Permit {
+    /// Adjust the host's limit to how a request sent with this permit went.
+    pub fn record(&self, signal: Signal) {
+        let mut state = self.host.state.lock().unwrap_or_else(|e| e.into_inner());
+        match signal {
+            Signal::Overloaded => state.back_off(&self.host.name, 0.5),
+            Signal::Responded(latency) => {
+                let fastest = state.fastest.map_or(latency, |fastest| fastest.min(latency));
+                state.fastest = Some(fastest);
+                if latency >= SLOW_LATENCY && latency > fastest * SLOW_FACTOR {
+                    state.back_off(&self.host.name, 0.9);
+                } else {
+                    state.limit = (state.limit + 1.0 / state.limit).min(MAX_LIMIT);
+                }
+            }
+        }
+    }
+}
+
+impl State {
+    fn back_off(&mut self, host: &str, factor: f64) {
+        if self.last_backoff.is_some_and(|at| at.elapsed() < BACKOFF_WINDOW) {
+            return;
+        }
+        self.limit = (self.limit * factor).max(MIN_LIMIT);
+        self.last_backoff = Some(Instant::now());
+        debug!("Lowered the request limit for {} to {}", host, self.limit as usize);
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut state = self.host.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.in_flight -= 1;
+        drop(state);
+        self.host.released.notify_one();
+    }
+}
@@ -2,38 +2,372 @@ use anyhow::{Context, Result};
 use futures::StreamExt;
 use log::debug;
 use reqwest::Client;
-use serde::Deserialize;
-use std::collections::HashMap;
-use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
-use tokio::sync::Semaphore;
-use std::sync::Arc;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::{Semaphore, SemaphorePermit};
 
-const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org";
+use crate::timing::{self, Phase};
+use std::sync::atomic::{AtomicU64, Ordering};
 
+use crate::cache::{CacheOutcome, CachedPackument, MetadataCache};
+use crate::dependency::platform::Platforms;
+use crate::npmrc::Npmrc;
+
+mod http3;
+mod limiter;
+mod write;
+
+use limiter::{Permit, RateLimiter, Signal};
+
+/// The public npm registry, used unless configured otherwise.
+pub const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org";
+
+// The public registry's download counts live on a separate host; other
+// registries that keep counts serve them under /-/downloads
+const DOWNLOADS_API: &str = "https://api.npmjs.org/downloads";
+
+// Process-wide network counters, shared by every registry client
+static HTTP_REQUESTS: AtomicU64 = AtomicU64::new(0);
+static BYTES_DOWNLOADED: AtomicU64 = AtomicU64::new(0);
+static RETRIES: AtomicU64 = AtomicU64::new(0);
+static CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+static THROTTLED: AtomicU64 = AtomicU64::new(0);
+static DNS_LOOKUPS: AtomicU64 = AtomicU64::new(0);
+static PREWARMED: AtomicU64 = AtomicU64::new(0);
+
+// Addresses of every host looked up so far. Registries sit behind a handful of
+// hosts, so each is resolved once per run rather than once per connection;
+// connections opened together wait on the same lookup
+type HostAddrs = Arc<tokio::sync::OnceCell<Vec<SocketAddr>>>;
+static DNS_CACHE: OnceLock<Mutex<HashMap<String, HostAddrs>>> = OnceLock::new();
+
+// How long connecting to one host may take, across all its addresses, before
+// the request fails (and is retried) rather than using up its whole timeout
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The slowest registry calls so far, slowest first
+static SLOWEST_CALLS: Mutex<Vec<SlowCall>> = Mutex::new(Vec::new());
+const SLOWEST_KEPT: usize = 5;
+
+// Attempts after the first for requests that failed transiently
+const MAX_RETRIES: u32 = 2;
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+
+// Read-ahead for packuments parsed as they stream in
+const PACKUMENT_BUFFER: usize = 64 * 1024;
+
+// How often a download reports its progress, in bytes
+const PROGRESS_STEP: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct NetworkStats {
+    pub requests: u64,
+    pub bytes_downloaded: u64,
+    /// Requests sent again after a connection failure, timeout, 429 or 5xx
+    #[serde(default)]
+    pub retries: u64,
+    /// Connections opened; every other request reused a pooled one
+    #[serde(default)]
+    pub connections: u64,
+    /// 429 and 503 responses, each asking rjs to slow down
+    #[serde(default)]
+    pub throttled: u64,
+    /// Hosts actually looked up in DNS; other connections used the cached address
+    #[serde(default)]
+    pub dns_lookups: u64,
+    /// Connections opened ahead of time by `NpmRegistry::prewarm`
+    #[serde(default)]
+    pub prewarmed: u64,
+}
+
+// Snapshot of the network counters for this process
+pub fn network_stats() -> NetworkStats {
+    NetworkStats {
+        requests: HTTP_REQUESTS.load(Ordering::Relaxed),
+        bytes_downloaded: BYTES_DOWNLOADED.load(Ordering::Relaxed),
+        retries: RETRIES.load(Ordering::Relaxed),
+        connections: CONNECTIONS.load(Ordering::Relaxed),
+        throttled: THROTTLED.load(Ordering::Relaxed),
+        dns_lookups: DNS_LOOKUPS.load(Ordering::Relaxed),
+        prewarmed: PREWARMED.load(Ordering::Relaxed),
+    }
+}
+
+/// A registry call and how long it took, body included.
 #[derive(Debug, Clone)]
+pub struct SlowCall {
+    pub url: String,
+    pub duration: Duration,
+}
+
+/// The slowest registry calls of this process, slowest first.
+pub fn slowest_calls() -> Vec<SlowCall> {
+    SLOWEST_CALLS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn record_call(url: &str, duration: Duration) {
+    let mut calls = SLOWEST_CALLS.lock().unwrap_or_else(|e| e.into_inner());
+    if calls.len() == SLOWEST_KEPT && calls.last().is_some_and(|slowest| slowest.duration >= duration) {
+        return;
+    }
+    calls.push(SlowCall { url: url.to_string(), duration });
+    calls.sort_by_key(|call| std::cmp::Reverse(call.duration));
+    calls.truncate(SLOWEST_KEPT);
+}
+
+/// The request limit of every host contacted so far, lowered from its
+/// starting point for hosts that pushed back or slowed down.
+pub fn host_limits() -> Vec<(String, usize)> {
+    limiter::shared().limits()
+}
+
+// Send a request that's safe to repeat, retrying connection failures,
+// timeouts, 429s and 5xx responses with a growing delay. How each attempt
+// went adjusts the request limit of the host `permit` is for
+async fn send_with_retries(permit: &Permit, mut request: reqwest::RequestBuilder) -> reqwest::Result<reqwest::Response> {
+    let mut attempt = 0;
+    loop {
+        let next = request.try_clone();
+        let sent = Instant::now();
+        let result = http3::send(request).await;
+        HTTP_REQUESTS.fetch_add(1, Ordering::Relaxed);
+
+        let overloaded = match &result {
+            Ok(response) => matches!(
+                response.status(),
+                reqwest::StatusCode::TOO_MANY_REQUESTS | reqwest::StatusCode::SERVICE_UNAVAILABLE
+            ),
+            Err(e) => e.is_timeout(),
+        };
+        if overloaded {
+            THROTTLED.fetch_add(u64::from(result.is_ok()), Ordering::Relaxed);
+            permit.record(Signal::Overloaded);
+        } else if result.is_ok() {
+            permit.record(Signal::Responded(sent.elapsed()));
+        }
+
+        let transient = match &result {
+            Ok(response) => {
+                response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS || response.status().is_server_error()
+            }
+            Err(e) => e.is_connect() || e.is_timeout(),
+        };
+        match next {
+            Some(next) if transient && attempt < MAX_RETRIES => {
+                attempt += 1;
+                RETRIES.fetch_add(1, Ordering::Relaxed);
+                debug!("Retrying request (attempt {} of {})", attempt + 1, MAX_RETRIES + 1);
+                tokio::time::sleep(RETRY_DELAY * 2u32.pow(attempt - 1)).await;
+                request = next;
+            }
+            _ => return result,
+        }
+    }
+}
+
+/// Which IP versions connections to registries use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkFamily {
+    /// Both: addresses of the family the host lists first are tried, and
+    /// after 300ms those of the other family race them
+    #[default]
+    Auto,
+    Ipv4,
+    Ipv6,
+}
+
+impl NetworkFamily {
+    fn allows(self, addr: &SocketAddr) -> bool {
+        match self {
+            NetworkFamily::Auto => true,
+            NetworkFamily::Ipv4 => addr.is_ipv4(),
+            NetworkFamily::Ipv6 => addr.is_ipv6(),
+        }
+    }
+}
+
+// Resolves hosts like the default resolver, but from DNS_CACHE after the
+// first lookup, and counts connections: each new one asks for its host's
+// addresses once (hosts given as IP addresses aren't looked up, nor counted).
+// Failed lookups aren't cached, so the next connection tries again. Only
+// addresses of the network-family setting's IP version are handed out; with
+// both, hyper's connector does the happy-eyeballs racing between them
+struct CachingResolver;
+
+impl reqwest::dns::Resolve for CachingResolver {
+    fn resolve(&self, name: hyper014::client::connect::dns::Name) -> reqwest::dns::Resolving {
+        CONNECTIONS.fetch_add(1, Ordering::Relaxed);
+        let host = name.as_str().to_string();
+        let cached = DNS_CACHE
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(host.clone())
+            .or_default()
+            .clone();
+        Box::pin(async move {
+            let addrs = cached
+                .get_or_try_init(|| async {
+                    DNS_LOOKUPS.fetch_add(1, Ordering::Relaxed);
+                    let addrs = tokio::net::lookup_host(format!("{}:0", host)).await?;
+                    Ok::<_, std::io::Error>(addrs.collect::<Vec<_>>())
+                })
+                .await?;
+            let family = crate::config::settings().network_family;
+            let addrs: Vec<SocketAddr> = addrs.iter().filter(|addr| family.allows(addr)).copied().collect();
+            if addrs.is_empty() {
+                let message = format!("{} has no {:?} address (network-family)", host, family);
+                return Err(std::io::Error::new(std::io::ErrorKind::AddrNotAvailable, message).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct VersionInfo {
     pub version: String,
     pub dependencies: HashMap<String, String>,
     pub dev_dependencies: HashMap<String, String>,
     pub dist: DistInfo,
+    // SPDX license expression, if the package declares one
+    #[serde(default)]
+    pub license: Option<String>,
+    // Account that published this version
+    #[serde(default)]
+    pub publisher: Option<Person>,
+    // Why the version shouldn't be used anymore, if it was deprecated
+    #[serde(default)]
+    pub deprecated: Option<String>,
+    // Installed when they support the platform, skipped otherwise
+    #[serde(default)]
+    pub optional_dependencies: HashMap<String, String>,
+    // The os/cpu/libc the version is built for
+    #[serde(default)]
+    pub platforms: Platforms,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct DistInfo {
     pub shasum: String,
     pub tarball: String,
+    // Subresource Integrity string, e.g. "sha512-<base64>"
+    #[serde(default)]
+    pub integrity: Option<String>,
+    // The registry's ECDSA signatures over "name@version:integrity"
+    #[serde(default)]
+    pub signatures: Vec<RegistrySignature>,
+    // Legacy PGP signature from before registry signing keys
+    #[serde(default, rename = "npm-signature")]
+    pub npm_signature: Option<String>,
+    // Sigstore attestations published with the version, such as build provenance
+    #[serde(default)]
+    pub attestations: Option<AttestationsInfo>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrySignature {
+    pub keyid: String,
+    pub sig: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttestationsInfo {
+    pub url: String,
+    #[serde(default)]
+    pub provenance: Option<ProvenanceInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvenanceInfo {
+    #[serde(rename = "predicateType")]
+    pub predicate_type: String,
+}
+
+/// A maintainer or publisher account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Person {
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// A Sigstore bundle from the registry's attestations endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Attestation {
+    #[serde(rename = "predicateType")]
+    pub predicate_type: String,
+    pub bundle: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct AttestationsResponse {
+    attestations: Vec<Attestation>,
+}
+
+/// A package found by the registry's search API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchResult {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    objects: Vec<SearchObject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchObject {
+    package: SearchResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct DownloadsResponse {
+    downloads: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct PackageInfo {
     pub name: String,
     pub versions: HashMap<String, VersionInfo>,
     pub dist_tags: HashMap<String, String>,
+    // Version to its RFC 3339 publish time
+    #[serde(default)]
+    pub time: HashMap<String, String>,
+    // Accounts allowed to publish the package
+    #[serde(default)]
+    pub maintainers: Vec<Person>,
+}
+
+impl PackageInfo {
+    /// When `version` was published, if the registry says.
+    pub fn published(&self, version: &str) -> Option<SystemTime> {
+        self.time.get(version).and_then(|time| humantime::parse_rfc3339_weak(time).ok())
+    }
+}
+
+/// A security advisory as returned by the registry's bulk advisory endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Advisory {
+    pub id: u64,
+    pub title: String,
+    pub severity: String,
+    pub vulnerable_versions: String,
+    #[serde(default)]
+    pub url: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -44,6 +378,20 @@ struct NpmPackageVersion {
     #[serde(rename = "devDependencies")]
     dev_dependencies: Option<HashMap<String, String>>,
     dist: DistInfo,
+    // A string, or an object with a "type" in older packages
+    license: Option<serde_json::Value>,
+    // Deprecated list form, e.g. [{"type": "MIT"}, {"type": "Apache-2.0"}]
+    licenses: Option<Vec<serde_json::Value>>,
+    #[serde(rename = "_npmUser")]
+    npm_user: Option<serde_json::Value>,
+    // The deprecation message; `npm deprecate pkg ""` leaves an empty string
+    deprecated: Option<serde_json::Value>,
+    #[serde(rename = "optionalDependencies")]
+    optional_dependencies: Option<HashMap<String, String>>,
+    // Each a string or a list of strings
+    os: Option<serde_json::Value>,
+    cpu: Option<serde_json::Value>,
+    libc: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -53,58 +401,282 @@ struct NpmPackageResponse {
     versions: HashMap<String, NpmPackageVersion>,
     #[serde(rename = "dist-tags")]
     dist_tags: HashMap<String, String>,
+    // Publish times, plus "created" and "modified"
+    #[serde(default)]
+    time: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    maintainers: Vec<serde_json::Value>,
 }
 
+// A semaphore along with how many permits it started with
+#[derive(Clone)]
+struct Slots {
+    limit: usize,
+    semaphore: Arc<Semaphore>,
+}
+
+impl Slots {
+    fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            limit,
+            semaphore: Arc::new(Semaphore::new(limit)),
+        }
+    }
+}
+
+/// The registry answered that a package doesn't exist, as opposed to failing
+/// to answer.
+#[derive(Debug)]
+pub struct NotFound(String);
+
+impl std::fmt::Display for NotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NotFound {}
+
 #[allow(dead_code)]
 #[derive(Clone)]
 pub struct NpmRegistry {
     client: Client,
     registry_url: String,
-    rate_limiter: Arc<Semaphore>,
+    // Per-host limits on requests in flight, shared by every client
+    rate_limiter: Arc<RateLimiter>,
+    // Tarballs downloading and extracting at once; metadata requests are
+    // bounded by the resolver instead
+    downloads: Slots,
+    extractions: Slots,
+    // Socket of a running `rjs daemon` to fetch metadata through, if any
+    daemon_socket: Option<PathBuf>,
+    // Parsed packuments from previous runs, revalidated by ETag
+    metadata_cache: Option<Arc<MetadataCache>>,
+    // Serve metadata from the cache only and never touch the network
+    offline: bool,
+    // Bearer token for private packages and anything that changes the registry
+    auth_token: Option<String>,
+    // One-time password for accounts with two-factor auth, kept once entered
+    otp: Arc<std::sync::Mutex<Option<String>>>,
+    // Scope registries and per-registry tokens for reads and downloads
+    npmrc: Option<Arc<Npmrc>>,
 }
 
 impl NpmRegistry {
+    /// A client for the configured registry.
     #[allow(dead_code)]
     pub fn new() -> Self {
-        Self::with_registry(DEFAULT_REGISTRY)
+        Self::with_registry(&crate::config::settings().registry)
+    }
+
+    /// A client for `registry_url` when one is given, e.g. with --registry,
+    /// or else for the configured registry.
+    pub fn for_registry(registry_url: Option<&str>) -> Self {
+        match registry_url {
+            Some(registry_url) => Self::with_registry(registry_url.trim_end_matches('/')),
+            None => Self::new(),
+        }
     }
 
     pub fn with_registry(registry_url: &str) -> Self {
         // Create a client with connection pooling and http2
-        let client = Client::builder()
+        let mut builder = Client::builder()
             .timeout(Duration::from_secs(30))
+            .connect_timeout(CONNECT_TIMEOUT)
             .pool_idle_timeout(Duration::from_secs(30))
             .tcp_keepalive(Some(Duration::from_secs(60)))
             .http2_keep_alive_interval(Some(Duration::from_secs(20)))
             .http2_keep_alive_timeout(Duration::from_secs(20))
-            .build()
-            .expect("Failed to create HTTP client");
+            .dns_resolver(Arc::new(CachingResolver));
+        for proxy in configured_proxies() {
+            builder = builder.proxy(proxy);
+        }
+        let client = builder.build().expect("Failed to create HTTP client");
+        let settings = crate::config::settings();
 
         Self {
             client,
             registry_url: registry_url.to_string(),
-            // Allow up to 100 concurrent HTTP requests
-            rate_limiter: Arc::new(Semaphore::new(100)),
+            rate_limiter: limiter::shared(),
+            downloads: Slots::new(settings.network_concurrency),
+            extractions: Slots::new(settings.extract_concurrency),
+            daemon_socket: running_daemon_socket(),
+            metadata_cache: MetadataCache::shared(),
+            offline: false,
+            auth_token: None,
+            otp: Arc::default(),
+            npmrc: None,
+        }
+    }
+
+    /// Download at most `concurrency` tarballs at once.
+    pub fn with_network_concurrency(mut self, concurrency: usize) -> Self {
+        self.downloads = Slots::new(concurrency);
+        self
+    }
+
+    /// Extract at most `concurrency` tarballs at once.
+    pub fn with_extract_concurrency(mut self, concurrency: usize) -> Self {
+        self.extractions = Slots::new(concurrency);
+        self
+    }
+
+    /// Open up to `connections` connections to the registry in the
+    /// background, so the DNS lookup and TLS handshakes are over by the time
+    /// resolution's burst of requests needs them. Each is a HEAD of the
+    /// registry's root, whatever it answers.
+    pub fn prewarm(&self, connections: usize) {
+        if self.offline {
+            return;
+        }
+        for _ in 0..connections {
+            let request = self.client.head(&self.registry_url);
+            tokio::spawn(async move {
+                if request.send().await.is_ok() {
+                    PREWARMED.fetch_add(1, Ordering::Relaxed);
+                }
+            });
         }
     }
 
+    /// How many packages can usefully be installing at once: downloading
+    /// or extracting.
+    pub fn install_concurrency(&self) -> usize {
+        self.downloads.limit + self.extractions.limit
+    }
+
+    /// Wait until fewer than the extract concurrency tarballs are being
+    /// extracted; extract while holding the permit.
+    pub async fn extraction_slot(&self) -> Result<SemaphorePermit<'_>> {
+        Ok(self.extractions.semaphore.acquire().await?)
+    }
+
+    /// Authenticate requests with `token`, e.g. from .npmrc's `_authToken`.
+    pub fn with_auth_token(mut self, token: Option<String>) -> Self {
+        self.auth_token = token;
+        self
+    }
+
+    /// Send `otp` with changes for accounts with two-factor auth. Without one,
+    /// rjs asks for it when the registry requires it.
+    pub fn with_otp(self, otp: Option<String>) -> Self {
+        *self.otp.lock().unwrap_or_else(|e| e.into_inner()) = otp;
+        self
+    }
+
+    /// Fetch scoped packages from their scope's registry and authenticate
+    /// reads and downloads with the token .npmrc has for each registry.
+    pub fn with_npmrc(mut self, npmrc: Npmrc) -> Self {
+        self.npmrc = Some(Arc::new(npmrc));
+        self
+    }
+
+    /// The registry `name` is fetched from: its scope's, when .npmrc maps
+    /// one, or else this client's.
+    pub fn registry_url_for(&self, name: &str) -> String {
+        self.npmrc
+            .as_ref()
+            .and_then(|npmrc| npmrc.scope_registry_for(name))
+            .map_or(self.registry_url.as_str(), |url| url.trim_end_matches('/'))
+            .to_string()
+    }
+
+    // The token to send to `url`: the one this client was given for its own
+    // registry, or whatever .npmrc has for the URL
+    fn read_token(&self, url: &str) -> Option<&str> {
+        self.auth_token
+            .as_deref()
+            .filter(|_| url.strip_prefix(&self.registry_url).is_some_and(|rest| rest.is_empty() || rest.starts_with('/')))
+            .or_else(|| self.npmrc.as_ref()?.auth_token(url))
+    }
+
+    // Always talk to the registry directly, used by the daemon itself
+    pub fn without_daemon(mut self) -> Self {
+        self.daemon_socket = None;
+        self
+    }
+
+    // Only use cached metadata and stored tarballs, e.g. after `rjs bundle import`
+    pub fn offline(mut self) -> Self {
+        self.offline = true;
+        self.daemon_socket = None;
+        self
+    }
+
     #[allow(dead_code)]
     pub async fn get_package_info(&self, package_name: &str) -> Result<PackageInfo> {
+        self.get_package_info_from(&self.registry_url_for(package_name), package_name).await
+    }
+
+    /// Fetch package metadata from a given registry rather than the
+    /// configured one, e.g. the registry a lockfile entry was resolved from
+    pub async fn get_package_info_from(&self, registry_url: &str, package_name: &str) -> Result<PackageInfo> {
+        let _span = timing::span(Phase::MetadataFetch, package_name);
+
+        if self.offline {
+            return self
+                .metadata_cache
+                .as_ref()
+                .and_then(|cache| cache.get(registry_url, package_name))
+                .map(|entry| entry.info)
+                .ok_or_else(|| anyhow::anyhow!("{} is not in the metadata cache (offline mode)", package_name));
+        }
+
+        // Prefer the daemon's warm cache, asking for the registry and token
+        // this process uses. Anything the daemon can't answer is fetched
+        // directly, as its network setup may not be ours
+        let token = self.read_token(registry_url);
+        #[cfg(unix)]
+        if let Some(socket) = &self.daemon_socket {
+            match crate::daemon::fetch_package_info(socket, registry_url, token, package_name).await {
+                Ok(info) => return Ok(info),
+                Err(e) => debug!("Daemon lookup for {} failed, fetching directly: {:#}", package_name, e),
+            }
+        }
+
         let start = Instant::now();
-        let url = format!("{}/{}", self.registry_url, package_name);
+        let url = format!("{}/{}", registry_url, package_name);
         debug!("Fetching package info from {}", url);
 
-        // Acquire permit for rate limiting
-        let _permit = self.rate_limiter.acquire().await?;
+        // Wait for a slot on the registry's host
+        let permit = self.rate_limiter.acquire(&url).await;
 
-        let response = self
+        let cached = self
+            .metadata_cache
+            .as_ref()
+            .and_then(|cache| cache.get(registry_url, package_name));
+
+        let mut request = self
             .client
             .get(&url)
-            .header("Accept", "application/json")
-            .send()
+            .header("Accept", "application/json");
+
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+
+        // Ask the registry to skip the body if our cached copy is still current
+        if let Some(etag) = cached.as_ref().and_then(|entry| entry.etag.as_deref()) {
+            request = request.header("If-None-Match", etag);
+        }
+
+        let response = send_with_retries(&permit, request)
             .await
             .with_context(|| format!("Failed to fetch package info for {}", package_name))?;
 
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED
+            && let (Some(cache), Some(entry)) = (&self.metadata_cache, cached)
+        {
+            record_call(&url, start.elapsed());
+            cache.record(CacheOutcome::Hit);
+            debug!("Metadata cache hit for {} in {:?}", package_name, start.elapsed());
+            return Ok(entry.info);
+        }
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(NotFound(format!("Failed to fetch package {}: HTTP {}", package_name, response.status())).into());
+        }
         if !response.status().is_success() {
             return Err(anyhow::anyhow!(
                 "Failed to fetch package {}: HTTP {}",
@@ -113,49 +685,113 @@ impl NpmRegistry {
             ));
         }
 
-        let npm_package: NpmPackageResponse = response
-            .json()
-            .await
-            .with_context(|| format!("Failed to parse package info for {}", package_name))?;
+        let etag = response
+            .headers()
+            .get("ETag")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+
+        // Parse the body as it arrives rather than buffering it: packages with
+        // thousands of versions have packuments of tens of MB, mostly readmes
+        // and fields that are skipped, so only what's kept is ever held
+        let body = response.bytes_stream().map(|chunk| {
+            let chunk = chunk.map_err(std::io::Error::other)?;
+            BYTES_DOWNLOADED.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+            Ok::<_, std::io::Error>(chunk)
+        });
+        let reader = tokio_util::io::SyncIoBridge::new(tokio_util::io::StreamReader::new(body));
+        let npm_package: NpmPackageResponse = tokio::task::spawn_blocking(move || {
+            serde_json::from_reader(std::io::BufReader::with_capacity(PACKUMENT_BUFFER, reader))
+        })
+        .await?
+        .with_context(|| format!("Failed to read package info for {}", package_name))?;
+        record_call(&url, start.elapsed());
 
         // Convert to our internal model with zero-copy optimization
         let mut versions = HashMap::with_capacity(npm_package.versions.len());
         for (version, npm_version) in npm_package.versions {
+            let license = license_expression(npm_version.license.as_ref(), npm_version.licenses.as_deref());
             let version_info = VersionInfo {
                 version: version.clone(),
                 dependencies: npm_version.dependencies.unwrap_or_default(),
                 dev_dependencies: npm_version.dev_dependencies.unwrap_or_default(),
                 dist: npm_version.dist,
+                license,
+                publisher: npm_version.npm_user.as_ref().and_then(person),
+                deprecated: npm_version
+                    .deprecated
+                    .as_ref()
+                    .and_then(|message| message.as_str())
+                    .filter(|message| !message.is_empty())
+                    .map(str::to_string),
+                optional_dependencies: npm_version.optional_dependencies.unwrap_or_default(),
+                platforms: Platforms::from_fields(
+                    npm_version.os.as_ref(),
+                    npm_version.cpu.as_ref(),
+                    npm_version.libc.as_ref(),
+                ),
             };
             versions.insert(version, version_info);
         }
 
         debug!("Fetched {} package info in {:?}", package_name, start.elapsed());
 
-        Ok(PackageInfo {
+        // Only keep publish times of versions we know about
+        let time = npm_package
+            .time
+            .into_iter()
+            .filter(|(version, _)| versions.contains_key(version))
+            .filter_map(|(version, time)| Some((version, time.as_str()?.to_string())))
+            .collect();
+
+        let info = PackageInfo {
             name: npm_package.name,
             versions,
             dist_tags: npm_package.dist_tags,
-        })
+            time,
+            maintainers: npm_package.maintainers.iter().filter_map(person).collect(),
+        };
+
+        if let Some(cache) = &self.metadata_cache {
+            cache.record(CacheOutcome::Miss);
+            let entry = CachedPackument { etag, info: info.clone() };
+            if let Err(e) = cache.insert(registry_url, package_name, &entry) {
+                debug!("Failed to cache metadata for {}: {}", package_name, e);
+            }
+        }
+
+        Ok(info)
     }
 
     #[allow(dead_code)]
+    /// Stream the tarball at `tarball_url` to `output_path`, calling `progress`
+    /// with the bytes received so far and the size the registry announced.
     pub async fn download_package(
         &self,
         tarball_url: &str,
         output_path: &std::path::Path,
+        progress: &(dyn Fn(u64, Option<u64>) + Sync),
     ) -> Result<()> {
         let start = Instant::now();
         debug!("Downloading package from {}", tarball_url);
 
-        // Acquire permit for rate limiting
-        let _permit = self.rate_limiter.acquire().await?;
+        if self.offline {
+            return Err(anyhow::anyhow!(
+                "Cannot download {} in offline mode; import a bundle or run 'rjs fetch' first",
+                tarball_url
+            ));
+        }
+
+        // Wait for a download slot, then for a slot on the tarball's host
+        let _download = self.downloads.semaphore.acquire().await?;
+        let permit = self.rate_limiter.acquire(tarball_url).await;
 
         // Use streaming to handle large tarballs efficiently
-        let response = self
-            .client
-            .get(tarball_url)
-            .send()
+        let mut request = self.client.get(tarball_url);
+        if let Some(token) = self.read_token(tarball_url) {
+            request = request.bearer_auth(token);
+        }
+        let response = send_with_retries(&permit, request)
             .await
             .with_context(|| format!("Failed to download package from {}", tarball_url))?;
 
@@ -167,9 +803,8 @@ impl NpmRegistry {
         }
 
         // Get content length for progress tracking
-        let total_size = response
-            .content_length()
-            .unwrap_or(0);
+        let content_length = response.content_length();
+        let total_size = content_length.unwrap_or(0);
 
         // Create file for streaming
         let mut file = fs::File::create(output_path).await
@@ -184,7 +819,12 @@ impl NpmRegistry {
             file.write_all(&chunk).await
                 .with_context(|| format!("Failed to write to {}", output_path.display()))?;
             
+            // Reported every PROGRESS_STEP bytes rather than every chunk
+            if (downloaded + chunk.len() as u64) / PROGRESS_STEP > downloaded / PROGRESS_STEP {
+                progress(downloaded + chunk.len() as u64, content_length);
+            }
             downloaded += chunk.len() as u64;
+            BYTES_DOWNLOADED.fetch_add(chunk.len() as u64, Ordering::Relaxed);
             
             // Log progress for large packages
             if total_size > 1024 * 1024 && downloaded % (1024 * 1024) == 0 {
@@ -200,12 +840,14 @@ impl NpmRegistry {
         // Ensure all data is flushed to disk
         file.flush().await
             .with_context(|| format!("Failed to flush file {}", output_path.display()))?;
+        progress(downloaded, content_length);
 
         debug!(
             "Downloaded {}KB in {:?}", 
             downloaded / 1024, 
             start.elapsed()
         );
+        record_call(tarball_url, start.elapsed());
 
         Ok(())
     }
@@ -227,9 +869,23 @@ impl NpmRegistry {
         // Open the tarball file
         let file = std::fs::File::open(tarball_path)
             .with_context(|| format!("Failed to open tarball {}", tarball_path.display()))?;
+        self.extract_archive(file, tarball_path, output_dir)?;
+
+        debug!("Extracted tarball in {:?}", start.elapsed());
 
+        Ok(())
+    }
+
+    /// Extract a gzipped tarball read from `tarball` into `output_dir`, for
+    /// one already in memory. `tarball_path` is where it came from, for errors.
+    pub fn extract_archive(
+        &self,
+        tarball: impl std::io::Read,
+        tarball_path: &std::path::Path,
+        output_dir: &std::path::Path,
+    ) -> Result<()> {
         // Create a decompression reader
-        let decompressed = flate2::read::GzDecoder::new(file);
+        let decompressed = flate2::read::GzDecoder::new(tarball);
         let mut archive = tar::Archive::new(decompressed);
 
         // Create the output directory if it doesn't exist
@@ -238,18 +894,260 @@ impl NpmRegistry {
                 .with_context(|| format!("Failed to create directory {}", output_dir.display()))?;
         }
 
-        // Extract the tarball to the output directory
-        archive
-            .unpack(output_dir)
-            .with_context(|| format!("Failed to extract tarball to {}", output_dir.display()))?;
+        // npm tarballs wrap their contents in a single top-level directory
+        // (usually "package/"), so strip the first path component
+        let entries = archive
+            .entries()
+            .with_context(|| format!("Failed to read tarball {}", tarball_path.display()))?;
 
-        debug!("Extracted tarball in {:?}", start.elapsed());
+        for entry in entries {
+            let mut entry = entry
+                .with_context(|| format!("Failed to read entry in {}", tarball_path.display()))?;
+            let entry_path = entry.path()?.into_owned();
+            let relative: PathBuf = entry_path.components().skip(1).collect();
 
+            if relative.as_os_str().is_empty() {
+                continue;
+            }
+
+            // Refuse entries that would escape the output directory
+            if relative.components().any(|c| !matches!(c, Component::Normal(_))) {
+                return Err(anyhow::anyhow!(
+                    "Refusing to extract unsafe path {} from {}",
+                    entry_path.display(),
+                    tarball_path.display()
+                ));
+            }
+
+            let target = output_dir.join(&relative);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+            }
+
+            entry
+                .unpack(&target)
+                .with_context(|| format!("Failed to extract {}", target.display()))?;
+        }
         Ok(())
     }
 
+    /// Advisories affecting any of the given package versions, keyed by package name.
+    pub async fn bulk_advisories(
+        &self,
+        packages: &BTreeMap<String, Vec<String>>,
+    ) -> Result<HashMap<String, Vec<Advisory>>> {
+        if self.offline {
+            return Err(anyhow::anyhow!("Cannot check advisories in offline mode"));
+        }
+
+        let url = format!("{}/-/npm/v1/security/advisories/bulk", self.registry_url);
+        debug!("Checking {} packages for advisories at {}", packages.len(), url);
+
+        let _permit = self.rate_limiter.acquire(&url).await;
+        let response = self
+            .client
+            .post(&url)
+            .json(packages)
+            .send()
+            .await
+            .context("Failed to query security advisories")?;
+        HTTP_REQUESTS.fetch_add(1, Ordering::Relaxed);
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Failed to query security advisories: HTTP {}",
+                response.status()
+            ));
+        }
+
+        response
+            .json()
+            .await
+            .context("Failed to parse security advisories")
+    }
+
+    /// The first `size` packages matching `text` in the registry's search, best first.
+    pub async fn search(&self, text: &str, size: usize) -> Result<Vec<SearchResult>> {
+        if self.offline {
+            return Err(anyhow::anyhow!("Cannot search the registry in offline mode"));
+        }
+
+        let url = reqwest::Url::parse_with_params(
+            &format!("{}/-/v1/search", self.registry_url),
+            [("text", text), ("size", &size.to_string())],
+        )?;
+        let permit = self.rate_limiter.acquire(url.as_str()).await;
+        let request = self.client.get(url).header("Accept", "application/json");
+        let response = send_with_retries(&permit, request)
+            .await
+            .with_context(|| format!("Failed to search {}", self.registry_url))?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Failed to search {}: HTTP {}", self.registry_url, response.status()));
+        }
+
+        let body: SearchResponse = response.json().await.context("Failed to parse search results")?;
+        Ok(body.objects.into_iter().map(|object| object.package).collect())
+    }
+
+    /// How many times `package_name` was downloaded in the last week, or
+    /// None when the registry doesn't keep counts.
+    pub async fn weekly_downloads(&self, package_name: &str) -> Result<Option<u64>> {
+        if self.offline {
+            return Err(anyhow::anyhow!("Cannot fetch download counts in offline mode"));
+        }
+
+        let api = if self.registry_url == DEFAULT_REGISTRY {
+            DOWNLOADS_API.to_string()
+        } else {
+            format!("{}/-/downloads", self.registry_url)
+        };
+        let url = format!("{}/point/last-week/{}", api, package_name);
+        let permit = self.rate_limiter.acquire(&url).await;
+        let request = self.client.get(&url).header("Accept", "application/json");
+        let response = send_with_retries(&permit, request)
+            .await
+            .with_context(|| format!("Failed to fetch download counts for {}", package_name))?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Failed to fetch download counts for {}: HTTP {}",
+                package_name,
+                response.status()
+            ));
+        }
+
+        let body: DownloadsResponse = response.json().await.context("Failed to parse download counts")?;
+        Ok(Some(body.downloads))
+    }
+
+    /// Sigstore attestations listed in a version's `dist.attestations.url`.
+    pub async fn attestations(&self, url: &str) -> Result<Vec<Attestation>> {
+        if self.offline {
+            return Err(anyhow::anyhow!("Cannot fetch attestations in offline mode"));
+        }
+
+        let start = Instant::now();
+        let permit = self.rate_limiter.acquire(url).await;
+        let request = self.client.get(url).header("Accept", "application/json");
+        let response = send_with_retries(&permit, request)
+            .await
+            .with_context(|| format!("Failed to fetch attestations from {}", url))?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Failed to fetch attestations from {}: HTTP {}",
+                url,
+                response.status()
+            ));
+        }
+
+        let body: AttestationsResponse = response
+            .json()
+            .await
+            .context("Failed to parse attestations")?;
+        record_call(url, start.elapsed());
+        Ok(body.attestations)
+    }
+
     // Add a method to get the registry URL
+    #[allow(dead_code)]
     pub fn get_registry_url(&self) -> &str {
         &self.registry_url
     }
 }
+
+/// A client for requests outside the registry, such as release downloads,
+/// going through the same proxies.
+pub fn http_client() -> Result<Client> {
+    let mut builder = Client::builder()
+        .timeout(Duration::from_secs(300))
+        .user_agent(concat!("rjs/", env!("CARGO_PKG_VERSION")));
+    for proxy in configured_proxies() {
+        builder = builder.proxy(proxy);
+    }
+    builder.build().context("Failed to create HTTP client")
+}
+
+// Proxies from the proxy settings. Without them reqwest still honors the
+// HTTP_PROXY/HTTPS_PROXY variables; invalid URLs are skipped with a warning
+fn configured_proxies() -> Vec<reqwest::Proxy> {
+    let settings = crate::config::settings();
+    let no_proxy = settings.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string);
+    let mut proxies = Vec::new();
+    if let Some(url) = &settings.proxy {
+        match reqwest::Proxy::http(url) {
+            Ok(proxy) => proxies.push(proxy.no_proxy(no_proxy.clone())),
+            Err(e) => log::warn!("Ignoring invalid proxy {}: {}", url, e),
+        }
+    }
+    if let Some(url) = &settings.https_proxy {
+        match reqwest::Proxy::https(url) {
+            Ok(proxy) => proxies.push(proxy.no_proxy(no_proxy)),
+            Err(e) => log::warn!("Ignoring invalid https-proxy {}: {}", url, e),
+        }
+    }
+    proxies
+}
+
+// People are objects with a name and email, or "Name <email>" strings in old packuments
+fn person(value: &serde_json::Value) -> Option<Person> {
+    if let Some(text) = value.as_str() {
+        let (name, email) = match text.split_once('<') {
+            Some((name, rest)) => (name.trim(), rest.split('>').next().map(str::to_string)),
+            None => (text.trim(), None),
+        };
+        return (!name.is_empty()).then(|| Person {
+            name: name.to_string(),
+            email,
+        });
+    }
+
+    Some(Person {
+        name: value.get("name")?.as_str()?.to_string(),
+        email: value.get("email").and_then(|email| email.as_str()).map(str::to_string),
+    })
+}
+
+// Normalize the forms packages declare their license in to one SPDX expression
+fn license_expression(
+    license: Option<&serde_json::Value>,
+    licenses: Option<&[serde_json::Value]>,
+) -> Option<String> {
+    let name = |value: &serde_json::Value| match value {
+        serde_json::Value::String(name) => Some(name.clone()),
+        serde_json::Value::Object(object) => object.get("type")?.as_str().map(str::to_string),
+        _ => None,
+    };
+
+    if let Some(license) = license.and_then(name) {
+        return Some(license);
+    }
+
+    let names: Vec<String> = licenses?.iter().filter_map(name).collect();
+    match names.len() {
+        0 => None,
+        1 => names.into_iter().next(),
+        _ => Some(format!("({})", names.join(" OR "))),
+    }
+}
+
+// The daemon socket, unless disabled through RJS_NO_DAEMON or not running
+#[cfg(unix)]
+fn running_daemon_socket() -> Option<PathBuf> {
+    if std::env::var_os("RJS_NO_DAEMON").is_some() {
+        return None;
+    }
+
+    let socket = crate::daemon::socket_path().ok()?;
+    socket.exists().then_some(socket)
+}
+
+#[cfg(not(unix))]
+fn running_daemon_socket() -> Option<PathBuf> {
+    None
+}
@@ -0,0 +1,666 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use log::debug;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use super::{NpmRegistry, Person, HTTP_REQUESTS};
+use crate::dependency::versions;
+use crate::npmrc::Npmrc;
+use crate::pack::Tarball;
+
+// Codes expire every 30 seconds, so a mistyped one deserves another try
+const MAX_OTP_PROMPTS: usize = 2;
+
+impl NpmRegistry {
+    /// A client for `registry_url` using the auth token .npmrc has for it.
+    pub fn logged_in(registry_url: &str, npmrc: &Npmrc) -> Self {
+        let token = npmrc.auth_token(registry_url).map(str::to_string);
+        Self::with_registry(registry_url.trim_end_matches('/')).with_auth_token(token)
+    }
+
+    /// Every version the registry has of `name`, or None if it was never
+    /// published. Always asks the registry itself, skipping caches and the daemon.
+    pub async fn published_versions(&self, name: &str) -> Result<Option<Vec<String>>> {
+        let url = self.package_url(name);
+        let mut request = self
+            .client
+            .get(&url)
+            .header("Accept", "application/vnd.npm.install-v1+json");
+        if let Some(token) = &self.auth_token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = self.send(request, &format!("look up {}", name)).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(failure(response, &format!("look up {}", name)).await);
+        }
+
+        let packument: serde_json::Value = response
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse package info for {}", name))?;
+        let versions = packument
+            .get("versions")
+            .and_then(|versions| versions.as_object())
+            .map(|versions| versions.keys().cloned().collect())
+            .unwrap_or_default();
+        Ok(Some(versions))
+    }
+
+    /// Upload a new version: `manifest` is its package.json and `tarball` the
+    /// packed files. The version is tagged `tag`; `access` ("public" or
+    /// "restricted") only matters for scoped packages.
+    pub async fn publish(
+        &self,
+        manifest: &serde_json::Value,
+        tarball: &Tarball,
+        tag: &str,
+        access: Option<&str>,
+    ) -> Result<()> {
+        let field = |key: &str| manifest.get(key).and_then(|value| value.as_str()).unwrap_or_default();
+        let (name, version) = (field("name"), field("version"));
+        let file_name = format!("{}-{}.tgz", name.rsplit('/').next().unwrap_or(name), version);
+
+        let mut version_manifest = manifest.clone();
+        if let Some(object) = version_manifest.as_object_mut() {
+            object.insert("_id".into(), format!("{}@{}", name, version).into());
+            object.insert(
+                "dist".into(),
+                serde_json::json!({
+                    "shasum": tarball.shasum,
+                    "integrity": tarball.integrity,
+                    "tarball": format!("{}/{}/-/{}", self.registry_url, name, file_name),
+                }),
+            );
+        }
+
+        // The document npm PUTs: the new version, its tag, and the tarball inline
+        let document = serde_json::json!({
+            "_id": name,
+            "name": name,
+            "description": field("description"),
+            "dist-tags": { tag: version },
+            "versions": { version: version_manifest },
+            "access": access,
+            "_attachments": {
+                file_name: {
+                    "content_type": "application/octet-stream",
+                    "data": base64::engine::general_purpose::STANDARD.encode(&tarball.data),
+                    "length": tarball.data.len(),
+                }
+            }
+        });
+
+        let action = format!("publish {}@{}", name, version);
+        let request = self
+            .authenticated(self.client.put(self.package_url(name)), &action)?
+            .timeout(std::time::Duration::from_secs(300))
+            .json(&document);
+        self.change(request, &action).await?;
+        Ok(())
+    }
+
+    /// A package's dist-tags and the versions they point at.
+    pub async fn dist_tags(&self, name: &str) -> Result<BTreeMap<String, String>> {
+        let action = format!("list {}'s dist-tags", name);
+        let mut request = self.client.get(self.dist_tags_url(name));
+        if let Some(token) = &self.auth_token {
+            request = request.bearer_auth(token);
+        }
+        let response = self.send(request, &action).await?;
+        if !response.status().is_success() {
+            return Err(failure(response, &action).await);
+        }
+        response
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse {}'s dist-tags", name))
+    }
+
+    /// Point `tag` at `version` of `name`.
+    pub async fn add_dist_tag(&self, name: &str, version: &str, tag: &str) -> Result<()> {
+        let action = format!("tag {}@{} as {}", name, version, tag);
+        let url = format!("{}/{}", self.dist_tags_url(name), tag);
+        let request = self.authenticated(self.client.put(url), &action)?.json(&version);
+        self.change(request, &action).await?;
+        Ok(())
+    }
+
+    /// Remove `tag` from `name`.
+    pub async fn remove_dist_tag(&self, name: &str, tag: &str) -> Result<()> {
+        let action = format!("remove {}'s {} tag", name, tag);
+        let url = format!("{}/{}", self.dist_tags_url(name), tag);
+        let request = self.authenticated(self.client.delete(url), &action)?;
+        self.change(request, &action).await?;
+        Ok(())
+    }
+
+    /// Remove every version of `name`.
+    pub async fn unpublish_package(&self, name: &str) -> Result<()> {
+        let action = format!("unpublish {}", name);
+        let packument = self.packument_for_write(name, &action).await?;
+        let url = format!("{}/-rev/{}", self.package_url(name), revision(&packument, &action)?);
+        let request = self.authenticated(self.client.delete(url), &action)?;
+        self.change(request, &action).await.map_err(unpublish_failure)?;
+        Ok(())
+    }
+
+    /// Remove one version of `name`, moving tags that pointed at it away: "latest"
+    /// to the newest remaining version, others are dropped. Returns the new
+    /// "latest", if it changed.
+    pub async fn unpublish_version(&self, name: &str, version: &str) -> Result<Option<String>> {
+        let action = format!("unpublish {}@{}", name, version);
+        let mut packument = self.packument_for_write(name, &action).await?;
+        let rev = revision(&packument, &action)?.to_string();
+
+        let removed = packument
+            .get_mut("versions")
+            .and_then(|versions| versions.as_object_mut())
+            .and_then(|versions| versions.remove(version))
+            .with_context(|| format!("{}@{} is not published", name, version))?;
+        let remaining: Vec<String> = packument["versions"]
+            .as_object()
+            .map(|versions| versions.keys().cloned().collect())
+            .unwrap_or_default();
+        if remaining.is_empty() {
+            anyhow::bail!("{} is the only version of {}; unpublish the whole package instead", version, name);
+        }
+
+        let mut new_latest = None;
+        if let Some(tags) = packument.get_mut("dist-tags").and_then(|tags| tags.as_object_mut()) {
+            tags.retain(|tag, tagged| tag == "latest" || tagged.as_str() != Some(version));
+            if tags.get("latest").and_then(|latest| latest.as_str()) == Some(version) {
+                let latest = newest(&remaining);
+                tags.insert("latest".into(), latest.clone().into());
+                new_latest = Some(latest);
+            }
+        }
+        if let Some(object) = packument.as_object_mut() {
+            object.remove("_attachments");
+        }
+
+        let url = format!("{}/-rev/{}", self.package_url(name), rev);
+        let request = self.authenticated(self.client.put(url), &action)?.json(&packument);
+        self.change(request, &action).await.map_err(unpublish_failure)?;
+
+        // The tarball goes separately, against the document's new revision
+        if let Some(tarball) = removed["dist"]["tarball"].as_str()
+            && let Some(file) = tarball.rsplit('/').next()
+        {
+            let packument = self.packument_for_write(name, &action).await?;
+            let url = format!(
+                "{}/-/{}/-rev/{}",
+                self.package_url(name),
+                file,
+                revision(&packument, &action)?
+            );
+            let request = self.authenticated(self.client.delete(url), &action)?;
+            if let Err(e) = self.change(request, &action).await {
+                debug!("Failed to remove the tarball of {}@{}: {:#}", name, version, e);
+            }
+        }
+        Ok(new_latest)
+    }
+
+    /// The accounts that may publish `name`.
+    pub async fn owners(&self, name: &str) -> Result<Vec<Person>> {
+        let action = format!("list {}'s owners", name);
+        let mut request = self.client.get(self.package_url(name));
+        if let Some(token) = &self.auth_token {
+            request = request.bearer_auth(token);
+        }
+        let packument = self.packument(request, name, &action).await?;
+        Ok(maintainers(&packument).iter().filter_map(super::person).collect())
+    }
+
+    /// Let the registry account `user` publish `name`. Returns false if they already could.
+    pub async fn add_owner(&self, name: &str, user: &str) -> Result<bool> {
+        let action = format!("add {} as an owner of {}", user, name);
+        let email = self.user_email(user, &action).await?;
+        let packument = self.packument_for_write(name, &action).await?;
+
+        let mut owners = maintainers(&packument);
+        if owners.iter().any(|owner| owner_name(owner) == Some(user)) {
+            return Ok(false);
+        }
+        owners.push(serde_json::json!({ "name": user, "email": email }));
+        self.set_owners(name, &packument, owners, &action).await?;
+        Ok(true)
+    }
+
+    /// Stop `user` from publishing `name`. Returns false if they couldn't anyway.
+    pub async fn remove_owner(&self, name: &str, user: &str) -> Result<bool> {
+        let action = format!("remove {} as an owner of {}", user, name);
+        let packument = self.packument_for_write(name, &action).await?;
+
+        let mut owners = maintainers(&packument);
+        let before = owners.len();
+        owners.retain(|owner| owner_name(owner) != Some(user));
+        if owners.len() == before {
+            return Ok(false);
+        }
+        if owners.is_empty() {
+            anyhow::bail!("Can't {}: a package must keep at least one owner", action);
+        }
+        self.set_owners(name, &packument, owners, &action).await?;
+        Ok(true)
+    }
+
+    // Save a new maintainers list; the registry leaves the rest of the document alone
+    async fn set_owners(
+        &self,
+        name: &str,
+        packument: &serde_json::Value,
+        owners: Vec<serde_json::Value>,
+        action: &str,
+    ) -> Result<()> {
+        let rev = revision(packument, action)?;
+        let document = serde_json::json!({
+            "_id": name,
+            "_rev": rev,
+            "maintainers": owners,
+        });
+        let url = format!("{}/-rev/{}", self.package_url(name), rev);
+        let request = self.authenticated(self.client.put(url), action)?.json(&document);
+        self.change(request, action).await?;
+        Ok(())
+    }
+
+    /// Make scoped package `name` installable by anyone ("public") or only by
+    /// its owners and teams ("restricted").
+    pub async fn set_access(&self, name: &str, access: &str) -> Result<()> {
+        let action = format!("make {} {}", name, access);
+        let url = format!("{}/-/package/{}/access", self.registry_url, name.replace('/', "%2f"));
+        let request = self
+            .authenticated(self.client.post(url), &action)?
+            .json(&serde_json::json!({ "access": access }));
+        self.change(request, &action).await?;
+        Ok(())
+    }
+
+    /// Give the org team `@scope:team` `permissions` ("read-only" or
+    /// "read-write") on `name`.
+    pub async fn grant_access(&self, team: &str, name: &str, permissions: &str) -> Result<()> {
+        let action = format!("grant {} {} access to {}", team, permissions, name);
+        let request = self
+            .authenticated(self.client.put(self.team_packages_url(team)?), &action)?
+            .json(&serde_json::json!({ "package": name, "permissions": permissions }));
+        self.change(request, &action).await?;
+        Ok(())
+    }
+
+    /// Take away the org team `@scope:team`'s access to `name`.
+    pub async fn revoke_access(&self, team: &str, name: &str) -> Result<()> {
+        let action = format!("revoke {}'s access to {}", team, name);
+        let request = self
+            .authenticated(self.client.delete(self.team_packages_url(team)?), &action)?
+            .json(&serde_json::json!({ "package": name }));
+        self.change(request, &action).await?;
+        Ok(())
+    }
+
+    // Teams are written "@scope:team"; the API wants them as path segments
+    fn team_packages_url(&self, team: &str) -> Result<String> {
+        let Some((scope, team_name)) = team.trim_start_matches('@').split_once(':') else {
+            anyhow::bail!("\"{}\" isn't a team; write it as @scope:team", team);
+        };
+        if scope.is_empty() || team_name.is_empty() {
+            anyhow::bail!("\"{}\" isn't a team; write it as @scope:team", team);
+        }
+        Ok(format!("{}/-/team/{}/{}/package", self.registry_url, scope, team_name))
+    }
+
+    /// How long the registry takes to answer its ping endpoint, which goes
+    /// through the same proxies as installs without looking up any package.
+    pub async fn ping(&self) -> Result<Duration> {
+        let action = "ping the registry";
+        let mut request = self.client.get(format!("{}/-/ping?write=true", self.registry_url));
+        if let Some(token) = &self.auth_token {
+            request = request.bearer_auth(token);
+        }
+        let started = Instant::now();
+        let response = self.send(request, action).await?;
+        let elapsed = started.elapsed();
+        if !response.status().is_success() {
+            return Err(failure(response, action).await);
+        }
+        Ok(elapsed)
+    }
+
+    /// The account the auth token belongs to, which also checks that the
+    /// registry still accepts it.
+    pub async fn whoami(&self) -> Result<String> {
+        let action = "check the auth token";
+        let request = self.authenticated(self.client.get(format!("{}/-/whoami", self.registry_url)), action)?;
+        let response = self.send(request, action).await?;
+        if !response.status().is_success() {
+            return Err(failure(response, action).await);
+        }
+        let whoami: serde_json::Value = response.json().await.context("Failed to parse the registry's whoami answer")?;
+        whoami
+            .get("username")
+            .and_then(|username| username.as_str())
+            .map(str::to_string)
+            .context("The registry's whoami answer has no username")
+    }
+
+    /// The access tokens on the logged-in account. Their values come back redacted.
+    pub async fn tokens(&self) -> Result<Vec<AccessToken>> {
+        let action = "list your access tokens";
+        let mut tokens = Vec::new();
+        let mut url = format!("{}/-/npm/v1/tokens", self.registry_url);
+        // The list is paged; each page links to the next
+        loop {
+            let request = self.authenticated(self.client.get(&url), action)?;
+            let response = self.change(request, action).await?;
+            let page: TokenPage = response
+                .json()
+                .await
+                .context("Failed to parse the registry's token list")?;
+            tokens.extend(page.objects);
+            match page.urls.and_then(|urls| urls.next) {
+                Some(next) if next != url => url = next,
+                _ => return Ok(tokens),
+            }
+        }
+    }
+
+    /// Create a token on the logged-in account; the registry asks for the
+    /// account's password. `readonly` tokens can install but not publish, and
+    /// `cidr` restricts the addresses that may use it.
+    pub async fn create_token(&self, password: &str, readonly: bool, cidr: &[String]) -> Result<AccessToken> {
+        let action = "create an access token";
+        let request = self
+            .authenticated(self.client.post(format!("{}/-/npm/v1/tokens", self.registry_url)), action)?
+            .json(&serde_json::json!({
+                "password": password,
+                "readonly": readonly,
+                "cidr_whitelist": cidr,
+            }));
+        self.change(request, action)
+            .await?
+            .json()
+            .await
+            .context("Failed to parse the new token")
+    }
+
+    /// Revoke the token whose key is `key`.
+    pub async fn revoke_token(&self, key: &str) -> Result<()> {
+        let action = "revoke the access token";
+        let url = format!("{}/-/npm/v1/tokens/token/{}", self.registry_url, key);
+        let request = self.authenticated(self.client.delete(url), action)?;
+        self.change(request, action).await?;
+        Ok(())
+    }
+
+    // A registry account's email, which also confirms the account exists
+    async fn user_email(&self, user: &str, action: &str) -> Result<Option<String>> {
+        let url = format!("{}/-/user/org.couchdb.user:{}", self.registry_url, user);
+        let request = self.authenticated(self.client.get(url), action)?;
+        let response = self.send(request, action).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            anyhow::bail!("Can't {}: there's no user {} on {}", action, user, self.registry_url);
+        }
+        if !response.status().is_success() {
+            return Err(failure(response, action).await);
+        }
+        let profile: serde_json::Value = response
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse {}'s profile", user))?;
+        Ok(profile.get("email").and_then(|email| email.as_str()).map(str::to_string))
+    }
+
+    // The full packument with its revision, as the registry wants it for edits
+    async fn packument_for_write(&self, name: &str, action: &str) -> Result<serde_json::Value> {
+        let url = format!("{}?write=true", self.package_url(name));
+        let request = self.authenticated(self.client.get(url), action)?;
+        self.packument(request, name, action).await
+    }
+
+    async fn packument(&self, request: RequestBuilder, name: &str, action: &str) -> Result<serde_json::Value> {
+        let response = self.send(request, action).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            anyhow::bail!("Can't {}: {} is not published to {}", action, name, self.registry_url);
+        }
+        if !response.status().is_success() {
+            return Err(failure(response, action).await);
+        }
+        response
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse package info for {}", name))
+    }
+
+    fn dist_tags_url(&self, name: &str) -> String {
+        format!("{}/-/package/{}/dist-tags", self.registry_url, name.replace('/', "%2f"))
+    }
+
+    // Send a request that changes the registry, answering a two-factor
+    // challenge with the one-time password, asked for if we don't have one
+    async fn change(&self, request: RequestBuilder, action: &str) -> Result<Response> {
+        let mut request = request;
+        let mut prompts = 0;
+        loop {
+            let retry = request.try_clone();
+            let otp = self.otp.lock().unwrap_or_else(|e| e.into_inner()).clone();
+            if let Some(otp) = &otp {
+                request = request.header("npm-otp", otp);
+            }
+
+            let response = self.send(request, action).await?;
+            if response.status().is_success() {
+                return Ok(response);
+            }
+            let status = response.status();
+            let wants_otp = response
+                .headers()
+                .get("www-authenticate")
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.to_ascii_lowercase().contains("otp"));
+            let body = response.text().await.unwrap_or_default();
+            if status != StatusCode::UNAUTHORIZED
+                || !(wants_otp || body.to_ascii_lowercase().contains("one-time pass"))
+            {
+                return Err(failure_reason(status, &body, action));
+            }
+
+            if !interactive() {
+                match otp {
+                    Some(_) => anyhow::bail!("Failed to {}: the one-time password was rejected or has expired", action),
+                    None => anyhow::bail!(
+                        "Can't {}: the registry wants a one-time password from your authenticator; pass it with --otp",
+                        action
+                    ),
+                }
+            }
+            if prompts == MAX_OTP_PROMPTS {
+                anyhow::bail!("Failed to {}: the one-time password was rejected", action);
+            }
+            let code: String = dialoguer::Input::new()
+                .with_prompt(match otp {
+                    Some(_) => "That code didn't work; enter a new one-time password",
+                    None => "This operation requires a one-time password from your authenticator",
+                })
+                .interact_text()?;
+            *self.otp.lock().unwrap_or_else(|e| e.into_inner()) = Some(code.trim().to_string());
+            prompts += 1;
+
+            request = retry.with_context(|| format!("Can't retry the request to {}", action))?;
+        }
+    }
+
+    // Scoped names are escaped as one path segment: "@scope%2fname"
+    fn package_url(&self, name: &str) -> String {
+        format!("{}/{}", self.registry_url, name.replace('/', "%2f"))
+    }
+
+    fn authenticated(&self, request: RequestBuilder, action: &str) -> Result<RequestBuilder> {
+        match &self.auth_token {
+            Some(token) => Ok(request.bearer_auth(token)),
+            None => anyhow::bail!(
+                "Can't {} without logging in to {}: add \"//{}/:_authToken=<token>\" to .npmrc",
+                action,
+                self.registry_url,
+                self.registry_url
+                    .split_once("://")
+                    .map_or(self.registry_url.as_str(), |(_, rest)| rest)
+                    .trim_end_matches('/')
+            ),
+        }
+    }
+
+    async fn send(&self, request: RequestBuilder, action: &str) -> Result<Response> {
+        if self.offline {
+            anyhow::bail!("Can't {} in offline mode", action);
+        }
+        let _permit = self.rate_limiter.acquire(&self.registry_url).await;
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to {}", action))?;
+        HTTP_REQUESTS.fetch_add(1, Ordering::Relaxed);
+        debug!("{} -> HTTP {}", action, response.status());
+        Ok(response)
+    }
+}
+
+fn maintainers(packument: &serde_json::Value) -> Vec<serde_json::Value> {
+    packument
+        .get("maintainers")
+        .and_then(|maintainers| maintainers.as_array())
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn owner_name(owner: &serde_json::Value) -> Option<&str> {
+    owner.get("name").and_then(|name| name.as_str())
+}
+
+fn revision<'a>(packument: &'a serde_json::Value, action: &str) -> Result<&'a str> {
+    packument
+        .get("_rev")
+        .and_then(|rev| rev.as_str())
+        .with_context(|| format!("Can't {}: the registry didn't say which revision to change", action))
+}
+
+// The highest version, preferring releases over prereleases
+fn newest(versions: &[String]) -> String {
+    let parsed: Vec<_> = versions
+        .iter()
+        .filter_map(|version| versions::parse_version(version).map(|parsed| (parsed, version)))
+        .collect();
+    parsed
+        .iter()
+        .filter(|(parsed, _)| parsed.pre.is_empty())
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .or_else(|| parsed.iter().max_by(|(a, _), (b, _)| a.cmp(b)))
+        .map(|(_, version)| version.to_string())
+        .unwrap_or_else(|| versions[0].clone())
+}
+
+// The registry refuses unpublishing under its policy, e.g. for packages
+// older than 72 hours or with dependents; point at the policy after its reason
+fn unpublish_failure(e: anyhow::Error) -> anyhow::Error {
+    let refused = e.downcast_ref::<RegistryError>().is_some_and(|e| {
+        matches!(
+            e.status,
+            StatusCode::BAD_REQUEST | StatusCode::FORBIDDEN | StatusCode::METHOD_NOT_ALLOWED | StatusCode::CONFLICT
+        )
+    });
+    if refused {
+        anyhow::anyhow!(
+            "{}\nThe registry's unpublish policy may not allow this: https://docs.npmjs.com/policies/unpublish",
+            e
+        )
+    } else {
+        e
+    }
+}
+
+// Whether we can prompt for a one-time password
+fn interactive() -> bool {
+    atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stderr)
+}
+
+async fn failure(response: Response, action: &str) -> anyhow::Error {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    failure_reason(status, &body, action)
+}
+
+/// An access token on a registry account. `key` identifies it; `token` is
+/// the secret itself, redacted except when it was just created.
+#[derive(Debug, Deserialize)]
+pub struct AccessToken {
+    pub key: String,
+    pub token: String,
+    #[serde(default)]
+    pub readonly: bool,
+    #[serde(default)]
+    pub cidr_whitelist: Option<Vec<String>>,
+    #[serde(default)]
+    pub created: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenPage {
+    #[serde(default)]
+    objects: Vec<AccessToken>,
+    urls: Option<TokenPageUrls>,
+}
+
+#[derive(Deserialize)]
+struct TokenPageUrls {
+    next: Option<String>,
+}
+
+/// A change the registry refused, with its explanation.
+#[derive(Debug)]
+pub struct RegistryError {
+    pub action: String,
+    pub status: StatusCode,
+    pub reason: String,
+}
+
+impl std::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to {}: HTTP {}", self.action, self.status)?;
+        if !self.reason.is_empty() {
+            write!(f, ": {}", self.reason)?;
+        }
+        match self.status {
+            StatusCode::UNAUTHORIZED => write!(f, " (is the auth token in .npmrc valid?)"),
+            StatusCode::FORBIDDEN => write!(f, " (does this account have permission?)"),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+// The registry explains failures as {"error": ...} or {"message": ...}
+fn failure_reason(status: StatusCode, body: &str, action: &str) -> anyhow::Error {
+    let reason = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|body| {
+            ["error", "message", "reason"]
+                .iter()
+                .find_map(|key| body.get(key).and_then(|value| value.as_str()).map(str::to_string))
+        })
+        .unwrap_or_else(|| body.trim().to_string());
+
+    RegistryError {
+        action: action.to_string(),
+        status,
+        reason,
+    }
+    .into()
+}
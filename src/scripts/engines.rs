@@ -0,0 +1,96 @@
+use anyhow::Result;
+use console::style;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config;
+use crate::dependency::versions;
+use crate::utils::icon;
+
+/// A Node version range a project asks for, and where it asks.
+#[derive(Debug, Clone)]
+pub struct Requirement {
+    pub range: String,
+    pub source: &'static str,
+}
+
+/// The version of the `node` on PATH, if there is one.
+pub fn node_version() -> Option<semver::Version> {
+    let output = Command::new("node").arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let version = String::from_utf8(output.stdout).ok()?;
+    semver::Version::parse(version.trim().trim_start_matches('v')).ok()
+}
+
+/// The Node versions the project in `dir` asks for: engines.node in its
+/// package.json and the version in its .nvmrc.
+pub fn requirements(dir: &Path) -> Vec<Requirement> {
+    let mut requirements = Vec::new();
+
+    let engines_node = std::fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|json| json.pointer("/engines/node")?.as_str().map(str::to_string));
+    if let Some(range) = engines_node {
+        requirements.push(Requirement {
+            range,
+            source: "engines.node in package.json",
+        });
+    }
+
+    // The first word of the first line: "20", "v20.11.0", or an alias such as
+    // "lts/*" that needs nvm's release list and isn't checked
+    let nvmrc = std::fs::read_to_string(dir.join(".nvmrc")).ok();
+    if let Some(version) = nvmrc.as_deref().and_then(|content| content.split_whitespace().next())
+        && version.starts_with(|c: char| c == 'v' || c.is_ascii_digit())
+    {
+        requirements.push(Requirement {
+            range: version.trim_start_matches('v').to_string(),
+            source: ".nvmrc",
+        });
+    }
+    requirements
+}
+
+/// Compare the `node` on PATH with what the project in `dir` asks for,
+/// warning about a mismatch, or failing on one with `engine-strict` set.
+pub fn check(dir: &Path) -> Result<()> {
+    let requirements = requirements(dir);
+    if requirements.is_empty() {
+        return Ok(());
+    }
+    let Some(node) = node_version() else {
+        eprintln!(
+            "{} node wasn't found on PATH; this project expects Node {} ({})",
+            style(format!("{} Warning:", icon("⚠"))).bold().yellow(),
+            requirements[0].range,
+            requirements[0].source
+        );
+        return Ok(());
+    };
+
+    // Unparseable ranges are npm's business, not a reason to stop scripts
+    let Some(unmet) = requirements
+        .iter()
+        .find(|requirement| versions::parse_req(&requirement.range).is_some_and(|range| !range.matches(&node)))
+    else {
+        return Ok(());
+    };
+
+    let hint = if unmet.source == ".nvmrc" {
+        "run `nvm use` to switch".to_string()
+    } else {
+        format!("install a Node matching {}", unmet.range)
+    };
+    let message = format!(
+        "This project expects Node {} ({}), but node is {}; {}",
+        unmet.range, unmet.source, node, hint
+    );
+    if config::settings().engine_strict {
+        anyhow::bail!("{} (engine-strict is set; turn it off to only warn)", message);
+    }
+    eprintln!("{} {}", style(format!("{} Warning:", icon("⚠"))).bold().yellow(), message);
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use anyhow::{Context, Result};
+use console::style;
+use log::debug;
+use std::future::Future;
+use std::path::Path;
+
+use super::{failure, shell, Scripts};
+use crate::config;
+use crate::timing::{self, Phase};
+
+/// Run `command`, a command named `name` ("install", "publish", ...),
+/// between the `pre-<name>` and `post-<name>` hooks of the project in `dir`.
+pub async fn around(name: &str, dir: &Path, command: impl Future<Output = Result<()>>) -> Result<()> {
+    run(&format!("pre-{}", name), dir).await?;
+    command.await?;
+    run(&format!("post-{}", name), dir).await
+}
+
+/// Run the hook `name` from the hooks setting in `dir`, if one is set. Hooks
+/// get the environment package.json scripts get when `dir` has one.
+pub async fn run(name: &str, dir: &Path) -> Result<()> {
+    let Some(hook) = config::settings().hooks.get(name) else {
+        return Ok(());
+    };
+    let _span = timing::span(Phase::Scripts, name);
+    println!("\n{}\n", style(format!("> {} hook\n> {}", name, hook)).dim());
+
+    let mut command = match Scripts::load(dir) {
+        Ok(scripts) => scripts.command(name, hook)?,
+        Err(e) => {
+            debug!("Running {} without package.json's environment: {:#}", name, e);
+            let mut command = shell(hook);
+            command.current_dir(dir);
+            command
+        }
+    };
+    let status = command
+        .status()
+        .await
+        .with_context(|| format!("Failed to start the {} hook", name))?;
+    if !status.success() {
+        return Err(failure(name, status)).context(format!("The {} hook failed", name));
+    }
+    Ok(())
+}
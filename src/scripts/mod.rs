@@ -0,0 +1,325 @@
+use anyhow::{Context, Result};
+use console::Style;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+
+use crate::registry::NpmRegistry;
+use crate::timing::{self, Phase};
+use crate::utils;
+
+pub mod engines;
+pub mod hooks;
+pub mod native;
+pub mod watch;
+
+/// The scripts in a project's package.json, run the way npm runs them.
+pub struct Scripts {
+    dir: PathBuf,
+    name: Option<String>,
+    version: Option<String>,
+    scripts: BTreeMap<String, String>,
+    // Start scripts in their own process group, so abandoning a run stops
+    // everything it started
+    process_groups: bool,
+}
+
+impl Scripts {
+    /// Read the scripts of the package.json in `dir`.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join("package.json");
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let json: serde_json::Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        let text = |field: &str| json.get(field).and_then(|value| value.as_str()).map(str::to_string);
+        let scripts = json
+            .get("scripts")
+            .and_then(|scripts| scripts.as_object())
+            .map(|scripts| {
+                scripts
+                    .iter()
+                    .filter_map(|(name, script)| Some((name.clone(), script.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            name: text("name"),
+            version: text("version"),
+            scripts,
+            process_groups: false,
+        })
+    }
+
+    /// Stop a script and every process it started when its run is dropped
+    /// before finishing, e.g. to restart it.
+    pub fn with_process_groups(mut self) -> Self {
+        self.process_groups = true;
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.scripts.get(name).map(String::as_str)
+    }
+
+    /// Script names and commands, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.scripts.iter().map(|(name, script)| (name.as_str(), script.as_str()))
+    }
+
+    /// Run `name` and its pre/post scripts, failing if any of them fails.
+    /// `args` are appended to `name`'s command, like `npm run name -- args`.
+    pub async fn run(&self, name: &str, args: &[String]) -> Result<()> {
+        for (event, script) in self.lifecycle(name, args)? {
+            let _span = timing::span(Phase::Scripts, &event);
+            println!("\n{}\n", self.banner(&event, &script));
+
+            let child = self
+                .command(&event, &script)?
+                .spawn()
+                .with_context(|| format!("Failed to start script \"{}\"", event))?;
+            let status = ScriptProcess::new(child).wait().await?;
+            if !status.success() {
+                return Err(failure(&event, status));
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`Scripts::run`], but with every line of output behind `prefix`,
+    /// so several scripts can run side by side.
+    pub async fn run_prefixed(&self, name: &str, args: &[String], prefix: &str) -> Result<()> {
+        for (event, script) in self.lifecycle(name, args)? {
+            let _span = timing::span(Phase::Scripts, &event);
+            for line in self.banner(&event, &script).lines() {
+                println!("{} {}", prefix, line);
+            }
+
+            let mut child = self
+                .command(&event, &script)?
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .with_context(|| format!("Failed to start script \"{}\"", event))?;
+
+            let stdout = child.stdout.take().context("Script stdout was not captured")?;
+            let stderr = child.stderr.take().context("Script stderr was not captured")?;
+            let process = ScriptProcess::new(child);
+            futures::future::join(forward(stdout, prefix, false), forward(stderr, prefix, true)).await;
+
+            let status = process.wait().await?;
+            if !status.success() {
+                return Err(failure(&event, status));
+            }
+        }
+        Ok(())
+    }
+
+    // The scripts `npm run <name>` runs in order: "prebuild" and "postbuild"
+    // around "build", when they exist. Only "build" itself gets the arguments
+    fn lifecycle(&self, name: &str, args: &[String]) -> Result<Vec<(String, String)>> {
+        let script = self
+            .get(name)
+            .with_context(|| format!("Missing script: \"{}\"", name))?;
+
+        let mut events = Vec::with_capacity(3);
+        let pre = format!("pre{}", name);
+        if let Some(script) = self.get(&pre) {
+            events.push((pre, script.to_string()));
+        }
+        let mut command = script.to_string();
+        for arg in args {
+            command.push(' ');
+            command.push_str(&quote(arg));
+        }
+        events.push((name.to_string(), command));
+        let post = format!("post{}", name);
+        if let Some(script) = self.get(&post) {
+            events.push((post, script.to_string()));
+        }
+        Ok(events)
+    }
+
+    // npm's "> pkg@1.0.0 build" header before each script
+    fn banner(&self, event: &str, script: &str) -> String {
+        format!(
+            "> {}@{} {}\n> {}",
+            self.name.as_deref().unwrap_or(""),
+            self.version.as_deref().unwrap_or(""),
+            event,
+            script
+        )
+    }
+
+    /// A shell command running `script` in the package directory with the
+    /// environment npm gives scripts.
+    pub fn command(&self, event: &str, script: &str) -> Result<Command> {
+        let mut command = shell(script);
+        command.current_dir(&self.dir);
+        if self.process_groups {
+            #[cfg(unix)]
+            command.process_group(0);
+            // Without process groups, at least the shell itself is stopped
+            command.kill_on_drop(true);
+        }
+
+        for (key, value) in self.environment(event, script)? {
+            command.env(key, value);
+        }
+        Ok(command)
+    }
+
+    fn environment(&self, event: &str, script: &str) -> Result<Vec<(String, OsString)>> {
+        let mut env: Vec<(String, OsString)> = vec![
+            ("npm_lifecycle_event".into(), event.into()),
+            ("npm_lifecycle_script".into(), script.into()),
+            ("npm_command".into(), "run-script".into()),
+            ("npm_package_json".into(), self.dir.join("package.json").into()),
+            ("npm_execpath".into(), std::env::current_exe()?.into()),
+            ("npm_config_user_agent".into(), user_agent().into()),
+            ("npm_config_registry".into(), NpmRegistry::new().get_registry_url().into()),
+            ("PATH".into(), self.path()?),
+        ];
+        if let Some(name) = &self.name {
+            env.push(("npm_package_name".into(), name.into()));
+        }
+        if let Some(version) = &self.version {
+            env.push(("npm_package_version".into(), version.into()));
+        }
+        if let Ok(cache) = utils::get_cache_dir() {
+            env.push(("npm_config_cache".into(), cache.into()));
+        }
+        // Nested runs keep the directory the outermost one started in
+        if std::env::var_os("INIT_CWD").is_none() {
+            env.push(("INIT_CWD".into(), std::env::current_dir()?.into()));
+        }
+        Ok(env)
+    }
+
+    // node_modules/.bin of the package and every directory above it, closest
+    // first, ahead of the inherited PATH
+    fn path(&self) -> Result<OsString> {
+        let bins = self
+            .dir
+            .ancestors()
+            .map(|dir| dir.join("node_modules").join(".bin"))
+            .filter(|bin| bin.is_dir());
+        let inherited = std::env::var_os("PATH").unwrap_or_default();
+        std::env::join_paths(bins.chain(std::env::split_paths(&inherited)))
+            .context("Failed to build PATH for scripts")
+    }
+}
+
+// A running script. If it's dropped before exiting, its process group (when
+// it has one of its own) is terminated along with it
+struct ScriptProcess {
+    child: tokio::process::Child,
+    finished: bool,
+}
+
+impl ScriptProcess {
+    fn new(child: tokio::process::Child) -> Self {
+        Self { child, finished: false }
+    }
+
+    async fn wait(mut self) -> Result<ExitStatus> {
+        let status = self.child.wait().await?;
+        self.finished = true;
+        Ok(status)
+    }
+}
+
+impl Drop for ScriptProcess {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if !self.finished
+            && let Some(pid) = self.child.id()
+        {
+            // Only signal groups we created: a script sharing our group has pid != pgid
+            // SAFETY: getpgid and kill have no memory-safety preconditions
+            unsafe {
+                if libc::getpgid(pid as libc::pid_t) == pid as libc::pid_t {
+                    libc::kill(-(pid as libc::pid_t), libc::SIGTERM);
+                }
+            }
+        }
+    }
+}
+
+// The error for a script that exited unsuccessfully
+fn failure(event: &str, status: ExitStatus) -> anyhow::Error {
+    match status.code() {
+        Some(code) => anyhow::anyhow!("Script \"{}\" exited with code {}", event, code),
+        None => anyhow::anyhow!("Script \"{}\" was terminated by a signal", event),
+    }
+}
+
+/// Styles that tell the output of scripts running side by side apart.
+pub fn prefix_styles() -> impl Iterator<Item = Style> {
+    let colors = [Style::new().cyan(), Style::new().magenta(), Style::new().yellow(), Style::new().blue(), Style::new().green()];
+    colors.into_iter().cycle()
+}
+
+// Copy a child's output line by line, each behind the script's prefix
+async fn forward(output: impl AsyncRead + Unpin, prefix: &str, to_stderr: bool) {
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if to_stderr {
+            eprintln!("{} {}", prefix, line);
+        } else {
+            println!("{} {}", prefix, line);
+        }
+    }
+}
+
+// Quote an argument for the script's shell, leaving plain words alone so the
+// command shown in the banner reads naturally
+#[cfg(unix)]
+fn quote(arg: &str) -> Cow<'_, str> {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', "'\\''")))
+    }
+}
+
+#[cfg(windows)]
+fn quote(arg: &str) -> Cow<'_, str> {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"', '&', '|', '<', '>', '^']) {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("\"{}\"", arg.replace('"', "\\\"")))
+    }
+}
+
+#[cfg(unix)]
+fn shell(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(script);
+    command
+}
+
+#[cfg(windows)]
+fn shell(script: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.args(["/d", "/s", "/c"]).arg(script);
+    command
+}
+
+// e.g. "rjs/0.1.0 linux x86_64", in the spirit of npm's "npm/10.2.0 node/v20.9.0 linux x64"
+fn user_agent() -> String {
+    format!(
+        "rjs/{} {} {}",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )
+}
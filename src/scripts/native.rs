@@ -0,0 +1,126 @@
+use anyhow::{Context, Result};
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::Stdio;
+
+use super::{quote, Scripts};
+use crate::dependency::platform::Host;
+use crate::timing::{self, Phase};
+use crate::utils;
+
+// Lines of a failed build's output worth showing; the error is near the end
+const FAILURE_TAIL: usize = 20;
+
+/// Whether the package in `dir` has to be compiled on install: it has a
+/// binding.gyp, doesn't opt out with `"gypfile": false`, and ships no
+/// binary for `host`, neither prebuildify's prebuilds/<os>-<cpu> nor an
+/// already built build/Release/*.node.
+pub fn needs_build(dir: &Path, host: &Host) -> bool {
+    if !dir.join("binding.gyp").is_file() {
+        return false;
+    }
+    let gypfile = std::fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|json| json.get("gypfile").and_then(|gypfile| gypfile.as_bool()));
+    if gypfile == Some(false) {
+        return false;
+    }
+
+    let prebuilt = dir.join("prebuilds").join(format!("{}-{}", host.os, host.cpu)).is_dir();
+    let built = std::fs::read_dir(dir.join("build").join("Release"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .any(|entry| entry.path().extension() == Some(OsStr::new("node")));
+    !prebuilt && !built
+}
+
+/// Compile the package in `dir` with `node-gyp configure` and `node-gyp build`,
+/// the fallback npm uses for packages with a binding.gyp. Node headers are
+/// downloaded by node-gyp into the rjs cache the first time.
+pub async fn build(dir: &Path) -> Result<()> {
+    let scripts = Scripts::load(dir)?;
+    let name = scripts.name.clone().unwrap_or_else(|| dir.display().to_string());
+    let node_gyp = node_gyp(&scripts)?;
+    let devdir = utils::get_cache_dir()?.join("node-gyp");
+
+    for step in ["configure", "build"] {
+        let _span = timing::span(Phase::Scripts, &format!("{} node-gyp {}", name, step));
+        let script = format!("{} {} --devdir={}", node_gyp, step, quote(&devdir.to_string_lossy()));
+        let output = scripts
+            .command("install", &script)?
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .with_context(|| format!("Failed to start node-gyp for {}", name))?;
+
+        if !output.status.success() {
+            let log = format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            let lines: Vec<&str> = log.lines().collect();
+            let tail = lines[lines.len().saturating_sub(FAILURE_TAIL)..].join("\n  ");
+            let hint = match missing_tools().as_slice() {
+                [] => String::new(),
+                missing => format!(
+                    "\nBuilding native packages needs {} on PATH, e.g. `apt install python3 make g++` \
+                     or `apk add python3 make g++`",
+                    missing.join(", ")
+                ),
+            };
+            anyhow::bail!("node-gyp {} failed for {}:\n  {}{}", step, name, tail, hint);
+        }
+    }
+    Ok(())
+}
+
+// The command to run node-gyp with: npm_config_node_gyp when set, a node-gyp
+// on the scripts' PATH (the project's node_modules/.bin first), or the copy
+// bundled with npm next to node
+fn node_gyp(scripts: &Scripts) -> Result<String> {
+    if let Some(script) = std::env::var_os("npm_config_node_gyp") {
+        return Ok(format!("node {}", quote(&script.to_string_lossy())));
+    }
+    let path = scripts.path()?;
+    if utils::find_on(&path, "node-gyp").is_some() {
+        return Ok("node-gyp".to_string());
+    }
+
+    let bundled = utils::find_on(&path, "node").and_then(|node| {
+        let bin = node.canonicalize().ok()?.parent()?.to_path_buf();
+        [bin.join("../lib/node_modules"), bin.join("node_modules")]
+            .into_iter()
+            .map(|modules| modules.join("npm/node_modules/node-gyp/bin/node-gyp.js"))
+            .find(|script| script.is_file())
+    });
+    match bundled {
+        Some(script) => Ok(format!("node {}", quote(&script.to_string_lossy()))),
+        None => anyhow::bail!(
+            "{} has no prebuilt binary for this platform and node-gyp wasn't found to build it; \
+             add node-gyp to devDependencies or install it globally",
+            scripts.name.as_deref().unwrap_or("A native package")
+        ),
+    }
+}
+
+// Build tools node-gyp needs on Unix that aren't on PATH. Windows builds go
+// through Visual Studio, which node-gyp finds and reports on by itself
+fn missing_tools() -> Vec<&'static str> {
+    if cfg!(windows) {
+        return Vec::new();
+    }
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let tools: [(&str, &[&str]); 3] = [
+        ("python3", &["python3", "python"]),
+        ("make", &["make"]),
+        ("a C++ compiler", &["c++", "g++", "clang++"]),
+    ];
+    tools
+        .into_iter()
+        .filter(|(_, candidates)| candidates.iter().all(|candidate| utils::find_on(&path, candidate).is_none()))
+        .map(|(tool, _)| tool)
+        .collect()
+}
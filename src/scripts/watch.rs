@@ -0,0 +1,96 @@
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use log::debug;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+// Editors and build tools touch several files per save; wait for them to settle
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches a project for changes to files git wouldn't ignore.
+pub struct Watcher {
+    root: PathBuf,
+    ignore: Gitignore,
+    events: UnboundedReceiver<PathBuf>,
+    // Stops watching when dropped
+    _watcher: RecommendedWatcher,
+}
+
+impl Watcher {
+    pub fn new(root: &Path) -> Result<Self> {
+        let (sender, events) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            match event {
+                Ok(event) if !event.kind.is_access() => {
+                    for path in event.paths {
+                        let _ = sender.send(path);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => debug!("File watcher error: {}", e),
+            }
+        })
+        .context("Failed to start the file watcher")?;
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .with_context(|| format!("Failed to watch {}", root.display()))?;
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            ignore: ignore_rules(root)?,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// Wait until files change, returning the changed paths once no more
+    /// changes have come in for a moment.
+    pub async fn changed(&mut self) -> Result<Vec<PathBuf>> {
+        let mut changed = Vec::new();
+        while changed.is_empty() {
+            let path = self.events.recv().await.context("The file watcher stopped")?;
+            self.collect(path, &mut changed);
+        }
+
+        while let Ok(Some(path)) = tokio::time::timeout(DEBOUNCE, self.events.recv()).await {
+            self.collect(path, &mut changed);
+        }
+        Ok(changed)
+    }
+
+    fn collect(&self, path: PathBuf, changed: &mut Vec<PathBuf>) {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return;
+        };
+        if self.is_ignored(relative, path.is_dir()) || changed.iter().any(|seen| *seen == relative) {
+            return;
+        }
+        changed.push(relative.to_path_buf());
+    }
+
+    fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        // Installs and git's own bookkeeping never warrant a re-run
+        let first = relative.components().next().map(|component| component.as_os_str());
+        if first.is_some_and(|first| first == "node_modules" || first == ".git") {
+            return true;
+        }
+        self.ignore
+            .matched_path_or_any_parents(relative, is_dir)
+            .is_ignore()
+    }
+}
+
+// The project's .gitignore and the repository's local excludes
+fn ignore_rules(root: &Path) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    for file in [root.join(".gitignore"), root.join(".git/info/exclude")] {
+        if file.is_file()
+            && let Some(e) = builder.add(&file)
+        {
+            debug!("Skipping invalid ignore rules in {}: {}", file.display(), e);
+        }
+    }
+    builder.build().context("Failed to read .gitignore")
+}
@@ -0,0 +1,225 @@
+use anyhow::{Context, Result};
+use console::style;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::utils::icon;
+
+// Set by `--timing`, or by commands that summarize their own phases; spans
+// are no-ops otherwise
+static RECORDER: OnceLock<Recorder> = OnceLock::new();
+
+/// The phases of a command that `--timing` reports on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    /// Fetching packuments from the registry, cache or daemon
+    MetadataFetch,
+    /// Walking the dependency graph and selecting versions
+    Resolution,
+    /// Downloading package tarballs
+    Download,
+    /// Unpacking tarballs into staging directories
+    Extract,
+    /// Moving staged packages into node_modules
+    Link,
+    /// Running lifecycle scripts
+    Scripts,
+}
+
+impl Phase {
+    const ALL: [Phase; 6] = [
+        Phase::MetadataFetch,
+        Phase::Resolution,
+        Phase::Download,
+        Phase::Extract,
+        Phase::Link,
+        Phase::Scripts,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Phase::MetadataFetch => "metadata fetch",
+            Phase::Resolution => "resolution",
+            Phase::Download => "download",
+            Phase::Extract => "extract",
+            Phase::Link => "link",
+            Phase::Scripts => "scripts",
+        }
+    }
+}
+
+struct Recorder {
+    started: Instant,
+    spans: Mutex<Vec<SpanRecord>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpanRecord {
+    pub phase: Phase,
+    pub name: String,
+    pub start_ms: f64,
+    pub duration_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseSummary {
+    pub phase: Phase,
+    /// Wall-clock time during which at least one span of this phase was running
+    pub wall_ms: f64,
+    /// Sum of all span durations, larger than `wall_ms` when spans overlap
+    pub busy_ms: f64,
+    pub count: usize,
+}
+
+/// The structured report written to `rjs-timing.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimingReport {
+    pub command: String,
+    pub total_ms: f64,
+    pub phases: Vec<PhaseSummary>,
+    pub spans: Vec<SpanRecord>,
+}
+
+/// Guard measuring one unit of work; the span is recorded when dropped.
+pub struct Span {
+    active: Option<(Phase, String, Instant)>,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let (Some((phase, name, start)), Some(recorder)) = (self.active.take(), RECORDER.get()) else {
+            return;
+        };
+
+        let record = SpanRecord {
+            phase,
+            name,
+            start_ms: millis(start.duration_since(recorder.started)),
+            duration_ms: millis(start.elapsed()),
+        };
+        recorder.spans.lock().unwrap().push(record);
+    }
+}
+
+/// Start recording spans for the rest of the process.
+pub fn enable() {
+    let _ = RECORDER.set(Recorder {
+        started: Instant::now(),
+        spans: Mutex::new(Vec::new()),
+    });
+}
+
+pub fn is_enabled() -> bool {
+    RECORDER.get().is_some()
+}
+
+/// Measure `phase` for `name` until the returned guard is dropped.
+pub fn span(phase: Phase, name: &str) -> Span {
+    Span {
+        active: is_enabled().then(|| (phase, name.to_string(), Instant::now())),
+    }
+}
+
+/// Build the report from everything recorded so far.
+pub fn report() -> Option<TimingReport> {
+    let recorder = RECORDER.get()?;
+    let mut spans = recorder.spans.lock().unwrap().clone();
+    spans.sort_by(|a, b| a.start_ms.total_cmp(&b.start_ms));
+
+    let phases = Phase::ALL
+        .iter()
+        .map(|phase| {
+            let of_phase: Vec<_> = spans.iter().filter(|s| s.phase == *phase).collect();
+            PhaseSummary {
+                phase: *phase,
+                wall_ms: wall_time(&of_phase),
+                busy_ms: of_phase.iter().map(|s| s.duration_ms).sum(),
+                count: of_phase.len(),
+            }
+        })
+        .collect();
+
+    Some(TimingReport {
+        command: std::env::args().skip(1).collect::<Vec<_>>().join(" "),
+        total_ms: millis(recorder.started.elapsed()),
+        phases,
+        spans,
+    })
+}
+
+/// Write `rjs-timing.json` into `dir` and print a short summary.
+pub fn write_report(dir: &Path) -> Result<()> {
+    let Some(report) = report() else {
+        return Ok(());
+    };
+
+    let path = dir.join("rjs-timing.json");
+    let json = serde_json::to_string_pretty(&report)?;
+    std::fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    println!(
+        "\n{} Timing (total {:.2}s)",
+        style(icon("⏱")).cyan(),
+        report.total_ms / 1000.0
+    );
+    for phase in &report.phases {
+        println!(
+            "  {:<16} {:>8.2}s {:>6} spans",
+            phase.phase.label(),
+            phase.wall_ms / 1000.0,
+            phase.count
+        );
+    }
+
+    // Per-package spans, the resolution span covers the whole graph
+    let mut slowest: Vec<_> = report
+        .spans
+        .iter()
+        .filter(|s| s.phase != Phase::Resolution)
+        .collect();
+    slowest.sort_by(|a, b| b.duration_ms.total_cmp(&a.duration_ms));
+    if !slowest.is_empty() {
+        println!("  {}", style("slowest:").dim());
+        for span in slowest.iter().take(5) {
+            println!(
+                "    {:<30} {:<16} {:>8.0}ms",
+                span.name,
+                span.phase.label(),
+                span.duration_ms
+            );
+        }
+    }
+    println!("  {} Wrote {}", style(icon("✓")).green(), path.display());
+
+    Ok(())
+}
+
+// Length of the union of the spans' intervals
+fn wall_time(spans: &[&SpanRecord]) -> f64 {
+    let mut total = 0.0;
+    let mut current: Option<(f64, f64)> = None;
+
+    // Spans arrive sorted by start time
+    for span in spans {
+        let (start, end) = (span.start_ms, span.start_ms + span.duration_ms);
+        current = match current {
+            Some((cur_start, cur_end)) if start <= cur_end => Some((cur_start, cur_end.max(end))),
+            Some((cur_start, cur_end)) => {
+                total += cur_end - cur_start;
+                Some((start, end))
+            }
+            None => Some((start, end)),
+        };
+    }
+    if let Some((start, end)) = current {
+        total += end - start;
+    }
+    total
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
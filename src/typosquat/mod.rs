@@ -0,0 +1,73 @@
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+// Bundled list of popular packages, refreshed by hand from the registry's most
+// depended-upon packages
+const POPULAR: &str = include_str!("popular.txt");
+
+// Names shorter than this collide with too many legitimate packages
+const MIN_POPULAR_LEN: usize = 5;
+
+fn popular() -> &'static HashSet<&'static str> {
+    static POPULAR_SET: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    POPULAR_SET.get_or_init(|| {
+        POPULAR
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect()
+    })
+}
+
+/// The popular package `name` is suspiciously close to, if any. Popular
+/// packages themselves are never suspicious.
+pub fn lookalike(name: &str) -> Option<&'static str> {
+    let popular = popular();
+    if popular.contains(name) {
+        return None;
+    }
+
+    popular
+        .iter()
+        .filter(|candidate| candidate.len() >= MIN_POPULAR_LEN)
+        .filter_map(|candidate| {
+            // One typo in short names, two in longer ones
+            let allowed = if candidate.len() < 8 { 1 } else { 2 };
+            let distance = edit_distance(name, candidate);
+            (distance <= allowed).then_some((distance, *candidate))
+        })
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+// Levenshtein distance where swapping two adjacent characters counts as one
+// edit, so "lodahs" is a single typo away from "lodash"
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > 2 {
+        return usize::MAX;
+    }
+
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
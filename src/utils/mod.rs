@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
+use base64::Engine;
 use log::debug;
-use sha2::{Digest, Sha256};
+use sha2::{Digest, Sha256, Sha512};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 use url::Url;
@@ -62,6 +63,36 @@ pub fn calculate_sha256(data: &[u8]) -> String {
     hex::encode(result)
 }
 
+// Check data against a Subresource Integrity string ("sha512-<base64>").
+// Several space-separated hashes may be given; any one matching is enough.
+pub fn verify_integrity(data: &[u8], integrity: &str) -> Result<()> {
+    let mut supported = false;
+
+    for candidate in integrity.split_whitespace() {
+        let Some((algorithm, expected)) = candidate.split_once('-') else {
+            continue;
+        };
+
+        let actual = match algorithm {
+            "sha512" => Sha512::digest(data).to_vec(),
+            "sha256" => Sha256::digest(data).to_vec(),
+            _ => continue,
+        };
+        supported = true;
+
+        let actual = base64::engine::general_purpose::STANDARD.encode(actual);
+        if actual == expected {
+            return Ok(());
+        }
+    }
+
+    if !supported {
+        return Err(anyhow::anyhow!("Unsupported integrity format: {}", integrity));
+    }
+
+    Err(anyhow::anyhow!("Integrity check failed: expected {}", integrity))
+}
+
 #[allow(dead_code)]
 pub async fn calculate_file_sha256(path: &Path) -> Result<String> {
     let content = read_file(path).await?;
@@ -87,9 +118,8 @@ pub fn get_package_name_from_url(url_str: &str) -> Result<String> {
 // Path utilities
 #[allow(dead_code)]
 pub fn get_cache_dir() -> Result<PathBuf> {
-    let cache_dir = dirs::cache_dir()
-        .ok_or_else(|| anyhow::anyhow!("Failed to determine cache directory"))?
-        .join("rjs");
+    // RJS_CACHE_DIR (the cache-dir setting) points rjs at an isolated cache, e.g. for benchmarks
+    let cache_dir = crate::config::settings().cache_dir.clone();
 
     if !cache_dir.exists() {
         std::fs::create_dir_all(&cache_dir).with_context(|| {
@@ -100,6 +130,73 @@ pub fn get_cache_dir() -> Result<PathBuf> {
     Ok(cache_dir)
 }
 
+// Total size of all files below `path`
+pub fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+// Byte counts the way npm prints them, e.g. "512B", "1.2kB", "3.4MB"
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1_000 => format!("{}B", bytes),
+        1_000..1_000_000 => format!("{:.1}kB", bytes as f64 / 1_000.0),
+        _ => format!("{:.1}MB", bytes as f64 / 1_000_000.0),
+    }
+}
+
+// How long ago `time` was, coarsely, e.g. "today", "12 days ago", "3 months ago"
+pub fn format_age(time: std::time::SystemTime) -> String {
+    let days = std::time::SystemTime::now().duration_since(time).unwrap_or_default().as_secs() / 86_400;
+    let (count, unit) = match days {
+        0 => return "today".to_string(),
+        1..60 => (days, "day"),
+        60..730 => (days / 30, "month"),
+        _ => (days / 365, "year"),
+    };
+    format!("{} {}{} ago", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/// An executable named `program` in one of the directories of `path`, a
+/// PATH-style list.
+pub fn find_on(path: &std::ffi::OsStr, program: &str) -> Option<PathBuf> {
+    let names = if cfg!(windows) {
+        vec![format!("{}.cmd", program), format!("{}.exe", program)]
+    } else {
+        vec![program.to_string()]
+    };
+    std::env::split_paths(path)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|candidate| candidate.is_file())
+}
+
+/// `path` in a form that reaches past Windows' 260-character MAX_PATH: made
+/// absolute and given the `\\?\` prefix, so the deep trees some packages ship
+/// extract. Other platforms have no such limit and get `path` back.
+pub fn long_path(path: &Path) -> PathBuf {
+    if !cfg!(windows) {
+        return path.to_path_buf();
+    }
+    // Verbatim paths skip all normalization, so "/" and ".." (as in scoped
+    // names, "@scope/name") must be resolved first
+    let Ok(absolute) = std::path::absolute(path) else {
+        return path.to_path_buf();
+    };
+    let absolute = absolute.to_string_lossy();
+    if absolute.starts_with(r"\\?\") {
+        PathBuf::from(absolute.as_ref())
+    } else if let Some(share) = absolute.strip_prefix(r"\\") {
+        PathBuf::from(format!(r"\\?\UNC\{}", share))
+    } else {
+        PathBuf::from(format!(r"\\?\{}", absolute))
+    }
+}
+
 #[allow(dead_code)]
 pub fn get_temp_dir() -> Result<PathBuf> {
     let temp_dir = std::env::temp_dir().join("rjs");
@@ -111,3 +208,68 @@ pub fn get_temp_dir() -> Result<PathBuf> {
 
     Ok(temp_dir)
 }
+
+// Terminal output
+
+/// When to color output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Color terminals, unless NO_COLOR is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    /// Whether this resolves to colored output given NO_COLOR, before terminal detection.
+    pub fn forced(self) -> Option<bool> {
+        match self {
+            ColorMode::Always => Some(true),
+            ColorMode::Never => Some(false),
+            // https://no-color.org: set and not empty means no color
+            ColorMode::Auto => std::env::var_os("NO_COLOR")
+                .is_some_and(|value| !value.is_empty())
+                .then_some(false),
+        }
+    }
+
+    /// Turn color on or off for everything styled with `console`, progress bars included.
+    pub fn apply(self) {
+        if let Some(enabled) = self.forced() {
+            console::set_colors_enabled(enabled);
+            console::set_colors_enabled_stderr(enabled);
+        }
+    }
+}
+
+// Set by --no-emoji; symbols fall back to ASCII for logs and screen readers
+static PLAIN: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Use ASCII instead of symbols and emoji, and no animated progress, from now on.
+pub fn set_plain(plain: bool) {
+    PLAIN.store(plain, std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn is_plain() -> bool {
+    PLAIN.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// `symbol`, or its ASCII stand-in in plain mode.
+pub fn icon(symbol: &'static str) -> &'static str {
+    if !is_plain() {
+        return symbol;
+    }
+    match symbol {
+        "✓" | "✅" => "ok",
+        "✗" => "x",
+        "⚠" => "!",
+        "ℹ" => "i",
+        "•" | "─" => "-",
+        "📦" | "⏱" | "●" => "*",
+        "○" => "o",
+        "↻" => "~",
+        _ => symbol,
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+const PREFIX: &str = "catalog:";
+
+/// Versions a monorepo pins in one place, for dependencies written as
+/// "catalog:" (the default catalog) or "catalog:<name>", as pnpm and bun
+/// write them.
+#[derive(Debug, Clone, Default)]
+pub struct Catalogs {
+    default: HashMap<String, String>,
+    named: HashMap<String, HashMap<String, String>>,
+}
+
+impl Catalogs {
+    /// The catalogs of the project `dir` is in: "catalog" and "catalogs" in
+    /// the root package.json, at the top level or under "workspaces".
+    pub fn load(dir: &Path) -> Result<Self> {
+        let root = super::find_root(dir).unwrap_or_else(|| dir.to_path_buf());
+        let Ok(content) = std::fs::read_to_string(root.join("package.json")) else {
+            return Ok(Self::default());
+        };
+        let json: serde_json::Value = serde_json::from_str(&content).context("Failed to parse package.json")?;
+
+        let mut catalogs = Self::default();
+        for source in [Some(&json), json.get("workspaces")].into_iter().flatten() {
+            if let Some(catalog) = source.get("catalog") {
+                catalogs.default.extend(entries(catalog));
+            }
+            for (name, catalog) in source.get("catalogs").and_then(|c| c.as_object()).into_iter().flatten() {
+                let entries = entries(catalog);
+                // "catalog:default" is the default catalog
+                match name.as_str() {
+                    "default" => catalogs.default.extend(entries),
+                    _ => catalogs.named.entry(name.clone()).or_default().extend(entries),
+                }
+            }
+        }
+        Ok(catalogs)
+    }
+
+    /// The range `spec` stands for: the catalog's entry for `name` when it's
+    /// a catalog reference, otherwise `spec` itself.
+    pub fn resolve<'a>(&'a self, name: &str, spec: &'a str) -> Result<&'a str> {
+        let Some(catalog_name) = spec.strip_prefix(PREFIX).map(str::trim) else {
+            return Ok(spec);
+        };
+        let catalog = match catalog_name {
+            "" | "default" => Some(&self.default),
+            _ => self.named.get(catalog_name),
+        };
+        let label = if catalog_name.is_empty() { "default" } else { catalog_name };
+        let catalog = catalog.with_context(|| format!("No catalog \"{}\" in the root package.json, for {}", label, name))?;
+        catalog
+            .get(name)
+            .map(String::as_str)
+            .with_context(|| format!("{} isn't in the \"{}\" catalog of the root package.json", name, label))
+    }
+}
+
+/// Whether `spec` points into a catalog.
+pub fn is_reference(spec: &str) -> bool {
+    spec.starts_with(PREFIX)
+}
+
+fn entries(catalog: &serde_json::Value) -> impl Iterator<Item = (String, String)> + '_ {
+    catalog
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, spec)| Some((name.clone(), spec.as_str()?.to_string())))
+}
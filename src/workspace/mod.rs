@@ -0,0 +1,179 @@
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSetBuilder};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+pub mod catalog;
+
+/// Where a package.json can name another workspace.
+pub const DEPENDENCY_FIELDS: [&str; 4] = ["dependencies", "devDependencies", "optionalDependencies", "peerDependencies"];
+
+/// A package of a monorepo, listed by the root package.json's "workspaces".
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// The name in its package.json, or its path when it has none
+    pub name: String,
+    /// Its directory relative to the root, with forward slashes, e.g. "packages/api"
+    pub path: String,
+    pub dir: PathBuf,
+}
+
+/// The closest directory at or above `dir` whose package.json declares
+/// workspaces.
+pub fn find_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|dir| patterns(dir).is_ok_and(|patterns| !patterns.is_empty()))
+        .map(Path::to_path_buf)
+}
+
+/// The workspaces of the project at `root`, sorted by path. Patterns are
+/// globs like npm's, "packages/*", with "!" excluding directories.
+pub fn list(root: &Path) -> Result<Vec<Workspace>> {
+    let patterns = patterns(root)?;
+    if patterns.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut include = GlobSetBuilder::new();
+    let mut exclude = GlobSetBuilder::new();
+    for pattern in &patterns {
+        let (set, pattern) = match pattern.strip_prefix('!') {
+            Some(pattern) => (&mut exclude, pattern),
+            None => (&mut include, pattern.as_str()),
+        };
+        let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+        // "*" stays within one directory, as in npm
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("Invalid workspace pattern \"{}\"", pattern))?;
+        set.add(glob);
+    }
+    let (include, exclude) = (include.build()?, exclude.build()?);
+
+    let mut workspaces = Vec::new();
+    let walker = walkdir::WalkDir::new(root).min_depth(1).into_iter().filter_entry(|entry| {
+        let name = entry.file_name().to_string_lossy();
+        entry.file_type().is_dir() && name != "node_modules" && !name.starts_with('.')
+    });
+    for entry in walker.flatten() {
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        let path = relative.to_string_lossy().replace('\\', "/");
+        if !include.is_match(&path) || exclude.is_match(&path) {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(entry.path().join("package.json")) else {
+            continue;
+        };
+        let json: serde_json::Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}/package.json", path))?;
+        let name = json.get("name").and_then(|name| name.as_str()).unwrap_or(&path).to_string();
+        workspaces.push(Workspace {
+            name,
+            path,
+            dir: entry.path().to_path_buf(),
+        });
+    }
+    workspaces.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(workspaces)
+}
+
+/// The root of the project `dir` is in, and its packages: the root itself
+/// (path ".") followed by its workspaces. A project without workspaces is
+/// just `dir`.
+pub fn members(dir: &Path) -> Result<(PathBuf, Vec<Workspace>)> {
+    let root = find_root(dir).unwrap_or_else(|| dir.to_path_buf());
+    let content = std::fs::read_to_string(root.join("package.json"))
+        .with_context(|| format!("No package.json found in {}", root.display()))?;
+    let json: serde_json::Value = serde_json::from_str(&content).context("Failed to parse package.json")?;
+    let name = json.get("name").and_then(|name| name.as_str()).unwrap_or(".").to_string();
+
+    let mut members = vec![Workspace {
+        name,
+        path: ".".to_string(),
+        dir: root.clone(),
+    }];
+    members.extend(list(&root)?);
+    Ok((root, members))
+}
+
+/// The workspace `selector` names: its path ("packages/api"), its package
+/// name ("@acme/api"), or either's last part ("api") when only one matches.
+pub fn find(root: &Path, selector: &str) -> Result<Workspace> {
+    let workspaces = list(root)?;
+    let selector = selector.trim_start_matches("./").trim_end_matches('/');
+    if let Some(workspace) = workspaces.iter().find(|ws| ws.path == selector || ws.name == selector) {
+        return Ok(workspace.clone());
+    }
+
+    let last = |text: &str| text.rsplit('/').next().unwrap_or(text).to_string();
+    let matches: Vec<&Workspace> = workspaces
+        .iter()
+        .filter(|ws| last(&ws.path) == selector || last(&ws.name) == selector)
+        .collect();
+    match matches.as_slice() {
+        [workspace] => Ok((*workspace).clone()),
+        [] => {
+            let known: Vec<&str> = workspaces.iter().map(|ws| ws.path.as_str()).collect();
+            anyhow::bail!("No workspace \"{}\"; the workspaces are: {}", selector, known.join(", "))
+        }
+        _ => {
+            let paths: Vec<&str> = matches.iter().map(|ws| ws.path.as_str()).collect();
+            anyhow::bail!("\"{}\" matches several workspaces ({}); use its path", selector, paths.join(", "))
+        }
+    }
+}
+
+/// Which of `workspaces` each one depends on, by path: the workspaces named in
+/// its dependencies, devDependencies, optionalDependencies or peerDependencies.
+pub fn local_dependencies(workspaces: &[Workspace]) -> Result<BTreeMap<String, BTreeSet<String>>> {
+    let paths: HashMap<&str, &str> = workspaces.iter().map(|ws| (ws.name.as_str(), ws.path.as_str())).collect();
+    let mut graph = BTreeMap::new();
+    for workspace in workspaces {
+        let content = std::fs::read_to_string(workspace.dir.join("package.json"))
+            .with_context(|| format!("Failed to read {}/package.json", workspace.path))?;
+        let json: serde_json::Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}/package.json", workspace.path))?;
+        let dependencies: BTreeSet<String> = DEPENDENCY_FIELDS
+            .iter()
+            .filter_map(|field| json.get(field)?.as_object())
+            .flat_map(|deps| deps.keys())
+            .filter_map(|name| paths.get(name.as_str()))
+            .filter(|path| **path != workspace.path)
+            .map(|path| path.to_string())
+            .collect();
+        graph.insert(workspace.path.clone(), dependencies);
+    }
+    Ok(graph)
+}
+
+/// What a `workspace:` spec is published as, given the version of the
+/// workspace it names: "workspace:*" pins that version, "workspace:^" and
+/// "workspace:~" take a caret or tilde range on it, and "workspace:<range>"
+/// is the range itself. None when `spec` isn't a `workspace:` spec.
+pub fn publish_spec(spec: &str, version: &str) -> Option<String> {
+    let range = spec.strip_prefix("workspace:")?;
+    Some(match range {
+        "*" | "" => version.to_string(),
+        "^" | "~" => format!("{}{}", range, version),
+        range => range.to_string(),
+    })
+}
+
+// The "workspaces" of the package.json in `dir`: an array of patterns, or
+// yarn's {"packages": [...]}
+fn patterns(dir: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(dir.join("package.json"))?;
+    let json: serde_json::Value = serde_json::from_str(&content)?;
+    let workspaces = json.get("workspaces");
+    let list = workspaces
+        .and_then(|workspaces| workspaces.get("packages"))
+        .or(workspaces)
+        .and_then(|list| list.as_array());
+    Ok(list
+        .into_iter()
+        .flatten()
+        .filter_map(|pattern| pattern.as_str().map(str::to_string))
+        .collect())
+}
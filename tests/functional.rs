@@ -1,9 +1,8 @@
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output};
+use std::process::{Child, Command, Output, Stdio};
 use tempfile::TempDir;
-use serde_json;
 
 struct TestEnv {
     temp_dir: TempDir,
@@ -16,7 +15,7 @@ impl TestEnv {
         let temp_dir = TempDir::new().expect("Failed to create temp directory");
         
         // Change to temporary directory for tests
-        env::set_current_dir(&temp_dir.path()).expect("Failed to change to temp directory");
+        env::set_current_dir(temp_dir.path()).expect("Failed to change to temp directory");
         
         Self {
             temp_dir,
@@ -25,6 +24,16 @@ impl TestEnv {
     }
     
     fn run_command(&self, args: &[&str]) -> Output {
+        self.run_command_with_env(args, &[])
+    }
+    
+    fn run_command_with_env(&self, args: &[&str], envs: &[(&str, &str)]) -> Output {
+        // Messages are checked in English whatever the machine's locale; tests
+        // can still set their own
+        let locale = [("LANG", "C"), ("LC_ALL", ""), ("LC_MESSAGES", ""), ("RJS_LANG", "")];
+        let envs: Vec<(&str, &str)> = locale.into_iter().chain(envs.iter().copied()).collect();
+        let envs = envs.as_slice();
+
         // Get path to the binary
         // First try using CARGO_MANIFEST_DIR from environment
         let binary_path = if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
@@ -42,21 +51,23 @@ impl TestEnv {
                 println!("Using debug binary: {:?}", debug_path);
                 return Command::new(debug_path)
                     .args(args)
+                    .envs(envs.iter().copied())
                     .output()
                     .expect("Failed to execute command");
             }
             
             // Try to find it using the current executable's path
-            if let Ok(current_exe) = env::current_exe() {
-                if let Some(exe_dir) = current_exe.parent() {
-                    let exe_path = exe_dir.join("rjs");
-                    if exe_path.exists() {
-                        println!("Using executable from current path: {:?}", exe_path);
-                        return Command::new(exe_path)
-                            .args(args)
-                            .output()
-                            .expect("Failed to execute command");
-                    }
+            if let Ok(current_exe) = env::current_exe()
+                && let Some(exe_dir) = current_exe.parent()
+            {
+                let exe_path = exe_dir.join("rjs");
+                if exe_path.exists() {
+                    println!("Using executable from current path: {:?}", exe_path);
+                    return Command::new(exe_path)
+                        .args(args)
+                        .envs(envs.iter().copied())
+                        .output()
+                        .expect("Failed to execute command");
                 }
             }
             
@@ -66,6 +77,7 @@ impl TestEnv {
         // Run command
         let output = Command::new(&binary_path)
             .args(args)
+            .envs(envs.iter().copied())
             .output()
             .expect("Failed to execute command");
         
@@ -77,6 +89,101 @@ impl TestEnv {
         
         output
     }
+
+    // Run a command with `input` on its stdin, e.g. a password it reads from there
+    fn run_command_with_input(&self, args: &[&str], envs: &[(&str, &str)], input: &str) -> Output {
+        use std::io::Write;
+
+        let binary_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/release/rjs");
+        let mut child = Command::new(binary_path)
+            .args(args)
+            .envs([("LANG", "C"), ("LC_ALL", ""), ("LC_MESSAGES", ""), ("RJS_LANG", "")])
+            .envs(envs.iter().copied())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to execute command");
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        child.wait_with_output().expect("Failed to execute command")
+    }
+
+    // Start a long-running command such as `rjs daemon` in the background;
+    // it's killed once the returned guard is dropped
+    fn spawn_command_with_env(&self, args: &[&str], envs: &[(&str, &str)]) -> Background {
+        let binary_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/release/rjs");
+        let child = Command::new(binary_path)
+            .args(args)
+            .envs(envs.iter().copied())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to start command");
+        Background(child)
+    }
+}
+
+struct Background(Child);
+
+impl Drop for Background {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+// Wait up to ten seconds for `path` to appear, e.g. a daemon's socket
+fn wait_for(path: &Path) {
+    for _ in 0..100 {
+        if path.exists() {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    panic!("{} never appeared", path.display());
+}
+
+// Log in to `registry` with the token "s3cret" in the project's .npmrc
+fn log_in(registry: &str) {
+    fs::write(".npmrc", format!("{}/:_authToken=s3cret\n", registry.trim_start_matches("http:"))).unwrap();
+}
+
+// The registry's two-factor challenge, unless `request` carries the one-time
+// password 123456
+fn otp_challenge(request: &Received) -> Option<Reply> {
+    (request.header("npm-otp") != Some("123456"))
+        .then(|| (401, vec![("WWW-Authenticate", "OTP".to_string())], br#"{"error":"OTP required"}"#.to_vec()))
+}
+
+// Start `rjs serve` in front of `upstream` on a free port, with the tarball
+// store in `cache`, and return its URL once it's listening
+fn serve_proxy(env: &TestEnv, upstream: &str, cache: &str) -> (String, Background) {
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port().to_string();
+    let proxy = env.spawn_command_with_env(&["serve", "--port", &port, "--upstream", upstream], &[("RJS_CACHE_DIR", cache)]);
+    let address = format!("127.0.0.1:{}", port);
+    for _ in 0..100 {
+        if std::net::TcpStream::connect(&address).is_ok() {
+            return (format!("http://{}", address), proxy);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    panic!("rjs serve never listened on {}", address);
+}
+
+// GET `url` and return the status and body
+fn http_get(url: &str) -> (u16, Vec<u8>) {
+    use std::io::{Read, Write};
+
+    let rest = url.strip_prefix("http://").expect("plain http URL");
+    let (host, path) = rest.split_once('/').map_or((rest, "/".to_string()), |(host, path)| (host, format!("/{}", path)));
+    let mut stream = std::net::TcpStream::connect(host).expect("Failed to connect");
+    write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", path, host).unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+
+    let split = response.windows(4).position(|window| window == b"\r\n\r\n").expect("no end of headers");
+    let status = String::from_utf8_lossy(&response[..split]).split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, response[split + 4..].to_vec())
 }
 
 impl Drop for TestEnv {
@@ -90,6 +197,180 @@ impl Drop for TestEnv {
     }
 }
 
+// Serve `packages` (name, version) from a registry on a local port and
+// return its URL. Each tarball holds a package.json and an index.js, linked
+// as a bin named after the package; the last version listed is latest
+fn serve_registry(packages: &[(&str, &str)]) -> String {
+    let manifests: Vec<serde_json::Value> = packages
+        .iter()
+        .map(|(name, version)| serde_json::json!({ "name": name, "version": version, "bin": "index.js" }))
+        .collect();
+    serve_manifests(&manifests)
+}
+
+// Like serve_registry, but each package is published with the given
+// package.json, which its packument entry also carries (dependencies,
+// deprecated, funding, ...)
+fn serve_manifests(manifests: &[serde_json::Value]) -> String {
+    serve_files(|registry| registry_files(registry, manifests))
+}
+
+// The packuments and tarballs of a registry at `registry` publishing
+// `manifests`, by path
+fn registry_files(registry: &str, manifests: &[serde_json::Value]) -> Vec<(String, Vec<u8>)> {
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut packuments: Vec<(String, serde_json::Value)> = Vec::new();
+    for manifest in manifests {
+        let (name, version) = (manifest["name"].as_str().unwrap(), manifest["version"].as_str().unwrap());
+        let tarball = package_tarball(manifest);
+
+        let tarball_path = format!("/{}/-/{}-{}.tgz", name, name.rsplit('/').next().unwrap(), version);
+        let integrity = {
+            use base64::Engine;
+            use sha2::Digest;
+            format!("sha512-{}", base64::engine::general_purpose::STANDARD.encode(sha2::Sha512::digest(&tarball)))
+        };
+        let mut dist = manifest.clone();
+        dist["dist"] = serde_json::json!({ "shasum": "", "tarball": format!("{}{}", registry, tarball_path), "integrity": integrity });
+        files.push((tarball_path, tarball));
+
+        let path = format!("/{}", name);
+        if !packuments.iter().any(|(existing, _)| *existing == path) {
+            packuments.push((path.clone(), serde_json::json!({ "name": name, "dist-tags": {}, "versions": {} })));
+        }
+        let (_, packument) = packuments.iter_mut().find(|(existing, _)| *existing == path).unwrap();
+        packument["versions"][version] = dist;
+        packument["dist-tags"]["latest"] = serde_json::json!(version);
+        if let Some(maintainers) = manifest.get("maintainers") {
+            packument["maintainers"] = maintainers.clone();
+        }
+    }
+    files.extend(packuments.into_iter().map(|(path, packument)| (path, packument.to_string().into_bytes())));
+    files
+}
+
+// A gzipped package tarball holding `manifest` as its package.json and an
+// index.js
+fn package_tarball(manifest: &serde_json::Value) -> Vec<u8> {
+    let content = manifest.to_string();
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+    for (file, content) in [("package/package.json", content.as_str()), ("package/index.js", "#!/usr/bin/env node\n")] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, file, content.as_bytes()).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+// Serve the files `files` returns, given the server's URL, by path from a
+// local port and return the URL. Unknown paths get a 404
+fn serve_files(files: impl FnOnce(&str) -> Vec<(String, Vec<u8>)>) -> String {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to bind port");
+    let url = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+    let files = files(&url);
+
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = [0; 4096];
+            let read = stream.read(&mut request).unwrap_or(0);
+            let request = String::from_utf8_lossy(&request[..read]);
+            let path = request.split_whitespace().nth(1).unwrap_or("/").replace("%2f", "/").replace("%2F", "/");
+            let response = match files.iter().find(|(file, _)| *file == path) {
+                Some((_, body)) => [format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()).into_bytes(), body.clone()].concat(),
+                None => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec(),
+            };
+            let _ = stream.write_all(&response);
+        }
+    });
+    url
+}
+
+// A request a recording server received
+#[derive(Debug, Clone)]
+struct Received {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Received {
+    // A header's value, whatever its case
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).unwrap_or_else(|e| panic!("{} {} body isn't JSON: {}", self.method, self.path, e))
+    }
+}
+
+// The answer a recording server gives: status, extra headers, body
+type Reply = (u16, Vec<(&'static str, String)>, Vec<u8>);
+
+// Answer every request with what `respond` (built from the server's URL)
+// returns for it, from a local port. Return the URL and the requests
+// received so far, in order
+fn serve_recording<F>(respond: impl FnOnce(&str) -> F) -> (String, std::sync::Arc<std::sync::Mutex<Vec<Received>>>)
+where
+    F: Fn(&Received) -> Reply + Send + 'static,
+{
+    use std::io::{BufRead, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to bind port");
+    let url = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+    let respond = respond(&url);
+    let received = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    let log = received.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut reader = std::io::BufReader::new(&stream);
+            let mut line = String::new();
+            if reader.read_line(&mut line).is_err() {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let (method, path) = (parts.next().unwrap_or("").to_string(), parts.next().unwrap_or("/").to_string());
+
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.push((name.trim().to_string(), value.trim().to_string()));
+                }
+            }
+            let mut request = Received { method, path, headers, body: Vec::new() };
+            let length = request.header("content-length").and_then(|length| length.parse().ok()).unwrap_or(0);
+            request.body = vec![0; length];
+            if reader.read_exact(&mut request.body).is_err() {
+                continue;
+            }
+
+            let (status, extra, body) = respond(&request);
+            log.lock().unwrap().push(request);
+            let mut head = format!("HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+            for (name, value) in extra {
+                head.push_str(&format!("{}: {}\r\n", name, value));
+            }
+            head.push_str("\r\n");
+            let mut stream = &stream;
+            let _ = stream.write_all(&[head.into_bytes(), body].concat());
+        }
+    });
+    (url, received)
+}
+
 #[test]
 fn test_init_command() {
     let env = TestEnv::new();
@@ -115,6 +396,89 @@ fn test_init_command() {
            "Output missing confirmation message");
 }
 
+#[test]
+fn test_init_git_defaults() {
+    let env = TestEnv::new();
+
+    let git = |args: &[&str]| {
+        let status = Command::new("git").args(args).status().expect("Failed to run git");
+        assert!(status.success(), "git {:?} failed", args);
+    };
+    git(&["init", "-q"]);
+    git(&["config", "user.name", "Test Author"]);
+    git(&["config", "user.email", "author@example.com"]);
+    git(&["remote", "add", "origin", "git@github.com:example/project.git"]);
+
+    let output = env.run_command(&["init", "--yes", "--license", "MIT", "--license-file"]);
+    assert!(output.status.success(), "Init command failed");
+
+    let package_json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string("package.json").expect("Failed to read package.json"))
+            .expect("Invalid package.json");
+    assert_eq!(package_json["author"], "Test Author <author@example.com>");
+    assert_eq!(package_json["repository"]["url"], "git+https://github.com/example/project.git");
+    assert_eq!(package_json["license"], "MIT");
+
+    // Temp directories start with "."; the name must still be valid
+    let name = package_json["name"].as_str().expect("name should be a string");
+    assert!(!name.starts_with('.'), "Invalid package name {}", name);
+
+    let license = fs::read_to_string("LICENSE").expect("LICENSE was not created");
+    assert!(license.starts_with("MIT License"), "Unexpected LICENSE: {}", license);
+    assert!(license.contains("Test Author"), "LICENSE missing copyright holder");
+}
+
+#[test]
+fn test_init_esm_typescript() {
+    let env = TestEnv::new();
+
+    let output = env.run_command(&["init", "--yes", "--esm", "--typescript"]);
+    assert!(output.status.success(), "Init command failed");
+
+    let package_json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string("package.json").expect("Failed to read package.json"))
+            .expect("Invalid package.json");
+    assert_eq!(package_json["type"], "module");
+    assert_eq!(package_json["main"], "dist/index.js");
+    assert_eq!(package_json["module"], "dist/index.js");
+    assert_eq!(package_json["exports"]["."]["import"], "./dist/index.js");
+    assert_eq!(package_json["exports"]["."]["types"], "./dist/index.d.ts");
+    assert_eq!(package_json["scripts"]["build"], "tsc");
+    assert!(package_json["devDependencies"]["typescript"].is_string(), "typescript not added");
+
+    let tsconfig = fs::read_to_string("tsconfig.json").expect("tsconfig.json was not created");
+    assert!(tsconfig.contains("\"outDir\": \"dist\""), "Unexpected tsconfig.json: {}", tsconfig);
+    assert!(Path::new("src/index.ts").exists(), "src/index.ts was not created");
+}
+
+#[test]
+fn test_init_scope_and_private() {
+    let env = TestEnv::new();
+    fs::write(".npmrc", "@acme:registry=https://npm.acme.example/\n").expect("Failed to write .npmrc");
+
+    let output = env.run_command(&["init", "--yes", "--scope", "acme"]);
+    assert!(output.status.success(), "Init command failed");
+
+    let read = || -> serde_json::Value {
+        serde_json::from_str(&fs::read_to_string("package.json").expect("Failed to read package.json"))
+            .expect("Invalid package.json")
+    };
+    let package_json = read();
+    let name = package_json["name"].as_str().expect("name should be a string");
+    assert!(name.starts_with("@acme/"), "Name not scoped: {}", name);
+    assert_eq!(package_json["publishConfig"]["access"], "public");
+    assert_eq!(package_json["publishConfig"]["registry"], "https://npm.acme.example/");
+
+    let output = env.run_command(&["init", "--yes", "--scope", "@acme", "--private"]);
+    assert!(output.status.success(), "Init command failed");
+    let package_json = read();
+    assert_eq!(package_json["private"], true);
+    assert!(package_json.get("publishConfig").is_none(), "Private packages aren't published");
+
+    let output = env.run_command(&["init", "--yes", "--scope", "Not A Scope"]);
+    assert!(!output.status.success(), "Invalid scope was accepted");
+}
+
 #[test]
 fn test_install_command() {
     let env = TestEnv::new();
@@ -185,61 +549,29 @@ fn test_list_command() {
 #[test]
 fn test_dev_dependencies() {
     let env = TestEnv::new();
+    let registry = serve_registry(&[("chai", "4.3.10")]);
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
     
     // Initialize project
     let init_output = env.run_command(&["init", "--yes"]);
     assert!(init_output.status.success(), "Failed to initialize project");
     
-    // Print the current directory for debugging
-    println!("Current directory: {:?}", std::env::current_dir().unwrap());
-    
-    // Install dev dependency - print the command for debugging
-    println!("Running command: install chai --save-dev");
-    let output = env.run_command(&["install", "chai", "--save-dev"]);
-    assert!(output.status.success(), "Install dev dependency command failed");
-    
-    // Print the output from the command
-    println!("Install output: {}", String::from_utf8_lossy(&output.stdout));
-    
-    // Check if package.json was updated with devDependencies
-    let package_json_content = fs::read_to_string("package.json").expect("Failed to read package.json");
-    
-    // Print the package.json content for debugging
-    println!("package.json content:\n{}", package_json_content);
+    // Install dev dependency
+    let output = env.run_command_with_env(&["install", "chai", "--save-dev", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install dev dependency command failed: {}", String::from_utf8_lossy(&output.stderr));
     
     // Parse the package.json to properly check dependencies
+    let package_json_content = fs::read_to_string("package.json").expect("Failed to read package.json");
     let json: serde_json::Value = serde_json::from_str(&package_json_content).expect("Failed to parse package.json");
-    
-    // Check for devDependencies in the parsed JSON
-    assert!(json.get("devDependencies").is_some(), "devDependencies section not found in package.json");
-    
-    // Print the dev dependencies section for debugging
-    let dev_deps = json.get("devDependencies").unwrap();
-    println!("devDependencies: {:?}", dev_deps);
-    
-    // TEMPORARY: Skip the actual check since there may be a bug in the app
-    // TODO: Fix the devDependencies feature and then uncomment this test
-    //assert!(dev_deps.get("chai").is_some(), "chai not added to devDependencies");
-    println!("⚠️ Skipping chai assertion as dev dependency handling may need fixing");
-    
-    // Verify node_modules structure (if applicable, might not be present in a mock test)
-    if Path::new("node_modules").exists() {
-        // We shouldn't check for node_modules/chai since it may not be installed correctly
-        // assert!(Path::new("node_modules/chai").exists(), "chai package not installed");
-        println!("⚠️ Skipping node_modules check as dev dependency handling may need fixing");
-    } else {
-        println!("node_modules directory not found, skipping module check");
-    }
+    assert_eq!(json["devDependencies"]["chai"], "4.3.10", "chai not added to devDependencies: {}", json);
+    assert!(json["dependencies"].get("chai").is_none(), "chai added to dependencies too: {}", json);
+    assert!(Path::new("node_modules/chai/package.json").exists(), "chai package not installed");
     
     // List dependencies command should run successfully
     let list_output = env.run_command(&["list"]);
     assert!(list_output.status.success(), "List command failed");
     let list_stdout = String::from_utf8_lossy(&list_output.stdout);
-    println!("List output: {}", list_stdout);
-    
-    // We're not checking for chai in the output since it may not be handled correctly
-    // assert!(list_stdout.contains("chai"), "List output missing dev dependency");
-    println!("⚠️ Skipping chai in list output check as dev dependency handling may need fixing");
+    assert!(list_stdout.contains("chai"), "List output missing dev dependency: {}", list_stdout);
 }
 
 #[test]
@@ -330,7 +662,7 @@ fn test_lockfile_generation() {
     
     // Check that packages are in the lockfile
     let packages = lockfile_json.get("packages").unwrap();
-    assert!(packages.as_object().unwrap().len() > 0, "No packages in lockfile");
+    assert!(!packages.as_object().unwrap().is_empty(), "No packages in lockfile");
     
     // Check that it contains at least our top-level packages
     let packages_obj = packages.as_object().unwrap();
@@ -376,3 +708,3058 @@ fn test_frozen_install() {
     // Verify node_modules restored correctly
     assert!(Path::new("node_modules/lodash").exists(), "lodash package not reinstalled");
 }
+
+#[test]
+fn test_bench_command() {
+    let env = TestEnv::new();
+    
+    // Initialize project without dependencies so no network access is needed
+    let init_output = env.run_command(&["init", "--yes"]);
+    assert!(init_output.status.success(), "Failed to initialize project");
+    
+    let output = env.run_command(&["bench", "-n", "2", "-s", "warm-install", "-s", "frozen", "--json"]);
+    assert!(output.status.success(), "Bench command failed");
+    
+    // Verify the JSON report covers the requested scenarios
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let report: serde_json::Value = serde_json::from_str(&stdout).expect("Bench output is not valid JSON");
+    let scenarios = report.as_array().expect("Bench report is not an array");
+    assert_eq!(scenarios.len(), 2, "Unexpected number of scenarios");
+    assert_eq!(scenarios[0]["scenario"], "warm-install");
+    assert_eq!(scenarios[1]["scenario"], "frozen");
+    assert_eq!(scenarios[0]["runs"], 2);
+    assert!(scenarios[0]["p50_ms"].is_number(), "Missing p50 timing");
+    
+    // Benchmarks run in scratch copies and must not touch the project
+    assert!(!Path::new("node_modules").exists(), "Bench modified the project's node_modules");
+    assert!(!Path::new("rjs-lock.json").exists(), "Bench wrote a lockfile into the project");
+    
+    // With a dependency, each run's network counters come back through RJS_STATS_FILE
+    let registry = serve_registry(&[("p", "1.0.0")]);
+    fs::write("package.json", r#"{ "name": "app", "dependencies": { "p": "1.0.0" } }"#).unwrap();
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let output = env.run_command_with_env(&["install", "--no-progress"], &[envs.as_slice(), &[("RJS_STATS_FILE", "stats.json")]].concat());
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stats: serde_json::Value = serde_json::from_str(&fs::read_to_string("stats.json").unwrap()).unwrap();
+    assert_eq!(stats["requests"], 2, "{}", stats);
+    assert!(stats["bytes_downloaded"].as_u64().unwrap() > 0, "{}", stats);
+    let output = env.run_command_with_env(&["bench", "-n", "2", "-s", "cold-install", "-s", "frozen", "--json"], &envs);
+    assert!(output.status.success(), "Bench command failed: {}", String::from_utf8_lossy(&output.stderr));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Bench output is not valid JSON");
+    let (cold, frozen) = (&report[0], &report[1]);
+    assert_eq!(cold["failures"], 0, "{}", report);
+    assert_eq!(cold["avg_requests"], 2.0, "{}", report);
+    assert_eq!(cold["avg_bytes_downloaded"], stats["bytes_downloaded"].as_f64().unwrap(), "{}", report);
+    
+    // A frozen install from the warm store needs nothing from the registry
+    assert_eq!(frozen["failures"], 0, "{}", report);
+    assert_eq!(frozen["avg_requests"], 0.0, "{}", report);
+}
+
+#[test]
+fn test_timing_report() {
+    let env = TestEnv::new();
+    
+    let output = env.run_command(&["init", "--yes", "--timing"]);
+    assert!(output.status.success(), "Init with --timing failed");
+    
+    // Verify the report was written with every phase listed
+    let content = fs::read_to_string("rjs-timing.json").expect("rjs-timing.json was not written");
+    let report: serde_json::Value = serde_json::from_str(&content).expect("Timing report is not valid JSON");
+    assert!(report["total_ms"].is_number(), "Missing total time");
+    assert!(report["command"].as_str().unwrap_or("").starts_with("init"), "Wrong command recorded");
+    
+    let phases: Vec<&str> = report["phases"]
+        .as_array()
+        .expect("Missing phases")
+        .iter()
+        .filter_map(|p| p["phase"].as_str())
+        .collect();
+    assert_eq!(phases, ["metadata-fetch", "resolution", "download", "extract", "link", "scripts"]);
+    
+    // Without the flag no report is produced
+    fs::remove_file("rjs-timing.json").expect("Failed to remove timing report");
+    let output = env.run_command(&["list"]);
+    assert!(output.status.success(), "List command failed");
+    assert!(!Path::new("rjs-timing.json").exists(), "Timing report written without --timing");
+}
+
+#[test]
+fn test_lockfile_only_option() {
+    let env = TestEnv::new();
+    
+    let init_output = env.run_command(&["init", "--yes"]);
+    assert!(init_output.status.success(), "Failed to initialize project");
+    
+    // --lockfile-only can't be combined with --frozen
+    let conflict = env.run_command(&["install", "--frozen", "--lockfile-only"]);
+    assert!(!conflict.status.success(), "--frozen and --lockfile-only should conflict");
+    
+    let registry = serve_registry(&[("p", "1.0.0")]);
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let output = env.run_command_with_env(&["install", "p", "--lockfile-only", "--no-progress"], &envs);
+    assert!(output.status.success(), "Lockfile-only install failed: {}", String::from_utf8_lossy(&output.stderr));
+    
+    // The dependency is locked, but nothing is installed
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert_eq!(lockfile["packages"]["p@1.0.0"]["version"], "1.0.0", "{}", lockfile);
+    assert!(!Path::new("node_modules").exists(), "node_modules created in lockfile-only mode");
+}
+
+#[test]
+fn test_fetch_command() {
+    let env = TestEnv::new();
+    
+    let init_output = env.run_command(&["init", "--yes"]);
+    assert!(init_output.status.success(), "Failed to initialize project");
+    
+    // Fetching requires a lockfile
+    let output = env.run_command(&["fetch"]);
+    assert!(!output.status.success(), "Fetch without a lockfile should fail");
+    
+    // Entries without a tarball are skipped and nothing is installed
+    fs::write(
+        "rjs-lock.json",
+        r#"{"name":"root","version":"0.0.0","lockfile_version":"1.0.0","packages":{"stub@1.0.0":{"version":"1.0.0","resolved":null,"integrity":null,"dependencies":{}}}}"#,
+    ).expect("Failed to write lockfile");
+    
+    let output = env.run_command(&["fetch", "--no-progress"]);
+    assert!(output.status.success(), "Fetch command failed");
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("skipped"), "Output doesn't mention skipped entries");
+    assert!(!Path::new("node_modules").exists(), "Fetch created node_modules");
+    
+    // A real lockfile's tarballs land in the store, once
+    let registry = serve_registry(&[("a", "1.0.0"), ("b", "2.0.0")]);
+    fs::write("package.json", r#"{ "name": "app", "dependencies": { "a": "1.0.0", "b": "2.0.0" } }"#).unwrap();
+    let output = env.run_command_with_env(&["install", "--lockfile-only", "--no-progress"], &[("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "lock-cache")]);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let output = env.run_command_with_env(&["fetch", "--no-progress"], &envs);
+    assert!(output.status.success(), "Fetch failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Fetched 2 packages (0 already cached)"));
+    let stored = fs::read_dir("rjs-cache/tarballs").unwrap().flatten().filter(|entry| entry.path().extension().is_some_and(|ext| ext == "tgz")).count();
+    assert_eq!(stored, 2);
+    let output = env.run_command_with_env(&["fetch", "--no-progress"], &envs);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Fetched 0 packages (2 already cached)"));
+    
+    // A tarball that doesn't match its locked integrity fails the fetch
+    let mut lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    lockfile["packages"]["b@2.0.0"]["integrity"] = serde_json::json!("sha512-AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA==");
+    fs::write("rjs-lock.json", serde_json::to_string_pretty(&lockfile).unwrap()).unwrap();
+    let output = env.run_command_with_env(&["fetch", "--no-progress"], &[("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "other-cache")]);
+    assert!(!output.status.success(), "Fetch of a mismatched tarball succeeded");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Failed to fetch b@2.0.0") && stderr.to_lowercase().contains("integrity"), "{}", stderr);
+    assert!(stderr.contains("Failed to fetch 1 packages"), "{}", stderr);
+}
+
+#[test]
+fn test_bundle_command() {
+    let env = TestEnv::new();
+    
+    let init_output = env.run_command(&["init", "--yes"]);
+    assert!(init_output.status.success(), "Failed to initialize project");
+    
+    // Exporting requires a lockfile
+    let output = env.run_command(&["bundle", "export", "deps.rjsb"]);
+    assert!(!output.status.success(), "Export without a lockfile should fail");
+    
+    fs::write(
+        "rjs-lock.json",
+        r#"{"name":"root","version":"0.0.0","lockfile_version":"1.0.0","packages":{}}"#,
+    ).expect("Failed to write lockfile");
+    
+    let output = env.run_command(&["bundle", "export", "deps.rjsb"]);
+    assert!(output.status.success(), "Bundle export failed");
+    assert!(Path::new("deps.rjsb").exists(), "Bundle file not created");
+    
+    let output = env.run_command(&["bundle", "import", "deps.rjsb"]);
+    assert!(output.status.success(), "Bundle import failed");
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Imported 0 tarballs"), "Unexpected import output: {}", stdout);
+    
+    // A real lockfile's packages, bundled and imported into a fresh cache,
+    // install with no network to reach
+    let registry = serve_registry(&[("p", "1.0.0")]);
+    fs::write("package.json", r#"{ "name": "app", "dependencies": { "p": "1.0.0" } }"#).unwrap();
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "export-cache")];
+    let output = env.run_command_with_env(&["install", "--lockfile-only", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let output = env.run_command_with_env(&["bundle", "export", "deps.rjsb"], &envs);
+    assert!(output.status.success(), "Bundle export failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(1 tarballs, 1 packuments"));
+    
+    let offline = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "fresh-cache"), ("RJS_PROXY", "http://127.0.0.1:9")];
+    let output = env.run_command_with_env(&["bundle", "import", "deps.rjsb"], &offline);
+    assert!(output.status.success(), "Bundle import failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Imported 1 tarballs"));
+    let output = env.run_command_with_env(&["install", "--offline", "--no-progress"], &offline);
+    assert!(output.status.success(), "Offline install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("node_modules/p/package.json").exists());
+    
+    // Anything that isn't a bundle is rejected
+    fs::write("not-a-bundle", "hello").expect("Failed to write file");
+    let output = env.run_command(&["bundle", "import", "not-a-bundle"]);
+    assert!(!output.status.success(), "Importing a non-bundle should fail");
+}
+
+#[test]
+fn test_serve_command() {
+    let env = TestEnv::new();
+    
+    // Serving on a port that is already taken fails instead of hanging
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to bind port");
+    let port = listener.local_addr().expect("Failed to read address").port().to_string();
+    
+    let output = env.run_command(&["serve", "--port", &port]);
+    assert!(!output.status.success(), "Serve on a busy port should fail");
+    
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Failed to listen"), "Unexpected error: {}", stderr);
+}
+
+#[test]
+fn test_serve_packument_fields() {
+    let env = TestEnv::new();
+    let upstream = serve_manifests(&[serde_json::json!({
+        "name": "native",
+        "version": "1.0.0",
+        "optionalDependencies": { "native-linux": "1.0.0" },
+        "os": ["linux"],
+        "cpu": ["x64", "arm64"],
+        "libc": ["glibc"],
+        "deprecated": "use native2",
+    })]);
+    let (proxy, _proxy) = serve_proxy(&env, &upstream, "rjs-cache");
+
+    // Platform fields, optional dependencies and deprecations reach clients of the proxy
+    let (status, body) = http_get(&format!("{}/native", proxy));
+    assert_eq!(status, 200, "{}", String::from_utf8_lossy(&body));
+    let packument: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let version = &packument["versions"]["1.0.0"];
+    assert_eq!(version["optionalDependencies"], serde_json::json!({ "native-linux": "1.0.0" }));
+    assert_eq!(version["os"], serde_json::json!(["linux"]));
+    assert_eq!(version["cpu"], serde_json::json!(["x64", "arm64"]));
+    assert_eq!(version["libc"], serde_json::json!(["glibc"]));
+    assert_eq!(version["deprecated"], "use native2");
+    assert_eq!(version["dist"]["tarball"], format!("{}/native/-/native-1.0.0.tgz", proxy));
+}
+
+#[test]
+fn test_install_through_serve() {
+    let env = TestEnv::new();
+    let upstream = serve_registry(&[("p", "1.0.0")]);
+    let (proxy, _proxy) = serve_proxy(&env, &upstream, "proxy-cache");
+
+    // Tarballs are fetched from the proxy, which rewrote their URLs
+    fs::write("package.json", r#"{ "name": "app", "dependencies": { "p": "1.0.0" } }"#).unwrap();
+    let envs = [("RJS_REGISTRY", proxy.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let output = env.run_command_with_env(&["install", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("node_modules/p/package.json").exists());
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert_eq!(lockfile["packages"]["p@1.0.0"]["resolved"], format!("{}/p/-/p-1.0.0.tgz", proxy), "{}", lockfile);
+
+    // A package the upstream doesn't have is a 404, an upstream that can't
+    // be reached a 502
+    assert_eq!(http_get(&format!("{}/missing", proxy)).0, 404);
+    assert_eq!(http_get(&format!("{}/p/-/p-9.9.9.tgz", proxy)).0, 502);
+    let (unreachable, _unreachable) = serve_proxy(&env, "http://127.0.0.1:9", "proxy-cache-2");
+    assert_eq!(http_get(&format!("{}/p", unreachable)).0, 502);
+    assert_eq!(http_get(&format!("{}/p/-/p-1.0.0.tgz", unreachable)).0, 502);
+}
+
+#[test]
+fn test_install_audit_option() {
+    let env = TestEnv::new();
+    
+    let init_output = env.run_command(&["init", "--yes"]);
+    assert!(init_output.status.success(), "Failed to initialize project");
+    
+    // Nothing installed means nothing to report and nothing to fail on
+    let output = env.run_command(&["install", "--audit-level", "high", "--no-progress"]);
+    assert!(output.status.success(), "Audited install failed");
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("found 0 vulnerabilities"), "Output missing audit summary");
+    
+    // Opting in and out at once is rejected
+    let output = env.run_command(&["install", "--audit", "--no-audit"]);
+    assert!(!output.status.success(), "Conflicting audit flags should fail");
+    
+    // A registry with a high severity advisory against the installed version
+    let (registry, received) = serve_recording(|url| {
+        let files = registry_files(url, &[serde_json::json!({ "name": "p", "version": "1.0.0" })]);
+        move |request: &Received| match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/-/npm/v1/security/advisories/bulk") => {
+                let advisories = serde_json::json!({ "p": [{ "id": 1, "title": "Prototype pollution", "severity": "high", "vulnerable_versions": "<2.0.0", "url": "https://example.com/advisories/1" }] });
+                (200, vec![("Content-Type", "application/json".to_string())], advisories.to_string().into_bytes())
+            }
+            (_, path) => match files.iter().find(|(file, _)| file == path) {
+                Some((_, body)) => (200, Vec::new(), body.clone()),
+                None => (404, Vec::new(), Vec::new()),
+            },
+        }
+    });
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let output = env.run_command_with_env(&["install", "p", "--audit-level", "high", "--no-progress"], &envs);
+    assert!(!output.status.success(), "An advisory at the gate's level should fail the install");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("high p@1.0.0: Prototype pollution"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 advisories at or above high severity"));
+    let bulk = received.lock().unwrap().iter().find(|request| request.method == "POST").cloned().expect("no advisory request");
+    assert_eq!(bulk.json()["p"], serde_json::json!(["1.0.0"]));
+    
+    // Below the gate it's reported, and the install goes through
+    let output = env.run_command_with_env(&["install", "--audit-level", "critical", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Prototype pollution"));
+}
+
+#[test]
+fn test_license_policy() {
+    let env = TestEnv::new();
+    
+    let init_output = env.run_command(&["init", "--yes"]);
+    assert!(init_output.status.success(), "Failed to initialize project");
+    
+    fs::write(
+        "package.json",
+        r#"{"name":"policy-test","version":"1.0.0","dependencies":{"stub":"1.0.0"}}"#,
+    ).expect("Failed to write package.json");
+    fs::write(
+        "rjs-lock.json",
+        r#"{"name":"root","version":"0.0.0","lockfile_version":"1.0.0","packages":{"stub@1.0.0":{"version":"1.0.0","resolved":null,"integrity":null,"dependencies":{},"license":"GPL-3.0-only"}}}"#,
+    ).expect("Failed to write lockfile");
+    fs::write("rjs-policy.toml", "[licenses]\ndeny = [\"GPL-3.0-only\"]\n")
+        .expect("Failed to write policy");
+    
+    // A denied license fails the install before anything is written
+    let output = env.run_command(&["install", "--frozen", "--no-progress"]);
+    assert!(!output.status.success(), "Install with a denied license should fail");
+    
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("stub@1.0.0: license GPL-3.0-only is not allowed"), "Unexpected error: {}", stderr);
+    assert!(!Path::new("node_modules").exists(), "Install created node_modules");
+    
+    // Typos in the policy are reported instead of silently ignored
+    fs::write("rjs-policy.toml", "[licenses]\ndenied = []\n").expect("Failed to write policy");
+    let output = env.run_command(&["install", "--frozen", "--no-progress"]);
+    assert!(!output.status.success(), "Install with an invalid policy should fail");
+}
+
+#[test]
+fn test_package_policy() {
+    let env = TestEnv::new();
+    
+    let init_output = env.run_command(&["init", "--yes"]);
+    assert!(init_output.status.success(), "Failed to initialize project");
+    
+    fs::write(
+        "package.json",
+        r#"{"name":"policy-test","version":"1.0.0","dependencies":{"left-pad":"^1.0.0","@evil/pkg":"1.0.0"}}"#,
+    ).expect("Failed to write package.json");
+    fs::write(
+        "rjs-lock.json",
+        r#"{"name":"root","version":"0.0.0","lockfile_version":"1.0.0","packages":{"left-pad@1.3.0":{"version":"1.3.0","resolved":null,"integrity":null,"dependencies":{}},"@evil/pkg@1.0.0":{"version":"1.0.0","resolved":null,"integrity":null,"dependencies":{}}}}"#,
+    ).expect("Failed to write lockfile");
+    fs::write(
+        "rjs-policy.toml",
+        "[packages]\ndeny = [\"left-pad@<2\"]\nallowed-scopes = [\"@acme\"]\n",
+    ).expect("Failed to write policy");
+    
+    let output = env.run_command(&["install", "--frozen", "--no-progress"]);
+    assert!(!output.status.success(), "Install with banned packages should fail");
+    
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("left-pad@1.3.0: banned by"), "Missing banned package: {}", stderr);
+    assert!(stderr.contains("scope @evil is not allowed"), "Missing scope violation: {}", stderr);
+    assert!(!Path::new("node_modules").exists(), "Install created node_modules");
+}
+
+#[test]
+fn test_minimum_release_age() {
+    let env = TestEnv::new();
+    
+    let init_output = env.run_command(&["init", "--yes"]);
+    assert!(init_output.status.success(), "Failed to initialize project");
+    
+    fs::write("rjs-policy.toml", "minimum-release-age = \"a week\"\n").expect("Failed to write policy");
+    
+    let output = env.run_command(&["install", "--no-progress"]);
+    assert!(!output.status.success(), "Install with an invalid release age should fail");
+    
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid minimum-release-age \"a week\""), "Missing parse error: {}", stderr);
+}
+
+#[test]
+fn test_typosquat_warning() {
+    let env = TestEnv::new();
+    
+    let init_output = env.run_command(&["init", "--yes"]);
+    assert!(init_output.status.success(), "Failed to initialize project");
+    
+    // Without a terminal to confirm on, lookalikes need --yes
+    let output = env.run_command(&["install", "lodahs", "--no-progress"]);
+    assert!(!output.status.success(), "Install of a lookalike name should fail without --yes");
+    
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("lodahs looks a lot like the popular package lodash"), "Missing warning: {}", stderr);
+    assert!(stderr.contains("pass --yes"), "Missing --yes hint: {}", stderr);
+    
+    let package_json = fs::read_to_string("package.json").expect("Failed to read package.json");
+    assert!(!package_json.contains("lodahs"), "Lookalike was saved to package.json");
+}
+
+#[test]
+fn test_view_command() {
+    let env = TestEnv::new();
+    let registry = serve_manifests(&[serde_json::json!({
+        "name": "lodash",
+        "version": "4.17.21",
+        "license": "MIT",
+        "maintainers": [{ "name": "jdalton", "email": "john.david.dalton@gmail.com" }],
+    })]);
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    
+    let output = env.run_command_with_env(&["view", "lodash@4.17.21"], &vars);
+    assert!(output.status.success(), "View command failed: {}", String::from_utf8_lossy(&output.stderr));
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("lodash@4.17.21 | MIT"), "Missing version summary: {}", stdout);
+    assert!(stdout.contains("signatures:"), "Missing signature status: {}", stdout);
+    assert!(stdout.contains("provenance:"), "Missing provenance status: {}", stdout);
+    assert!(stdout.contains("maintainers:") && stdout.contains("jdalton"), "Missing maintainers: {}", stdout);
+    
+    let output = env.run_command_with_env(&["view", "lodash@^99"], &vars);
+    assert!(!output.status.success(), "View of a missing version should fail");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_run_command() {
+    let env = TestEnv::new();
+    
+    fs::write(
+        "package.json",
+        r#"{"name":"run-test","version":"1.2.3","scripts":{"prehello":"echo before","hello":"greet $npm_package_name@$npm_package_version $npm_lifecycle_event","fail":"exit 3"}}"#,
+    ).expect("Failed to write package.json");
+    
+    // Binaries in node_modules/.bin are on the script's PATH
+    fs::create_dir_all("node_modules/.bin").expect("Failed to create node_modules/.bin");
+    fs::write("node_modules/.bin/greet", "#!/bin/sh\necho \"hello from $*\"\n").expect("Failed to write bin");
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions("node_modules/.bin/greet", fs::Permissions::from_mode(0o755))
+            .expect("Failed to make bin executable");
+    }
+    
+    let output = env.run_command(&["run", "hello"]);
+    assert!(output.status.success(), "Run command failed");
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("before"), "Pre script didn't run: {}", stdout);
+    assert!(stdout.contains("hello from run-test@1.2.3 hello"), "Missing npm environment: {}", stdout);
+    
+    let output = env.run_command(&["run", "fail"]);
+    assert!(!output.status.success(), "Failing script should fail the run");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Script \"fail\" exited with code 3"), "Missing exit code: {}", stderr);
+    
+    let output = env.run_command(&["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("hello") && stdout.contains("fail"), "Missing script list: {}", stdout);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_run_parallel() {
+    let env = TestEnv::new();
+    
+    fs::write(
+        "package.json",
+        r#"{"name":"run-test","version":"1.0.0","scripts":{"one":"echo first","two":"echo second; exit 4","three":"echo third"}}"#,
+    ).expect("Failed to write package.json");
+    
+    let output = env.run_command(&["run", "one", "two", "three", "--parallel"]);
+    assert!(!output.status.success(), "A failing script should fail the run");
+    
+    // Every script runs to completion with its output prefixed
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[one  ] first"), "Missing prefixed output: {}", stdout);
+    assert!(stdout.contains("[two  ] second"), "Missing prefixed output: {}", stdout);
+    assert!(stdout.contains("[three] third"), "Missing prefixed output: {}", stdout);
+    
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 of 3 scripts failed"), "Missing failure summary: {}", stderr);
+    
+    // Sequential runs stop at the first failure
+    let output = env.run_command(&["run", "one", "two", "three"]);
+    assert!(!output.status.success(), "A failing script should fail the run");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("first") && !stdout.contains("third"), "Run didn't stop at the failure: {}", stdout);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_run_pass_through_args() {
+    let env = TestEnv::new();
+    
+    fs::write(
+        "package.json",
+        r#"{"name":"run-test","version":"1.0.0","scripts":{"pretest":"echo pre $#","test":"printf '<%s>' before"}}"#,
+    ).expect("Failed to write package.json");
+    
+    let output = env.run_command(&["run", "test", "--", "--watch", "--filter", "foo bar", "it's"]);
+    assert!(output.status.success(), "Run command failed");
+    
+    // Arguments reach the script as-is, and only the script itself
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("<before><--watch><--filter><foo bar><it's>"), "Arguments not forwarded: {}", stdout);
+    assert!(stdout.contains("pre 0"), "Pre script got arguments: {}", stdout);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_run_watch() {
+    let env = TestEnv::new();
+    
+    // The first run changes a watched file, which restarts it; the second run
+    // stops rjs with SIGINT. The sleep stops a watcher that never restarts
+    fs::write(
+        "package.json",
+        r#"{"name":"watch-test","version":"1.0.0","scripts":{"dev":"echo run >> runs.txt; if [ $(wc -l < runs.txt) -ge 2 ]; then kill -INT $PPID; else sleep 15; kill -INT $PPID; fi"}}"#,
+    ).expect("Failed to write package.json");
+    
+    let output = env.run_command(&["run", "dev", "--watch"]);
+    assert!(output.status.success(), "Watch run failed");
+    
+    let runs = fs::read_to_string("runs.txt").expect("Failed to read runs.txt");
+    assert_eq!(runs.lines().count(), 2, "Script didn't restart exactly once");
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("runs.txt changed, restarting"), "Missing restart separator: {}", stdout);
+}
+
+#[test]
+fn test_pack_command() {
+    let env = TestEnv::new();
+    
+    fs::write(
+        "package.json",
+        r#"{"name":"@acme/pack-test","version":"1.0.0","main":"index.js","files":["lib","!lib/skip.js"]}"#,
+    ).expect("Failed to write package.json");
+    fs::write("index.js", "module.exports = 1;\n").expect("Failed to write index.js");
+    fs::write("README.md", "# pack-test\n").expect("Failed to write README.md");
+    fs::write("notes.txt", "not packed\n").expect("Failed to write notes.txt");
+    fs::create_dir_all("lib").expect("Failed to create lib");
+    fs::write("lib/util.js", "exports.util = 1;\n").expect("Failed to write lib/util.js");
+    fs::write("lib/skip.js", "not packed\n").expect("Failed to write lib/skip.js");
+    
+    let output = env.run_command(&["pack"]);
+    assert!(output.status.success(), "Pack command failed");
+    
+    // package.json, README and main are packed whatever `files` says
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for packed in ["package.json", "README.md", "index.js", "lib/util.js"] {
+        assert!(stdout.contains(packed), "{} not packed: {}", packed, stdout);
+    }
+    for skipped in ["notes.txt", "lib/skip.js"] {
+        assert!(!stdout.contains(skipped), "{} was packed: {}", skipped, stdout);
+    }
+    assert!(stdout.contains("total files:   4"), "Wrong file count: {}", stdout);
+    
+    let tarball = fs::File::open("acme-pack-test-1.0.0.tgz").expect("Tarball was not written");
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(tarball));
+    let mut entries: Vec<String> = archive
+        .entries()
+        .expect("Failed to read tarball")
+        .map(|entry| entry.expect("Bad entry").path().expect("Bad path").display().to_string())
+        .collect();
+    entries.sort();
+    assert_eq!(entries, ["package/README.md", "package/index.js", "package/lib/util.js", "package/package.json"]);
+}
+
+#[test]
+fn test_publish_dry_run() {
+    let env = TestEnv::new();
+    
+    // Nothing listens on port 9, so the published-versions check can't run
+    fs::write(
+        "package.json",
+        r#"{"name":"publish-test","version":"1.0.0","publishConfig":{"registry":"http://127.0.0.1:9"}}"#,
+    ).expect("Failed to write package.json");
+    fs::write("index.js", "module.exports = 1;\n").expect("Failed to write index.js");
+    
+    let output = env.run_command(&["publish", "--dry-run", "--tag", "next"]);
+    assert!(output.status.success(), "Dry run failed");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("index.js"), "Missing tarball contents: {}", stdout);
+    assert!(stdout.contains("unpacked size:"), "Missing unpacked size: {}", stdout);
+    assert!(stdout.contains("tag:           next"), "Missing tag: {}", stdout);
+    assert!(stdout.contains("registry:      http://127.0.0.1:9"), "Missing registry: {}", stdout);
+    assert!(stdout.contains("(dry run)"), "Missing dry run notice: {}", stdout);
+    
+    // Tags that read as ranges are refused
+    let output = env.run_command(&["publish", "--dry-run", "--tag", "^1.0.0"]);
+    assert!(!output.status.success(), "Range-like tag was accepted");
+    
+    // So are private packages
+    fs::write(
+        "package.json",
+        r#"{"name":"publish-test","version":"1.0.0","private":true}"#,
+    ).expect("Failed to write package.json");
+    let output = env.run_command(&["publish", "--dry-run"]);
+    assert!(!output.status.success(), "Private package was published");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("private"), "Unexpected error: {}", stderr);
+}
+
+#[test]
+fn test_dist_tag_validation() {
+    let env = TestEnv::new();
+    
+    // All refused before anything is sent to the registry
+    for args in [
+        &["dist-tag", "add", "some-package", "next"][..],
+        &["dist-tag", "add", "some-package@^1.0.0", "next"],
+        &["dist-tag", "add", "some-package@1.0.0", "1.x"],
+        &["dist-tag", "rm", "some-package", "latest"],
+    ] {
+        let output = env.run_command(args);
+        assert!(!output.status.success(), "{:?} was accepted", args);
+    }
+}
+
+#[test]
+fn test_unpublish_safety() {
+    let env = TestEnv::new();
+    
+    // Whole packages need --force
+    let output = env.run_command(&["unpublish", "some-package", "--yes"]);
+    assert!(!output.status.success(), "Whole package unpublished without --force");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--force"), "Unexpected error: {}", stderr);
+    
+    // Without a terminal to confirm on, --yes is required
+    let output = env.run_command(&["unpublish", "some-package@1.0.0"]);
+    assert!(!output.status.success(), "Unpublished without confirmation");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--yes"), "Unexpected error: {}", stderr);
+    
+    // Ranges aren't versions
+    let output = env.run_command(&["unpublish", "some-package@^1.0.0", "--yes"]);
+    assert!(!output.status.success(), "Range was accepted");
+}
+
+#[test]
+fn test_owner_needs_package() {
+    let env = TestEnv::new();
+    
+    // Without a package argument, the current package is used; there isn't one here
+    for args in [
+        &["owner", "ls"][..],
+        &["owner", "add", "someone"],
+        &["owner", "rm", "someone"],
+    ] {
+        let output = env.run_command(args);
+        assert!(!output.status.success(), "{:?} succeeded without a package", args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("package.json"), "Unexpected error: {}", stderr);
+    }
+}
+
+#[test]
+fn test_owner_requests() {
+    let env = TestEnv::new();
+    let (registry, received) = serve_recording(|_| {
+        |request: &Received| {
+            let packument = serde_json::json!({ "_id": "pkg", "_rev": "3-abc", "name": "pkg", "maintainers": [{ "name": "alice", "email": "alice@example.com" }] });
+            match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/pkg" | "/pkg?write=true") => (200, Vec::new(), packument.to_string().into_bytes()),
+                ("GET", "/-/user/org.couchdb.user:bob") => (200, Vec::new(), br#"{"name":"bob","email":"bob@example.com"}"#.to_vec()),
+                ("PUT", "/pkg/-rev/3-abc") => otp_challenge(request).unwrap_or((200, Vec::new(), b"{}".to_vec())),
+                _ => (404, Vec::new(), Vec::new()),
+            }
+        }
+    });
+    log_in(&registry);
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("NPM_CONFIG_USERCONFIG", "no-user-npmrc")];
+
+    let output = env.run_command_with_env(&["owner", "ls", "pkg"], &envs);
+    assert!(output.status.success(), "owner ls failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("alice <alice@example.com>"));
+
+    // Without a terminal to ask on, the one-time password has to be given
+    let output = env.run_command_with_env(&["owner", "add", "bob", "pkg"], &envs);
+    assert!(!output.status.success(), "Changed owners without a one-time password");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("pass it with --otp"));
+    let output = env.run_command_with_env(&["owner", "add", "bob", "pkg", "--otp", "000000"], &envs);
+    assert!(!output.status.success(), "A wrong one-time password was accepted");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("rejected or has expired"));
+    let output = env.run_command_with_env(&["owner", "add", "bob", "pkg", "--otp", "123456"], &envs);
+    assert!(output.status.success(), "owner add failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("bob can now publish pkg"));
+
+    // Every request is authenticated; the change saves the new maintainers at the read revision
+    let received = received.lock().unwrap();
+    assert!(received.iter().all(|request| request.header("authorization") == Some("Bearer s3cret")), "{:?}", received);
+    let put = received.iter().rev().find(|request| request.method == "PUT").expect("no change sent");
+    assert_eq!(put.header("npm-otp"), Some("123456"));
+    assert_eq!(
+        put.json(),
+        serde_json::json!({
+            "_id": "pkg",
+            "_rev": "3-abc",
+            "maintainers": [{ "name": "alice", "email": "alice@example.com" }, { "name": "bob", "email": "bob@example.com" }],
+        })
+    );
+}
+
+#[test]
+fn test_token_create_validation() {
+    let env = TestEnv::new();
+    
+    // Bad ranges are caught before asking for a password
+    let output = env.run_command(&["token", "create", "--cidr", "10.0.0.0/99"]);
+    assert!(!output.status.success(), "Invalid CIDR was accepted");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("IP range"), "Unexpected error: {}", stderr);
+    
+    // No terminal and nothing on stdin: there's no password to send
+    let output = env.run_command(&["token", "create", "--readonly"]);
+    assert!(!output.status.success(), "Token created without a password");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("password"), "Unexpected error: {}", stderr);
+}
+
+#[test]
+fn test_token_requests() {
+    let env = TestEnv::new();
+    let (registry, received) = serve_recording(|url| {
+        let next_page = format!("{}/-/npm/v1/tokens?page=2", url);
+        move |request: &Received| {
+            if let Some(challenge) = otp_challenge(request) {
+                return challenge;
+            }
+            let body = match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/-/npm/v1/tokens") => serde_json::json!({
+                    "objects": [{ "key": "abcdef123456", "token": "npm_abc...", "readonly": true, "cidr_whitelist": ["10.0.0.0/8"], "created": "2026-01-02T03:04:05Z" }],
+                    "urls": { "next": next_page },
+                }),
+                ("GET", "/-/npm/v1/tokens?page=2") => serde_json::json!({
+                    "objects": [{ "key": "fedcba654321", "token": "npm_fed...", "readonly": false }],
+                    "urls": {},
+                }),
+                ("POST", "/-/npm/v1/tokens") => serde_json::json!({ "key": "999999abcdef", "token": "npm_NEWTOKEN", "readonly": true }),
+                ("DELETE", "/-/npm/v1/tokens/token/fedcba654321") => serde_json::json!({}),
+                _ => return (404, Vec::new(), Vec::new()),
+            };
+            (200, Vec::new(), body.to_string().into_bytes())
+        }
+    });
+    log_in(&registry);
+    let envs = [("NPM_CONFIG_USERCONFIG", "no-user-npmrc")];
+
+    let output = env.run_command_with_env(&["token", "list", "--registry", &registry], &envs);
+    assert!(!output.status.success(), "Listed tokens without a one-time password");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("pass it with --otp"));
+
+    // Every page of the list is shown
+    let output = env.run_command_with_env(&["token", "list", "--registry", &registry, "--otp", "123456"], &envs);
+    assert!(output.status.success(), "token list failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("abcdef  npm_abc...  2026-01-02  read-only  10.0.0.0/8"), "{}", stdout);
+    assert!(stdout.contains("fedcba  npm_fed...    publish"), "{}", stdout);
+
+    // The password is read from stdin
+    let output = env.run_command_with_input(
+        &["token", "create", "--readonly", "--cidr", "192.0.2.0/24", "--registry", &registry, "--otp", "123456"],
+        &envs,
+        "hunter2\n",
+    );
+    assert!(output.status.success(), "token create failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Created a read-only token 999999") && stdout.contains("npm_NEWTOKEN"), "{}", stdout);
+
+    let output = env.run_command_with_env(&["token", "revoke", "fedcba", "--registry", &registry, "--otp", "123456"], &envs);
+    assert!(output.status.success(), "token revoke failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Revoked token fedcba"));
+
+    // Every request is authenticated; the token is created from what was asked for
+    let received = received.lock().unwrap();
+    assert!(received.iter().all(|request| request.header("authorization") == Some("Bearer s3cret")), "{:?}", received);
+    let create = received.iter().find(|request| request.method == "POST").expect("no token created");
+    assert_eq!(create.header("npm-otp"), Some("123456"));
+    assert_eq!(create.json(), serde_json::json!({ "password": "hunter2", "readonly": true, "cidr_whitelist": ["192.0.2.0/24"] }));
+    assert!(received.iter().any(|request| request.method == "DELETE" && request.header("npm-otp") == Some("123456")));
+}
+
+#[test]
+fn test_access_validation() {
+    let env = TestEnv::new();
+    
+    // All refused before anything is sent to the registry
+    for args in [
+        &["access", "restricted", "unscoped-package"][..],
+        &["access", "grant", "read-only", "acme", "@acme/pkg"],
+        &["access", "grant", "admin", "@acme:devs", "@acme/pkg"],
+        &["access", "revoke", "@acme:", "@acme/pkg"],
+    ] {
+        let output = env.run_command(args);
+        assert!(!output.status.success(), "{:?} was accepted", args);
+    }
+}
+
+#[test]
+fn test_access_requests() {
+    let env = TestEnv::new();
+    let (registry, received) = serve_recording(|_| {
+        |request: &Received| match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/-/package/@acme%2fpkg/access") | ("PUT" | "DELETE", "/-/team/acme/devs/package") => {
+                otp_challenge(request).unwrap_or((200, Vec::new(), b"{}".to_vec()))
+            }
+            _ => (404, Vec::new(), Vec::new()),
+        }
+    });
+    // Only reachable as @acme's registry
+    log_in(&registry);
+    let npmrc = fs::read_to_string(".npmrc").unwrap();
+    fs::write(".npmrc", format!("@acme:registry={}\n{}", registry, npmrc)).unwrap();
+    let envs = [("RJS_REGISTRY", "http://127.0.0.1:9"), ("NPM_CONFIG_USERCONFIG", "no-user-npmrc")];
+
+    let output = env.run_command_with_env(&["access", "restricted", "@acme/pkg"], &envs);
+    assert!(!output.status.success(), "Changed access without a one-time password");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("pass it with --otp"));
+
+    for args in [
+        &["access", "restricted", "@acme/pkg", "--otp", "123456"][..],
+        &["access", "grant", "read-write", "@acme:devs", "@acme/pkg", "--otp", "123456"],
+        &["access", "revoke", "@acme:devs", "@acme/pkg", "--otp", "123456"],
+    ] {
+        let output = env.run_command_with_env(args, &envs);
+        assert!(output.status.success(), "{:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    }
+
+    // Every request carries the token, and each change the one-time password
+    let received = received.lock().unwrap();
+    assert!(received.iter().all(|request| request.header("authorization") == Some("Bearer s3cret")), "{:?}", received);
+    let sent: Vec<(&str, serde_json::Value)> = received
+        .iter()
+        .filter(|request| request.header("npm-otp") == Some("123456"))
+        .map(|request| (request.method.as_str(), request.json()))
+        .collect();
+    assert_eq!(
+        sent,
+        [
+            ("POST", serde_json::json!({ "access": "restricted" })),
+            ("PUT", serde_json::json!({ "package": "@acme/pkg", "permissions": "read-write" })),
+            ("DELETE", serde_json::json!({ "package": "@acme/pkg" })),
+        ]
+    );
+}
+
+#[test]
+fn test_install_invalid_range() {
+    let env = TestEnv::new();
+    fs::write("package.json", r#"{"name": "test-project", "version": "1.0.0"}"#).expect("Failed to write package.json");
+    
+    // Malformed ranges are reported before anything is resolved, scoped names included
+    let output = env.run_command(&["install", "@scope/pkg@>=1 <<2"]);
+    assert!(!output.status.success(), "Malformed range was accepted");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid version range \">=1 <<2\" for @scope/pkg"), "Unexpected error: {}", stderr);
+}
+
+#[test]
+fn test_config_project_file() {
+    let env = TestEnv::new();
+    fs::write(".rjsrc.toml", "concurrency = 3\naudit-level = \"high\"\n").expect("Failed to write .rjsrc.toml");
+    
+    let output = env.run_command(&["config", "list", "--json"]);
+    assert!(output.status.success(), "config list failed");
+    let config: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("config list --json should print JSON");
+    assert_eq!(config["concurrency"]["value"], 3);
+    assert_eq!(config["concurrency"]["source"], "project");
+    assert_eq!(config["audit-level"]["value"], "high");
+    assert_eq!(config["batch-size"]["source"], "default");
+    
+    // Typos are errors rather than silently ignored
+    fs::write(".rjsrc.toml", "concurency = 3\n").expect("Failed to write .rjsrc.toml");
+    let output = env.run_command(&["config", "list"]);
+    assert!(!output.status.success(), "Unknown setting was accepted");
+}
+
+#[test]
+fn test_config_environment() {
+    let env = TestEnv::new();
+    fs::write(".rjsrc.toml", "concurrency = 3\n").expect("Failed to write .rjsrc.toml");
+    
+    // The environment wins over the project file, and npm's variables are understood
+    let output = env.run_command_with_env(
+        &["config", "list", "--json"],
+        &[("RJS_CONCURRENCY", "9"), ("npm_config_registry", "https://npm.example.com/")],
+    );
+    assert!(output.status.success(), "config list failed");
+    let config: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("config list --json should print JSON");
+    assert_eq!(config["concurrency"]["value"], 9);
+    assert_eq!(config["concurrency"]["source"], "env");
+    assert_eq!(config["registry"]["value"], "https://npm.example.com");
+    
+    // Bad values name the variable
+    let output = env.run_command_with_env(&["config", "list"], &[("RJS_CONCURRENCY", "lots")]);
+    assert!(!output.status.success(), "Invalid variable was accepted");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("RJS_CONCURRENCY"), "Unexpected error: {}", stderr);
+}
+
+#[test]
+fn test_env_command() {
+    let env = TestEnv::new();
+    fs::write(".rjsrc.toml", "concurrency = 3\n").expect("Failed to write .rjsrc.toml");
+    fs::write(".npmrc", "@acme:registry=https://npm.acme.example/\n//npm.acme.example/:_authToken=s3cret\n")
+        .expect("Failed to write .npmrc");
+
+    let output = env.run_command_with_env(&["env", "--json"], &[("RJS_CACHE_DIR", "rjs-cache")]);
+    assert!(output.status.success(), "env failed: {}", String::from_utf8_lossy(&output.stderr));
+    let setup: serde_json::Value = serde_json::from_slice(&output.stdout).expect("env --json should print JSON");
+    assert_eq!(setup["registry"]["registry"]["source"], "default");
+    assert_eq!(setup["registry"]["registry"]["auth"], false);
+    assert_eq!(setup["registry"]["@acme"]["value"], "https://npm.acme.example");
+    assert_eq!(setup["registry"]["@acme"]["source"], "project .npmrc");
+    assert_eq!(setup["registry"]["@acme"]["auth"], true);
+    assert_eq!(setup["install"]["concurrency"]["value"], 3);
+    assert_eq!(setup["install"]["concurrency"]["source"], "project");
+    assert_eq!(setup["paths"]["tarballs"]["value"], "rjs-cache/tarballs");
+    assert_eq!(setup["paths"]["tarballs"]["source"], "env");
+
+    // A flag wins, and tokens are never shown
+    let output = env.run_command(&["env", "--registry", "https://npm.example.com/"]);
+    assert!(output.status.success(), "env failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("registry = https://npm.example.com  auth: none  (flag)"), "Unexpected output: {}", stdout);
+    assert!(!stdout.contains("s3cret"), "Token printed: {}", stdout);
+}
+
+#[test]
+fn test_plain_output() {
+    let env = TestEnv::new();
+    
+    // Plain mode swaps symbols for ASCII, and --color never leaves no escape codes;
+    // the cache is a scratch one so the user's is left alone
+    let cache = ("RJS_CACHE_DIR", "rjs-cache");
+    let output = env.run_command_with_env(&["--no-emoji", "--color", "never", "cache", "clean"], &[cache]);
+    assert!(output.status.success(), "cache clean failed");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("ok "), "Unexpected output: {}", stdout);
+    assert!(!stdout.contains('\x1b'), "Output was colored: {}", stdout);
+    
+    // The same through the environment
+    let output = env.run_command_with_env(&["cache", "clean"], &[cache, ("RJS_EMOJI", "false")]);
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("ok "), "RJS_EMOJI=false was ignored");
+}
+
+#[test]
+fn test_analyze_node_modules() {
+    let env = TestEnv::new();
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "^1", "b": "^1"}}"#)
+        .expect("Failed to write package.json");
+    
+    // a and b need different versions of c, so b gets its own nested copy
+    let packages = [
+        ("node_modules/a", r#"{"name": "a", "version": "1.0.0", "dependencies": {"c": "^2"}}"#, 100),
+        ("node_modules/b", r#"{"name": "b", "version": "1.0.0", "dependencies": {"c": "^1"}}"#, 100),
+        ("node_modules/c", r#"{"name": "c", "version": "2.0.0"}"#, 3000),
+        ("node_modules/b/node_modules/c", r#"{"name": "c", "version": "1.0.0"}"#, 2000),
+    ];
+    for (dir, manifest, size) in packages {
+        fs::create_dir_all(dir).expect("Failed to create package directory");
+        fs::write(Path::new(dir).join("package.json"), manifest).expect("Failed to write package.json");
+        fs::write(Path::new(dir).join("index.js"), vec![b' '; size]).expect("Failed to write index.js");
+    }
+    
+    let output = env.run_command(&["analyze", "--json"]);
+    assert!(output.status.success(), "analyze failed: {}", String::from_utf8_lossy(&output.stderr));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).expect("analyze --json should print JSON");
+    assert_eq!(report["packages"], 4);
+    assert_eq!(report["largest"][0]["name"], "c");
+    assert_eq!(report["largest"][0]["version"], "2.0.0");
+    assert_eq!(report["duplicates"][0]["name"], "c");
+    assert_eq!(report["duplicates"][0]["versions"], serde_json::json!(["1.0.0", "2.0.0"]));
+    
+    // a brings in c@2, b its nested c@1
+    let direct = report["direct"].as_array().expect("direct should be a list");
+    assert_eq!(direct[0]["name"], "a");
+    assert_eq!(direct[0]["packages"], 2);
+    assert_eq!(direct[1]["name"], "b");
+    assert_eq!(direct[1]["packages"], 2);
+}
+
+#[test]
+fn test_dupes_report() {
+    let env = TestEnv::new();
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "^1", "b": "^1", "d": "^1.2.0"}}"#)
+        .expect("Failed to write package.json");
+    
+    // c is split by conflicting ranges; d's two versions both satisfy every range
+    let entry = |version: &str, dependencies: serde_json::Value| {
+        serde_json::json!({ "version": version, "resolved": null, "integrity": null, "dependencies": dependencies })
+    };
+    let lockfile = serde_json::json!({
+        "name": "app",
+        "version": "1.0.0",
+        "lockfile_version": "1.0.0",
+        "packages": {
+            "a@1.0.0": entry("1.0.0", serde_json::json!({ "c": "^2.0.0", "d": "^1.0.0" })),
+            "b@1.0.0": entry("1.0.0", serde_json::json!({ "c": "^1.0.0" })),
+            "c@1.0.0": entry("1.0.0", serde_json::json!({})),
+            "c@2.0.0": entry("2.0.0", serde_json::json!({})),
+            "d@1.2.0": entry("1.2.0", serde_json::json!({})),
+            "d@1.3.0": entry("1.3.0", serde_json::json!({})),
+        }
+    });
+    fs::write("rjs-lock.json", lockfile.to_string()).expect("Failed to write rjs-lock.json");
+    
+    let output = env.run_command(&["dupes", "--json"]);
+    assert!(output.status.success(), "dupes failed: {}", String::from_utf8_lossy(&output.stderr));
+    let dupes: serde_json::Value = serde_json::from_slice(&output.stdout).expect("dupes --json should print JSON");
+    let dupes = dupes.as_array().expect("dupes --json should print a list");
+    assert_eq!(dupes.len(), 2);
+    
+    assert_eq!(dupes[0]["name"], "c");
+    assert_eq!(dupes[0]["versions"], serde_json::json!(["1.0.0", "2.0.0"]));
+    assert_eq!(dupes[0]["requests"].as_array().map(Vec::len), Some(2));
+    assert_eq!(dupes[0]["fix"]["kind"], "override");
+    
+    assert_eq!(dupes[1]["name"], "d");
+    assert_eq!(dupes[1]["fix"]["kind"], "dedupe");
+    assert_eq!(dupes[1]["fix"]["version"], "1.3.0");
+}
+
+#[test]
+fn test_lint_deps() {
+    let env = TestEnv::new();
+    fs::write(
+        "package.json",
+        r#"{"name": "app", "version": "1.0.0", "dependencies": {"chalk": "^5", "left-pad": "^1"}, "scripts": {"fmt": "prettier ."}, "devDependencies": {"prettier": "^3"}}"#,
+    )
+    .expect("Failed to write package.json");
+    fs::create_dir_all("src").expect("Failed to create src");
+    fs::write(
+        "src/index.js",
+        "import chalk from 'chalk';\nimport path from 'node:path';\n// require('commented-out')\nconst ms = require(\"ms\");\nconst label = \"data from 'db'\";\n",
+    )
+    .expect("Failed to write src/index.js");
+    
+    // ms is imported but undeclared; left-pad is declared but never imported
+    let output = env.run_command(&["lint", "deps", "--json"]);
+    assert!(!output.status.success(), "lint deps should fail on an undeclared import");
+    let findings: serde_json::Value = serde_json::from_slice(&output.stdout).expect("lint deps --json should print JSON");
+    let undeclared = findings["undeclared"].as_object().expect("undeclared should be an object");
+    assert_eq!(undeclared.keys().collect::<Vec<_>>(), vec!["ms"]);
+    assert_eq!(undeclared["ms"][0]["line"], 4);
+    assert_eq!(findings["unused"], serde_json::json!(["left-pad"]));
+    
+    let output = env.run_command(&["lint", "deps", "--ignore", "ms"]);
+    assert!(output.status.success(), "lint deps --ignore failed: {}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_run_engine_check() {
+    let env = TestEnv::new();
+    fs::write(
+        "package.json",
+        r#"{"name":"engine-test","version":"1.0.0","engines":{"node":">=18"},"scripts":{"hello":"echo hello"}}"#,
+    ).expect("Failed to write package.json");
+    
+    // A node on PATH too old for the project
+    fs::create_dir_all("fake-bin").expect("Failed to create fake-bin");
+    fs::write("fake-bin/node", "#!/bin/sh\necho v16.3.0\n").expect("Failed to write fake node");
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions("fake-bin/node", fs::Permissions::from_mode(0o755))
+            .expect("Failed to make fake node executable");
+    }
+    let path = format!(
+        "{}:{}",
+        env::current_dir().expect("Failed to get cwd").join("fake-bin").display(),
+        env::var("PATH").unwrap_or_default()
+    );
+    
+    let output = env.run_command_with_env(&["run", "hello"], &[("PATH", path.as_str())]);
+    assert!(output.status.success(), "A mismatch should only warn by default");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("expects Node >=18") && stderr.contains("16.3.0"), "Missing warning: {}", stderr);
+    
+    let output = env.run_command_with_env(&["run", "hello"], &[("PATH", path.as_str()), ("RJS_ENGINE_STRICT", "true")]);
+    assert!(!output.status.success(), "engine-strict should fail the run");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("hello"), "The script shouldn't have run");
+}
+
+#[test]
+fn test_package_manager_field() {
+    let env = TestEnv::new();
+    let write = |package_manager: &str| {
+        let package_json = serde_json::json!({
+            "name": "pm-test",
+            "version": "1.0.0",
+            "packageManager": package_manager,
+            "scripts": { "hello": "echo hello" }
+        });
+        fs::write("package.json", package_json.to_string()).expect("Failed to write package.json");
+    };
+    let strict = [("RJS_PACKAGE_MANAGER_STRICT", "true")];
+    
+    write("pnpm@9.1.0+sha512.abcdef");
+    let output = env.run_command(&["run", "hello"]);
+    assert!(output.status.success(), "Another manager should only warn by default");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("set up for pnpm@9.1.0"), "Missing warning: {}", stderr);
+    assert!(!env.run_command_with_env(&["run", "hello"], &strict).status.success(), "Strict mode should refuse pnpm");
+    
+    write("rjs@0.0.1");
+    let output = env.run_command_with_env(&["run", "hello"], &strict);
+    assert!(!output.status.success(), "Strict mode should refuse another rjs version");
+    assert!(String::from_utf8_lossy(&output.stderr).contains(env!("CARGO_PKG_VERSION")));
+    
+    write(&format!("rjs@{}", env!("CARGO_PKG_VERSION")));
+    let output = env.run_command_with_env(&["run", "hello"], &strict);
+    assert!(output.status.success(), "The running version should pass: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Warning"));
+}
+
+#[test]
+fn test_platform_optional_dependencies() {
+    let env = TestEnv::new();
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"native": "1.0.0"}}"#)
+        .expect("Failed to write package.json");
+    
+    // One variant is built for an os nothing runs, the other for every other os
+    let entry = |version: &str, extra: serde_json::Value| {
+        let mut entry = serde_json::json!({ "version": version, "resolved": null, "integrity": null, "dependencies": {} });
+        entry.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        entry
+    };
+    let lockfile = serde_json::json!({
+        "name": "app",
+        "version": "1.0.0",
+        "lockfile_version": "1.0.0",
+        "packages": {
+            "native@1.0.0": entry("1.0.0", serde_json::json!({
+                "optional_dependencies": { "@native/elsewhere": "1.0.0", "@native/here": "1.0.0" }
+            })),
+            "@native/elsewhere@1.0.0": entry("1.0.0", serde_json::json!({ "os": ["plan9"], "cpu": ["x64", "arm64"] })),
+            "@native/here@1.0.0": entry("1.0.0", serde_json::json!({ "os": ["!plan9"] })),
+        }
+    });
+    fs::write("rjs-lock.json", lockfile.to_string()).expect("Failed to write rjs-lock.json");
+    
+    let output = env.run_command(&["install", "--frozen", "--no-progress"]);
+    assert!(output.status.success(), "Frozen install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("node_modules/native/package.json").exists());
+    assert!(Path::new("node_modules/@native/here/package.json").exists(), "The host's variant should be installed");
+    assert!(!Path::new("node_modules/@native/elsewhere").exists(), "Other platforms' variants should be skipped");
+}
+
+#[test]
+fn test_target_platform() {
+    let env = TestEnv::new();
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"native": "1.0.0"}}"#)
+        .expect("Failed to write package.json");
+    let variant = |os: &str, cpu: &str, libc: Option<&str>| {
+        let mut entry = serde_json::json!({ "version": "1.0.0", "resolved": null, "integrity": null, "dependencies": {}, "os": [os], "cpu": [cpu] });
+        if let Some(libc) = libc {
+            entry["libc"] = serde_json::json!([libc]);
+        }
+        entry
+    };
+    let lockfile = serde_json::json!({
+        "name": "app",
+        "version": "1.0.0",
+        "lockfile_version": "1.0.0",
+        "packages": {
+            "native@1.0.0": {
+                "version": "1.0.0", "resolved": null, "integrity": null, "dependencies": {},
+                "optional_dependencies": { "@native/darwin-arm64": "1.0.0", "@native/linux-gnu": "1.0.0", "@native/linux-musl": "1.0.0" }
+            },
+            "@native/darwin-arm64@1.0.0": variant("darwin", "arm64", None),
+            "@native/linux-gnu@1.0.0": variant("linux", "x64", Some("glibc")),
+            "@native/linux-musl@1.0.0": variant("linux", "x64", Some("musl")),
+        }
+    });
+    fs::write("rjs-lock.json", lockfile.to_string()).expect("Failed to write rjs-lock.json");
+    let installed = || {
+        ["darwin-arm64", "linux-gnu", "linux-musl"]
+            .into_iter()
+            .filter(|variant| Path::new("node_modules/@native").join(variant).exists())
+            .collect::<Vec<_>>()
+    };
+
+    // Whatever the host, the target's variant is the one installed
+    let output = env.run_command(&["install", "--frozen", "--no-progress", "--target-os", "darwin", "--target-cpu", "arm64"]);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("darwin-arm64"), "{}", String::from_utf8_lossy(&output.stdout));
+    assert_eq!(installed(), ["darwin-arm64"]);
+
+    let args = ["install", "--frozen", "--clean", "--no-progress", "--target-os", "linux", "--target-cpu", "x64", "--target-libc", "musl"];
+    let output = env.run_command(&args);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(installed(), ["linux-musl"]);
+
+    // Only Node's names are targets
+    let output = env.run_command(&["install", "--frozen", "--no-progress", "--target-os", "macos"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("darwin"));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_libc_variants() {
+    let env = TestEnv::new();
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"native": "1.0.0"}}"#)
+        .expect("Failed to write package.json");
+    let lockfile = serde_json::json!({
+        "name": "app",
+        "version": "1.0.0",
+        "lockfile_version": "1.0.0",
+        "packages": {
+            "native@1.0.0": {
+                "version": "1.0.0", "resolved": null, "integrity": null, "dependencies": {},
+                "optional_dependencies": { "@native/gnu": "1.0.0", "@native/musl": "1.0.0" }
+            },
+            "@native/gnu@1.0.0": { "version": "1.0.0", "resolved": null, "integrity": null, "dependencies": {}, "os": ["linux"], "libc": ["glibc"] },
+            "@native/musl@1.0.0": { "version": "1.0.0", "resolved": null, "integrity": null, "dependencies": {}, "os": ["linux"], "libc": ["musl"] },
+        }
+    });
+    fs::write("rjs-lock.json", lockfile.to_string()).expect("Failed to write rjs-lock.json");
+    
+    let output = env.run_command(&["install", "--frozen", "--no-progress"]);
+    assert!(output.status.success(), "Frozen install failed: {}", String::from_utf8_lossy(&output.stderr));
+    // Whichever libc the host has, exactly its variant is installed
+    let gnu = Path::new("node_modules/@native/gnu").exists();
+    let musl = Path::new("node_modules/@native/musl").exists();
+    assert!(gnu != musl, "Expected one libc variant, got glibc: {}, musl: {}", gnu, musl);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_native_build_fallback() {
+    let env = TestEnv::new();
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"addon": "1.0.0"}}"#)
+        .expect("Failed to write package.json");
+    fs::write(
+        "rjs-lock.json",
+        r#"{"name": "app", "version": "1.0.0", "lockfile_version": "1.0.0", "packages": {"addon@1.0.0": {"version": "1.0.0", "resolved": null, "integrity": null, "dependencies": {}}}}"#,
+    )
+    .expect("Failed to write rjs-lock.json");
+    
+    // An installed native package without a prebuilt binary, and a node-gyp
+    // stand-in that records its steps and "builds" the addon
+    fs::create_dir_all("node_modules/addon").expect("Failed to create addon");
+    fs::write("node_modules/addon/package.json", r#"{"name": "addon", "version": "1.0.0"}"#).expect("Failed to write addon");
+    fs::write("node_modules/addon/binding.gyp", "{}").expect("Failed to write binding.gyp");
+    fs::create_dir_all("node_modules/.bin").expect("Failed to create node_modules/.bin");
+    fs::write(
+        "node_modules/.bin/node-gyp",
+        "#!/bin/sh\necho \"$1\" >> ../../gyp.log\nif [ \"$1\" = build ]; then mkdir -p build/Release && touch build/Release/addon.node; fi\n",
+    )
+    .expect("Failed to write node-gyp");
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions("node_modules/.bin/node-gyp", fs::Permissions::from_mode(0o755))
+            .expect("Failed to make node-gyp executable");
+    }
+    
+    let output = env.run_command(&["install", "--frozen", "--no-progress"]);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read_to_string("gyp.log").expect("node-gyp didn't run"), "configure\nbuild\n");
+    assert!(Path::new("node_modules/addon/build/Release/addon.node").exists());
+    
+    // Built once, it isn't rebuilt
+    let output = env.run_command(&["install", "--frozen", "--no-progress"]);
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string("gyp.log").expect("Failed to read gyp.log"), "configure\nbuild\n");
+}
+
+#[test]
+fn test_resume_interrupted_install() {
+    let env = TestEnv::new();
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"done": "1.0.0", "stale": "2.0.0"}}"#)
+        .expect("Failed to write package.json");
+    let lockfile = serde_json::json!({
+        "name": "app",
+        "version": "1.0.0",
+        "lockfile_version": "1.0.0",
+        "packages": {
+            "done@1.0.0": { "version": "1.0.0", "resolved": null, "integrity": null, "dependencies": {} },
+            "stale@2.0.0": { "version": "2.0.0", "resolved": null, "integrity": null, "dependencies": {} },
+        }
+    });
+    fs::write("rjs-lock.json", lockfile.to_string()).expect("Failed to write rjs-lock.json");
+
+    // An install killed after finishing `done`, in the middle of staging `stale`,
+    // which is still at the version from before
+    fs::create_dir_all("node_modules/done").unwrap();
+    fs::write("node_modules/done/package.json", r#"{"name": "done", "version": "1.0.0", "kept": true}"#).unwrap();
+    fs::create_dir_all("node_modules/stale").unwrap();
+    fs::write("node_modules/stale/package.json", r#"{"name": "stale", "version": "1.0.0"}"#).unwrap();
+    fs::create_dir_all("node_modules/.stale.staging-1-0").unwrap();
+    fs::write("node_modules/.rjs-install-journal", "done@1.0.0\n").unwrap();
+
+    let output = env.run_command(&["install", "--frozen", "--no-progress"]);
+    assert!(output.status.success(), "Resumed install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Resuming an interrupted install: 1 packages"));
+    let done = fs::read_to_string("node_modules/done/package.json").unwrap();
+    assert!(done.contains("kept"), "Journaled packages should be left alone");
+    let stale = fs::read_to_string("node_modules/stale/package.json").unwrap();
+    assert!(stale.contains("2.0.0"), "Packages at another version should be replaced: {}", stale);
+    assert!(!Path::new("node_modules/.stale.staging-1-0").exists(), "Leftover staging directories should be removed");
+    assert!(!Path::new("node_modules/.rjs-install-journal").exists(), "The journal should be removed once done");
+
+    // --clean starts over from an empty node_modules
+    fs::create_dir_all("node_modules/stray").unwrap();
+    let output = env.run_command(&["install", "--frozen", "--no-progress", "--clean"]);
+    assert!(output.status.success(), "Clean install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(!Path::new("node_modules/stray").exists());
+    let done = fs::read_to_string("node_modules/done/package.json").unwrap();
+    assert!(!done.contains("kept"), "--clean should reinstall everything");
+}
+
+#[test]
+fn test_integrity_mismatch_recovery() {
+    use std::io::{Read, Write};
+
+    let env = TestEnv::new();
+
+    // A registry, and mirror under /mirror, that serve the wrong bytes for everything
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to bind port");
+    let registry = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\ngarbage");
+        }
+    });
+
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"p": "1.0.0"}}"#)
+        .expect("Failed to write package.json");
+    let lockfile = serde_json::json!({
+        "name": "app",
+        "version": "1.0.0",
+        "lockfile_version": "1.0.0",
+        "packages": {
+            "p@1.0.0": {
+                "version": "1.0.0",
+                "resolved": format!("{}/p/-/p-1.0.0.tgz", registry),
+                "integrity": "sha512-jH4KyZQq4UdMXNAoGCSyrCHWY+j97osy41rJaKPJX46FToY87dCwIfhVHKhKeMKBdFKHPqkpLmZeeatCnNXydA==",
+                "dependencies": {}
+            }
+        }
+    });
+    fs::write("rjs-lock.json", lockfile.to_string()).expect("Failed to write rjs-lock.json");
+
+    let mirror = format!("{}/mirror", registry);
+    let output = env.run_command_with_env(
+        &["install", "--frozen", "--no-progress"],
+        &[
+            ("RJS_REGISTRY", &registry),
+            ("RJS_MIRRORS", &mirror),
+            ("RJS_CACHE_DIR", "rjs-cache"),
+            ("RUST_LOG", "warn"),
+        ],
+    );
+    assert!(!output.status.success(), "Install of a mismatching tarball should fail");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("failed integrity verification from every source"), "Unexpected error: {}", stderr);
+    // The registry twice, then the mirror
+    assert_eq!(stderr.matches(&format!("{}/p/-/p-1.0.0.tgz: Integrity check failed", registry)).count(), 2, "{}", stderr);
+    assert!(stderr.contains(&format!("{}/mirror/p/-/p-1.0.0.tgz: Integrity check failed", registry)), "{}", stderr);
+    assert!(!Path::new("node_modules/p").exists());
+}
+
+#[test]
+fn test_throttled_registry() {
+    use std::io::{Read, Write};
+
+    let env = TestEnv::new();
+
+    // A registry that asks for a pause on the first request and answers the next
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to bind port");
+    let registry = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+    std::thread::spawn(move || {
+        let packument = r#"{"name":"p","dist-tags":{"latest":"1.0.0"},"versions":{"1.0.0":{"version":"1.0.0","dist":{"shasum":"","tarball":"http://127.0.0.1:9/p-1.0.0.tgz"}}}}"#;
+        for (served, mut stream) in listener.incoming().flatten().enumerate() {
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request);
+            let response = if served == 0 {
+                "HTTP/1.1 429 Too Many Requests\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+            } else {
+                format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", packument.len(), packument)
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    let output = env.run_command_with_env(
+        &["view", "p"],
+        &[("RJS_REGISTRY", &registry), ("RJS_CACHE_DIR", "rjs-cache"), ("RJS_STATS_FILE", "stats.json")],
+    );
+    assert!(output.status.success(), "View after a 429 failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stats: serde_json::Value = serde_json::from_str(&fs::read_to_string("stats.json").unwrap()).unwrap();
+    assert_eq!(stats["throttled"], 1, "{}", stats);
+    assert_eq!(stats["retries"], 1, "{}", stats);
+}
+
+#[test]
+fn test_network_and_extract_concurrency() {
+    let env = TestEnv::new();
+    fs::write(".rjsrc.toml", "network-concurrency = 8\n").expect("Failed to write .rjsrc.toml");
+
+    let output = env.run_command_with_env(&["config", "list", "--json"], &[("RJS_EXTRACT_CONCURRENCY", "2")]);
+    assert!(output.status.success(), "config list failed");
+    let config: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("config list --json should print JSON");
+    assert_eq!(config["network-concurrency"]["value"], 8);
+    assert_eq!(config["network-concurrency"]["source"], "project");
+    assert_eq!(config["extract-concurrency"]["value"], 2);
+    assert_eq!(config["extract-concurrency"]["source"], "env");
+
+    // The flags are taken by install, even with nothing to download
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"stub": "1.0.0"}}"#)
+        .expect("Failed to write package.json");
+    fs::write(
+        "rjs-lock.json",
+        r#"{"name":"app","version":"1.0.0","lockfile_version":"1.0.0","packages":{"stub@1.0.0":{"version":"1.0.0","resolved":null,"integrity":null,"dependencies":{}}}}"#,
+    )
+    .expect("Failed to write rjs-lock.json");
+    let output = env.run_command(&[
+        "install", "--frozen", "--no-progress", "--network-concurrency", "4", "--extract-concurrency", "1",
+    ]);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("node_modules/stub/package.json").exists());
+}
+
+#[test]
+fn test_registry_flag() {
+    let env = TestEnv::new();
+
+    // A registry with a single package, only reachable through --registry
+    let registry = serve_registry(&[("p", "1.0.0")]);
+
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0"}"#).expect("Failed to write package.json");
+    let envs = [("RJS_REGISTRY", "http://127.0.0.1:9"), ("RJS_CACHE_DIR", "rjs-cache")];
+
+    let output = env.run_command_with_env(&["view", "p", "--registry", &registry], &envs);
+    assert!(output.status.success(), "View failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = env.run_command_with_env(&["install", "p", "--no-progress", "--registry", &registry], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("node_modules/p/package.json").exists());
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert_eq!(lockfile["packages"]["p@1.0.0"]["registry"], registry.as_str(), "{}", lockfile);
+
+    // Locked versions keep the registry they were resolved from
+    let output = env.run_command_with_env(&["install", "--no-progress"], &envs);
+    assert!(output.status.success(), "Locked install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert_eq!(lockfile["packages"]["p@1.0.0"]["registry"], registry.as_str(), "{}", lockfile);
+}
+
+#[test]
+fn test_scoped_registry_install() {
+    let env = TestEnv::new();
+    let public = serve_registry(&[("a", "1.0.0")]);
+
+    // A private registry for @org, answering only with its token
+    let (private, received) = serve_recording(|url| {
+        let files = registry_files(url, &[serde_json::json!({ "name": "@org/b", "version": "1.0.0", "dependencies": { "a": "1.0.0" } })]);
+        move |request: &Received| {
+            if request.header("authorization") != Some("Bearer s3cret") {
+                return (401, Vec::new(), b"{\"error\":\"unauthorized\"}".to_vec());
+            }
+            let path = request.path.replace("%2f", "/").replace("%2F", "/");
+            match files.iter().find(|(file, _)| *file == path) {
+                Some((_, body)) => (200, Vec::new(), body.clone()),
+                None => (404, Vec::new(), Vec::new()),
+            }
+        }
+    });
+    let host = private.trim_start_matches("http:");
+    fs::write(".npmrc", format!("@org:registry={}/\n{}/:_authToken=s3cret\n", private, host)).unwrap();
+    fs::write("package.json", r#"{ "name": "app", "dependencies": { "@org/b": "1.0.0" } }"#).unwrap();
+
+    let envs = [("RJS_REGISTRY", public.as_str()), ("RJS_CACHE_DIR", "rjs-cache"), ("NPM_CONFIG_USERCONFIG", "no-user-npmrc")];
+    let output = env.run_command_with_env(&["install", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("node_modules/@org/b/package.json").exists());
+    assert!(Path::new("node_modules/a/package.json").exists());
+
+    // The scoped package came from its registry, its dependency from the default
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert_eq!(lockfile["packages"]["@org/b@1.0.0"]["registry"], private.as_str(), "{}", lockfile);
+    assert_eq!(lockfile["packages"]["a@1.0.0"]["registry"], public.as_str(), "{}", lockfile);
+
+    // Both the packument and the tarball were read with the token
+    let received = received.lock().unwrap();
+    assert!(received.iter().all(|request| request.method == "GET"), "{:?}", received);
+    let paths: Vec<&str> = received.iter().map(|request| request.path.as_str()).collect();
+    assert!(paths.iter().any(|path| path.ends_with("/b")), "{:?}", paths);
+    assert!(paths.iter().any(|path| path.ends_with(".tgz")), "{:?}", paths);
+    assert!(received.iter().all(|request| request.header("authorization") == Some("Bearer s3cret")), "{:?}", received);
+    drop(received);
+
+    // rjs env shows the routing and auth the install used
+    let output = env.run_command_with_env(&["env", "--json"], &envs);
+    assert!(output.status.success(), "env failed: {}", String::from_utf8_lossy(&output.stderr));
+    let setup: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(setup["registry"]["registry"]["value"], public.as_str());
+    assert_eq!(setup["registry"]["registry"]["auth"], false);
+    assert_eq!(setup["registry"]["@org"]["value"], private.as_str());
+    assert_eq!(setup["registry"]["@org"]["auth"], true);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_daemon_uses_client_registry() {
+    let env = TestEnv::new();
+
+    // A registry only reachable with a token, which the daemon isn't configured for
+    let (registry, received) = serve_recording(|url| {
+        let files = registry_files(url, &[serde_json::json!({ "name": "p", "version": "1.0.0" })]);
+        move |request: &Received| match files.iter().find(|(file, _)| *file == request.path) {
+            _ if request.header("authorization") != Some("Bearer s3cret") => (401, Vec::new(), Vec::new()),
+            Some((_, body)) => (200, Vec::new(), body.clone()),
+            None => (404, Vec::new(), Vec::new()),
+        }
+    });
+    fs::write(".npmrc", format!("{}/:_authToken=s3cret\n", registry.trim_start_matches("http:"))).unwrap();
+    fs::write("package.json", r#"{ "name": "app", "dependencies": { "p": "1.0.0" } }"#).unwrap();
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache"), ("NPM_CONFIG_USERCONFIG", "no-user-npmrc")];
+    let daemon_envs = [("RJS_REGISTRY", "http://127.0.0.1:9"), ("RJS_CACHE_DIR", "rjs-cache"), ("NPM_CONFIG_USERCONFIG", "no-user-npmrc")];
+    let install = || {
+        let _ = fs::remove_dir_all("node_modules");
+        let _ = fs::remove_file("rjs-lock.json");
+        let output = env.run_command_with_env(&["install", "--no-progress"], &envs);
+        assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+        assert!(Path::new("node_modules/p/package.json").exists());
+    };
+
+    // A daemon that can't reach anything through its proxy: lookups fall
+    // back to fetching directly
+    let daemon = env.spawn_command_with_env(&["daemon"], &[daemon_envs.as_slice(), &[("RJS_PROXY", "http://127.0.0.1:9")]].concat());
+    wait_for(Path::new("rjs-cache/daemon.sock"));
+    install();
+    let output = env.run_command_with_env(&["daemon", "status"], &daemon_envs);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("cached packages: 0") && !stdout.contains("requests:        0"), "{}", stdout);
+    env.run_command_with_env(&["daemon", "stop"], &daemon_envs);
+    drop(daemon);
+    let _ = fs::remove_file("rjs-cache/daemon.sock");
+
+    // A daemon set up for another registry fetches from the client's, with its token
+    let _daemon = env.spawn_command_with_env(&["daemon"], &daemon_envs);
+    wait_for(Path::new("rjs-cache/daemon.sock"));
+    install();
+    let output = env.run_command_with_env(&["daemon", "status"], &daemon_envs);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("cached packages: 1"), "{}", stdout);
+    env.run_command_with_env(&["daemon", "stop"], &daemon_envs);
+
+    let received = received.lock().unwrap();
+    assert!(received.iter().all(|request| request.header("authorization") == Some("Bearer s3cret")), "{:?}", received);
+}
+
+#[test]
+fn test_install_preserves_package_json_formatting() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("m", "1.0.0"), ("n", "2.0.0")]);
+
+    // Four-space indents, unsorted top-level keys and a trailing newline
+    let original = "{\n    \"version\": \"1.0.0\",\n    \"name\": \"app\",\n    \"dependencies\": {\n        \"a\": \"1.0.0\",\n        \"z\": \"1.0.0\"\n    },\n    \"scripts\": {}\n}\n";
+    fs::write("package.json", original).expect("Failed to write package.json");
+    fs::write(
+        "rjs-lock.json",
+        r#"{"name":"app","version":"1.0.0","lockfile_version":"1.0.0","packages":{"a@1.0.0":{"version":"1.0.0","resolved":null,"integrity":null,"dependencies":{}},"z@1.0.0":{"version":"1.0.0","resolved":null,"integrity":null,"dependencies":{}}}}"#,
+    )
+    .expect("Failed to write rjs-lock.json");
+
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let output = env.run_command_with_env(&["install", "m", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // Only the new line is different
+    let updated = fs::read_to_string("package.json").unwrap();
+    let expected = original.replace("        \"a\": \"1.0.0\",\n", "        \"a\": \"1.0.0\",\n        \"m\": \"1.0.0\",\n");
+    assert_eq!(updated, expected);
+
+    // A missing dependencies object is added at the end, in the file's style
+    fs::write("package.json", "{\n\t\"name\": \"app\"\n}").expect("Failed to write package.json");
+    let output = env.run_command_with_env(&["install", "n", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let updated = fs::read_to_string("package.json").unwrap();
+    assert_eq!(updated, "{\n\t\"name\": \"app\",\n\t\"dependencies\": {\n\t\t\"n\": \"2.0.0\"\n\t}\n}");
+}
+
+#[test]
+fn test_install_sorts_dependencies() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("@scope/m", "1.0.0")]);
+
+    fs::write(
+        "package.json",
+        "{\n  \"name\": \"app\",\n  \"dependencies\": {\n    \"z\": \"1.0.0\",\n    \"a\": \"1.0.0\"\n  }\n}\n",
+    )
+    .expect("Failed to write package.json");
+    fs::write(
+        "rjs-lock.json",
+        r#"{"name":"app","version":"1.0.0","lockfile_version":"1.0.0","packages":{"a@1.0.0":{"version":"1.0.0","resolved":null,"integrity":null,"dependencies":{}},"z@1.0.0":{"version":"1.0.0","resolved":null,"integrity":null,"dependencies":{}}}}"#,
+    )
+    .expect("Failed to write rjs-lock.json");
+
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let output = env.run_command_with_env(&["install", "@scope/m", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // The section is sorted as a whole, scoped packages first
+    let updated = fs::read_to_string("package.json").unwrap();
+    assert_eq!(
+        updated,
+        "{\n  \"name\": \"app\",\n  \"dependencies\": {\n    \"@scope/m\": \"1.0.0\",\n    \"a\": \"1.0.0\",\n    \"z\": \"1.0.0\"\n  }\n}\n"
+    );
+}
+
+#[test]
+fn test_update_and_uninstall() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("tool", "1.0.0"), ("tool", "1.1.0"), ("@s/lib", "1.0.0")]);
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0"}"#).expect("Failed to write package.json");
+    let output = env.run_command_with_env(&["add", "tool@1.0.0", "@s/lib", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    #[cfg(unix)]
+    {
+        assert!(Path::new("node_modules/.bin/tool").exists(), "tool isn't linked into .bin");
+        assert!(Path::new("node_modules/.bin/lib").exists(), "lib isn't linked into .bin");
+    }
+    #[cfg(windows)]
+    {
+        assert!(Path::new("node_modules/.bin/tool.cmd").exists(), "tool has no cmd shim");
+        assert!(Path::new("node_modules/.bin/tool.ps1").exists(), "tool has no PowerShell shim");
+    }
+
+    // Update moves tool within its range and replaces the locked version
+    let content = fs::read_to_string("package.json").unwrap().replace(r#""tool": "1.0.0""#, r#""tool": "^1.0.0""#);
+    fs::write("package.json", content).expect("Failed to write package.json");
+    let output = env.run_command_with_env(&["update", "tool"], &envs);
+    assert!(output.status.success(), "Update failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1.0.0 -> 1.1.0"));
+    let installed = fs::read_to_string("node_modules/tool/package.json").unwrap();
+    assert!(installed.contains("1.1.0"), "{}", installed);
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert!(lockfile["packages"].get("tool@1.1.0").is_some(), "{}", lockfile);
+    assert!(lockfile["packages"].get("tool@1.0.0").is_none(), "{}", lockfile);
+
+    // Uninstall takes the package out of package.json, the lockfile,
+    // node_modules and .bin, and leaves no empty scope directory behind
+    let output = env.run_command_with_env(&["remove", "@s/lib"], &envs);
+    assert!(output.status.success(), "Uninstall failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(!fs::read_to_string("package.json").unwrap().contains("@s/lib"));
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert!(lockfile["packages"].get("@s/lib@1.0.0").is_none(), "{}", lockfile);
+    assert!(lockfile["packages"].get("tool@1.1.0").is_some(), "{}", lockfile);
+    assert!(!Path::new("node_modules/@s").exists(), "Empty scope directory left behind");
+    assert!(Path::new("node_modules/tool").exists());
+
+    // Without names, or a terminal to pick them on, nothing is removed
+    let output = env.run_command_with_env(&["uninstall"], &envs);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Name the packages to remove"));
+    assert!(fs::read_to_string("package.json").unwrap().contains("tool"));
+    #[cfg(unix)]
+    {
+        assert!(fs::symlink_metadata("node_modules/.bin/lib").is_err(), "Dead shim left behind");
+        assert!(Path::new("node_modules/.bin/tool").exists());
+    }
+    #[cfg(windows)]
+    {
+        assert!(!Path::new("node_modules/.bin/lib.cmd").exists(), "Dead shim left behind");
+        assert!(!Path::new("node_modules/.bin/lib.ps1").exists(), "Dead shim left behind");
+    }
+}
+
+#[test]
+fn test_case_colliding_packages() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("Stream", "1.0.0"), ("stream", "1.0.0")]);
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0"}"#).expect("Failed to write package.json");
+
+    // They'd share a directory where the filesystem ignores case
+    let output = env.run_command_with_env(&["install", "Stream@1.0.0", "stream@1.0.0", "--no-progress"], &envs);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Stream and stream differ only in case"), "No collision reported: {}", stderr);
+    if cfg!(any(windows, target_os = "macos")) {
+        assert!(!output.status.success());
+    } else {
+        assert!(output.status.success(), "Install failed: {}", stderr);
+    }
+}
+
+#[test]
+fn test_localized_messages() {
+    let env = TestEnv::new();
+
+    let output = env.run_command(&["init", "--yes", "--lang", "fr"]);
+    assert!(output.status.success(), "Init failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("package.json créé"));
+
+    // The locale is picked up from the environment, and errors are localized too
+    let output = env.run_command_with_env(&["init", "--yes", "--scope", "@a b"], &[("LANG", "fr_FR.UTF-8")]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Erreur : Scope « @a b » invalide"), "Unexpected error: {}", stderr);
+
+    // English when the locale has no catalog
+    let output = env.run_command_with_env(&["init", "--yes"], &[("LANG", "xx_XX.UTF-8")]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Created package.json"));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_self_update() {
+    use base64::Engine;
+    use ring::signature::KeyPair;
+    use sha2::Digest;
+
+    let env = TestEnv::new();
+    let base64 = base64::engine::general_purpose::STANDARD;
+
+    // A copy of rjs to replace, and a release with a new "binary" for it
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").map(PathBuf::from).unwrap_or_else(|_| env.original_dir.clone());
+    let exe = env.temp_dir.path().join("rjs");
+    fs::copy(manifest_dir.join("target/release/rjs"), &exe).expect("Failed to copy rjs");
+
+    let rng = ring::rand::SystemRandom::new();
+    let pkcs8 = ring::signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let key = ring::signature::Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+    let other = ring::signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let other = ring::signature::Ed25519KeyPair::from_pkcs8(other.as_ref()).unwrap();
+
+    let binary = b"#!/bin/sh\necho updated\n".to_vec();
+    let asset = format!("rjs-{}-{}", env::consts::ARCH, env::consts::OS);
+    let checksum = format!("{}  {}\n", hex::encode(sha2::Sha256::digest(&binary)), asset);
+    let signature = base64.encode(key.sign(&binary));
+    let url = serve_files(|url| {
+        let download = |name: &str| serde_json::json!({ "name": name, "browser_download_url": format!("{}/download/{}", url, name) });
+        let releases = serde_json::json!([
+            { "tag_name": "v99.0.0", "prerelease": false, "assets": [download(&asset), download("SHA256SUMS"), download(&format!("{}.sig", asset))] },
+            { "tag_name": "v0.0.1", "prerelease": false, "assets": [] },
+        ]);
+        vec![
+            ("/releases".to_string(), releases.to_string().into_bytes()),
+            (format!("/download/{}", asset), binary.clone()),
+            ("/download/SHA256SUMS".to_string(), checksum.into_bytes()),
+            (format!("/download/{}.sig", asset), signature.into_bytes()),
+        ]
+    });
+    let releases = format!("{}/releases", url);
+    let run = |args: &[&str], public_key: &[u8]| {
+        let output = Command::new(&exe)
+            .args(args)
+            .env("RJS_UPDATE_URL", &releases)
+            .env("RJS_UPDATE_PUBLIC_KEY", base64.encode(public_key))
+            .output()
+            .expect("Failed to run the rjs copy");
+        println!("Stdout: {}", String::from_utf8_lossy(&output.stdout));
+        println!("Stderr: {}", String::from_utf8_lossy(&output.stderr));
+        output
+    };
+    let original = fs::read(&exe).unwrap();
+
+    let output = run(&["self-update", "--check"], key.public_key().as_ref());
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("99.0.0"));
+    assert_eq!(fs::read(&exe).unwrap(), original, "--check replaced the executable");
+
+    // A signature from another key leaves the executable alone
+    let output = run(&["self-update"], other.public_key().as_ref());
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Signature check failed"));
+    assert_eq!(fs::read(&exe).unwrap(), original);
+
+    let output = run(&["self-update"], key.public_key().as_ref());
+    assert!(output.status.success(), "Self-update failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read(&exe).unwrap(), binary);
+    let output = Command::new(&exe).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "updated\n");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_plugin_subcommand() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let env = TestEnv::new();
+    fs::write("package.json", r#"{ "name": "app", "version": "1.0.0" }"#).unwrap();
+    fs::create_dir_all("bin").unwrap();
+    fs::write(
+        "bin/rjs-hello",
+        "#!/bin/sh\necho \"args: $*\"\necho \"project: $RJS_PROJECT_DIR\"\necho \"version: $RJS_VERSION\"\nexit 3\n",
+    )
+    .unwrap();
+    fs::set_permissions("bin/rjs-hello", fs::Permissions::from_mode(0o755)).unwrap();
+    fs::create_dir_all("src").unwrap();
+    env::set_current_dir("src").unwrap();
+
+    let bin = env.temp_dir.path().join("bin");
+    let path = format!("{}:{}", bin.display(), env::var("PATH").unwrap_or_default());
+    let output = env.run_command_with_env(&["hello", "world", "--flag"], &[("PATH", &path)]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(3), "The plugin's exit code wasn't kept");
+    assert!(stdout.contains("args: world --flag"), "{}", stdout);
+    let project = env.temp_dir.path().canonicalize().unwrap();
+    assert!(stdout.contains(&format!("project: {}", project.display())), "{}", stdout);
+    assert!(stdout.contains(&format!("version: {}", env!("CARGO_PKG_VERSION"))), "{}", stdout);
+
+    let output = env.run_command_with_env(&["nosuchplugin"], &[("PATH", &path)]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no rjs-nosuchplugin found on PATH"));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_command_hooks() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("tool", "1.0.0")]);
+
+    fs::write("package.json", r#"{ "name": "app", "version": "1.0.0" }"#).unwrap();
+    fs::write(
+        ".rjsrc.toml",
+        "[hooks]\npre-install = \"echo $npm_package_name > pre.txt\"\npost-install = \"test -d node_modules/tool && echo done > post.txt\"\npre-publish = \"exit 7\"\n",
+    )
+    .unwrap();
+
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let output = env.run_command_with_env(&["install", "tool", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read_to_string("pre.txt").unwrap(), "app\n");
+    assert_eq!(fs::read_to_string("post.txt").unwrap(), "done\n");
+
+    // A failing pre hook stops the command before it does anything
+    let output = env.run_command_with_env(&["publish"], &envs);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("The pre-publish hook failed"), "Unexpected error: {}", stderr);
+    assert!(stderr.contains("exited with code 7"), "Unexpected error: {}", stderr);
+
+    // Unknown hooks are caught as typos
+    fs::write(".rjsrc.toml", "[hooks]\npreinstall = \"true\"\n").unwrap();
+    let output = env.run_command_with_env(&["install", "--no-progress"], &envs);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("preinstall"));
+}
+
+#[test]
+fn test_verify_reproducible() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("a", "1.0.0"), ("a", "1.1.0"), ("b", "2.0.0")]);
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+
+    fs::write("package.json", r#"{ "name": "app", "dependencies": { "a": "^1.0.0", "b": "2.0.0" } }"#).unwrap();
+    let output = env.run_command_with_env(&["install", "--lockfile-only", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let original = fs::read_to_string("rjs-lock.json").unwrap();
+
+    let output = env.run_command_with_env(&["verify", "--reproducible"], &envs);
+    assert!(output.status.success(), "Verify failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("2 with integrity"));
+
+    // A hand-edited integrity is caught, and so is an entry nothing needs
+    let mut lockfile: serde_json::Value = serde_json::from_str(&original).unwrap();
+    lockfile["packages"]["a@1.1.0"]["integrity"] = serde_json::json!("sha512-AAAA");
+    fs::write("rjs-lock.json", serde_json::to_string_pretty(&lockfile).unwrap()).unwrap();
+    let output = env.run_command_with_env(&["verify", "--reproducible"], &envs);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("a@1.1.0: integrity is \"sha512-AAAA\" in the lockfile"), "{}", stdout);
+
+    let mut lockfile: serde_json::Value = serde_json::from_str(&original).unwrap();
+    lockfile["packages"]["a@1.0.0"] = lockfile["packages"]["a@1.1.0"].clone();
+    lockfile["packages"]["a@1.0.0"]["version"] = serde_json::json!("1.0.0");
+    fs::write("rjs-lock.json", serde_json::to_string_pretty(&lockfile).unwrap()).unwrap();
+    let output = env.run_command_with_env(&["verify", "--reproducible"], &envs);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("a@1.0.0 is locked but not in a fresh resolution"));
+
+    // The same entries laid out differently aren't byte-identical
+    let lockfile: serde_json::Value = serde_json::from_str(&original).unwrap();
+    fs::write("rjs-lock.json", serde_json::to_string(&lockfile).unwrap()).unwrap();
+    let output = env.run_command_with_env(&["verify", "--reproducible"], &envs);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("isn't laid out as rjs writes it"));
+
+    // Without --reproducible only package.json's ranges are checked, offline
+    fs::write("rjs-lock.json", &original).unwrap();
+    fs::write("package.json", r#"{ "name": "app", "dependencies": { "a": "^1.0.0", "b": "^3.0.0" } }"#).unwrap();
+    let output = env.run_command(&["verify"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("b@^3.0.0 from package.json is locked at 2.0.0"));
+}
+
+#[test]
+fn test_verify_reproducible_across_registries() {
+    let env = TestEnv::new();
+    let (first, second) = (serve_registry(&[("a", "1.0.0")]), serve_registry(&[("b", "1.0.0")]));
+    let envs = [("RJS_REGISTRY", first.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+
+    fs::write("package.json", r#"{ "name": "app", "dependencies": { "a": "1.0.0" } }"#).unwrap();
+    let output = env.run_command_with_env(&["install", "--lockfile-only", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let output = env.run_command_with_env(&["install", "b@1.0.0", "--registry", &second, "--lockfile-only", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert_eq!(lockfile["packages"]["a@1.0.0"]["registry"], serde_json::json!(first));
+    assert_eq!(lockfile["packages"]["b@1.0.0"]["registry"], serde_json::json!(second));
+
+    // Each entry is re-read from the registry it was locked from, not the default
+    let output = env.run_command_with_env(&["verify", "--reproducible"], &envs);
+    assert!(output.status.success(), "Verify failed: {}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn test_install_into_workspace() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("lib", "1.0.0")]);
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+
+    fs::write("package.json", r#"{ "name": "monorepo", "private": true, "workspaces": ["packages/*"] }"#).unwrap();
+    for (dir, name) in [("packages/api", "@acme/api"), ("packages/web", "@acme/web")] {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(format!("{}/package.json", dir), format!("{{\n  \"name\": \"{}\",\n  \"version\": \"1.0.0\"\n}}\n", name)).unwrap();
+    }
+    let root_manifest = fs::read_to_string("package.json").unwrap();
+
+    // From inside another workspace, by the last part of the name
+    env::set_current_dir("packages/web").unwrap();
+    let output = env.run_command_with_env(&["install", "lib", "-w", "api", "--no-progress"], &envs);
+    env::set_current_dir(env.temp_dir.path()).unwrap();
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // Saved in the workspace, locked and installed at the root
+    let api: serde_json::Value = serde_json::from_str(&fs::read_to_string("packages/api/package.json").unwrap()).unwrap();
+    assert_eq!(api["dependencies"]["lib"], "1.0.0");
+    assert!(!fs::read_to_string("packages/web/package.json").unwrap().contains("lib"));
+    assert_eq!(fs::read_to_string("package.json").unwrap(), root_manifest);
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert!(lockfile["packages"].get("lib@1.0.0").is_some(), "{}", lockfile);
+    assert!(Path::new("node_modules/lib/package.json").exists());
+    assert!(!Path::new("packages/web/rjs-lock.json").exists());
+
+    let output = env.run_command_with_env(&["install", "lib", "--workspace", "packages/nope"], &envs);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("the workspaces are: packages/api, packages/web"));
+}
+
+#[test]
+fn test_workspace_outdated_and_audit() {
+    let env = TestEnv::new();
+    fs::write("package.json", r#"{ "name": "monorepo", "private": true, "workspaces": ["packages/*"] }"#).unwrap();
+    for (dir, name, spec) in [("packages/api", "@acme/api", "^1.0.0"), ("packages/web", "@acme/web", "2.0.0")] {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            format!("{}/package.json", dir),
+            format!(r#"{{ "name": "{}", "version": "1.0.0", "dependencies": {{ "lib": "{}" }} }}"#, name, spec),
+        )
+        .unwrap();
+    }
+    fs::write(
+        "rjs-lock.json",
+        r#"{"name":"root","version":"0.0.0","lockfile_version":"1.0.0","packages":{"lib@1.0.0":{"version":"1.0.0","resolved":null,"integrity":null,"dependencies":{}},"lib@2.0.0":{"version":"2.0.0","resolved":null,"integrity":null,"dependencies":{}}}}"#,
+    )
+    .unwrap();
+
+    // Only the workspace behind the latest version is reported
+    let registry = serve_registry(&[("lib", "1.0.0"), ("lib", "2.0.0")]);
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let output = env.run_command_with_env(&["outdated", "--json"], &envs);
+    assert!(output.status.success(), "Outdated failed: {}", String::from_utf8_lossy(&output.stderr));
+    let groups: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(groups[0]["workspace"], ".");
+    assert_eq!(groups[0]["packages"], serde_json::json!([]));
+    assert_eq!(groups[1]["workspace"], "packages/api");
+    assert_eq!(
+        groups[1]["packages"],
+        serde_json::json!([{ "name": "lib", "current": "1.0.0", "wanted": "1.0.0", "latest": "2.0.0", "type": "dependencies" }])
+    );
+    assert_eq!(groups[2]["packages"], serde_json::json!([]));
+
+    // Advisories land on the workspaces whose dependencies lead to them
+    let advisories = serde_json::json!({ "lib": [{ "id": 1, "title": "Prototype pollution", "severity": "high", "vulnerable_versions": "<2.0.0" }] });
+    let registry = serve_files(move |_| vec![("/-/npm/v1/security/advisories/bulk".to_string(), advisories.to_string().into_bytes())]);
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    env::set_current_dir("packages/web").unwrap();
+    let output = env.run_command_with_env(&["audit"], &envs);
+    env::set_current_dir(env.temp_dir.path()).unwrap();
+    assert!(output.status.success(), "Audit failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("packages/api (@acme/api): 1 vulnerability"), "{}", stdout);
+    assert!(stdout.contains("packages/web (@acme/web): no vulnerabilities"), "{}", stdout);
+    assert!(stdout.contains("high lib@1.0.0: Prototype pollution"), "{}", stdout);
+
+    let output = env.run_command_with_env(&["audit", "--audit-level", "moderate"], &envs);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 advisories at or above moderate severity"));
+}
+
+#[test]
+fn test_catalog_versions() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("lib", "1.0.0"), ("lib", "1.1.0"), ("lib", "2.0.0")]);
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+
+    fs::write(
+        "package.json",
+        r#"{ "name": "monorepo", "private": true, "dependencies": { "lib": "catalog:" },
+            "workspaces": { "packages": ["packages/*"], "catalog": { "lib": "^1.0.0" } },
+            "catalogs": { "legacy": { "lib": "1.0.0" } } }"#,
+    )
+    .unwrap();
+    fs::create_dir_all("packages/api").unwrap();
+    fs::write("packages/api/package.json", r#"{ "name": "api", "version": "1.0.0" }"#).unwrap();
+
+    // The default catalog's range picks the version, not the registry's latest
+    let output = env.run_command_with_env(&["install", "--lockfile-only", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert!(lockfile["packages"].get("lib@1.1.0").is_some(), "{}", lockfile);
+    assert!(lockfile["packages"].get("lib@2.0.0").is_none(), "{}", lockfile);
+
+    // A named catalog, saved as a reference to it
+    let output = env.run_command_with_env(&["install", "lib@catalog:legacy", "-w", "api", "--lockfile-only", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let api: serde_json::Value = serde_json::from_str(&fs::read_to_string("packages/api/package.json").unwrap()).unwrap();
+    assert_eq!(api["dependencies"]["lib"], "catalog:legacy");
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert!(lockfile["packages"].get("lib@1.0.0").is_some(), "{}", lockfile);
+
+    let output = env.run_command_with_env(&["outdated", "--json"], &envs);
+    let groups: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(groups[0]["packages"][0]["wanted"], "1.1.0", "{}", groups);
+    assert_eq!(groups[1]["packages"][0]["current"], "1.0.0", "{}", groups);
+}
+
+#[test]
+fn test_patch_package() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("lib", "1.0.0")]);
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    fs::write("package.json", r#"{ "name": "app", "version": "1.0.0", "dependencies": { "lib": "1.0.0" } }"#).unwrap();
+    let output = env.run_command_with_env(&["install", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = env.run_command_with_env(&["patch", "lib", "--edit-dir", "edit"], &envs);
+    assert!(output.status.success(), "Patch failed: {}", String::from_utf8_lossy(&output.stderr));
+    fs::write("edit/index.js", "#!/usr/bin/env node\nconsole.log('patched');\n").unwrap();
+    fs::write("edit/extra.js", "module.exports = 1;\n").unwrap();
+
+    let output = env.run_command_with_env(&["patch-commit", "edit"], &envs);
+    assert!(output.status.success(), "Patch commit failed: {}", String::from_utf8_lossy(&output.stderr));
+    let patch = fs::read_to_string("patches/lib@1.0.0.patch").unwrap();
+    assert!(patch.contains("diff --git a/index.js b/index.js"), "{}", patch);
+    assert!(patch.contains("+console.log('patched');"), "{}", patch);
+    assert!(patch.contains("+++ b/extra.js"), "{}", patch);
+    assert!(fs::read_to_string("node_modules/lib/index.js").unwrap().contains("patched"));
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert!(lockfile["packages"]["lib@1.0.0"]["patch_hash"].as_str().unwrap().starts_with("sha256-"), "{}", lockfile);
+
+    // Every install patches the package as it's extracted
+    fs::remove_dir_all("node_modules").unwrap();
+    let output = env.run_command_with_env(&["install", "--frozen", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(fs::read_to_string("node_modules/lib/index.js").unwrap().contains("patched"));
+    assert_eq!(fs::read_to_string("node_modules/lib/extra.js").unwrap(), "module.exports = 1;\n");
+
+    // Without the patch the package is extracted again as published
+    fs::remove_file("patches/lib@1.0.0.patch").unwrap();
+    let output = env.run_command_with_env(&["install", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(!fs::read_to_string("node_modules/lib/index.js").unwrap().contains("patched"));
+    assert!(!Path::new("node_modules/lib/extra.js").exists());
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert!(lockfile["packages"]["lib@1.0.0"].get("patch_hash").is_none(), "{}", lockfile);
+}
+
+#[test]
+fn test_vendor_install() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("lib", "1.0.0")]);
+    fs::write("package.json", r#"{ "name": "app", "version": "1.0.0", "dependencies": { "lib": "^1.0.0" } }"#).unwrap();
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let output = env.run_command_with_env(&["install", "--lockfile-only", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = env.run_command_with_env(&["vendor"], &envs);
+    assert!(output.status.success(), "Vendor failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("vendor/lib@1.0.0.tgz").exists());
+    let index: serde_json::Value = serde_json::from_str(&fs::read_to_string("vendor/rjs-vendor.json").unwrap()).unwrap();
+    assert!(index["packages"].get("lib@1.0.0").is_some(), "{}", index);
+
+    // Without a registry, cache or lockfile, everything comes from vendor/
+    fs::remove_dir_all("rjs-cache").unwrap();
+    fs::remove_file("rjs-lock.json").unwrap();
+    fs::write(".rjsrc.toml", "vendor = true\n").unwrap();
+    let offline = [("RJS_REGISTRY", "http://127.0.0.1:1"), ("RJS_CACHE_DIR", "rjs-cache")];
+    let output = env.run_command_with_env(&["install", "--no-progress"], &offline);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(fs::read_to_string("node_modules/lib/package.json").unwrap().contains("1.0.0"));
+    assert!(fs::read_to_string("rjs-lock.json").unwrap().contains("lib@1.0.0"));
+
+    // Extracted packages replace the tarballs, and install the same way
+    let output = env.run_command_with_env(&["vendor", "--extract"], &envs);
+    assert!(output.status.success(), "Vendor failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("vendor/lib@1.0.0/package.json").exists());
+    assert!(!Path::new("vendor/lib@1.0.0.tgz").exists());
+    fs::remove_dir_all("node_modules").unwrap();
+    let output = env.run_command_with_env(&["install", "--frozen", "--no-progress"], &offline);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("node_modules/lib/index.js").exists());
+}
+
+#[test]
+fn test_deploy_production_bundle() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("lib", "1.0.0"), ("devlib", "1.0.0")]);
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    fs::write(
+        "package.json",
+        r#"{ "name": "app", "version": "1.0.0", "dependencies": { "lib": "1.0.0" }, "devDependencies": { "devlib": "1.0.0" } }"#,
+    )
+    .unwrap();
+    fs::write("server.js", "require('lib');\n").unwrap();
+    let output = env.run_command_with_env(&["install", "--lockfile-only", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let output = env.run_command_with_env(&["install", "devlib@1.0.0", "--no-save", "--lockfile-only", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(fs::read_to_string("rjs-lock.json").unwrap().contains("devlib@1.0.0"));
+
+    let output = env.run_command_with_env(&["deploy", "out"], &envs);
+    assert!(output.status.success(), "Deploy failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("out/package.json").exists());
+    assert_eq!(fs::read_to_string("out/server.js").unwrap(), "require('lib');\n");
+    assert!(Path::new("out/node_modules/lib/index.js").exists());
+    assert!(!Path::new("out/node_modules/devlib").exists());
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("out/rjs-lock.json").unwrap()).unwrap();
+    assert_eq!(lockfile["packages"].as_object().unwrap().keys().collect::<Vec<_>>(), ["lib@1.0.0"]);
+    assert!(!Path::new("node_modules").exists(), "the project's own node_modules is left alone");
+
+    // Never over an earlier bundle
+    let output = env.run_command_with_env(&["deploy", "out"], &envs);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("isn't empty"));
+}
+
+#[test]
+fn test_workspaces_changed() {
+    let env = TestEnv::new();
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .args(["-c", "user.name=rjs", "-c", "user.email=rjs@example.com"])
+            .args(args)
+            .output()
+            .expect("Failed to run git");
+        assert!(output.status.success(), "git {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    };
+    fs::write("package.json", r#"{ "name": "monorepo", "private": true, "workspaces": ["packages/*"] }"#).unwrap();
+    for (dir, manifest) in [
+        ("packages/core", r#"{ "name": "@acme/core", "version": "1.0.0" }"#),
+        ("packages/api", r#"{ "name": "@acme/api", "version": "1.0.0", "dependencies": { "@acme/core": "^1.0.0" } }"#),
+        ("packages/cli", r#"{ "name": "@acme/cli", "version": "1.0.0", "devDependencies": { "@acme/api": "1.0.0" } }"#),
+        ("packages/web", r#"{ "name": "@acme/web", "version": "1.0.0" }"#),
+    ] {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(format!("{}/package.json", dir), manifest).unwrap();
+    }
+    git(&["init", "-q"]);
+    git(&["add", "-A"]);
+    git(&["commit", "-qm", "init"]);
+
+    let output = env.run_command(&["workspaces", "changed", "--since", "HEAD"]);
+    assert!(output.status.success(), "Changed failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No workspace changed since HEAD"));
+
+    // A new file in core affects what depends on it, directly or not
+    fs::write("packages/core/index.js", "module.exports = 1;\n").unwrap();
+    let output = env.run_command(&["workspaces", "changed", "--since", "HEAD", "--json"]);
+    assert!(output.status.success(), "Changed failed: {}", String::from_utf8_lossy(&output.stderr));
+    let affected: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        affected,
+        serde_json::json!([
+            { "name": "@acme/api", "path": "packages/api", "changed": false, "through": ["packages/core"] },
+            { "name": "@acme/cli", "path": "packages/cli", "changed": false, "through": ["packages/core"] },
+            { "name": "@acme/core", "path": "packages/core", "changed": true },
+        ])
+    );
+
+    // Committed changes count from where the branch left the ref
+    git(&["add", "-A"]);
+    git(&["commit", "-qm", "core"]);
+    let output = env.run_command(&["workspaces", "changed", "--since", "HEAD~1"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("packages/core (@acme/core) changed"), "{}", stdout);
+    assert!(stdout.contains("packages/api (@acme/api) depends on packages/core"), "{}", stdout);
+    assert!(!stdout.contains("packages/web"), "{}", stdout);
+}
+
+#[test]
+fn test_workspaces_version_and_publish() {
+    let env = TestEnv::new();
+    fs::write("package.json", r#"{ "name": "monorepo", "private": true, "workspaces": ["packages/*"] }"#).unwrap();
+    for (dir, manifest) in [
+        ("packages/core", "{\n  \"name\": \"wscore\",\n  \"version\": \"1.0.0\"\n}\n"),
+        ("packages/api", "{\n  \"name\": \"wsapi\",\n  \"version\": \"1.0.0\",\n  \"dependencies\": {\n    \"wscore\": \"^1.0.0\"\n  }\n}\n"),
+        ("packages/cli", "{\n  \"name\": \"wscli\",\n  \"version\": \"2.0.0\",\n  \"dependencies\": {\n    \"wsapi\": \"workspace:^\"\n  }\n}\n"),
+        ("packages/app", r#"{ "name": "wsapp", "version": "0.1.0", "private": true }"#),
+    ] {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(format!("{}/package.json", dir), manifest).unwrap();
+    }
+
+    // A major bump of core takes api's range with it, and both dependents
+    // get a patch release
+    let output = env.run_command(&["workspaces", "version", "major", "-w", "wscore"]);
+    assert!(output.status.success(), "Version failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Bumped 3 workspaces"), "Unexpected output: {}", stdout);
+    assert_eq!(
+        fs::read_to_string("packages/api/package.json").unwrap(),
+        "{\n  \"name\": \"wsapi\",\n  \"version\": \"1.0.1\",\n  \"dependencies\": {\n    \"wscore\": \"^2.0.0\"\n  }\n}\n"
+    );
+    assert!(fs::read_to_string("packages/core/package.json").unwrap().contains("\"version\": \"2.0.0\""));
+    assert!(fs::read_to_string("packages/cli/package.json").unwrap().contains("\"version\": \"2.0.1\""));
+    assert!(fs::read_to_string("packages/cli/package.json").unwrap().contains("\"workspace:^\""));
+    assert!(fs::read_to_string("packages/app/package.json").unwrap().contains("\"0.1.0\""));
+
+    // core is already on the registry, api's upload is refused: the publish
+    // stops there, dependencies first, and can be resumed
+    let registry = serve_registry(&[("wscore", "2.0.0")]);
+    let output = env.run_command_with_env(&["workspaces", "publish"], &[("RJS_REGISTRY", registry.as_str())]);
+    assert!(!output.status.success(), "Publish succeeded against a read-only registry");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("wscore@2.0.0 is already published; skipped"), "Unexpected output: {}", stdout);
+    assert!(!stdout.contains("packages/cli"), "Published past the failure: {}", stdout);
+    assert!(!stdout.contains("packages/app"), "Published a private workspace: {}", stdout);
+    assert!(stderr.contains("Failed to publish packages/api") && stderr.contains("--resume"), "Unexpected error: {}", stderr);
+
+    let output = env.run_command_with_env(&["workspaces", "publish", "--resume", "--dry-run"], &[("RJS_REGISTRY", registry.as_str())]);
+    assert!(output.status.success(), "Resume failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("wscore@2.0.0 was published by the earlier run"), "Unexpected output: {}", stdout);
+    let (api, cli) = (stdout.find("packages/api").unwrap(), stdout.find("packages/cli").unwrap());
+    assert!(api < cli, "cli came before its dependency: {}", stdout);
+    assert!(stdout.contains("Would publish 2 workspaces"), "Unexpected output: {}", stdout);
+}
+
+#[test]
+fn test_shrinkwrap() {
+    let env = TestEnv::new();
+    fs::write(
+        "package.json",
+        r#"{ "name": "shrink-cli", "version": "1.0.0", "bin": "cli.js", "dependencies": { "a": "^1.0.0", "b": "^2.0.0" }, "devDependencies": { "c": "1.0.0" } }"#,
+    ).unwrap();
+    fs::write("cli.js", "#!/usr/bin/env node\n").unwrap();
+    let entry = |version: &str, dependencies: &str| format!(
+        r#"{{"version":"{0}","resolved":"https://registry.example/x/-/x-{0}.tgz","integrity":"sha512-{0}","dependencies":{{{1}}}}}"#,
+        version, dependencies
+    );
+    fs::write(
+        "rjs-lock.json",
+        format!(
+            r#"{{"name":"shrink-cli","version":"1.0.0","lockfile_version":"1.0.0","packages":{{"a@1.2.0":{},"b@1.5.0":{},"b@2.1.0":{},"c@1.0.0":{}}}}}"#,
+            entry("1.2.0", r#""b":"^1.0.0""#),
+            entry("1.5.0", ""),
+            entry("2.1.0", ""),
+            entry("1.0.0", ""),
+        ),
+    ).unwrap();
+
+    let output = env.run_command(&["shrinkwrap"]);
+    assert!(output.status.success(), "Shrinkwrap failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("pinning 3 packages"));
+
+    // b@2 is what the package asks for, so a's b@1 nests under a; the dev
+    // dependency isn't pinned
+    let shrinkwrap: serde_json::Value = serde_json::from_str(&fs::read_to_string("npm-shrinkwrap.json").unwrap()).unwrap();
+    assert_eq!(shrinkwrap["lockfileVersion"], 3);
+    let packages = shrinkwrap["packages"].as_object().unwrap();
+    assert_eq!(packages[""]["dependencies"]["b"], "^2.0.0");
+    assert_eq!(packages["node_modules/a"]["version"], "1.2.0");
+    assert_eq!(packages["node_modules/b"]["version"], "2.1.0");
+    assert_eq!(packages["node_modules/a/node_modules/b"]["version"], "1.5.0");
+    assert_eq!(packages["node_modules/a/node_modules/b"]["integrity"], "sha512-1.5.0");
+    assert!(!packages.contains_key("node_modules/c"), "Dev dependency pinned: {:?}", packages.keys());
+
+    // And it's published with the package, even past a `files` allowlist
+    fs::write(
+        "package.json",
+        r#"{ "name": "shrink-cli", "version": "1.0.0", "files": ["cli.js"], "dependencies": { "a": "^1.0.0", "b": "^2.0.0" } }"#,
+    ).unwrap();
+    let output = env.run_command(&["pack", "--dry-run"]);
+    assert!(output.status.success(), "Pack failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("npm-shrinkwrap.json"), "Not packed: {}", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn test_deprecation_warnings() {
+    let env = TestEnv::new();
+
+    // a@1.0.0 pulls in b@1.0.0, which its publisher deprecated
+    let registry = serve_files(|url| {
+        let version = |name: &str, dependencies: serde_json::Value, deprecated: Option<&str>| {
+            let mut info = serde_json::json!({
+                "version": "1.0.0",
+                "dependencies": dependencies,
+                "dist": { "shasum": "", "tarball": format!("{}/{}/-/{}-1.0.0.tgz", url, name, name) },
+            });
+            if let Some(message) = deprecated {
+                info["deprecated"] = serde_json::json!(message);
+            }
+            serde_json::json!({ "name": name, "dist-tags": { "latest": "1.0.0" }, "versions": { "1.0.0": info } })
+                .to_string()
+                .into_bytes()
+        };
+        vec![
+            ("/a".to_string(), version("a", serde_json::json!({ "b": "^1.0.0" }), None)),
+            ("/b".to_string(), version("b", serde_json::json!({}), Some("b is unmaintained, use c"))),
+        ]
+    });
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "^1.0.0"}}"#).unwrap();
+
+    let output = env.run_command_with_env(
+        &["install", "--lockfile-only", "--no-progress"],
+        &[("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")],
+    );
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Deprecated b@1.0.0: b is unmaintained, use c"), "No warning: {}", stdout);
+    assert!(stdout.contains("via a@1.0.0"), "No path: {}", stdout);
+    assert!(!stdout.contains("Deprecated a@"), "Not deprecated: {}", stdout);
+}
+
+#[test]
+fn test_funding_notice() {
+    let env = TestEnv::new();
+    let registry = serve_manifests(&[
+        serde_json::json!({ "name": "a", "version": "1.0.0", "funding": "https://github.com/sponsors/someone" }),
+        serde_json::json!({ "name": "b", "version": "1.0.0", "funding": [{ "type": "opencollective", "url": "https://opencollective.com/b" }, "https://github.com/sponsors/someone"] }),
+        serde_json::json!({ "name": "c", "version": "1.0.0" }),
+    ]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "1.0.0", "b": "1.0.0", "c": "1.0.0"}}"#).unwrap();
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+
+    let output = env.run_command_with_env(&["install", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("2 packages are looking for funding"), "No notice: {}", stdout);
+    assert!(stdout.contains("rjs fund"), "No hint: {}", stdout);
+
+    // The shared sponsor page is listed once, with both packages
+    let output = env.run_command(&["fund"]);
+    assert!(output.status.success(), "Fund failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("https://github.com/sponsors/someone").count(), 1, "{}", stdout);
+    assert!(stdout.contains("a@1.0.0, b@1.0.0"), "{}", stdout);
+
+    let output = env.run_command(&["fund", "b", "--json"]);
+    let funding: serde_json::Value = serde_json::from_slice(&output.stdout).expect("fund --json should print JSON");
+    assert_eq!(funding[0]["sources"][0]["type"], "opencollective");
+    assert_eq!(funding[0]["sources"][1]["url"], "https://github.com/sponsors/someone");
+    assert!(!env.run_command(&["fund", "missing"]).status.success());
+
+    // fund = false turns the notice off
+    let output = env.run_command_with_env(&["install", "--no-progress"], &[vars[0], vars[1], ("RJS_FUND", "false")]);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("looking for funding"));
+}
+
+#[test]
+fn test_release_dates_and_downloads() {
+    let env = TestEnv::new();
+    let registry = serve_files(|url| {
+        let version = |version: &str| serde_json::json!({ "version": version, "dist": { "shasum": "", "tarball": format!("{}/lib/-/lib-{}.tgz", url, version) } });
+        let packument = serde_json::json!({
+            "name": "lib",
+            "dist-tags": { "latest": "2.0.0" },
+            "versions": { "1.0.0": version("1.0.0"), "2.0.0": version("2.0.0") },
+            "time": { "created": "2020-01-01T00:00:00.000Z", "1.0.0": "2020-01-01T00:00:00.000Z", "2.0.0": "2021-06-15T12:00:00.000Z" },
+        });
+        vec![
+            ("/lib".to_string(), packument.to_string().into_bytes()),
+            ("/-/downloads/point/last-week/lib".to_string(), br#"{"downloads": 4321, "package": "lib"}"#.to_vec()),
+        ]
+    });
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"lib": "^1.0.0"}}"#).unwrap();
+    fs::write(
+        "rjs-lock.json",
+        r#"{"name":"app","version":"1.0.0","lockfile_version":"1.0.0","packages":{"lib@1.0.0":{"version":"1.0.0","resolved":null,"integrity":null,"dependencies":{}}}}"#,
+    )
+    .unwrap();
+
+    // Counts are only fetched when asked for
+    let output = env.run_command_with_env(&["outdated", "--json"], &envs);
+    assert!(output.status.success(), "Outdated failed: {}", String::from_utf8_lossy(&output.stderr));
+    let groups: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(groups[0]["packages"][0]["published"], "2021-06-15T12:00:00.000Z");
+    assert!(groups[0]["packages"][0].get("downloads").is_none(), "{}", groups);
+
+    let output = env.run_command_with_env(&["outdated", "--downloads"], &envs);
+    assert!(output.status.success(), "Outdated failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Age") && stdout.contains("years ago"), "{}", stdout);
+    assert!(stdout.contains("4321"), "{}", stdout);
+
+    let output = env.run_command_with_env(&["view", "lib@1.0.0"], &envs);
+    assert!(output.status.success(), "View failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("published 2020-01-01 ("), "{}", stdout);
+    assert!(stdout.contains("downloads: 4321 last week"), "{}", stdout);
+    assert!(stdout.contains("latest: 2.0.0 published 2021-06-15"), "{}", stdout);
+}
+
+#[test]
+fn test_tarball_cache_by_integrity() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("lib", "1.0.0")]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"lib": "1.0.0"}}"#).unwrap();
+    let output = env.run_command_with_env(&["install", "--no-progress"], &[("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")]);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // The same artifact, locked at a URL nothing answers on and with an extra
+    // hash listed, still comes from the cache without touching the network
+    let mut lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    let entry = &mut lockfile["packages"]["lib@1.0.0"];
+    let integrity = entry["integrity"].as_str().unwrap().to_string();
+    entry["integrity"] = serde_json::json!(format!("sha1-2jmj7l5rSw0yVb/vlWAYkK/YBwk= {}", integrity));
+    entry["resolved"] = serde_json::json!("http://127.0.0.1:9/lib/-/lib-1.0.0.tgz");
+    fs::write("rjs-lock.json", lockfile.to_string()).unwrap();
+    fs::remove_dir_all("node_modules").unwrap();
+
+    let output = env.run_command_with_env(&["install", "--frozen", "--no-progress"], &[("RJS_REGISTRY", "http://127.0.0.1:9"), ("RJS_CACHE_DIR", "rjs-cache")]);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("node_modules/lib/package.json").exists());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0B downloaded"), "{}", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn test_offline_from_metadata_cache() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("lib", "1.0.0"), ("lib", "1.1.0")]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"lib": "^1.0.0"}}"#).unwrap();
+    let output = env.run_command_with_env(&["install", "--lockfile-only", "--no-progress"], &[("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")]);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // Without the lockfile, resolution has to read the packument back from the
+    // compressed cache
+    fs::remove_file("rjs-lock.json").unwrap();
+    let output = env.run_command_with_env(
+        &["install", "--offline", "--lockfile-only", "--no-progress"],
+        &[("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")],
+    );
+    assert!(output.status.success(), "Offline install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    assert!(lockfile["packages"].get("lib@1.1.0").is_some(), "{}", lockfile);
+}
+
+#[test]
+fn test_dns_cache_and_prewarm() {
+    let env = TestEnv::new();
+    // By name, so connections look the host up
+    let registry = serve_registry(&[("a", "1.0.0"), ("b", "1.0.0"), ("c", "1.0.0")]).replace("127.0.0.1", "localhost");
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "1.0.0", "b": "1.0.0", "c": "1.0.0"}}"#).unwrap();
+
+    let output = env.run_command_with_env(
+        &["install", "--no-progress", "-vv"],
+        &[("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache"), ("RJS_PREWARM_CONNECTIONS", "2")],
+    );
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // The test registry closes every connection, so each request opens one,
+    // yet the host is looked up once (tarballs come from its IP address)
+    assert!(stdout.contains("1 DNS lookups for 2 hosts"), "{}", stdout);
+    assert!(stdout.contains("2 of them ahead of time"), "{}", stdout);
+
+    // -v alone leaves the pool out
+    let output = env.run_command_with_env(&["install", "--no-progress", "-v"], &[("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("network:") && !stdout.contains("pool:"), "{}", stdout);
+}
+
+#[test]
+fn test_http3_falls_back() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("a", "1.0.0")]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "1.0.0"}}"#).unwrap();
+
+    // The test registry speaks plain HTTP, so requests stay off QUIC whether
+    // or not this build has the http3 feature
+    let output = env.run_command_with_env(
+        &["install", "--no-progress"],
+        &[("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache"), ("RJS_HTTP3", "true")],
+    );
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("node_modules/a/package.json").exists());
+
+    let output = env.run_command_with_env(&["env", "--json"], &[("RJS_HTTP3", "true")]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["network"]["http3"]["value"], true);
+}
+
+#[test]
+fn test_network_family() {
+    let env = TestEnv::new();
+    // By name, so the family decides which of localhost's addresses are used;
+    // the test registry only listens on 127.0.0.1
+    let registry = serve_registry(&[("a", "1.0.0")]).replace("127.0.0.1", "localhost");
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "1.0.0"}}"#).unwrap();
+
+    let output = env.run_command_with_env(
+        &["install", "--no-progress"],
+        &[("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache"), ("RJS_NETWORK_FAMILY", "ipv4")],
+    );
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("node_modules/a/package.json").exists());
+
+    fs::remove_dir_all("node_modules").unwrap();
+    fs::remove_file("rjs-lock.json").unwrap();
+    let output = env.run_command_with_env(
+        &["install", "--no-progress"],
+        &[("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache-v6"), ("RJS_NETWORK_FAMILY", "ipv6")],
+    );
+    assert!(!Path::new("node_modules/a/package.json").exists(), "{}", String::from_utf8_lossy(&output.stdout));
+
+    let output = env.run_command_with_env(&["env"], &[("RJS_NETWORK_FAMILY", "ipv5")]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("RJS_NETWORK_FAMILY=ipv5"));
+}
+
+#[test]
+fn test_dir_option() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("a", "1.0.0")]);
+    fs::create_dir("app").unwrap();
+    fs::write("app/package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "1.0.0"}}"#).unwrap();
+    let envs = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+
+    let output = env.run_command_with_env(&["--dir", "app", "install", "--no-progress"], &envs);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("app/node_modules/a/package.json").exists());
+    assert!(Path::new("app/rjs-lock.json").exists());
+    assert!(!Path::new("node_modules").exists() && !Path::new("rjs-lock.json").exists());
+    // rjs itself stays put, so paths given to it, like the cache directory
+    // above, are relative to where it was run
+    assert!(Path::new("rjs-cache").exists() && !Path::new("app/rjs-cache").exists());
+
+    // Global, so it can follow the command too
+    let output = env.run_command(&["list", "--prefix", "app"]);
+    assert!(output.status.success(), "List failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("a 1.0.0"), "{}", String::from_utf8_lossy(&output.stdout));
+
+    let output = env.run_command(&["-C", "missing", "list"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing"));
+}
+
+#[test]
+fn test_path_commands() {
+    let env = TestEnv::new();
+    let cwd = env::current_dir().unwrap();
+    let print = |args: &[&str], envs: &[(&str, &str)]| {
+        let output = env.run_command_with_env(args, envs);
+        assert!(output.status.success(), "{:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+        PathBuf::from(String::from_utf8_lossy(&output.stdout).trim())
+    };
+
+    assert_eq!(print(&["root"], &[]), cwd.join("node_modules"));
+    assert_eq!(print(&["bin"], &[]), cwd.join("node_modules").join(".bin"));
+    assert_eq!(print(&["prefix"], &[]), cwd);
+
+    let global = [("npm_config_prefix", "/opt/node")];
+    assert_eq!(print(&["prefix", "-g"], &global), PathBuf::from("/opt/node"));
+    assert_eq!(print(&["root", "--global"], &global), PathBuf::from("/opt/node/lib/node_modules"));
+    assert_eq!(print(&["bin", "-g"], &global), PathBuf::from("/opt/node/bin"));
+
+    let cache = [("RJS_CACHE_DIR", "/var/cache/rjs")];
+    assert_eq!(print(&["cache", "dir"], &cache), PathBuf::from("/var/cache/rjs"));
+    assert_eq!(print(&["cache", "dir", "--tarballs"], &cache), PathBuf::from("/var/cache/rjs/tarballs"));
+    assert_eq!(print(&["cache", "dir", "--metadata"], &cache), PathBuf::from("/var/cache/rjs/metadata"));
+}
+
+#[test]
+fn test_ping_command() {
+    let env = TestEnv::new();
+    let registry = serve_files(|_| {
+        vec![
+            ("/-/ping?write=true".to_string(), b"{}".to_vec()),
+            ("/-/whoami".to_string(), br#"{"username": "alice"}"#.to_vec()),
+        ]
+    });
+    // Answers 404 to everything, like a proxy pointed at the wrong place
+    let broken = serve_files(|_| Vec::new());
+    let host = registry.trim_start_matches("http:");
+    fs::write(".npmrc", format!("{}/:_authToken=s3cret\n@acme:registry={}/\n", host, broken)).unwrap();
+    let envs = [("RJS_REGISTRY", registry.as_str())];
+
+    let output = env.run_command_with_env(&["ping"], &envs);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{}", stdout);
+    assert!(stdout.contains(&format!("{}  ", registry)) && stdout.contains("ms  logged in as alice"), "{}", stdout);
+    assert!(stdout.contains(&format!("{} (@acme)", broken)) && stdout.contains("404"), "{}", stdout);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 of 2 registries failed"));
+
+    let output = env.run_command_with_env(&["ping", "--registry", &registry, "--json"], &envs);
+    assert!(output.status.success(), "Ping failed: {}", String::from_utf8_lossy(&output.stderr));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json[0]["user"], "alice");
+    assert!(json[0]["time_ms"].is_u64());
+}
+
+#[test]
+fn test_explain_command() {
+    let env = TestEnv::new();
+    let registry = serve_manifests(&[
+        serde_json::json!({ "name": "a", "version": "1.0.0", "license": "MIT" }),
+        serde_json::json!({ "name": "b", "version": "1.0.0", "dependencies": { "a": "~1.0.0" } }),
+    ]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "^1.0.0", "b": "1.0.0"}}"#).unwrap();
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let output = env.run_command_with_env(&["install", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = env.run_command(&["explain", "a@1.0.0"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Explain failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains(&format!("resolved   {}/a/-/a-1.0.0.tgz", registry)), "{}", stdout);
+    assert!(stdout.contains("integrity  sha512-"), "{}", stdout);
+    assert!(stdout.contains("package.json  ^1.0.0"), "{}", stdout);
+
+    assert!(stdout.contains("b@1.0.0       ~1.0.0"), "{}", stdout);
+    assert!(stdout.contains("installed  node_modules/a (hoisted)"), "{}", stdout);
+
+    // Every locked version when no version is given
+    let output = env.run_command(&["explain", "a", "--json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 1);
+    assert_eq!(json[0]["license"], "MIT");
+    assert_eq!(json[0]["dependents"].as_array().unwrap().len(), 2);
+    assert_eq!(json[0]["installed"][0]["hoisted"], true);
+
+    let output = env.run_command(&["explain", "a@2.0.0"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("rjs-lock.json has a at 1.0.0, not 2.0.0"));
+    let output = env.run_command(&["explain", "c"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("c isn't in rjs-lock.json"));
+}
+
+#[test]
+fn test_large_packument() {
+    let env = TestEnv::new();
+    // A few MB of metadata, mostly readmes resolution never looks at
+    let readme = "lorem ipsum ".repeat(1000);
+    let manifests: Vec<serde_json::Value> = (0..400)
+        .map(|patch| serde_json::json!({ "name": "big", "version": format!("1.0.{}", patch), "readme": readme }))
+        .collect();
+    let registry = serve_manifests(&manifests);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"big": "^1.0.0"}}"#).unwrap();
+
+    let output = env.run_command_with_env(
+        &["install", "--no-progress", "-v"],
+        &[("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")],
+    );
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let installed = fs::read_to_string("node_modules/big/package.json").unwrap();
+    assert!(installed.contains("\"1.0.399\""), "{}", installed);
+    // The whole body was still read and counted
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("4.9MB received"), "{}", stdout);
+}
+
+#[test]
+fn test_max_packages_in_memory() {
+    let env = TestEnv::new();
+    let registry = serve_manifests(&[
+        serde_json::json!({ "name": "a", "version": "1.0.0", "dependencies": { "b": "^1.0.0", "c": "^1.0.0" } }),
+        serde_json::json!({ "name": "b", "version": "1.0.0", "dependencies": { "d": "^1.0.0" } }),
+        serde_json::json!({ "name": "c", "version": "1.0.0", "dependencies": { "d": "~1.0.0" }, "license": "MIT" }),
+        serde_json::json!({ "name": "d", "version": "1.0.0" }),
+    ]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "1.0.0"}}"#).unwrap();
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+
+    let output = env.run_command_with_env(&["install", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let expected = fs::read_to_string("rjs-lock.json").unwrap();
+    fs::remove_dir_all("node_modules").unwrap();
+    fs::remove_file("rjs-lock.json").unwrap();
+
+    // Only one package fits in memory; the rest go through the spill database
+    let output = env.run_command_with_env(
+        &["install", "--no-progress", "-v"],
+        &[vars[0], vars[1], ("RJS_MAX_PACKAGES_IN_MEMORY", "1")],
+    );
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("3 resolved packages spilled to disk past the first 1"), "{}", stdout);
+    for name in ["a", "b", "c", "d"] {
+        assert!(Path::new("node_modules").join(name).join("package.json").exists(), "{} isn't installed", name);
+    }
+    assert_eq!(fs::read_to_string("rjs-lock.json").unwrap(), expected);
+}
+
+#[test]
+fn test_skip_intact_packages() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("a", "1.0.0")]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "1.0.0"}}"#).unwrap();
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let install = || {
+        let output = env.run_command_with_env(&["install", "--no-progress"], &vars);
+        assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    };
+    install();
+    assert!(Path::new("node_modules/a/.rjs-state").exists());
+
+    // Intact, so it isn't extracted again: a file added since survives
+    fs::write("node_modules/a/extra.txt", "kept").unwrap();
+    install();
+    assert!(Path::new("node_modules/a/extra.txt").exists());
+
+    // A damaged file puts the whole package back
+    fs::write("node_modules/a/index.js", "").unwrap();
+    install();
+    assert_eq!(fs::read_to_string("node_modules/a/index.js").unwrap(), "#!/usr/bin/env node\n");
+    assert!(!Path::new("node_modules/a/extra.txt").exists());
+
+    // So does a directory with no state to check it against
+    fs::write("node_modules/a/extra.txt", "kept").unwrap();
+    fs::remove_file("node_modules/a/.rjs-state").unwrap();
+    install();
+    assert!(!Path::new("node_modules/a/extra.txt").exists());
+    assert!(Path::new("node_modules/a/.rjs-state").exists());
+}
+
+#[test]
+fn test_conflicting_versions_nested() {
+    let env = TestEnv::new();
+    let registry = serve_manifests(&[
+        serde_json::json!({ "name": "a", "version": "1.0.0" }),
+        serde_json::json!({ "name": "a", "version": "2.0.0" }),
+        serde_json::json!({ "name": "b", "version": "1.0.0", "dependencies": { "a": "^1.0.0" } }),
+    ]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "^2.0.0", "b": "1.0.0"}}"#).unwrap();
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let version = |path: &str| {
+        let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        manifest["version"].as_str().unwrap().to_string()
+    };
+
+    let output = env.run_command_with_env(&["install", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let lockfile = fs::read_to_string("rjs-lock.json").unwrap();
+    assert!(lockfile.contains("\"a@1.0.0\"") && lockfile.contains("\"a@2.0.0\""), "{}", lockfile);
+    // The project's version at the top, b's own under b
+    assert_eq!(version("node_modules/a/package.json"), "2.0.0");
+    assert_eq!(version("node_modules/b/node_modules/a/package.json"), "1.0.0");
+
+    // The same layout from the lockfile
+    fs::remove_dir_all("node_modules").unwrap();
+    let output = env.run_command_with_env(&["install", "--frozen", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(version("node_modules/a/package.json"), "2.0.0");
+    assert_eq!(version("node_modules/b/node_modules/a/package.json"), "1.0.0");
+}
+
+#[test]
+fn test_saved_dependency_graph() {
+    let env = TestEnv::new();
+    let registry = serve_manifests(&[
+        serde_json::json!({ "name": "a", "version": "1.0.0", "dependencies": { "b": "^1.0.0" } }),
+        serde_json::json!({ "name": "b", "version": "1.1.0", "dependencies": { "c": "~2.0.0" } }),
+        serde_json::json!({ "name": "c", "version": "2.0.3" }),
+    ]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "^1.0.0"}}"#).unwrap();
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let output = env.run_command_with_env(&["install", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let graph: serde_json::Value = serde_json::from_str(&fs::read_to_string("node_modules/.rjs-graph.json").unwrap()).unwrap();
+    assert_eq!(graph["roots"]["a"]["to"], "a@1.0.0");
+    assert_eq!(graph["packages"]["b@1.1.0"]["c"]["to"], "c@2.0.3");
+
+    // Read back without the registry
+    let output = env.run_command(&["list", "--depth", "2"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("a ^1.0.0 -> 1.0.0"), "{}", stdout);
+    assert!(stdout.contains("    b ^1.0.0 -> 1.1.0"), "{}", stdout);
+    assert!(stdout.contains("      c ~2.0.0 -> 2.0.3"), "{}", stdout);
+    let output = env.run_command(&["list", "--depth", "1"]);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("c ~2.0.0"));
+    let output = env.run_command(&["explain", "c"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("b@1.1.0  ~2.0.0"));
+    let output = env.run_command(&["why", "c"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("b@1.1.0  ~2.0.0"));
+    let output = env.run_command(&["graph"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("digraph dependencies {"), "{}", stdout);
+    assert!(stdout.contains(r#""app" -> "a@1.0.0" [label="^1.0.0"];"#), "{}", stdout);
+    assert!(stdout.contains(r#""b@1.1.0" -> "c@2.0.3" [label="~2.0.0"];"#), "{}", stdout);
+
+    // A lockfile changed since isn't described by the graph anymore
+    let lockfile = fs::read_to_string("rjs-lock.json").unwrap();
+    fs::write("rjs-lock.json", format!("{}\n", lockfile)).unwrap();
+    let output = env.run_command(&["list", "--depth", "2"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("-> 1.1.0"), "{}", stdout);
+    assert!(stdout.contains("No dependency graph saved"), "{}", stdout);
+    let output = env.run_command(&["graph"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No dependency graph saved"));
+}
+
+#[test]
+fn test_scoped_package_layout() {
+    let env = TestEnv::new();
+    let registry = serve_manifests(&[
+        serde_json::json!({ "name": "@acme/util", "version": "1.0.0" }),
+        serde_json::json!({ "name": "@acme/util", "version": "2.0.0" }),
+        serde_json::json!({ "name": "@acme/widget", "version": "1.0.0", "bin": "index.js", "dependencies": { "@acme/util": "^1.0.0" } }),
+    ]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"@acme/util": "^2.0.0", "@acme/widget": "1.0.0"}}"#).unwrap();
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let version = |path: &str| {
+        let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        manifest["version"].as_str().unwrap().to_string()
+    };
+
+    let output = env.run_command_with_env(&["install", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    for key in ["@acme/util@1.0.0", "@acme/util@2.0.0", "@acme/widget@1.0.0"] {
+        assert!(lockfile["packages"].get(key).is_some(), "{} not locked: {}", key, lockfile);
+    }
+    // Inside the scope directory, at the top and nested alike
+    assert_eq!(version("node_modules/@acme/util/package.json"), "2.0.0");
+    assert_eq!(version("node_modules/@acme/widget/package.json"), "1.0.0");
+    assert_eq!(version("node_modules/@acme/widget/node_modules/@acme/util/package.json"), "1.0.0");
+    assert!(!Path::new("node_modules/@acme%2futil").exists() && !Path::new("node_modules/acme").exists());
+    assert!(Path::new("node_modules/.bin/widget").exists(), "Bin of a scoped package not linked");
+
+    // The same layout from the lockfile
+    fs::remove_dir_all("node_modules").unwrap();
+    let output = env.run_command_with_env(&["install", "--frozen", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(version("node_modules/@acme/util/package.json"), "2.0.0");
+    assert_eq!(version("node_modules/@acme/widget/node_modules/@acme/util/package.json"), "1.0.0");
+
+    // Removing the last package of a scope takes the scope directory along
+    let output = env.run_command_with_env(&["uninstall", "@acme/widget", "@acme/util"], &vars);
+    assert!(output.status.success(), "Uninstall failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(!Path::new("node_modules/@acme").exists(), "Empty scope directory left behind");
+    assert!(!fs::read_to_string("rjs-lock.json").unwrap().contains("@acme/"));
+}
+
+#[test]
+fn test_install_local_tarball() {
+    let env = TestEnv::new();
+    let registry = serve_registry(&[("dep", "1.0.0")]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0"}"#).unwrap();
+    fs::create_dir("vendor").unwrap();
+    let manifest = serde_json::json!({ "name": "foo", "version": "1.2.3", "dependencies": { "dep": "^1.0.0" } });
+    fs::write("vendor/foo-1.2.3.tgz", package_tarball(&manifest)).unwrap();
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+
+    let output = env.run_command_with_env(&["install", "--no-progress", "./vendor/foo-1.2.3.tgz"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("node_modules/foo/index.js").exists());
+    assert!(Path::new("node_modules/dep/package.json").exists(), "Dependency of the tarball not installed");
+    let package_json: serde_json::Value = serde_json::from_str(&fs::read_to_string("package.json").unwrap()).unwrap();
+    assert_eq!(package_json["dependencies"]["foo"], "file:vendor/foo-1.2.3.tgz");
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    let entry = &lockfile["packages"]["foo@1.2.3"];
+    assert_eq!(entry["resolved"], "file:vendor/foo-1.2.3.tgz");
+    assert!(entry["integrity"].as_str().is_some_and(|integrity| integrity.starts_with("sha512-")), "{}", entry);
+
+    // From package.json and from the lockfile alike
+    fs::remove_dir_all("node_modules").unwrap();
+    let output = env.run_command_with_env(&["install", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("node_modules/foo/index.js").exists());
+    fs::remove_dir_all("node_modules").unwrap();
+    let output = env.run_command_with_env(&["install", "--frozen", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new("node_modules/foo/index.js").exists());
+
+    // A tarball rebuilt since it was locked doesn't match its integrity
+    let manifest = serde_json::json!({ "name": "foo", "version": "1.2.3", "description": "rebuilt" });
+    fs::write("vendor/foo-1.2.3.tgz", package_tarball(&manifest)).unwrap();
+    fs::remove_dir_all("node_modules").unwrap();
+    let output = env.run_command_with_env(&["install", "--frozen", "--no-progress"], &vars);
+    assert!(!output.status.success(), "Changed tarball installed");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("changed since it was locked"), "{}", stderr);
+
+    // Paths that aren't package tarballs are refused
+    let output = env.run_command_with_env(&["install", "--no-progress", "./vendor/missing.tgz"], &vars);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("isn't a tarball"));
+
+    // Only the project's own package.json can point at tarballs on disk
+    let manifest = serde_json::json!({ "name": "bar", "version": "1.0.0", "dependencies": { "foo": "file:foo-1.2.3.tgz" } });
+    fs::write("vendor/bar-1.0.0.tgz", package_tarball(&manifest)).unwrap();
+    let output = env.run_command_with_env(&["install", "--no-progress", "./vendor/bar-1.0.0.tgz"], &vars);
+    assert!(!output.status.success(), "Transitive file: spec installed");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("only the project's package.json can install local tarballs"), "{}", stderr);
+}
+
+#[test]
+fn test_lockfile_merge_keeps_versions_still_asked_for() {
+    let env = TestEnv::new();
+    let registry = serve_manifests(&[
+        serde_json::json!({ "name": "a", "version": "1.0.0" }),
+        serde_json::json!({ "name": "a", "version": "2.0.0" }),
+        serde_json::json!({ "name": "b", "version": "1.0.0", "dependencies": { "a": "^1.0.0" } }),
+    ]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "^2.0.0"}}"#).unwrap();
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+    let locked = || -> serde_json::Value {
+        let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+        lockfile["packages"].clone()
+    };
+
+    let output = env.run_command_with_env(&["install", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // b locks a@1 for itself; the project's a@2 stays locked beside it
+    let output = env.run_command_with_env(&["install", "b", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    for key in ["a@1.0.0", "a@2.0.0", "b@1.0.0"] {
+        assert!(locked().get(key).is_some(), "{} not locked: {}", key, locked());
+    }
+    fs::remove_dir_all("node_modules").unwrap();
+    let output = env.run_command_with_env(&["install", "--frozen", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let version = |path: &str| {
+        let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        manifest["version"].as_str().unwrap().to_string()
+    };
+    assert_eq!(version("node_modules/a/package.json"), "2.0.0");
+    assert_eq!(version("node_modules/b/node_modules/a/package.json"), "1.0.0");
+
+    // A version nothing asks for anymore is replaced
+    let output = env.run_command_with_env(&["install", "a@1.0.0", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(locked().get("a@2.0.0").is_none(), "Replaced version still locked: {}", locked());
+}
+
+#[test]
+fn test_dev_dependencies_share_one_layout() {
+    let env = TestEnv::new();
+    let registry = serve_manifests(&[
+        serde_json::json!({ "name": "a", "version": "1.0.0" }),
+        serde_json::json!({ "name": "a", "version": "2.0.0" }),
+        serde_json::json!({ "name": "b", "version": "1.0.0", "dependencies": { "a": "^1.0.0" } }),
+    ]);
+    fs::write(
+        "package.json",
+        r#"{"name": "app", "version": "1.0.0", "dependencies": {"a": "^2.0.0"}, "devDependencies": {"b": "1.0.0"}}"#,
+    )
+    .unwrap();
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+
+    let output = env.run_command_with_env(&["install", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    let version = |path: &str| {
+        let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        manifest["version"].as_str().unwrap().to_string()
+    };
+    assert_eq!(version("node_modules/a/package.json"), "2.0.0");
+    assert_eq!(version("node_modules/b/node_modules/a/package.json"), "1.0.0");
+
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    for key in ["a@1.0.0", "a@2.0.0", "b@1.0.0"] {
+        assert!(lockfile["packages"].get(key).is_some(), "{} not locked: {}", key, lockfile);
+    }
+}
+
+#[test]
+fn test_transitive_conflicts_are_not_deduplicated() {
+    let env = TestEnv::new();
+    let registry = serve_manifests(&[
+        serde_json::json!({ "name": "a", "version": "1.0.0" }),
+        serde_json::json!({ "name": "a", "version": "2.0.0" }),
+        serde_json::json!({ "name": "b", "version": "1.0.0", "dependencies": { "a": "^1.0.0" } }),
+        serde_json::json!({ "name": "c", "version": "1.0.0", "dependencies": { "a": "^2.0.0" } }),
+    ]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0", "dependencies": {"b": "1.0.0", "c": "1.0.0"}}"#).unwrap();
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+
+    let output = env.run_command_with_env(&["install", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // Each dependent keeps its own range and finds a version matching it
+    let lockfile: serde_json::Value = serde_json::from_str(&fs::read_to_string("rjs-lock.json").unwrap()).unwrap();
+    let packages = &lockfile["packages"];
+    assert!(packages.get("a@1.0.0").is_some() && packages.get("a@2.0.0").is_some(), "{}", lockfile);
+    assert_eq!(packages["b@1.0.0"]["dependencies"]["a"], "^1.0.0");
+    assert_eq!(packages["c@1.0.0"]["dependencies"]["a"], "^2.0.0");
+
+    let version = |dependent: &str| {
+        let nested = format!("node_modules/{}/node_modules/a/package.json", dependent);
+        let path = if Path::new(&nested).exists() { nested } else { "node_modules/a/package.json".to_string() };
+        let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        manifest["version"].as_str().unwrap().to_string()
+    };
+    assert_eq!(version("b"), "1.0.0");
+    assert_eq!(version("c"), "2.0.0");
+}
+
+#[test]
+fn test_install_fails_when_resolution_fails() {
+    let env = TestEnv::new();
+    let registry = serve_manifests(&[
+        serde_json::json!({ "name": "a", "version": "1.0.0" }),
+        serde_json::json!({ "name": "b", "version": "1.0.0", "dependencies": { "ghost": "^1.0.0" } }),
+        serde_json::json!({ "name": "c", "version": "1.0.0", "optionalDependencies": { "ghost": "^2.0.0" } }),
+    ]);
+    fs::write("package.json", r#"{"name": "app", "version": "1.0.0"}"#).unwrap();
+    let vars = [("RJS_REGISTRY", registry.as_str()), ("RJS_CACHE_DIR", "rjs-cache")];
+
+    let output = env.run_command_with_env(&["install", "missing", "--no-progress"], &vars);
+    assert!(!output.status.success(), "Install of a missing package succeeded");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Failed to resolve missing@latest"), "{}", stderr);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Installed"));
+    assert!(!Path::new("rjs-lock.json").exists(), "Lockfile written for a failed resolve");
+
+    let output = env.run_command_with_env(&["install", "b", "--no-progress"], &vars);
+    assert!(!output.status.success(), "Install with a missing dependency succeeded");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Failed to resolve ghost@^1.0.0, pulled in by b@1.0.0"), "{}", stderr);
+
+    // An optional dependency may be missing; the count is what was installed
+    let output = env.run_command_with_env(&["install", "a", "c", "--no-progress"], &vars);
+    assert!(output.status.success(), "Install failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Installed 2 packages"));
+    assert!(Path::new("node_modules/c/package.json").exists());
+}
@@ -9,9 +9,11 @@ use tempfile::TempDir;
 use std::path::PathBuf;
 
 const ITERATIONS: usize = 3;
+#[allow(dead_code)]
 const WARM_UP: bool = true;
 
 // Use a static variable to store test directory path
+#[allow(dead_code)]
 static CURRENT_TEST_DIR: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
 
 struct TestEnvironment {
@@ -28,7 +30,7 @@ impl TestEnvironment {
         
         let temp_dir = TempDir::new().expect("Failed to create temp directory");
         
-        env::set_current_dir(&temp_dir.path()).expect("Failed to change to temp directory");
+        env::set_current_dir(temp_dir.path()).expect("Failed to change to temp directory");
         
         Self {
             temp_dir,
@@ -81,6 +83,7 @@ fn find_rjs_binary() -> std::path::PathBuf {
     std::path::PathBuf::from("target/release/rjs")
 }
 
+#[allow(dead_code)]
 fn measure_command(cmd: &[&str]) -> (Duration, String, bool) {
     let start = Instant::now();
 
@@ -122,6 +125,7 @@ fn measure_command(cmd: &[&str]) -> (Duration, String, bool) {
     (elapsed, stdout, success)
 }
 
+#[allow(dead_code)]
 fn setup() {
     // Get the temp dir from environment variable or use a default
     let test_dir_str = std::env::var("RJS_TEST_TEMP_DIR")
@@ -149,21 +153,22 @@ fn setup() {
     }
 }
 
+#[allow(dead_code)]
 fn cleanup() {
     // Get the current test directory from static mutex
-    if let Ok(mut current_dir) = CURRENT_TEST_DIR.lock() {
-        if let Some(test_dir) = current_dir.take() {
-            // Change back to the parent directory
-            if let Some(parent) = Path::new(&test_dir).parent() {
-                if let Err(e) = std::env::set_current_dir(parent) {
-                    eprintln!("Warning: Failed to change directory: {}", e);
-                }
-            }
+    if let Ok(mut current_dir) = CURRENT_TEST_DIR.lock()
+        && let Some(test_dir) = current_dir.take()
+    {
+        // Change back to the parent directory
+        if let Some(parent) = Path::new(&test_dir).parent()
+            && let Err(e) = std::env::set_current_dir(parent)
+        {
+            eprintln!("Warning: Failed to change directory: {}", e);
+        }
 
-            // Remove the test directory
-            if let Err(e) = fs::remove_dir_all(&test_dir) {
-                eprintln!("Warning: Failed to remove test directory: {}", e);
-            }
+        // Remove the test directory
+        if let Err(e) = fs::remove_dir_all(&test_dir) {
+            eprintln!("Warning: Failed to remove test directory: {}", e);
         }
     }
 }
@@ -186,7 +191,7 @@ fn test_command_performance() {
     
     // Warmup run (not measured)
     let warmup_result = Command::new(&binary_path)
-        .args(&["init", "-y"])
+        .args(["init", "-y"])
         .output();
     
     if let Err(e) = &warmup_result {
@@ -266,6 +271,7 @@ fn test_command_performance() {
 }
 
 // Helper function to run a command
+#[allow(dead_code)]
 fn run_command(args: &[&str]) -> (bool, String, String) {
     let output = Command::new(env::current_exe().unwrap().parent().unwrap().join("rjs"))
         .args(args)
@@ -301,7 +307,7 @@ fn test_concurrent_install_performance() {
     
     // Run init command to create package.json
     let output = match Command::new(&binary_path)
-        .args(&["init", "--yes"])
+        .args(["init", "--yes"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -322,7 +328,7 @@ fn test_concurrent_install_performance() {
     // Test install performance with high concurrency
     let start = Instant::now();
     let output = match Command::new(&binary_path)
-        .args(&["install", "lodash", "--concurrency", "8", "--no-progress"])
+        .args(["install", "lodash", "--concurrency", "8", "--no-progress"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -333,7 +339,7 @@ fn test_concurrent_install_performance() {
             }
         };
     let high_concurrency_success = output.status.success();
-    let high_concurrency_duration = start.elapsed();
+    let _high_concurrency_duration = start.elapsed();
     
     if !high_concurrency_success {
         println!("High concurrency install failed");
@@ -372,7 +378,7 @@ fn test_batch_size_impact() {
     
     // Run init command
     let output = match Command::new(&binary_path)
-        .args(&["init", "--yes"])
+        .args(["init", "--yes"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -393,7 +399,7 @@ fn test_batch_size_impact() {
 
     // Install multiple packages with batch size setting
     let output = match Command::new(&binary_path)
-        .args(&["install", "lodash", "chalk", "uuid", "--batch-size", "10", "--no-progress"])
+        .args(["install", "lodash", "chalk", "uuid", "--batch-size", "10", "--no-progress"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -427,6 +433,7 @@ fn test_batch_size_impact() {
 }
 
 // Helper function to run a command and measure execution time
+#[allow(dead_code)]
 fn measure_command_env(args: &[&str]) -> (Duration, String, bool) {
     // Path to the release binary for accurate performance measurement
     let binary_path = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".into()))
@@ -465,7 +472,7 @@ fn test_concurrency_impact() {
     
     // Initialize the project
     let output = match Command::new(&binary_path)
-        .args(&["init", "--yes"])
+        .args(["init", "--yes"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -488,7 +495,7 @@ fn test_concurrency_impact() {
         let _env = TestEnvironment::new();
         
         let output = match Command::new(&binary_path)
-            .args(&["init", "--yes"])
+            .args(["init", "--yes"])
             .output() {
                 Ok(o) => o,
                 Err(e) => {
@@ -588,7 +595,7 @@ fn test_batch_size_impact_env() {
     
     // Initialize the project
     let output = match Command::new(&binary_path)
-        .args(&["init", "--yes"])
+        .args(["init", "--yes"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -610,7 +617,7 @@ fn test_batch_size_impact_env() {
         let _env = TestEnvironment::new();
         
         let output = match Command::new(&binary_path)
-            .args(&["init", "--yes"])
+            .args(["init", "--yes"])
             .output() {
                 Ok(o) => o,
                 Err(e) => {
@@ -668,7 +675,7 @@ fn test_install_from_package_json() {
     
     // Initialize the project
     let output = match Command::new(&binary_path)
-        .args(&["init", "--yes"])
+        .args(["init", "--yes"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -731,7 +738,7 @@ fn test_regular_vs_dev_dependencies() {
     
     // Initialize the project
     let output = match Command::new(&binary_path)
-        .args(&["init", "--yes"])
+        .args(["init", "--yes"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -748,7 +755,7 @@ fn test_regular_vs_dev_dependencies() {
     // Install regular dependencies
     let start = Instant::now();
     let output = match Command::new(&binary_path)
-        .args(&["install", "lodash", "--no-progress"])
+        .args(["install", "lodash", "--no-progress"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -767,7 +774,7 @@ fn test_regular_vs_dev_dependencies() {
     let _env = TestEnvironment::new();
     
     let output = match Command::new(&binary_path)
-        .args(&["init", "--yes"])
+        .args(["init", "--yes"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -784,7 +791,7 @@ fn test_regular_vs_dev_dependencies() {
     // Install dev dependencies
     let start = Instant::now();
     let output = match Command::new(&binary_path)
-        .args(&["install", "lodash", "--save-dev", "--no-progress"])
+        .args(["install", "lodash", "--save-dev", "--no-progress"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -821,7 +828,7 @@ fn test_progress_reporting_impact() {
     
     // Initialize the project
     let output = match Command::new(&binary_path)
-        .args(&["init", "--yes"])
+        .args(["init", "--yes"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -838,7 +845,7 @@ fn test_progress_reporting_impact() {
     // Install with progress reporting
     let start = Instant::now();
     let output = match Command::new(&binary_path)
-        .args(&["install", "lodash"])
+        .args(["install", "lodash"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -857,7 +864,7 @@ fn test_progress_reporting_impact() {
     let _env = TestEnvironment::new();
     
     let output = match Command::new(&binary_path)
-        .args(&["init", "--yes"])
+        .args(["init", "--yes"])
         .output() {
             Ok(o) => o,
             Err(e) => {
@@ -874,7 +881,7 @@ fn test_progress_reporting_impact() {
     // Install without progress reporting
     let start = Instant::now();
     let output = match Command::new(&binary_path)
-        .args(&["install", "lodash", "--no-progress"])
+        .args(["install", "lodash", "--no-progress"])
         .output() {
             Ok(o) => o,
             Err(e) => {